@@ -0,0 +1,161 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+use crate::click::ClickKind;
+use crate::keybindings::KeyCombo;
+
+/// Work a pick queues for once the overlay is off the screen, in global points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AfterHide {
+    /// `then_keys` is sent to the app that has focus once the click has landed.
+    Click { point: egui::Pos2, kind: ClickKind, then_keys: Option<KeyCombo> },
+    /// A click on its way to `point`: each step moves the pointer further along from `from`,
+    /// and the click goes once the glide that began at `started_at` is over.
+    Glide { from: egui::Pos2, started_at: Instant, point: egui::Pos2, kind: ClickKind, then_keys: Option<KeyCombo> },
+    /// A held click's button goes up; a double click presses again for its second click.
+    Release { point: egui::Pos2, kind: ClickKind, click_state: i64, then_keys: Option<KeyCombo> },
+    /// The shortcut a click-then-keys pick sends, due once the clicked app has had time to take focus.
+    Keys { combo: KeyCombo },
+    /// Moves the drag the physical left button is holding to the point; letting go drops it there.
+    Drop { point: egui::Pos2 },
+    Screenshot { region: egui::Rect },
+    /// Reads the color under the point; the grid comes back to show it.
+    SampleColor { point: egui::Pos2 },
+}
+
+/// How far the overlay is in getting out of the way of queued work. The overlay's own
+/// visibility and fade are tracked separately; this only follows the queued work.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum HidePhase {
+    #[default]
+    Idle,
+    /// Picked while the overlay is up; waits for the fade-out to finish.
+    Queued(AfterHide),
+    /// The window is gone; runs at `due`, once the window server has let go of the overlay.
+    Waiting { action: AfterHide, due: Instant },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HideEvent {
+    /// A pick queued work, replacing anything queued before it.
+    Queue(AfterHide),
+    /// The fade-out that started at `fade_started_at` finished and the window is hidden.
+    Hidden { fade_started_at: Instant },
+    /// Escape, a hide without a pick, or the grid shown again: queued work must not run.
+    Cancel,
+    Tick(Instant),
+    /// Work that just ran left `action` to do at `due`.
+    Continue { action: AfterHide, due: Instant },
+}
+
+impl HidePhase {
+    /// The one place the phase changes. Returns the next phase and the work that is due now, if any.
+    /// `delay` is how long after the fade began the work may run.
+    pub fn step(self, event: HideEvent, delay: Duration) -> (HidePhase, Option<AfterHide>) {
+        match (self, event) {
+            (_, HideEvent::Cancel) => (HidePhase::Idle, None),
+            (_, HideEvent::Queue(action)) => (HidePhase::Queued(action), None),
+            (_, HideEvent::Continue { action, due }) => (HidePhase::Waiting { action, due }, None),
+            (HidePhase::Queued(action), HideEvent::Hidden { fade_started_at }) => {
+                (HidePhase::Waiting { action, due: fade_started_at + delay }, None)
+            }
+            (HidePhase::Waiting { action, due }, HideEvent::Tick(now)) if now >= due => (HidePhase::Idle, Some(action)),
+            (phase, HideEvent::Hidden { .. } | HideEvent::Tick(_)) => (phase, None),
+        }
+    }
+
+    /// The work queued or waiting, if any.
+    pub fn action(&self) -> Option<AfterHide> {
+        match self {
+            HidePhase::Idle => None,
+            HidePhase::Queued(action) | HidePhase::Waiting { action, .. } => Some(*action),
+        }
+    }
+
+    /// The window is hidden and work is about to run; the grid mustn't come up over it.
+    pub fn is_waiting(&self) -> bool {
+        matches!(self, HidePhase::Waiting { .. })
+    }
+
+    /// When the waiting work is due.
+    pub fn due(&self) -> Option<Instant> {
+        match self {
+            HidePhase::Waiting { due, .. } => Some(*due),
+            HidePhase::Idle | HidePhase::Queued(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DELAY: Duration = Duration::from_millis(150);
+
+    fn click() -> AfterHide {
+        AfterHide::Click { point: egui::pos2(10.0, 20.0), kind: ClickKind::Left, then_keys: None }
+    }
+
+    fn release() -> AfterHide {
+        AfterHide::Drop { point: egui::pos2(30.0, 40.0) }
+    }
+
+    #[test]
+    fn every_phase_and_event() {
+        let start = Instant::now();
+        let due = start + DELAY;
+        let waiting = HidePhase::Waiting { action: click(), due };
+        let hidden = HideEvent::Hidden { fade_started_at: start };
+        let later = HideEvent::Continue { action: release(), due: due + DELAY };
+        let waiting_later = HidePhase::Waiting { action: release(), due: due + DELAY };
+        let table = [
+            // (phase, event, next phase, work due now)
+            (HidePhase::Idle, HideEvent::Queue(click()), HidePhase::Queued(click()), None),
+            (HidePhase::Idle, hidden, HidePhase::Idle, None),
+            (HidePhase::Idle, HideEvent::Cancel, HidePhase::Idle, None),
+            (HidePhase::Idle, HideEvent::Tick(due), HidePhase::Idle, None),
+            (HidePhase::Idle, later, waiting_later, None),
+            (HidePhase::Queued(click()), HideEvent::Queue(release()), HidePhase::Queued(release()), None),
+            (HidePhase::Queued(click()), hidden, waiting, None),
+            (HidePhase::Queued(click()), HideEvent::Cancel, HidePhase::Idle, None),
+            (HidePhase::Queued(click()), HideEvent::Tick(due), HidePhase::Queued(click()), None),
+            (HidePhase::Queued(click()), later, waiting_later, None),
+            (waiting, HideEvent::Queue(release()), HidePhase::Queued(release()), None),
+            (waiting, HideEvent::Hidden { fade_started_at: due }, waiting, None),
+            (waiting, HideEvent::Cancel, HidePhase::Idle, None),
+            (waiting, HideEvent::Tick(start), waiting, None),
+            (waiting, HideEvent::Tick(due - Duration::from_millis(1)), waiting, None),
+            (waiting, HideEvent::Tick(due), HidePhase::Idle, Some(click())),
+            (waiting, HideEvent::Tick(due + DELAY), HidePhase::Idle, Some(click())),
+            (waiting, later, waiting_later, None),
+        ];
+        for (phase, event, next, work) in table {
+            assert_eq!(phase.step(event, DELAY), (next, work), "{:?} on {:?}", phase, event);
+        }
+    }
+
+    #[test]
+    fn queued_work_runs_once_after_the_delay() {
+        let start = Instant::now();
+        let (phase, _) = HidePhase::Idle.step(HideEvent::Queue(release()), DELAY);
+        assert_eq!(phase.action(), Some(release()));
+        assert!(!phase.is_waiting());
+        let (phase, _) = phase.step(HideEvent::Hidden { fade_started_at: start }, DELAY);
+        assert!(phase.is_waiting());
+        let (phase, work) = phase.step(HideEvent::Tick(start + DELAY), DELAY);
+        assert_eq!(work, Some(release()));
+        assert_eq!(phase, HidePhase::Idle);
+        assert_eq!(phase.step(HideEvent::Tick(start + DELAY * 2), DELAY), (HidePhase::Idle, None));
+    }
+
+    #[test]
+    fn continued_work_waits_for_its_own_deadline() {
+        let start = Instant::now();
+        let (phase, work) = HidePhase::Idle.step(HideEvent::Continue { action: click(), due: start + DELAY }, DELAY);
+        assert_eq!(work, None);
+        assert_eq!(phase.due(), Some(start + DELAY));
+        assert_eq!(phase.step(HideEvent::Tick(start), DELAY).1, None);
+        assert_eq!(phase.step(HideEvent::Tick(start + DELAY), DELAY), (HidePhase::Idle, Some(click())));
+    }
+}
@@ -4,10 +4,7 @@ use std::time::{Instant, Duration};
 use std::sync::mpsc::Receiver;
 
 use eframe::egui;
-use core_graphics::event::{CGEventType, CGEventTapLocation, CGMouseButton, CGEvent};
-use core_graphics::geometry::CGPoint;
-use core_graphics::event_source::CGEventSourceStateID;
-use mouse_rs::Mouse;
+use core_graphics::geometry::{CGPoint, CGSize};
 use raw_window_handle::{HasWindowHandle, RawWindowHandle};
 use objc::{msg_send, sel, sel_impl};
 use objc::runtime::Object;
@@ -16,14 +13,104 @@ use objc::runtime::Object;
 use objc2_app_kit::{NSWindowCollectionBehavior, NSWindowStyleMask};
 #[cfg(target_os = "macos")]
 const NSNONACTIVATING_PANEL_MASK: u64 = 1 << 7;
+/// Frames spent retrying the panel setup before focusing the window anyway.
+#[cfg(target_os = "macos")]
+const PANEL_SETUP_MAX_FRAMES: u32 = 60;
+/// How long the "Copied" confirmation stays up before the overlay hides.
+const COPY_FLASH_MS: u64 = 700;
+/// Smaller focused windows (or slivers of one on this display) get the whole-display grid instead.
+const MIN_FOCUSED_WINDOW_SIDE: f32 = 80.0;
+/// Upper bound for either sub-grid side when changing density at runtime.
+const MAX_SUB_GRID_SIDE: usize = 12;
+/// Fade-in/out duration of the overlay.
+const FADE_SECS: f32 = 0.08;
+/// Time from the start of the fade-out to the click, leaving the window server time to drop the hidden window.
+const HIDE_TO_CLICK_MS: u64 = 150;
+/// Fallback repaint interval while hidden. GlobalEvents wake the UI themselves, so this is only a
+/// safety net.
+const HIDDEN_IDLE_REPAINT_MS: u64 = 2000;
+/// The self-test shows the grid on a schedule, which nothing else would wake the UI for.
+const SELF_TEST_IDLE_REPAINT_MS: u64 = 50;
+/// How long a cell picked through a corrected typo stays outlined.
+const FUZZY_FLASH_MS: u64 = 250;
+/// How long the "Saved bookmark" confirmation stays up.
+const BOOKMARK_FLASH_MS: u64 = 900;
+/// How long the HUD shows the overlay opacity after it changes.
+const OPACITY_FLASH_MS: u64 = 900;
+/// Pins for bookmarks scoped to the frontmost app.
+const APP_BOOKMARK_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 120, 60);
+/// Pins for global bookmarks.
+const GLOBAL_BOOKMARK_COLOR: egui::Color32 = egui::Color32::from_rgb(80, 160, 255);
+/// How long the shown overlay waits for a usable screen rect (it is briefly empty while displays
+/// are reconfigured) before hiding itself.
+const LAYOUT_TIMEOUT_MS: u64 = 2000;
+/// Delay before the first layout retry; it doubles after each failed attempt up to the max.
+const LAYOUT_RETRY_MIN_MS: u64 = 16;
+const LAYOUT_RETRY_MAX_MS: u64 = 250;
+/// Wait before reading the entries of a menu just pressed open, and how long to keep trying.
+const MENU_OPEN_DELAY_MS: u64 = 60;
+const MENU_OPEN_TIMEOUT_MS: u64 = 1000;
+
+use crate::after_hide::{AfterHide, HideEvent, HidePhase};
+use crate::bisect::{BisectRegions, Quadrant};
+use crate::bookmarks::{BookmarkPrompt, Bookmarks, Namespace};
+use crate::grid;
+use crate::grid_controller::{GridController, RefinementLayout, Step};
+use crate::hud::Hud;
+use crate::overlay_opacity::{self, OverlayOpacity};
+use crate::sub_cell_memory::SubCellMemory;
+use crate::event_handler::GlobalEvent;
+use crate::feedback::{self, Cue};
+use crate::click::{self, ClickKind};
+use crate::click_marker::ClickMarker;
+use crate::clipboard;
+use crate::color_sample::{self, SampledColor};
+use crate::config::{self, Config, ConfirmCenterAction};
+use crate::displays::{self, OverlayDisplay};
+use crate::keybindings::{self, Action, KeyBindings, KeyCombo};
+use crate::label_font;
+use crate::magnifier::{self, Magnifier};
+use crate::pick_mode::{KeyPrompt, PickMode};
+use crate::screenshot;
+use crate::scroll::{self, ScrollRepeater};
+use crate::selftest::SelfTest;
+use crate::tap_gesture::{Clock, SystemClock, TapAction};
+use crate::theme::Theme;
+use crate::tutorial;
+use crate::two_point::{Measurement, TwoPointPicker, TwoPointPurpose};
+use crate::window_picker::{self, WindowPickAction};
+use crate::window_snap::SnapPreset;
+use crate::pointer_motion::{HeldDirections, PointerMotion};
+use crate::profiles::{self, ProfileConfig};
+use crate::quick_jump::QuickJumpTarget;
+#[cfg(target_os = "macos")]
+use crate::ax;
+#[cfg(target_os = "macos")]
+use crate::dock;
+#[cfg(target_os = "macos")]
+use crate::macos;
+#[cfg(target_os = "macos")]
+use crate::menu_hints::{MenuStep, MenuWalker};
+#[cfg(target_os = "macos")]
+use crate::window_snap;
 
-use crate::grid::{self, MAIN_GRID_COLS, MAIN_GRID_ROWS, SUB_GRID_COLS, SUB_GRID_ROWS};
-use crate::event_handler::{GlobalEvent, RCMD_DOUBLE_TAP_MAX_DELAY_MS};
+/// What the copy key arms the next pick to copy, instead of clicking it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum CopyTarget {
+    /// The point's global coordinates, as "x,y".
+    Coordinates,
+    /// The color of the pixel under the point, as sRGB hex.
+    Color,
+}
 
 #[derive(Clone)]
 pub struct EframeControl {
     pub hide_requested: Arc<AtomicBool>,
     pub is_visible: Arc<AtomicBool>,
+    /// Set once a frame has actually been painted since the last show. `is_visible` flips before
+    /// the window is on screen, so the event tap uses this to decide whether Escape is ours.
+    pub overlay_rendered: Arc<AtomicBool>,
+    pub pointer_keys: Arc<HeldDirections>,
 }
 
 impl Default for EframeControl {
@@ -31,487 +118,3795 @@ impl Default for EframeControl {
         Self {
             hide_requested: Arc::new(AtomicBool::new(false)),
             is_visible: Arc::new(AtomicBool::new(false)),
+            overlay_rendered: Arc::new(AtomicBool::new(false)),
+            pointer_keys: Arc::new(HeldDirections::default()),
         }
     }
 }
 
-fn key_to_char(key: egui::Key, _modifiers: egui::Modifiers) -> Option<char> {
+/// Key presses in `events`, each with the label character it typed. Runs inside `ctx.input` so the
+/// frame's events are borrowed rather than cloned. The character comes from the Text event the key
+/// press produced, so non-QWERTY layouts like AZERTY type what their keycaps show. When the layout
+/// produced no label character (e.g. a Cyrillic layout) the key's US position is used instead.
+/// Bound keys never type a character.
+fn key_presses(events: &[egui::Event], key_bindings: &KeyBindings) -> Vec<(egui::Key, Option<char>)> {
+    let mut presses = Vec::new();
+    for (index, event) in events.iter().enumerate() {
+        let egui::Event::Key { key, pressed: true, .. } = event else {
+            continue;
+        };
+        if key_bindings.action_for(*key).is_some() {
+            presses.push((*key, None));
+            continue;
+        }
+        let produced_text = events[index + 1..].iter()
+            .take_while(|later| !matches!(later, egui::Event::Key { pressed: true, .. }))
+            .find_map(|later| match later {
+                egui::Event::Text(text) => Some(text),
+                _ => None,
+            });
+        let mut produced_chars = produced_text.map(|text| text.chars().map(|c| c.to_ascii_uppercase())).into_iter().flatten();
+        let typed = match (produced_chars.next(), produced_chars.next()) {
+            (Some(c), None) if keybindings::is_label_char(c) => Some(c),
+            _ => keybindings::key_label_char(*key),
+        };
+        presses.push((*key, typed));
+    }
+    presses
+}
+
+fn key_to_display_number(key: egui::Key) -> Option<usize> {
     match key {
-        egui::Key::A => Some('A'), egui::Key::B => Some('B'), egui::Key::C => Some('C'),
-        egui::Key::D => Some('D'), egui::Key::E => Some('E'), egui::Key::F => Some('F'),
-        egui::Key::G => Some('G'), egui::Key::H => Some('H'), egui::Key::I => Some('I'),
-        egui::Key::J => Some('J'), egui::Key::K => Some('K'), egui::Key::L => Some('L'),
-        egui::Key::M => Some('M'), egui::Key::N => Some('N'), egui::Key::O => Some('O'),
-        egui::Key::P => Some('P'), egui::Key::Q => Some('Q'), egui::Key::R => Some('R'),
-        egui::Key::S => Some('S'), egui::Key::T => Some('T'), egui::Key::U => Some('U'),
-        egui::Key::V => Some('V'), egui::Key::W => Some('W'), egui::Key::X => Some('X'),
-        egui::Key::Y => Some('Y'), egui::Key::Z => Some('Z'),
+        egui::Key::Num1 => Some(1), egui::Key::Num2 => Some(2), egui::Key::Num3 => Some(3),
+        egui::Key::Num4 => Some(4), egui::Key::Num5 => Some(5), egui::Key::Num6 => Some(6),
+        egui::Key::Num7 => Some(7), egui::Key::Num8 => Some(8), egui::Key::Num9 => Some(9),
         _ => None,
     }
 }
 
-#[derive(Clone)]
-struct PendingRCmdTapInfo {
-    tap_time: Instant,
-    cursor_pos: Option<egui::Pos2>,
+#[cfg(target_os = "macos")]
+fn ns_window_from_frame(frame: Option<&eframe::Frame>) -> Option<*mut Object> {
+    match frame?.window_handle() {
+        Ok(handle) => match handle.as_raw() {
+            RawWindowHandle::AppKit(app_kit_handle) => {
+                let view_ptr = app_kit_handle.ns_view.as_ptr() as *mut Object;
+                let window_ptr: *mut Object = unsafe { msg_send![view_ptr, window] };
+                if window_ptr.is_null() { None } else { Some(window_ptr) }
+            }
+            _ => None,
+        },
+        Err(_) => None,
+    }
+}
+
+/// Moves `window` from `current` to `target` (global points), resizing it only when it is
+/// `resizable` and the size changes. The target is first kept on the display its top-left lands on.
+#[cfg(target_os = "macos")]
+fn set_window_frame(window: &ax::AXElement, current: egui::Rect, mut target: egui::Rect, resizable: bool) {
+    let displays = displays::active_displays();
+    if let Some(index) = displays::display_containing(&displays, target.min) {
+        target = window_picker::fit_on_display(target, displays[index].frame);
+    }
+    println!("Placing the focused window at {:?}", target);
+    let size = (resizable && target.size() != current.size()).then(|| CGSize::new(target.width() as f64, target.height() as f64));
+    window.set_frame(CGPoint::new(target.min.x as f64, target.min.y as f64), size);
+}
+
+fn paint_dimmed_outside(painter: &egui::Painter, screen_rect: egui::Rect, clear_zone: egui::Rect, dim_color: egui::Color32) {
+    for dim_rect in [
+        egui::Rect::from_min_max(screen_rect.min, egui::pos2(screen_rect.max.x, clear_zone.min.y)),
+        egui::Rect::from_min_max(egui::pos2(screen_rect.min.x, clear_zone.max.y), screen_rect.max),
+        egui::Rect::from_min_max(egui::pos2(screen_rect.min.x, clear_zone.min.y), egui::pos2(clear_zone.min.x, clear_zone.max.y)),
+        egui::Rect::from_min_max(egui::pos2(clear_zone.max.x, clear_zone.min.y), egui::pos2(screen_rect.max.x, clear_zone.max.y)),
+    ] {
+        if dim_rect.is_positive() {
+            painter.rect_filled(dim_rect, 0.0, dim_color);
+        }
+    }
+}
+
+/// Rounded hint bar centered near the bottom of the screen, above the HUD.
+fn paint_hint_pill(painter: &egui::Painter, screen_rect: egui::Rect, text: String) {
+    let hint_anchor = screen_rect.center_bottom() - egui::vec2(0.0, 68.0);
+    let galley = painter.layout_no_wrap(text, egui::FontId::proportional(16.0), egui::Color32::WHITE);
+    let hint_rect = egui::Rect::from_center_size(hint_anchor, galley.size()).expand2(egui::vec2(14.0, 8.0));
+    painter.rect_filled(hint_rect, hint_rect.height() / 2.0, egui::Color32::from_rgba_unmultiplied(20, 20, 20, 200));
+    painter.galley(hint_rect.center() - galley.size() / 2.0, galley, egui::Color32::WHITE);
+}
+
+/// Two borders with a gap between them, so a marked cell stands out by shape as well as color.
+fn paint_double_outline(painter: &egui::Painter, rect: egui::Rect, stroke: egui::Stroke) {
+    painter.rect_stroke(rect.shrink(stroke.width / 2.0), 0.0, stroke);
+    painter.rect_stroke(rect.shrink(stroke.width * 2.5), 0.0, egui::Stroke::new(stroke.width / 2.0, stroke.color));
+}
+
+const LABEL_OUTLINE_OFFSETS: [egui::Vec2; 4] = [egui::vec2(1.0, 0.0), egui::vec2(-1.0, 0.0), egui::vec2(0.0, 1.0), egui::vec2(0.0, -1.0)];
+
+/// Cell label text, optionally outlined so it stays legible over busy content. Light text gets a
+/// dark outline and dark text (the light theme) a light one. Position and size are snapped to the
+/// painter's physical pixels, so labels stay crisp at any pixels_per_point.
+fn paint_label(painter: &egui::Painter, pos: egui::Pos2, align: egui::Align2, text: &str, font: egui::FontId, color: egui::Color32, outline: bool) {
+    let pos = painter.round_pos_to_pixels(pos);
+    let font = egui::FontId::new(painter.round_to_pixel(font.size).max(1.0), font.family);
+    if outline {
+        let luma = 0.299 * color.r() as f32 + 0.587 * color.g() as f32 + 0.114 * color.b() as f32;
+        let shade = if luma >= 128.0 { 0 } else { 255 };
+        let outline_color = egui::Color32::from_rgba_unmultiplied(shade, shade, shade, color.a().max(160));
+        for offset in LABEL_OUTLINE_OFFSETS {
+            painter.text(pos + offset, align, text, font.clone(), outline_color);
+        }
+    }
+    painter.text(pos, align, text, font, color);
+}
+
+/// Label of a cell still matching the typed prefix: the typed part small and faded, the keys
+/// left to press large and in the accent color.
+fn paint_filtered_label(painter: &egui::Painter, rect: egui::Rect, typed: &str, rest: &str, theme: &Theme, outline: bool) {
+    let font_size = rect.height().min(rect.width()) * 0.4 * theme.label_scale;
+    let typed_font = label_font::label_font(font_size * 0.6);
+    let rest_font = label_font::label_font(font_size * 1.4);
+    let typed_size = painter.layout_no_wrap(typed.to_string(), typed_font.clone(), egui::Color32::WHITE).size();
+    let rest_size = painter.layout_no_wrap(rest.to_string(), rest_font.clone(), egui::Color32::WHITE).size();
+    let left = rect.center().x - (typed_size.x + rest_size.x) / 2.0;
+    let typed_pos = egui::pos2(left, rect.center().y - typed_size.y / 2.0);
+    let rest_pos = egui::pos2(left + typed_size.x, rect.center().y - rest_size.y / 2.0);
+    paint_label(painter, typed_pos, egui::Align2::LEFT_TOP, typed, typed_font, theme.label_color.gamma_multiply(0.45), outline);
+    paint_label(painter, rest_pos, egui::Align2::LEFT_TOP, rest, rest_font, theme.accent, outline);
 }
 
 pub struct MouselessApp {
     display_mode: grid::DisplayMode,
+    /// Label typed in the hint modes; the grid modes type into `selection`.
     key_input_buffer: String,
-    selected_main_cell_index: Option<usize>,
-    previewed_first_char: Option<char>,
-    main_grid_labels: Vec<String>,
-    main_grid_rects: Vec<egui::Rect>,
-    sub_grid_labels: Vec<String>,
-    sub_grid_rects: Vec<egui::Rect>,
+    /// Main grid, picked levels and typed label of the grid modes.
+    selection: GridController,
+    /// Runtime flip of `grid.levels` for the current selection.
+    extra_level_toggled: bool,
+    /// Sub-grid columns and rows; starts from config and keeps density changes for the session.
+    sub_grid_dims: (usize, usize),
+    previewed_prefix: Option<String>,
+    /// Menu bar and notch of the display this window is on.
+    screen_top_insets: displays::ScreenTopInsets,
     last_layout_screen_rect: egui::Rect,
-    mouse_handler: Mouse,
+    /// Since when the screen rect has been too small to lay out, and the current retry delay.
+    layout_wait: Option<(Instant, Duration)>,
     eframe_control: EframeControl,
     _initial_target_rect: egui::Rect,
-    initial_focus_requested: bool,
+    /// Focus asked for by show_grid, sent once the window can take it without activating the app.
+    focus_requested: bool,
     #[cfg(target_os = "macos")]
     macos_panel_properties_set: bool,
+    #[cfg(target_os = "macos")]
+    panel_setup_frames: u32,
     event_rx: Receiver<GlobalEvent>,
     lshift_key_is_pressed: Arc<AtomicBool>,
-    is_hiding_to_perform_click: bool,
-    hide_initiated_at: Option<Instant>,
-    pending_click_pos_after_hide: Option<egui::Pos2>,
-    pending_rcmd_single_tap: Option<PendingRCmdTapInfo>,
+    /// Work a pick queued for once the overlay is gone; only changed through `step_hide_phase`.
+    hide_phase: HidePhase,
+    /// A tap that asked for the grid while the overlay was still on its way out, run once it's gone.
+    pending_show: Option<(TapAction, Option<egui::Pos2>)>,
+    /// Set while the overlay fades out; the window is hidden once the fade finishes.
+    fade_out_started_at: Option<Instant>,
+    /// Time for the hide phase, so the wait before queued work can be driven without real time.
+    clock: Box<dyn Clock>,
+    /// Click used when a point is committed without the action prompt; set by the gesture that showed the grid.
+    default_click_kind: ClickKind,
+    /// Button picked with a prefix key in the sub-grid or stored with a recalled bookmark, for the next pick only.
+    sub_grid_click_kind: Option<ClickKind>,
+    /// Global point and kind of the last posted click, for the repeat-last-click gesture.
+    last_click: Option<(CGPoint, ClickKind)>,
+    /// Ring drawn where the last click landed, while feedback.click_marker is on.
+    click_marker: Option<ClickMarker>,
+    /// Sub-grid labels click straight away and the grid stays up, for clicking the same area fast.
+    rapid_fire: bool,
+    config: Config,
+    action_prompt_point: Option<egui::Pos2>,
+    action_prompt_started_at: Option<Instant>,
+    previous_frontmost_pid: Option<i32>,
+    /// Overrides for the app that was frontmost when the grid was shown.
+    active_profile: ProfileConfig,
+    /// Sub-grid size to go back to once no profile overrides it.
+    sub_grid_dims_before_profile: Option<(usize, usize)>,
+    overlay_displays: Vec<OverlayDisplay>,
+    home_display_index: Option<usize>,
+    selected_display_index: Option<usize>,
+    pick_mode: PickMode,
+    /// Physical mouse movement since the overlay appeared, summed so jitter cancels out.
+    mouse_drift: egui::Vec2,
+    /// Armed by the screenshot and measure keys: the next two picks are a start and an end.
+    two_point: TwoPointPicker,
+    /// Shown until Escape after the second measure pick.
+    measurement: Option<Measurement>,
+    /// Pressing the copy key steps through coordinates, color and off.
+    copy_target: Option<CopyTarget>,
+    /// Sampled color and the global point it came from; shown until Escape.
+    sampled_color: Option<(SampledColor, egui::Pos2)>,
+    /// Debug overlay: each main cell shows its global center. Kept across shows.
+    show_cell_coordinates: bool,
+    /// Text just copied, the point it came from, and when; shown briefly before hiding.
+    copy_flash: Option<(String, egui::Pos2, Instant)>,
+    /// Main cell picked through a corrected typo, and when.
+    fuzzy_flash: Option<(egui::Rect, Instant)>,
+    overlay_opacity: OverlayOpacity,
+    /// When the overlay opacity last changed, so the HUD can show the new value.
+    opacity_flash: Option<Instant>,
+    sub_cell_memory: SubCellMemory,
+    /// Lens beside the cell being refined, when [magnifier] is enabled.
+    magnifier: Magnifier,
+    bookmarks: Bookmarks,
+    /// Set by the bookmark key: the next typed character names a bookmark.
+    bookmark_prompt: Option<BookmarkPrompt>,
+    /// The displays as they were when the bookmark prompt opened, for the pins painted while it waits.
+    bookmark_displays: Vec<OverlayDisplay>,
+    bookmark_flash: Option<(String, Instant)>,
+    /// Set by the quick jump key; while placing a window it offers the snap presets instead.
+    key_prompt: Option<KeyPrompt>,
+    /// Bundle id of the app that was frontmost when the grid was shown; bookmarks are scoped to it.
+    frontmost_bundle_id: Option<String>,
+    /// Global frame of that app's focused window, read on show when [displays] focused_window is on.
+    focused_window_frame: Option<egui::Rect>,
+    /// Label of the main cell picked last, for resuming in its sub-grid.
+    last_main_cell_label: Option<String>,
+    /// Set on show; the last main cell is reopened once the grid is laid out.
+    resume_last_region_requested: bool,
+    /// Clickable elements or menu entries of the frontmost app (window-relative) and their labels.
+    hint_labels: Vec<String>,
+    hint_rects: Vec<egui::Rect>,
+    /// Hints, MenuHints, DockHints or WindowHints, asked for before the overlay had a layout to place hints in.
+    requested_hint_mode: Option<grid::DisplayMode>,
+    #[cfg(target_os = "macos")]
+    menu_walker: Option<MenuWalker>,
+    /// Dock items behind the hints in dock mode, in hint order.
+    #[cfg(target_os = "macos")]
+    dock_items: Vec<ax::AXElement>,
+    /// Windows behind the hints in window mode, in hint order; their rects may be off this display.
+    #[cfg(target_os = "macos")]
+    picker_windows: Vec<macos::WindowInfo>,
+    /// When the menu being waited for was pressed open.
+    menu_opened_at: Option<Instant>,
+    /// Window-relative to global offset captured when fine adjust starts, for the coordinate readout.
+    fine_adjust_global_offset: egui::Vec2,
+    scroll_point: Option<egui::Pos2>,
+    scroll_repeater: ScrollRepeater,
+    #[cfg(target_os = "macos")]
+    mouse_passthrough_guard: Option<macos::IgnoreMouseEventsGuard>,
+    self_test: Option<SelfTest>,
+    /// Driven by a test rather than eframe: the picked click is logged instead of posted.
+    headless: bool,
+    /// First run: the tutorial is shown over the grid once the app is up.
+    tutorial_pending: bool,
+    /// Kept in eframe's storage, so the tutorial shows on the first launch only.
+    tutorial_seen: bool,
+    /// The tutorial card is up; the next key only dismisses it.
+    tutorial_visible: bool,
+    pointer_motion: Option<PointerMotion>,
+    bisect_regions: BisectRegions,
 }
 
 impl MouselessApp {
     pub fn new(
-        cc: &eframe::CreationContext<'_>,
+        egui_ctx: &egui::Context,
         eframe_control: EframeControl,
         initial_target_rect: egui::Rect,
         event_rx: Receiver<GlobalEvent>,
         lshift_key_is_pressed: Arc<AtomicBool>,
+        config: Config,
+        self_test_runs: Option<usize>,
     ) -> Self {
+        let label_alphabet = config.grid.label_alphabet();
         let (labels, _) = grid::generate_main_grid_layout(
-            MAIN_GRID_COLS,
-            MAIN_GRID_ROWS,
+            config.grid.main_cols,
+            config.grid.main_rows,
             egui::Rect::from_min_size(egui::Pos2::ZERO, initial_target_rect.size()),
-        );
+            &config.label_alphabets,
+            label_alphabet.as_deref(),
+        ).unwrap_or_else(|e| {
+            eprintln!("Failed to lay out main grid: {}", e);
+            (Vec::new(), Vec::new())
+        });
         
+        // Rects come with the first frame's layout, once the window's real size is known
+        let mut selection = GridController::default();
+        selection.set_main_grid(labels, Vec::new(), Vec::new());
+        let sub_grid_dims = (config.grid.sub_grid_cols, config.grid.sub_grid_rows);
+        let sub_cell_memory = if config.grid.remember_sub_cells { SubCellMemory::load() } else { SubCellMemory::default() };
         let s = Self {
             display_mode: grid::DisplayMode::MainGrid,
             key_input_buffer: String::new(),
-            selected_main_cell_index: None,
-            previewed_first_char: None,
-            main_grid_labels: labels,
-            main_grid_rects: Vec::new(),
-            sub_grid_labels: Vec::new(),
-            sub_grid_rects: Vec::new(),
+            selection,
+            extra_level_toggled: false,
+            sub_grid_dims,
+            previewed_prefix: None,
+            screen_top_insets: displays::ScreenTopInsets::default(),
             last_layout_screen_rect: egui::Rect::NOTHING,
-            mouse_handler: Mouse::new(),
+            layout_wait: None,
             eframe_control,
             _initial_target_rect: initial_target_rect,
-            initial_focus_requested: false,
+            focus_requested: false,
             #[cfg(target_os = "macos")]
             macos_panel_properties_set: false,
+            #[cfg(target_os = "macos")]
+            panel_setup_frames: 0,
             event_rx,
             lshift_key_is_pressed,
-            is_hiding_to_perform_click: false,
-            hide_initiated_at: None,
-            pending_click_pos_after_hide: None,
-            pending_rcmd_single_tap: None,
+            fade_out_started_at: None,
+            clock: Box::new(SystemClock),
+            hide_phase: HidePhase::Idle,
+            pending_show: None,
+            default_click_kind: ClickKind::Left,
+            sub_grid_click_kind: None,
+            last_click: None,
+            click_marker: None,
+            rapid_fire: false,
+            config,
+            action_prompt_point: None,
+            action_prompt_started_at: None,
+            previous_frontmost_pid: None,
+            active_profile: ProfileConfig::default(),
+            sub_grid_dims_before_profile: None,
+            overlay_displays: Vec::new(),
+            home_display_index: None,
+            selected_display_index: None,
+            pick_mode: PickMode::Click,
+            copy_target: None,
+            sampled_color: None,
+            mouse_drift: egui::Vec2::ZERO,
+            two_point: TwoPointPicker::default(),
+            measurement: None,
+            show_cell_coordinates: false,
+            copy_flash: None,
+            fuzzy_flash: None,
+            overlay_opacity: OverlayOpacity::default(),
+            opacity_flash: None,
+            sub_cell_memory,
+            magnifier: Magnifier::default(),
+            hint_labels: Vec::new(),
+            hint_rects: Vec::new(),
+            requested_hint_mode: None,
+            #[cfg(target_os = "macos")]
+            menu_walker: None,
+            #[cfg(target_os = "macos")]
+            dock_items: Vec::new(),
+            #[cfg(target_os = "macos")]
+            picker_windows: Vec::new(),
+            menu_opened_at: None,
+            last_main_cell_label: None,
+            bookmarks: Bookmarks::load(),
+            bookmark_prompt: None,
+            bookmark_displays: Vec::new(),
+            bookmark_flash: None,
+            key_prompt: None,
+            frontmost_bundle_id: None,
+            focused_window_frame: None,
+            resume_last_region_requested: false,
+            fine_adjust_global_offset: egui::Vec2::ZERO,
+            scroll_point: None,
+            scroll_repeater: ScrollRepeater::default(),
+            #[cfg(target_os = "macos")]
+            mouse_passthrough_guard: None,
+            self_test: self_test_runs.map(SelfTest::new),
+            headless: false,
+            tutorial_pending: self_test_runs.is_none(),
+            tutorial_seen: false,
+            tutorial_visible: false,
+            pointer_motion: None,
+            bisect_regions: BisectRegions::default(),
         };
 
-        let mut style = (*cc.egui_ctx.style()).clone();
+        let mut style = (*egui_ctx.style()).clone();
         style.visuals.window_fill = egui::Color32::TRANSPARENT;
         style.visuals.panel_fill = egui::Color32::TRANSPARENT;
-        cc.egui_ctx.set_style(style);
+        egui_ctx.set_style(style);
+        label_font::install(egui_ctx, &s.config.theme);
         s
     }
-    
-    fn perform_mouse_click(&mut self, _ctx: &egui::Context, window_relative_point: egui::Pos2) {
-        let current_viewport_outer_rect = _ctx.input(|i| i.viewport().outer_rect);
-        if let Some(window_outer_rect) = current_viewport_outer_rect {
-            let window_origin_global = window_outer_rect.min;
-            let global_click_point = window_origin_global + window_relative_point.to_vec2();
 
-            println!("Preparing click at {:?}", global_click_point);
+    /// App for driving with `headless_frame` from a test instead of eframe: there is no native
+    /// window, and a picked click queues as usual but neither warps the cursor nor is posted. Sub-cells
+    /// aren't remembered so runs don't depend on each other; pass a config with the settings under test.
+    #[cfg(test)]
+    pub fn headless(egui_ctx: &egui::Context, screen_rect: egui::Rect, event_rx: Receiver<GlobalEvent>, mut config: Config) -> Self {
+        config.grid.remember_sub_cells = false;
+        let mut app = Self::new(egui_ctx, EframeControl::default(), screen_rect, event_rx, Arc::new(AtomicBool::new(false)), config, None);
+        app.headless = true;
+        app.tutorial_pending = false;
+        app
+    }
 
-            if let Err(e) = self.mouse_handler.move_to(global_click_point.x as i32, global_click_point.y as i32) {
-                eprintln!("Failed to move mouse: {:?}", e);
-                self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
-                self.pending_click_pos_after_hide = None;
-                return;
-            } else {
-                println!("Mouse moved to ({}, {})", global_click_point.x as i32, global_click_point.y as i32);
-            }
-            
-            self.pending_click_pos_after_hide = Some(global_click_point);
-            println!("Click queued, hiding app");
+    /// Reads back what `save` stored on the last run.
+    pub fn restore(&mut self, storage: &dyn eframe::Storage) {
+        if let Some(saved) = eframe::get_value(storage, overlay_opacity::STORAGE_KEY) {
+            self.overlay_opacity = OverlayOpacity::restored(saved);
+        }
+        self.tutorial_seen = eframe::get_value(storage, tutorial::STORAGE_KEY).unwrap_or(false);
+        self.tutorial_pending &= !self.tutorial_seen;
+    }
 
+    /// One frame without eframe, run inside `egui::Context::run` with the events under test.
+    #[cfg(test)]
+    pub fn headless_frame(&mut self, ctx: &egui::Context) {
+        self.run_frame(ctx, None);
+    }
+
+    /// Global point and kind of the click waiting for the overlay to hide, if one was picked.
+    #[cfg(test)]
+    pub fn pending_click(&self) -> Option<(egui::Pos2, ClickKind)> {
+        match self.hide_phase.action() {
+            Some(AfterHide::Click { point, kind, .. }) => Some((point, kind)),
+            _ => None,
+        }
+    }
+    
+    fn show_grid(&mut self, ctx: &egui::Context, cursor_pos_opt: Option<egui::Pos2>) {
+        self.eframe_control.overlay_rendered.store(false, AtomicOrdering::SeqCst);
+        self.eframe_control.is_visible.store(true, AtomicOrdering::SeqCst);
+        self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
+        self.fade_out_started_at = None;
+        if let Some(cursor_pos) = cursor_pos_opt {
+            println!("Setting OuterPosition near cursor at {:?}", cursor_pos);
+            ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(cursor_pos));
+            ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(true)); 
         } else {
-            eprintln!("Failed to get window rect for click at {:?}", window_relative_point);
-            self.pending_click_pos_after_hide = None;
+            println!("No cursor position provided, ensuring maximized on default monitor.");
+            ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(true));
+        }
+        #[cfg(target_os = "macos")]
+        {
+            self.previous_frontmost_pid = macos::frontmost_app_pid().filter(|pid| *pid != macos::own_pid());
+            println!("Frontmost app before showing grid: {:?}", self.previous_frontmost_pid);
+        }
+        #[cfg(target_os = "macos")]
+        let frontmost_bundle_id = macos::frontmost_app_bundle_id().filter(|_| self.previous_frontmost_pid.is_some());
+        #[cfg(not(target_os = "macos"))]
+        let frontmost_bundle_id: Option<String> = None;
+        self.apply_profile(profiles::profile_for(&self.config.profiles, frontmost_bundle_id.as_deref()));
+        self.frontmost_bundle_id = frontmost_bundle_id;
+        self.focused_window_frame = None;
+        #[cfg(target_os = "macos")]
+        if self.config.displays.focused_window {
+            self.focused_window_frame = self.previous_frontmost_pid
+                .and_then(ax::focused_window)
+                .and_then(|window| window.frame())
+                .map(|frame| egui::Rect::from_min_size(
+                    egui::pos2(frame.origin.x as f32, frame.origin.y as f32),
+                    egui::vec2(frame.size.width as f32, frame.size.height as f32),
+                ));
+            match self.focused_window_frame {
+                Some(frame) => println!("Laying the grid over the focused window at {:?}", frame),
+                None => println!("Couldn't read the focused window's frame, using the whole display"),
+            }
+        }
+        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+        // Sent from update once the window is a non-activating panel, so it never steals focus
+        self.focus_requested = true;
+        self.clear_typed();
+        self.selection.reset();
+        self.extra_level_toggled = false;
+        self.previewed_prefix = None;
+        self.fuzzy_flash = None;
+        self.bookmark_prompt = None;
+        self.bookmark_flash = None;
+        self.key_prompt = None;
+        self.display_mode = grid::DisplayMode::MainGrid;
+        self.action_prompt_point = None;
+        self.action_prompt_started_at = None;
+        self.selection.clear_main_grid();
+        self.layout_wait = None;
+        self.clear_hints();
+        self.resume_last_region_requested = self.config.grid.resume_last_region || self.lshift_key_is_pressed.load(AtomicOrdering::SeqCst);
+        self.selected_display_index = None;
+        self.exit_passthrough_modes();
+        self.default_click_kind = self.active_profile.default_click.unwrap_or(ClickKind::Left);
+        self.sub_grid_click_kind = None;
+        self.rapid_fire = false;
+        self.tutorial_visible = false;
+        feedback::play(Cue::GridShown, &self.config.feedback);
+        // Whatever was queued before the grid came back up belongs to a pick that no longer applies
+        if let Some(action) = self.hide_phase.action() {
+            println!("Dropping queued {:?}, the grid is up again", action);
+        }
+        self.step_hide_phase(HideEvent::Cancel);
+        self.mouse_drift = egui::Vec2::ZERO;
+        self.bisect_regions.clear();
+        if self.config.bisect.default_mode {
+            // The root region is seeded once the layout rect is known
+            self.display_mode = grid::DisplayMode::Bisect;
+        }
+        if self.config.displays.all_displays {
+            self.overlay_displays = displays::active_displays();
+            self.home_display_index = cursor_pos_opt
+                .and_then(|pos| displays::display_containing(&self.overlay_displays, pos))
+                .or_else(|| displays::primary_display(&self.overlay_displays));
+            println!("Showing grid on {} displays (home display {:?})", self.overlay_displays.len(), self.home_display_index.map(|i| i + 1));
         }
-        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
     }
-}
 
-impl eframe::App for MouselessApp {
-    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) { 
-        while let Ok(event) = self.event_rx.try_recv() {
-            match event {
-                GlobalEvent::PotentialSingleRCmdTap { tap_time, cursor_pos } => {
-                    println!("App received PotentialSingleRCmdTap");
-                    self.pending_rcmd_single_tap = Some(PendingRCmdTapInfo { tap_time, cursor_pos });
-                    
-                    ctx.request_repaint_after(Duration::from_millis(50)); 
-                }
-                GlobalEvent::RCmdDoubleTap => {
-                    println!("App received RCmdDoubleTap");
-                    self.pending_rcmd_single_tap = None; 
-                    // TODO: Implement actual double-tap specific action if needed beyond hiding
-                    println!("RCmd Double Tap action! (currently hides if visible)");
-                    if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
-                        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
-                    }
-                }
-                GlobalEvent::CancelPendingRCmdTap => {
-                    println!("App received CancelPendingRCmdTap");
-                    self.pending_rcmd_single_tap = None;
+    fn apply_profile(&mut self, profile: ProfileConfig) {
+        let unprofiled_dims = self.sub_grid_dims_before_profile.unwrap_or(self.sub_grid_dims);
+        match profile.sub_grid_dims(unprofiled_dims) {
+            Some(dims) => {
+                self.sub_grid_dims_before_profile = Some(unprofiled_dims);
+                self.sub_grid_dims = dims;
+            }
+            None => {
+                if let Some(dims) = self.sub_grid_dims_before_profile.take() {
+                    self.sub_grid_dims = dims;
                 }
             }
         }
+        if profile != self.active_profile {
+            println!("Using profile {:?}", profile);
+        }
+        self.active_profile = profile;
+    }
 
-        if let Some(pending_tap_info) = &self.pending_rcmd_single_tap {
-            let single_tap_threshold = Duration::from_millis(RCMD_DOUBLE_TAP_MAX_DELAY_MS as u64 + 30); 
-            if pending_tap_info.tap_time.elapsed() >= single_tap_threshold {
-                println!("Pending RCmd tap timed out. Executing as single tap.");
-                let cursor_pos_opt = pending_tap_info.cursor_pos;
-                self.pending_rcmd_single_tap = None; 
-
-                if !self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
-                    println!("Single RCmd tap action: showing grid");
-                    
-                    self.eframe_control.is_visible.store(true, AtomicOrdering::SeqCst);
-                    self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
-                    if let Some(cursor_pos) = cursor_pos_opt {
-                        println!("Setting OuterPosition near cursor at {:?}", cursor_pos);
-                        ctx.send_viewport_cmd(egui::ViewportCommand::OuterPosition(cursor_pos));
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(true)); 
-                    } else {
-                        println!("No cursor position provided, ensuring maximized on default monitor.");
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Maximized(true));
-                    }
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
-                    ctx.send_viewport_cmd(egui::ViewportCommand::Focus); 
-                    self.initial_focus_requested = true;
-                    self.key_input_buffer.clear();
-                    self.selected_main_cell_index = None;
-                    self.previewed_first_char = None;
-                    self.display_mode = grid::DisplayMode::MainGrid;
-                    self.main_grid_rects.clear();
-                } else {
-                    println!("Single RCmd tap action: app was already visible, hiding instead (or other toggle logic).");
-                    self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
-                }
-            } else {
-                ctx.request_repaint_after(Duration::from_millis(50)); 
+    /// Final point chosen from the grid: either clicks right away or opens the action prompt.
+    fn select_point(&mut self, ctx: &egui::Context, window_relative_point: egui::Pos2) {
+        if let Some(target) = self.copy_target {
+            match target {
+                CopyTarget::Coordinates => self.copy_point_coordinates(ctx, window_relative_point),
+                CopyTarget::Color => self.sample_color_after_hide(ctx, window_relative_point),
             }
+        } else if self.pick_mode == PickMode::Scroll {
+            self.enter_scroll_mode(ctx, window_relative_point);
+        } else if self.pick_mode == PickMode::FineAdjust || self.config.fine_adjust.always {
+            println!("Entering fine adjust at {:?}", window_relative_point);
+            if self.pick_mode == PickMode::FineAdjust {
+                self.pick_mode = PickMode::Click;
+            }
+            self.fine_adjust_global_offset = self.to_global_point(ctx, egui::Pos2::ZERO).unwrap_or_default().to_vec2();
+            self.display_mode = grid::DisplayMode::FineAdjust { point: window_relative_point, origin: window_relative_point };
+        } else {
+            self.commit_point(ctx, window_relative_point);
         }
+    }
 
-        let hide_req = self.eframe_control.hide_requested.load(AtomicOrdering::SeqCst);
-        if hide_req {
-            if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
-                println!("Hiding window");
-                self.eframe_control.is_visible.store(false, AtomicOrdering::SeqCst);
-                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
-                self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
-                self.key_input_buffer.clear();
-                self.selected_main_cell_index = None;
-                self.previewed_first_char = None;
-                self.display_mode = grid::DisplayMode::MainGrid;
-                println!("Hide initiated");
-                self.is_hiding_to_perform_click = self.pending_click_pos_after_hide.is_some();
-                if self.is_hiding_to_perform_click {
-                    self.hide_initiated_at = Some(Instant::now());
-                }
-                return;
-            }
-            else if hide_req { 
-                 self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
-                 if self.pending_click_pos_after_hide.is_some() {
-                    println!("Clearing pending click");
-                    self.pending_click_pos_after_hide = None;
-                 }
-                 self.is_hiding_to_perform_click = false;
-                 self.hide_initiated_at = None;
-                 self.previewed_first_char = None;
-            }
-        }
-
-        if self.is_hiding_to_perform_click {
-            if let Some(initiated_at) = self.hide_initiated_at {
-                if initiated_at.elapsed() >= Duration::from_millis(150) {
-                    if let Some(pos_to_click) = self.pending_click_pos_after_hide.take() {
-                        println!("Performing click at {:?}", pos_to_click);
-                        
-                        #[cfg(target_os = "macos")]
-                        let mut ns_window_ptr_for_mouse_ignore: *mut Object = std::ptr::null_mut();
-                        
-                        #[cfg(target_os = "macos")]
-                        match frame.window_handle() { 
-                            Ok(handle) => match handle.as_raw() {
-                                RawWindowHandle::AppKit(app_kit_handle) => {
-                                    let view_ptr = app_kit_handle.ns_view.as_ptr() as *mut Object;
-                                    unsafe {
-                                        let window_ptr: *mut Object = msg_send![view_ptr, window];
-                                        if !window_ptr.is_null() {
-                                            ns_window_ptr_for_mouse_ignore = window_ptr;
-                                            let _:() = msg_send![ns_window_ptr_for_mouse_ignore, setIgnoresMouseEvents:true];
-                                            println!("Window set to ignore mouse events");
-                                        }
-                                    }
-                                }
-                                _ => {}
-                            }
-                            Err(_) => {}
-                        }
+    /// Point is final: click it, or let the action prompt pick the click kind.
+    fn commit_point(&mut self, ctx: &egui::Context, window_relative_point: egui::Pos2) {
+        if self.pick_mode == PickMode::Drop {
+            self.perform_drop(ctx, window_relative_point);
+        } else if let Some(purpose) = self.two_point.purpose() {
+            self.pick_two_point(ctx, purpose, window_relative_point);
+        } else if self.pick_mode == PickMode::SelectTextStart {
+            println!("Text selection start at {:?}", window_relative_point);
+            self.pick_mode = PickMode::SelectTextEnd;
+            self.perform_mouse_click(ctx, window_relative_point, ClickKind::Double);
+        } else if self.pick_mode == PickMode::SelectTextEnd {
+            println!("Text selection end at {:?}", window_relative_point);
+            self.pick_mode = PickMode::Click;
+            self.perform_mouse_click(ctx, window_relative_point, ClickKind::ShiftLeft);
+        } else if let Some(kind) = self.sub_grid_click_kind.take() {
+            self.perform_mouse_click(ctx, window_relative_point, kind);
+        } else if self.config.actions.enabled {
+            println!("Entering action prompt at {:?}", window_relative_point);
+            self.action_prompt_point = Some(window_relative_point);
+            self.action_prompt_started_at = Some(Instant::now());
+            self.display_mode = grid::DisplayMode::ActionPrompt;
+        } else {
+            self.perform_mouse_click(ctx, window_relative_point, self.default_click_kind);
+        }
+    }
 
-                        let click_point_cg = CGPoint::new(pos_to_click.x as f64, pos_to_click.y as f64);
-                        let (mouse_down_event_type, mouse_up_event_type, button_for_log) = 
-                            if self.lshift_key_is_pressed.load(AtomicOrdering::SeqCst) {
-                                println!("Using right click (shift held)");
-                                (CGEventType::RightMouseDown, CGEventType::RightMouseUp, "Right")
-                            } else {
-                                println!("Using left click");
-                                (CGEventType::LeftMouseDown, CGEventType::LeftMouseUp, "Left")
-                            };
-                        let mouse_button_to_use = if button_for_log == "Right" { CGMouseButton::Right } else { CGMouseButton::Left };
-
-                        match core_graphics::event_source::CGEventSource::new(CGEventSourceStateID::Private) {
-                            Ok(event_source) => {
-                                let mouse_down = CGEvent::new_mouse_event(event_source.clone(), mouse_down_event_type, click_point_cg, mouse_button_to_use);
-                                let mouse_up = CGEvent::new_mouse_event(event_source, mouse_up_event_type, click_point_cg, mouse_button_to_use);
-
-                                if let Ok(down_event) = mouse_down {
-                                    down_event.post(CGEventTapLocation::HID);
-                                    println!("Posted {} click down", button_for_log.to_lowercase());
-                                } else { eprintln!("Failed to create {} click down event", button_for_log.to_lowercase()); }
-
-                                if let Ok(up_event) = mouse_up {
-                                    up_event.post(CGEventTapLocation::HID);
-                                    println!("Posted {} click up", button_for_log.to_lowercase());
-                                } else { eprintln!("Failed to create {} click up event", button_for_log.to_lowercase()); }
-                            }
-                            Err(e) => { eprintln!("Failed to create event source: {:?}", e); }
-                        }
-                        
-                        #[cfg(target_os = "macos")]
-                        if !ns_window_ptr_for_mouse_ignore.is_null() {
-                            unsafe {
-                                let _:() = msg_send![ns_window_ptr_for_mouse_ignore, setIgnoresMouseEvents:false];
-                                println!("Window restored to normal mouse handling");
-                            }
-                        }
-                    }
-                    self.is_hiding_to_perform_click = false;
-                    self.hide_initiated_at = None;
-                    self.pending_click_pos_after_hide = None;
-                    self.previewed_first_char = None;
-                    self.key_input_buffer.clear();
-                    self.selected_main_cell_index = None;
-                    self.display_mode = grid::DisplayMode::MainGrid;
-                    self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
-                    println!("Click sequence complete");
-                } else {
-                    ctx.request_repaint_after(Duration::from_millis(20)); 
-                }
-            } else { 
-                self.is_hiding_to_perform_click = false;
-                self.pending_click_pos_after_hide = None;
-                self.previewed_first_char = None;
-                self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
+    fn run_tap_action(&mut self, ctx: &egui::Context, action: TapAction, cursor_pos: Option<egui::Pos2>) {
+        match action {
+            TapAction::None => {}
+            TapAction::ShowGrid => self.show_grid(ctx, cursor_pos),
+            TapAction::ShowGridRightClick => {
+                self.show_grid(ctx, cursor_pos);
+                self.default_click_kind = ClickKind::Right;
             }
+            TapAction::ShowScrollGrid => {
+                self.show_grid(ctx, cursor_pos);
+                self.pick_mode = PickMode::Scroll;
+            }
+            TapAction::ShowHints => {
+                self.show_grid(ctx, cursor_pos);
+                self.requested_hint_mode = Some(grid::DisplayMode::Hints);
+            }
+            TapAction::ShowMenuHints => {
+                self.show_grid(ctx, cursor_pos);
+                self.requested_hint_mode = Some(grid::DisplayMode::MenuHints);
+            }
+            TapAction::ShowDockHints => {
+                self.show_grid(ctx, cursor_pos);
+                self.requested_hint_mode = Some(grid::DisplayMode::DockHints);
+            }
+            TapAction::ShowWindowHints => {
+                self.show_grid(ctx, cursor_pos);
+                self.requested_hint_mode = Some(grid::DisplayMode::WindowHints);
+            }
+            TapAction::ShowRapidFireGrid => {
+                self.show_grid(ctx, cursor_pos);
+                self.rapid_fire = true;
+            }
+            TapAction::RepeatLastClick => match self.last_click {
+                Some((point, kind)) => click::post_click(point, kind, Duration::from_millis(self.config.click.hold_ms)),
+                None => println!("No click to repeat yet"),
+            },
         }
+    }
 
-        if !self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) && !self.is_hiding_to_perform_click {
-            ctx.request_repaint_after(Duration::from_millis(50));
-            return;
+    fn action_for_char(&self, char_code: char) -> Option<ClickKind> {
+        let actions = &self.config.actions;
+        if char_code == actions.plain_key {
+            Some(ClickKind::Left)
+        } else if char_code == actions.right_key {
+            Some(ClickKind::Right)
+        } else if char_code == actions.middle_key {
+            Some(ClickKind::Middle)
+        } else if char_code == actions.cmd_click_key {
+            Some(ClickKind::CommandLeft)
+        } else if char_code == actions.control_click_key {
+            Some(ClickKind::ControlLeft)
+        } else if char_code == actions.double_key {
+            Some(ClickKind::Double)
+        } else {
+            None
         }
+    }
 
-        #[cfg(target_os = "macos")]
-        if !self.macos_panel_properties_set {
-            match frame.window_handle() {
-                Ok(handle) => match handle.as_raw() {
-                    RawWindowHandle::AppKit(app_kit_handle) => {
-                        let view_ptr = app_kit_handle.ns_view.as_ptr() as *mut Object;
-                        unsafe {
-                            let window_ptr: *mut Object = msg_send![view_ptr, window];
-                            if !window_ptr.is_null() {
-                                let collection_behavior = 
-                                    NSWindowCollectionBehavior::CanJoinAllSpaces |
-                                    NSWindowCollectionBehavior::FullScreenAuxiliary |
-                                    NSWindowCollectionBehavior::Stationary;
-                                let _: () = msg_send![window_ptr, setCollectionBehavior: collection_behavior];
-                                let current_style_mask: NSWindowStyleMask = msg_send![window_ptr, styleMask];
-                                let new_style_mask = current_style_mask.bits() | NSNONACTIVATING_PANEL_MASK as usize;
-                                let _: () = msg_send![window_ptr, setStyleMask: NSWindowStyleMask::from_bits_truncate(new_style_mask)];
-                                println!("Configured window as non-activating panel");
-                                self.macos_panel_properties_set = true;
-                            } else {
-                            }
-                        }
-                    }
-                    _ => { 
-                    }
-                }
-                Err(_) => {
-                }
+    fn total_levels(&self) -> usize {
+        match (self.config.grid.levels, self.extra_level_toggled) {
+            (levels, false) => levels,
+            (3, true) => 2,
+            (_, true) => 3,
+        }
+    }
+
+    fn main_grid_dims(&self, layout_rect: egui::Rect) -> (usize, usize) {
+        let grid_config = self.active_profile.apply_to(&self.config.grid);
+        if !grid_config.auto {
+            return (grid_config.main_cols, grid_config.main_rows);
+        }
+        if let Some(alphabet) = grid_config.label_alphabet() {
+            // Generated labels don't tie a side to an alphabet, only the total to the longest label
+            let max_labels = alphabet.len().pow(grid::MAX_LABEL_LEN as u32);
+            return grid::compute_auto_dims(layout_rect.size(), grid_config.target_cells, max_labels, max_labels, max_labels);
+        }
+        let alphabets = &self.config.label_alphabets;
+        grid::compute_auto_dims(
+            layout_rect.size(),
+            grid_config.target_cells,
+            alphabets.second_chars.len(),
+            alphabets.first_chars.len(),
+            alphabets.first_chars.len() * alphabets.second_chars.len(),
+        )
+    }
+
+    /// How the levels below the main grid are laid out, with this session's sub-grid size.
+    fn refinement_layout(&self) -> RefinementLayout {
+        RefinementLayout {
+            sub_grid_dims: self.sub_grid_dims,
+            auto_sub_grid: self.config.grid.auto_sub_grid,
+            sub_grid_shape: self.config.grid.sub_grid_shape,
+            radial_rings: self.config.grid.radial_rings,
+            radial_spokes: self.config.grid.radial_spokes,
+            deeper_dims: (self.config.grid.third_level_cols, self.config.grid.third_level_rows),
+            alphabets: self.config.label_alphabets.clone(),
+        }
+    }
+
+    /// Picks a cell: shows the next level inside it, or selects its center at the last level.
+    /// A level with a single cell has nothing to choose, so it is passed through.
+    fn pick_cell(&mut self, ctx: &egui::Context, index: usize, rect: egui::Rect) {
+        if self.selection.selected_levels().is_empty() && self.display_mode == grid::DisplayMode::MainGrid {
+            self.last_main_cell_label = self.selection.main_labels().get(index).cloned();
+        }
+        let mut refine = self.selection.level() + 1 < self.total_levels();
+        // Only main cells: deeper levels are small by design, and levels = 3 asked for them
+        if refine && self.selection.selected_levels().is_empty() && rect.width().min(rect.height()) < self.config.grid.min_refine_side {
+            println!("Main cell {:.0}x{:.0} is below grid.min_refine_side, clicking it directly", rect.width(), rect.height());
+            refine = false;
+        }
+        if refine {
+            self.push_level(index);
+            if self.selection.sub_rects().len() == 1 {
+                let only_rect = self.selection.sub_rects()[0];
+                self.pick_cell(ctx, 0, only_rect);
             }
+        } else {
+            let point = self.snap_to_clickable(ctx, rect).unwrap_or(rect.center());
+            self.select_point(ctx, point);
+        }
+    }
+
+    /// Window-relative center of a clickable accessibility element near the center of the picked cell.
+    fn snap_to_clickable(&self, ctx: &egui::Context, rect: egui::Rect) -> Option<egui::Pos2> {
+        if !self.config.click.snap_to_elements {
+            return None;
+        }
+        #[cfg(target_os = "macos")]
+        {
+            let offset = self.to_global_point(ctx, egui::Pos2::ZERO)?.to_vec2();
+            let max_distance = rect.width().max(rect.height()) / 2.0;
+            let snapped = ax::clickable_near(self.previous_frontmost_pid, click::to_cg_point(rect.center() + offset), max_distance as f64)?;
+            Some(egui::pos2(snapped.x as f32, snapped.y as f32) - offset)
         }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = (ctx, rect);
+            None
+        }
+    }
 
-        let current_content_rect = egui::Rect::from_min_size(egui::Pos2::ZERO, ctx.screen_rect().size());
-        if self.main_grid_rects.is_empty() || self.last_layout_screen_rect != current_content_rect {
-            println!("Recalculating layout");
-            let (labels, rects) = grid::generate_main_grid_layout(MAIN_GRID_COLS, MAIN_GRID_ROWS, current_content_rect);
-            self.main_grid_labels = labels;
-            self.main_grid_rects = rects;
-            self.last_layout_screen_rect = current_content_rect;
+    /// Sub-cell covering the spot last picked in the selected main cell, if that was remembered.
+    fn remembered_sub_cell(&self) -> Option<usize> {
+        let &[main_cell] = self.selection.selected_levels() else {
+            return None;
+        };
+        let point = self.sub_cell_memory.recall(main_cell.index, main_cell.rect)?;
+        self.selection.sub_rects().iter().position(|rect| rect.contains(point))
+    }
 
-            if self.display_mode == grid::DisplayMode::SubGrid {
-                 if let Some(main_idx) = self.selected_main_cell_index {
-                    if main_idx < self.main_grid_rects.len() {
-                        let selected_main_rect = self.main_grid_rects[main_idx];
-                        let (sg_labels, sg_rects) = grid::generate_sub_grid_layout(selected_main_rect, SUB_GRID_COLS, SUB_GRID_ROWS);
-                        self.sub_grid_labels = sg_labels;
-                        self.sub_grid_rects = sg_rects;
-                    } else { self.display_mode = grid::DisplayMode::MainGrid; } 
-                 } else { self.display_mode = grid::DisplayMode::MainGrid; } 
+    /// Records a pick in the first sub-grid; deeper levels are not remembered.
+    fn remember_sub_cell(&mut self, sub_index: usize) {
+        if !self.config.grid.remember_sub_cells {
+            return;
+        }
+        let &[main_cell] = self.selection.selected_levels() else {
+            return;
+        };
+        if let Some(sub_rect) = self.selection.sub_rects().get(sub_index) {
+            self.sub_cell_memory.remember(main_cell.index, main_cell.rect, sub_rect.center());
+        }
+    }
+
+    fn forget_sub_cells(&mut self) {
+        println!("Forgetting remembered sub-cells");
+        self.sub_cell_memory.clear();
+    }
+
+    /// The screen rect is degenerate: retry with backoff, and hide with an error once it stays so too long.
+    fn wait_for_layout(&mut self, ctx: &egui::Context, screen_rect: egui::Rect) {
+        let (since, delay) = *self.layout_wait.get_or_insert_with(|| {
+            println!("Screen rect {:?} can't hold a grid, waiting for the display", screen_rect);
+            (Instant::now(), Duration::from_millis(LAYOUT_RETRY_MIN_MS))
+        });
+        self.selection.clear_main_grid();
+        if since.elapsed() >= Duration::from_millis(LAYOUT_TIMEOUT_MS) {
+            eprintln!("No usable screen rect after {} ms, hiding the overlay", LAYOUT_TIMEOUT_MS);
+            self.layout_wait = None;
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            ctx.request_repaint();
+            return;
+        }
+        ctx.request_repaint_after(delay);
+        self.layout_wait = Some((since, (delay * 2).min(Duration::from_millis(LAYOUT_RETRY_MAX_MS))));
+    }
+
+    /// Reopens the sub-grid of the main cell picked last, so only the sub-cell is left to type.
+    fn resume_last_region(&mut self) {
+        if self.display_mode != grid::DisplayMode::MainGrid || self.total_levels() < 2 {
+            return;
+        }
+        let Some(label) = self.last_main_cell_label.clone() else {
+            return;
+        };
+        match self.selection.main_labels().iter().position(|main_label| *main_label == label) {
+            Some(index) if self.selection.is_main_cell_pickable(index) => {
+                println!("Resuming in main cell {}", label);
+                self.push_level(index);
             }
+            _ => println!("Last main cell {} is not on this grid, starting from the main grid", label),
         }
-        
-        if self.display_mode == grid::DisplayMode::MainGrid {
-            let events = ctx.input(|i| i.events.clone());
-            for event in events {
-                if let egui::Event::Key { key, pressed: true, .. } = event {
-                    if let Some(char_code) = key_to_char(key, Default::default()) {
-                        self.key_input_buffer.push(char_code);
-                        if self.key_input_buffer.len() == 1 {
-                            if self.main_grid_labels.iter().any(|lab| lab.starts_with(char_code)) {
-                                self.previewed_first_char = Some(char_code);
-                            } else {
-                                self.key_input_buffer.clear();
-                                self.previewed_first_char = None;
-                            }
-                        } else if self.key_input_buffer.len() == 2 {
-                            self.previewed_first_char = None;
-                            if let Some(index) = self.main_grid_labels.iter().position(|label| *label == self.key_input_buffer) {
-                                self.selected_main_cell_index = Some(index);
-                                self.display_mode = grid::DisplayMode::SubGrid;
-                                self.key_input_buffer.clear();
-                                 if let Some(main_idx) = self.selected_main_cell_index { 
-                                    if main_idx < self.main_grid_rects.len() {
-                                        let selected_main_rect = self.main_grid_rects[main_idx];
-                                        let (sg_labels, sg_rects) = grid::generate_sub_grid_layout(selected_main_rect, SUB_GRID_COLS, SUB_GRID_ROWS);
-                                        self.sub_grid_labels = sg_labels;
-                                        self.sub_grid_rects = sg_rects;
-                                    } else { self.display_mode = grid::DisplayMode::MainGrid;}
-                                 } else { self.display_mode = grid::DisplayMode::MainGrid;}
-                            } else {
-                                self.key_input_buffer.clear();
-                                self.previewed_first_char = None;
-                            }
-                        } else if key == egui::Key::Escape {
-                            self.key_input_buffer.clear();
-                            self.previewed_first_char = None;
-                        }
-                    }
+    }
+
+    fn change_sub_grid_density(&mut self, delta: isize) {
+        let (cols, rows) = self.sub_grid_dims;
+        let resize = |side: usize| side.saturating_add_signed(delta).clamp(1, MAX_SUB_GRID_SIDE);
+        let new_dims = (resize(cols), resize(rows));
+        if new_dims == self.sub_grid_dims {
+            return;
+        }
+        self.sub_grid_dims = new_dims;
+        println!("Sub-grid is now {}x{}", new_dims.0, new_dims.1);
+        self.clear_typed();
+        self.relayout_levels();
+        // A profile's sub-grid size isn't the global one, so it is never written back
+        if self.config.grid.persist_sub_grid && self.sub_grid_dims_before_profile.is_none()
+            && let Err(e) = config::save_sub_grid_dims(new_dims.0, new_dims.1)
+        {
+            eprintln!("Failed to save sub-grid size: {}", e);
+        }
+    }
+
+    /// Feeds this frame's typing to the hint labels. Back deletes a letter, or leaves the hints
+    /// when nothing is typed. Returns the hint whose label was typed in full.
+    fn type_hint_label(&mut self, ctx: &egui::Context) -> Option<usize> {
+        let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+        for (key, typed_char) in presses {
+            if self.config.key_bindings.is(key, Action::Back) {
+                if self.key_input_buffer.pop().is_none() {
+                    self.go_back();
+                    return None;
                 }
+                continue;
             }
-        } else if self.display_mode == grid::DisplayMode::SubGrid {
-            self.previewed_first_char = None;
-            let events = ctx.input(|i| i.events.clone());
-            for event in events {
-                if let egui::Event::Key { key, pressed: true, .. } = event {
-                    if key == egui::Key::Space { 
-                        if let Some(main_idx) = self.selected_main_cell_index {
-                            if main_idx < self.main_grid_rects.len() {
-                                self.perform_mouse_click(ctx, self.main_grid_rects[main_idx].center());
-                                break;
-                            }
-                        }
-                    }
-                    if let Some(char_code) = key_to_char(key, Default::default()) {
-                        if let Some(sub_idx) = self.sub_grid_labels.iter().position(|label| *label == char_code.to_string()) {
-                            if sub_idx < self.sub_grid_rects.len() {
-                                self.perform_mouse_click(ctx, self.sub_grid_rects[sub_idx].center());
-                                break;
-                            }
-                        }
+            if let Some(char_code) = typed_char {
+                self.key_input_buffer.push(char_code);
+                match grid::match_label(&self.hint_labels, &self.key_input_buffer, |index| index < self.hint_rects.len()) {
+                    grid::LabelMatch::Exact(index) => {
+                        self.key_input_buffer.clear();
+                        return Some(index);
                     }
+                    grid::LabelMatch::Prefix => {}
+                    grid::LabelMatch::None => self.key_input_buffer.clear(),
                 }
             }
         }
+        None
+    }
 
-        egui::CentralPanel::default()
-            .frame(egui::Frame::none().fill(egui::Color32::TRANSPARENT))
-            .show(ctx, |ui| {
-                let painter = ui.painter();
-                let main_cell_bg_color = egui::Color32::from_rgba_unmultiplied(50, 50, 50, 120); 
-                let line_stroke = egui::Stroke::new(0.5, egui::Color32::from_rgba_unmultiplied(200, 200, 200, 100)); 
-                let text_color = egui::Color32::from_rgba_unmultiplied(255, 255, 255, 200); 
-                let preview_highlight_color = egui::Color32::from_rgba_unmultiplied(80, 120, 80, 150);
-
-                if !self.main_grid_rects.is_empty() {
-                    for (index, rect) in self.main_grid_rects.iter().enumerate() {
-                        let mut current_bg_color = main_cell_bg_color;
-                        if self.display_mode == grid::DisplayMode::SubGrid && Some(index) != self.selected_main_cell_index {
-                            current_bg_color = egui::Color32::from_rgba_unmultiplied(30, 30, 30, 70);
-                        } else if self.display_mode == grid::DisplayMode::MainGrid {
-                            if let Some(preview_char) = self.previewed_first_char {
-                                if index < self.main_grid_labels.len() && self.main_grid_labels[index].starts_with(preview_char) {
-                                    current_bg_color = preview_highlight_color;
-                                }
-                            }
-                        }
-                        painter.rect_filled(*rect, 0.0, current_bg_color);
-                        painter.rect_stroke(*rect, 0.0, line_stroke);
-                        if index < self.main_grid_labels.len() {
-                            let cell_center = rect.center();
-                            let font_size = rect.height().min(rect.width()) * 0.4;
-                            painter.text(cell_center, egui::Align2::CENTER_CENTER, &self.main_grid_labels[index], egui::FontId::proportional(font_size), text_color);
-                        }
-                    }
-                } else if self.display_mode == grid::DisplayMode::MainGrid {
-                     painter.text(ctx.screen_rect().center(), egui::Align2::CENTER_CENTER, "Waiting for layout...", egui::FontId::default(), text_color);
-                }
+    /// Drops the label typed so far, in the grid and in the hint modes.
+    fn clear_typed(&mut self) {
+        self.key_input_buffer.clear();
+        self.selection.clear_typed();
+    }
 
-                if self.display_mode == grid::DisplayMode::SubGrid {
-                    if self.sub_grid_rects.is_empty() && self.selected_main_cell_index.is_some() {
-                         if let Some(idx) = self.selected_main_cell_index {
-                            if idx < self.main_grid_rects.len() {
-                                 let selected_rect = self.main_grid_rects[idx];
-                                 painter.text(selected_rect.center(), egui::Align2::CENTER_CENTER, "Waiting for sub-layout...", egui::FontId::proportional(selected_rect.height() * 0.15), egui::Color32::YELLOW);
-                            }
-                        }
-                    } else {
-                        let sub_cell_bg_color = egui::Color32::from_rgba_unmultiplied(70, 70, 20, 160); 
-                        let sub_text_color = egui::Color32::WHITE; 
-                        for (index, rect) in self.sub_grid_rects.iter().enumerate() {
-                            painter.rect_filled(*rect, 0.0, sub_cell_bg_color);
-                            painter.rect_stroke(*rect, 0.0, line_stroke);
-                            if index < self.sub_grid_labels.len() {
-                                let cell_center = rect.center();
-                                let font_size = rect.height().min(rect.width()) * 0.5;
-                                painter.text(cell_center, egui::Align2::CENTER_CENTER, &self.sub_grid_labels[index], egui::FontId::proportional(font_size), sub_text_color);
-                            }
-                        }
-                    }
-                }
-            });
-        ctx.request_repaint();
+    fn push_level(&mut self, index: usize) {
+        let layout = self.refinement_layout();
+        if let Err(e) = self.selection.descend(index, &layout) {
+            eprintln!("Failed to lay out sub-grid: {}", e);
+        }
+        self.previewed_prefix = None;
+        self.display_mode = grid::DisplayMode::SubGrid;
+        feedback::play(Cue::SubGridEntered, &self.config.feedback);
+        println!("Picked cell {} at level {}", index, self.selection.level());
     }
-    
-    fn save(&mut self, _storage: &mut dyn eframe::Storage) {}
 
-    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
-        [0.0, 0.0, 0.0, 0.0]
+    /// Re-derives the picked rects from their indices after the main grid was laid out again.
+    fn relayout_levels(&mut self) {
+        let layout = self.refinement_layout();
+        if let Err(e) = self.selection.relayout(&layout) {
+            eprintln!("Failed to lay out sub-grid: {}", e);
+        }
+        if self.selection.selected_levels().is_empty() && self.display_mode == grid::DisplayMode::SubGrid {
+            self.display_mode = grid::DisplayMode::MainGrid;
+        }
+    }
+
+    /// Stage the point was picked in, for leaving fine adjust or the action prompt.
+    fn selection_stage(&self) -> grid::DisplayMode {
+        if !self.hint_rects.is_empty() {
+            grid::DisplayMode::Hints
+        } else if self.bisect_regions.is_empty() {
+            grid::DisplayMode::SubGrid
+        } else {
+            grid::DisplayMode::Bisect
+        }
     }
-} 
\ No newline at end of file
+
+    /// Backs out one selection level. Returns false when already at the main grid.
+    fn go_back(&mut self) -> bool {
+        if self.measurement.is_some() || self.sampled_color.is_some() {
+            // The readout is the end of measuring or sampling; Escape closes the overlay with it
+            return false;
+        }
+        if self.bookmark_prompt.take().is_some() {
+            println!("Bookmark prompt closed");
+            return true;
+        }
+        if let Some(prompt) = self.key_prompt.take() {
+            println!("{} closed", prompt.label());
+            return true;
+        }
+        match self.display_mode {
+            grid::DisplayMode::MainGrid => {
+                if self.selected_display_index.take().is_some() {
+                    println!("Clearing display selection");
+                    self.clear_typed();
+                    self.previewed_prefix = None;
+                    self.selection.clear_main_grid();
+                    true
+                } else {
+                    false
+                }
+            }
+            grid::DisplayMode::SubGrid => {
+                self.sub_grid_click_kind = None;
+                let layout = self.refinement_layout();
+                if let Err(e) = self.selection.ascend(&layout) {
+                    eprintln!("Failed to lay out sub-grid: {}", e);
+                }
+                if self.selection.selected_levels().is_empty() {
+                    println!("Returning to main grid");
+                    self.display_mode = grid::DisplayMode::MainGrid;
+                } else {
+                    println!("Returning to level {}", self.selection.level() + 1);
+                }
+                self.previewed_prefix = None;
+                true
+            }
+            grid::DisplayMode::Scroll | grid::DisplayMode::Pointer => false,
+            grid::DisplayMode::Hints | grid::DisplayMode::DockHints | grid::DisplayMode::WindowHints => {
+                println!("Returning to main grid");
+                self.clear_hints();
+                self.clear_typed();
+                self.display_mode = grid::DisplayMode::MainGrid;
+                true
+            }
+            grid::DisplayMode::MenuHints => {
+                self.menu_opened_at = None;
+                #[cfg(target_os = "macos")]
+                if self.menu_walker.as_mut().is_some_and(|walker| walker.back()) {
+                    // Relabel once the closed menu is gone
+                    println!("Closed a menu");
+                    self.hint_labels.clear();
+                    self.hint_rects.clear();
+                    self.menu_opened_at = Some(Instant::now());
+                    return true;
+                }
+                println!("Returning to main grid");
+                self.close_menus();
+                self.clear_hints();
+                self.clear_typed();
+                self.display_mode = grid::DisplayMode::MainGrid;
+                true
+            }
+            grid::DisplayMode::Bisect => {
+                if self.bisect_regions.back() {
+                    println!("Bisect zoomed out to depth {}", self.bisect_regions.depth());
+                    true
+                } else if self.config.bisect.default_mode {
+                    false
+                } else {
+                    println!("Returning to main grid");
+                    self.bisect_regions.clear();
+                    self.display_mode = grid::DisplayMode::MainGrid;
+                    true
+                }
+            }
+            grid::DisplayMode::FineAdjust { point, origin } => {
+                if point != origin {
+                    println!("Resetting fine adjust to the unadjusted point");
+                    self.display_mode = grid::DisplayMode::FineAdjust { point: origin, origin };
+                } else {
+                    self.display_mode = self.selection_stage();
+                    println!("Returning to {:?}", self.display_mode);
+                }
+                true
+            }
+            grid::DisplayMode::ActionPrompt => {
+                self.display_mode = self.selection_stage();
+                println!("Returning to {:?}", self.display_mode);
+                self.action_prompt_point = None;
+                self.action_prompt_started_at = None;
+                true
+            }
+        }
+    }
+
+    /// Hands keyboard focus back to the app that was frontmost when the grid was shown,
+    /// unless something else (e.g. the clicked window's app) has already taken it.
+    fn restore_previous_focus(&mut self) {
+        #[cfg(target_os = "macos")]
+        if let Some(pid) = self.previous_frontmost_pid.take()
+            && macos::frontmost_app_pid() == Some(macos::own_pid())
+        {
+            if macos::activate_app(pid) {
+                println!("Re-activated previously frontmost app (pid {})", pid);
+            } else {
+                eprintln!("Failed to re-activate app with pid {}", pid);
+            }
+        }
+    }
+
+    fn all_displays_active(&self) -> bool {
+        self.config.displays.all_displays && !self.overlay_displays.is_empty()
+    }
+
+    /// Display picked with the number prefix, when it isn't the one the main window sits on.
+    fn selected_secondary_display(&self) -> Option<&OverlayDisplay> {
+        match self.selected_display_index {
+            Some(index) if Some(index) != self.home_display_index => self.overlay_displays.get(index),
+            _ => None,
+        }
+    }
+
+    fn select_display(&mut self, display_number: usize) {
+        let index = display_number - 1;
+        if index >= self.overlay_displays.len() {
+            println!("No display {}", display_number);
+            return;
+        }
+        println!("Selected display {}", display_number);
+        self.selected_display_index = Some(index);
+        self.clear_typed();
+        self.previewed_prefix = None;
+        self.selection.clear_main_grid();
+    }
+
+    /// Rect the main grid is laid out in, local to whichever display is receiving keys.
+    fn active_layout_rect(&self, ctx: &egui::Context) -> egui::Rect {
+        match self.selected_secondary_display() {
+            Some(display) => egui::Rect::from_min_size(egui::Pos2::ZERO, display.frame.size()),
+            None => {
+                let mut rect = egui::Rect::from_min_size(egui::Pos2::ZERO, ctx.screen_rect().size());
+                if self.config.displays.skip_menu_bar {
+                    rect.min.y = self.screen_top_insets.menu_bar.min(rect.max.y - 1.0).max(rect.min.y);
+                }
+                let window_rect = self.focused_window_frame
+                    .zip(self.to_global_point(ctx, egui::Pos2::ZERO))
+                    .map(|(frame, origin)| frame.translate(-origin.to_vec2()).intersect(rect))
+                    .filter(|window_rect| window_rect.width() >= MIN_FOCUSED_WINDOW_SIDE && window_rect.height() >= MIN_FOCUSED_WINDOW_SIDE);
+                window_rect.unwrap_or(rect)
+            }
+        }
+    }
+
+    /// `displays.exclude` in the coordinates of a window whose top-left is at global `origin`.
+    fn excluded_regions(&self, origin: Option<egui::Pos2>) -> Vec<egui::Rect> {
+        let Some(origin) = origin else {
+            return Vec::new();
+        };
+        self.config.displays.exclude.iter().map(|region| region.rect().translate(-origin.to_vec2())).collect()
+    }
+
+    /// Grid shown on displays that aren't receiving keys: labels only, dimmed once another display is chosen.
+    fn paint_passive_display(&self, painter: &egui::Painter, screen_rect: egui::Rect, origin: Option<egui::Pos2>) {
+        let local_rect = egui::Rect::from_min_size(egui::Pos2::ZERO, screen_rect.size());
+        let (cols, rows) = self.main_grid_dims(local_rect);
+        let alphabet = self.config.grid.label_alphabet();
+        let Ok((labels, rects)) = grid::generate_main_grid_layout(cols, rows, local_rect, &self.config.label_alphabets, alphabet.as_deref()) else {
+            return;
+        };
+        let (labels, rects) = grid::exclude_cells(labels, rects, &self.excluded_regions(origin));
+        let theme = &self.config.appearance;
+        let dimmed = self.selected_display_index.is_some();
+        let bg_color = if dimmed { theme.dimmed_fill } else { theme.main_cell_fill };
+        let text_color = if dimmed { theme.label_color.gamma_multiply(0.4) } else { theme.label_color };
+        for (rect, label) in rects.iter().zip(labels.iter()) {
+            painter.rect_filled(*rect, 0.0, bg_color);
+            painter.rect_stroke(*rect, 0.0, theme.grid_stroke);
+            let (anchor, align, font_size) = grid::label_anchor(*rect, local_rect, self.config.theme.label_position, rect.height().min(rect.width()) * 0.4 * theme.label_scale);
+            paint_label(painter, anchor, align, label, label_font::label_font(font_size), text_color, self.config.theme.label_outline);
+        }
+    }
+
+    fn paint_display_badge(&self, painter: &egui::Painter, screen_rect: egui::Rect, index: usize) {
+        let is_selected = self.selected_display_index == Some(index);
+        let badge_rect = egui::Rect::from_min_size(screen_rect.min + egui::vec2(16.0, 16.0), egui::vec2(56.0, 56.0));
+        let badge_fill = if is_selected {
+            egui::Color32::from_rgba_unmultiplied(80, 120, 80, 230)
+        } else {
+            egui::Color32::from_rgba_unmultiplied(20, 20, 20, 200)
+        };
+        painter.rect_filled(badge_rect, 8.0, badge_fill);
+        painter.text(badge_rect.center(), egui::Align2::CENTER_CENTER, (index + 1).to_string(), egui::FontId::proportional(36.0), egui::Color32::WHITE);
+    }
+
+    /// Overlay opacity, easing toward fully shown or fully hidden over FADE_SECS.
+    fn fade_opacity(&self, ctx: &egui::Context, shown: bool) -> f32 {
+        ctx.animate_value_with_time(egui::Id::new("mouseless-overlay-fade"), if shown { 1.0 } else { 0.0 }, FADE_SECS)
+    }
+
+    fn paint_displays(&self, ctx: &egui::Context, opacity: f32) {
+        let opacity = opacity * self.overlay_opacity.value();
+        if self.all_displays_active() {
+            self.show_secondary_display_viewports(ctx, opacity);
+        }
+
+        egui::CentralPanel::default()
+            .frame(egui::Frame::none().fill(egui::Color32::TRANSPARENT))
+            .show(ctx, |ui| {
+                let mut painter = ui.painter().clone();
+                painter.set_opacity(opacity);
+                if self.selected_secondary_display().is_some() {
+                    let origin = ctx.input(|i| i.viewport().outer_rect).map(|rect| rect.min);
+                    self.paint_passive_display(&painter, ctx.screen_rect(), origin);
+                } else {
+                    self.paint_overlay(&painter, ctx.screen_rect());
+                    self.paint_hud(&painter, ctx.screen_rect());
+                    self.paint_opacity_flash(&painter, ctx.screen_rect());
+                    self.paint_bookmarks(&painter, ctx.screen_rect());
+                    self.paint_quick_jump(&painter, ctx.screen_rect());
+                    self.paint_window_snap(&painter, ctx.screen_rect());
+                    self.paint_two_point(&painter, ctx.screen_rect());
+                    if self.tutorial_visible {
+                        tutorial::paint(&painter, ctx.screen_rect(), &self.tutorial_lines());
+                    }
+                }
+                if self.all_displays_active()
+                    && let Some(home_index) = self.home_display_index
+                {
+                    self.paint_display_badge(&painter, ctx.screen_rect(), home_index);
+                }
+            });
+    }
+
+    fn show_secondary_display_viewports(&self, ctx: &egui::Context, opacity: f32) {
+        for (index, display) in self.overlay_displays.iter().enumerate() {
+            if Some(index) == self.home_display_index {
+                continue;
+            }
+            let viewport_id = egui::ViewportId::from_hash_of(("mouseless-display", display.id));
+            let builder = egui::ViewportBuilder::default()
+                .with_title("Mouseless Overlay")
+                .with_position(display.frame.min)
+                .with_inner_size(display.frame.size())
+                .with_transparent(true)
+                .with_decorations(false)
+                .with_always_on_top()
+                .with_mouse_passthrough(true);
+            ctx.show_viewport_immediate(viewport_id, builder, |display_ctx, _class| {
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::none().fill(egui::Color32::TRANSPARENT))
+                    .show(display_ctx, |ui| {
+                        let screen_rect = display_ctx.screen_rect();
+                        let mut painter = ui.painter().clone();
+                        painter.set_opacity(opacity);
+                        if self.selected_display_index == Some(index) {
+                            self.paint_overlay(&painter, screen_rect);
+                            self.paint_hud(&painter, screen_rect);
+                            self.paint_quick_jump(&painter, screen_rect);
+                            self.paint_window_snap(&painter, screen_rect);
+                            self.paint_two_point(&painter, screen_rect);
+                        } else {
+                            self.paint_passive_display(&painter, screen_rect, Some(display.frame.min));
+                        }
+                        self.paint_display_badge(&painter, screen_rect, index);
+                    });
+            });
+        }
+    }
+
+    fn paint_fine_adjust(&self, painter: &egui::Painter, screen_rect: egui::Rect, point: egui::Pos2) {
+        // Dim everything but a window around the crosshair so the target itself stays readable
+        let clear_zone = egui::Rect::from_center_size(point, egui::vec2(120.0, 120.0)).intersect(screen_rect);
+        paint_dimmed_outside(painter, screen_rect, clear_zone, egui::Color32::from_rgba_unmultiplied(20, 20, 20, 150));
+        painter.rect_stroke(clear_zone, 0.0, egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(200, 200, 200, 120)));
+
+        let crosshair_stroke = egui::Stroke::new(1.0, egui::Color32::from_rgb(255, 60, 60));
+        painter.line_segment([point - egui::vec2(12.0, 0.0), point - egui::vec2(3.0, 0.0)], crosshair_stroke);
+        painter.line_segment([point + egui::vec2(3.0, 0.0), point + egui::vec2(12.0, 0.0)], crosshair_stroke);
+        painter.line_segment([point - egui::vec2(0.0, 12.0), point - egui::vec2(0.0, 3.0)], crosshair_stroke);
+        painter.line_segment([point + egui::vec2(0.0, 3.0), point + egui::vec2(0.0, 12.0)], crosshair_stroke);
+
+        let global_point = point + self.fine_adjust_global_offset;
+        let readout = format!("({:.0}, {:.0})   arrows nudge, shift ×5 · Enter click · Esc reset", global_point.x, global_point.y);
+        let readout_anchor = egui::pos2(point.x, clear_zone.max.y + 6.0);
+        painter.text(readout_anchor, egui::Align2::CENTER_TOP, readout, egui::FontId::monospace(13.0), egui::Color32::WHITE);
+    }
+
+    fn paint_bisect(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        let Some(region) = self.bisect_regions.current() else {
+            return;
+        };
+        paint_dimmed_outside(painter, screen_rect, region, egui::Color32::from_rgba_unmultiplied(20, 20, 20, 150));
+        painter.rect_stroke(region, 0.0, egui::Stroke::new(2.0, egui::Color32::YELLOW));
+
+        let center = region.center();
+        let guide_stroke = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(255, 255, 255, 140));
+        painter.line_segment([egui::pos2(center.x, region.min.y), egui::pos2(center.x, region.max.y)], guide_stroke);
+        painter.line_segment([egui::pos2(region.min.x, center.y), egui::pos2(region.max.x, center.y)], guide_stroke);
+
+        // Quadrant keys only while there's room; after a few zooms the guides alone are enough
+        let font_size = (region.width().min(region.height()) * 0.2).min(48.0);
+        if font_size >= 10.0 {
+            for quadrant in Quadrant::ALL {
+                painter.text(
+                    quadrant.of(region).center(),
+                    egui::Align2::CENTER_CENTER,
+                    self.config.bisect.key_for(quadrant).to_string(),
+                    label_font::label_font(font_size),
+                    egui::Color32::from_rgba_unmultiplied(255, 255, 255, 180),
+                );
+            }
+        }
+        painter.circle_filled(center, 3.0, egui::Color32::from_rgb(255, 60, 60));
+
+        let bisect = &self.config.bisect;
+        paint_hint_pill(painter, screen_rect, format!(
+            "BISECT   {} {} {} {} zoom · Enter click · Backspace zoom out   {:.0}×{:.0} px",
+            bisect.top_left_key, bisect.top_right_key, bisect.bottom_left_key, bisect.bottom_right_key,
+            region.width(), region.height()
+        ));
+    }
+
+    /// A badge at the top-left of each element whose label still matches what was typed.
+    fn paint_hints(&self, painter: &egui::Painter, screen_rect: egui::Rect, legend: &str) {
+        for (label, rect) in self.hint_labels.iter().zip(self.hint_rects.iter()) {
+            let Some(rest) = label.strip_prefix(self.key_input_buffer.as_str()) else {
+                continue;
+            };
+            painter.rect_stroke(*rect, 2.0, egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(255, 210, 0, 120)));
+            let galley = painter.layout_no_wrap(rest.to_string(), egui::FontId::monospace(13.0), egui::Color32::BLACK);
+            let badge = egui::Rect::from_min_size(rect.min, galley.size() + egui::vec2(8.0, 4.0));
+            let badge = badge.translate(screen_rect.clamp(badge.max) - badge.max);
+            painter.rect_filled(badge, 3.0, egui::Color32::from_rgb(255, 210, 0));
+            painter.rect_stroke(badge, 3.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(120, 90, 0)));
+            painter.galley(badge.min + egui::vec2(4.0, 2.0), galley, egui::Color32::BLACK);
+        }
+        paint_hint_pill(painter, screen_rect, legend.to_string());
+    }
+
+    /// A large label at each window's center; windows on other displays are labeled at the edge facing them.
+    fn paint_window_hints(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        let anchors = window_picker::hint_anchors(&self.hint_rects, screen_rect);
+        for (index, (label, anchor)) in self.hint_labels.iter().zip(anchors).enumerate() {
+            let Some(rest) = label.strip_prefix(self.key_input_buffer.as_str()) else {
+                continue;
+            };
+            if screen_rect.contains(self.hint_rects[index].center()) {
+                painter.rect_stroke(self.hint_rects[index], 4.0, egui::Stroke::new(2.0, egui::Color32::from_rgba_unmultiplied(255, 210, 0, 120)));
+            }
+            let galley = painter.layout_no_wrap(rest.to_string(), egui::FontId::monospace(28.0), egui::Color32::BLACK);
+            let badge = egui::Rect::from_center_size(anchor, galley.size() + egui::vec2(16.0, 8.0));
+            painter.rect_filled(badge, 6.0, egui::Color32::from_rgb(255, 210, 0));
+            painter.rect_stroke(badge, 6.0, egui::Stroke::new(1.0, egui::Color32::from_rgb(120, 90, 0)));
+            painter.galley(badge.center() - galley.size() / 2.0, galley, egui::Color32::BLACK);
+            #[cfg(target_os = "macos")]
+            if let Some(window) = self.picker_windows.get(index) {
+                paint_label(painter, badge.center_bottom() + egui::vec2(0.0, 4.0), egui::Align2::CENTER_TOP, &window.owner, egui::FontId::proportional(13.0), egui::Color32::WHITE, true);
+            }
+        }
+        paint_hint_pill(painter, screen_rect, "WINDOWS   type a label to pick a window · Backspace back · Esc exit".to_string());
+    }
+
+    /// The overlay's new opacity for a moment after it changed, whether or not the HUD is on.
+    fn paint_opacity_flash(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        let Some(changed_at) = self.opacity_flash else {
+            return;
+        };
+        let remaining = Duration::from_millis(OPACITY_FLASH_MS).saturating_sub(changed_at.elapsed());
+        if !remaining.is_zero() {
+            paint_hint_pill(painter, screen_rect, format!("Opacity {}%", self.overlay_opacity.percent()));
+            painter.ctx().request_repaint_after(remaining);
+        }
+    }
+
+    /// Pins for this display's bookmarks while the bookmark key waits for a name, and the save confirmation.
+    fn paint_bookmarks(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        if let Some((text, saved_at)) = &self.bookmark_flash
+            && saved_at.elapsed() < Duration::from_millis(BOOKMARK_FLASH_MS)
+        {
+            paint_hint_pill(painter, screen_rect, text.clone());
+        }
+        let Some(prompt) = self.bookmark_prompt else {
+            return;
+        };
+        let offset = self.to_global_point(painter.ctx(), egui::Pos2::ZERO).unwrap_or_default().to_vec2();
+        let app = self.frontmost_bundle_id.as_deref();
+        for (name, point, namespace) in self.bookmarks.list(app, &self.bookmark_displays) {
+            let point = point - offset;
+            if !screen_rect.contains(point) {
+                continue;
+            }
+            let color = match namespace {
+                Namespace::App(_) => APP_BOOKMARK_COLOR,
+                Namespace::Global => GLOBAL_BOOKMARK_COLOR,
+            };
+            painter.circle_filled(point, 4.0, color);
+            paint_label(painter, point + egui::vec2(6.0, -6.0), egui::Align2::LEFT_BOTTOM, &name.to_string(), egui::FontId::monospace(14.0), egui::Color32::WHITE, true);
+        }
+        let mut legend = match prompt {
+            BookmarkPrompt::Recall => "BOOKMARK   type a name to jump there · bookmark key again: save the cursor · Esc cancel",
+            BookmarkPrompt::SaveCursor | BookmarkPrompt::SavePoint(_) if app.is_some() => "SAVE BOOKMARK   type a name for this app (orange) · Shift+name: global (blue) · Backspace: delete one instead · Esc cancel",
+            BookmarkPrompt::SaveCursor | BookmarkPrompt::SavePoint(_) => "SAVE BOOKMARK   type a name (global) · Backspace: delete one instead · Esc cancel",
+            BookmarkPrompt::Delete => "DELETE BOOKMARK   type a name · Esc cancel",
+        }.to_string();
+        let unavailable = self.bookmarks.unavailable(app, &self.bookmark_displays);
+        if prompt == BookmarkPrompt::Recall && !unavailable.is_empty() {
+            legend.push_str(&format!(" · display not connected: {}", unavailable.iter().collect::<String>()));
+        }
+        paint_hint_pill(painter, screen_rect, legend);
+    }
+
+    /// From the first pick to the current candidate (the picked cell's center, or the fine adjust
+    /// target): a region while picking a screenshot, a segment while measuring. Then the measurement.
+    fn paint_two_point(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        let offset = self.to_global_point(painter.ctx(), egui::Pos2::ZERO).unwrap_or_default().to_vec2();
+        let stroke = egui::Stroke::new(2.0, egui::Color32::from_rgb(120, 200, 255));
+        if let Some(measurement) = &self.measurement {
+            let (start, end) = (measurement.start - offset, measurement.end - offset);
+            painter.line_segment([start, end], stroke);
+            painter.circle_stroke(start, 4.0, stroke);
+            painter.circle_stroke(end, 4.0, stroke);
+            paint_hint_pill(painter, screen_rect, format!("{}   copied · Esc close", measurement.summary()));
+            return;
+        }
+        let (Some(purpose), Some(start)) = (self.two_point.purpose(), self.two_point.first()) else {
+            return;
+        };
+        let start = start - offset;
+        let candidate = match self.display_mode {
+            grid::DisplayMode::FineAdjust { point, .. } => Some(point),
+            _ => self.selection.selected_levels().last().map(|cell| cell.rect.center()),
+        };
+        if let Some(candidate) = candidate {
+            match purpose {
+                TwoPointPurpose::Screenshot | TwoPointPurpose::PlaceWindow => {
+                    let region = egui::Rect::from_two_pos(start, candidate);
+                    painter.rect_filled(region, 0.0, egui::Color32::from_rgba_unmultiplied(120, 200, 255, 40));
+                    painter.rect_stroke(region, 0.0, stroke);
+                }
+                TwoPointPurpose::Measure => {
+                    painter.line_segment([start, candidate], stroke);
+                }
+            }
+        }
+        if screen_rect.contains(start) {
+            painter.circle_stroke(start, 6.0, stroke);
+        }
+    }
+
+    /// Swatch and values of the sampled color, next to the point it came from.
+    fn paint_sampled_color(&self, painter: &egui::Painter, screen_rect: egui::Rect, color: &SampledColor, global_point: egui::Pos2) {
+        let offset = self.to_global_point(painter.ctx(), egui::Pos2::ZERO).unwrap_or_default().to_vec2();
+        let point = global_point - offset;
+        if screen_rect.contains(point) {
+            painter.circle_stroke(point, 6.0, egui::Stroke::new(2.0, egui::Color32::WHITE));
+            painter.circle_stroke(point, 8.0, egui::Stroke::new(2.0, egui::Color32::BLACK));
+        }
+        let text = format!("{}   copied · Esc close", color.summary());
+        let galley = painter.layout_no_wrap(text, egui::FontId::proportional(16.0), egui::Color32::WHITE);
+        let swatch_size = egui::vec2(galley.size().y, galley.size().y);
+        let content_size = egui::vec2(swatch_size.x + 10.0 + galley.size().x, galley.size().y);
+        let pill_rect = egui::Rect::from_center_size(screen_rect.center_bottom() - egui::vec2(0.0, 68.0), content_size)
+            .expand2(egui::vec2(14.0, 8.0));
+        painter.rect_filled(pill_rect, pill_rect.height() / 2.0, egui::Color32::from_rgba_unmultiplied(20, 20, 20, 200));
+        let swatch_rect = egui::Rect::from_min_size(pill_rect.min + egui::vec2(14.0, 8.0), swatch_size);
+        painter.rect_filled(swatch_rect, 3.0, color.color32());
+        painter.rect_stroke(swatch_rect, 3.0, egui::Stroke::new(1.0, egui::Color32::WHITE));
+        painter.galley(swatch_rect.right_top() + egui::vec2(10.0, 0.0), galley, egui::Color32::WHITE);
+    }
+
+    /// Each quick jump target with its key while the quick jump key waits for one.
+    fn paint_quick_jump(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        if self.key_prompt != Some(KeyPrompt::QuickJump) {
+            return;
+        }
+        let quick_jump = &self.config.quick_jump;
+        for target in QuickJumpTarget::ALL {
+            let point = target.point_in(screen_rect, quick_jump.inset_px);
+            painter.circle_filled(point, 4.0, egui::Color32::from_rgb(255, 220, 60));
+            // Key label on the inward side of the dot, so edge and corner labels stay on screen
+            let inward = screen_rect.center() - point;
+            let offset = if inward.length() > 1.0 { inward.normalized() * 16.0 } else { egui::vec2(0.0, -16.0) };
+            paint_label(painter, point + offset, egui::Align2::CENTER_CENTER, &quick_jump.key_for(target).to_string(), egui::FontId::monospace(16.0), egui::Color32::WHITE, true);
+        }
+        paint_hint_pill(painter, screen_rect, "JUMP   type a key to pick the center, an edge or a corner · Esc cancel".to_string());
+    }
+
+    /// The snap presets and their keys while the snap prompt waits for one.
+    fn paint_window_snap(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        if self.key_prompt != Some(KeyPrompt::WindowSnap) {
+            return;
+        }
+        let presets = self.config.window_snap.presets().iter()
+            .map(|preset| format!("{} {}", preset.key.to_ascii_lowercase(), preset.name))
+            .collect::<Vec<_>>()
+            .join(" · ");
+        paint_hint_pill(painter, screen_rect, format!("SNAP   {} · Esc cancel", presets));
+    }
+
+    fn paint_hud(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        if !self.config.theme.hud {
+            return;
+        }
+        // Spelled out, since the HUD is what viewers of a recording read to follow along
+        let mode = match self.display_mode {
+            grid::DisplayMode::MainGrid => "Main grid",
+            grid::DisplayMode::SubGrid => "Sub grid",
+            grid::DisplayMode::ActionPrompt => "Click action",
+            grid::DisplayMode::Scroll => "Scroll",
+            grid::DisplayMode::Pointer => "Pointer",
+            grid::DisplayMode::Bisect => "Bisect",
+            grid::DisplayMode::FineAdjust { .. } => "Fine adjust",
+            grid::DisplayMode::Hints => "Hints",
+            grid::DisplayMode::MenuHints => "Menus",
+            grid::DisplayMode::DockHints => "Dock",
+            grid::DisplayMode::WindowHints => "Windows",
+        };
+        let mut hud = Hud::new();
+        hud.push(mode);
+        let typed = if self.key_input_buffer.is_empty() { self.selection.typed() } else { self.key_input_buffer.as_str() };
+        if !typed.is_empty() {
+            hud.push(format!("keys {}", typed));
+        }
+        for (armed, name) in [
+            (self.copy_target == Some(CopyTarget::Coordinates), "copy"),
+            (self.copy_target == Some(CopyTarget::Color), "color"),
+            (self.pick_mode == PickMode::Scroll, "scroll"),
+            (self.pick_mode == PickMode::Drop, "drop"),
+            (self.two_point.purpose() == Some(TwoPointPurpose::Screenshot), "screenshot"),
+            (self.two_point.purpose() == Some(TwoPointPurpose::Measure), "measure"),
+            (self.two_point.purpose() == Some(TwoPointPurpose::PlaceWindow), "window"),
+            (self.pick_mode == PickMode::FineAdjust, "adjust"),
+            (self.rapid_fire, "rapid fire"),
+            (self.pick_mode.is_selecting_text(), "select text"),
+        ] {
+            if armed {
+                hud.push(format!("armed: {}", name));
+            }
+        }
+        let click_kind = if let Some(kind) = self.sub_grid_click_kind {
+            kind
+        } else if self.default_click_kind == ClickKind::Left && self.lshift_key_is_pressed.load(AtomicOrdering::SeqCst) {
+            ClickKind::Right
+        } else {
+            self.default_click_kind
+        };
+        hud.push(format!("click: {}", click_kind.label()));
+        if let Some((color, _)) = &self.sampled_color {
+            hud.push(format!("color {}", color.hex()));
+        }
+        if let Some(target) = self.target_point(painter.ctx()).and_then(|point| self.to_click_point(painter.ctx(), point)) {
+            hud.push(format!("at {}", click::format_point(target)));
+        }
+
+        let point_rect = |point: egui::Pos2| egui::Rect::from_center_size(point, egui::vec2(40.0, 40.0));
+        let avoid = match self.display_mode {
+            grid::DisplayMode::SubGrid => self.selection.selected_levels().last().map(|cell| cell.rect),
+            grid::DisplayMode::FineAdjust { point, .. } => Some(point_rect(point)),
+            grid::DisplayMode::Scroll => self.scroll_point.map(point_rect),
+            grid::DisplayMode::ActionPrompt => self.action_prompt_point.map(point_rect),
+            grid::DisplayMode::Bisect => self.bisect_regions.current().map(|region| point_rect(region.center())),
+            grid::DisplayMode::MainGrid => self.previewed_prefix.as_ref().and_then(|prefix| {
+                self.selection.main_labels().iter().zip(self.selection.main_rects().iter())
+                    .filter(|(label, _)| label.starts_with(prefix.as_str()))
+                    .map(|(_, rect)| *rect)
+                    .reduce(|union, rect| union.union(rect))
+            }),
+            grid::DisplayMode::Pointer | grid::DisplayMode::Hints | grid::DisplayMode::MenuHints | grid::DisplayMode::DockHints
+            | grid::DisplayMode::WindowHints => None,
+        };
+        hud.paint(painter, screen_rect, avoid, &self.config.appearance.hud);
+    }
+
+    fn paint_overlay(&self, painter: &egui::Painter, screen_rect: egui::Rect) {
+        if self.display_mode == grid::DisplayMode::Hints {
+            self.paint_hints(painter, screen_rect, "HINTS   type a label · Backspace back · Esc exit");
+            return;
+        }
+        if self.display_mode == grid::DisplayMode::MenuHints {
+            self.paint_hints(painter, screen_rect, "MENUS   type a label to open or run · Esc closes a menu");
+            return;
+        }
+        if self.display_mode == grid::DisplayMode::DockHints {
+            self.paint_hints(painter, screen_rect, "DOCK   type a label to open an app · Backspace back · Esc exit");
+            return;
+        }
+        if self.display_mode == grid::DisplayMode::WindowHints {
+            self.paint_window_hints(painter, screen_rect);
+            return;
+        }
+        if self.display_mode == grid::DisplayMode::Pointer {
+            paint_hint_pill(painter, screen_rect, "POINTER   h j k l move · Space click (Shift: right) · Esc exit".to_string());
+            return;
+        }
+        if let grid::DisplayMode::FineAdjust { point, .. } = self.display_mode {
+            self.paint_fine_adjust(painter, screen_rect, point);
+            return;
+        }
+        if self.display_mode == grid::DisplayMode::Scroll {
+            if let Some(point) = self.scroll_point {
+                painter.circle_stroke(point, 10.0, egui::Stroke::new(2.0, egui::Color32::YELLOW));
+                painter.text(
+                    point + egui::vec2(0.0, 18.0),
+                    egui::Align2::CENTER_TOP,
+                    "SCROLL  arrows to scroll, hold to repeat  ·  PageUp/PageDown by page  ·  Esc to exit",
+                    egui::FontId::proportional(14.0),
+                    egui::Color32::WHITE,
+                );
+            }
+            return;
+        }
+        if let Some((text, point, _)) = &self.copy_flash {
+            painter.circle_stroke(*point, 6.0, egui::Stroke::new(2.0, egui::Color32::GREEN));
+            paint_hint_pill(painter, screen_rect, format!("Copied {}", text));
+            return;
+        }
+        if let Some((color, point)) = &self.sampled_color {
+            self.paint_sampled_color(painter, screen_rect, color, *point);
+            return;
+        }
+        let armed_banner = if self.pick_mode == PickMode::Drop {
+            Some("DROP: keep holding the button and pick where to release it")
+        } else if let Some(purpose) = self.two_point.purpose() {
+            Some(match (purpose, self.two_point.first()) {
+                (TwoPointPurpose::Screenshot, None) => "SCREENSHOT: pick one corner of the region",
+                (TwoPointPurpose::Screenshot, Some(_)) => "SCREENSHOT: pick the opposite corner",
+                (TwoPointPurpose::Measure, None) => "MEASURE: pick where to measure from",
+                (TwoPointPurpose::Measure, Some(_)) => "MEASURE: pick where to measure to",
+                (TwoPointPurpose::PlaceWindow, None) => "WINDOW: pick where the window's top-left goes, or ? for a preset",
+                (TwoPointPurpose::PlaceWindow, Some(_)) => "WINDOW: pick the opposite corner to resize it, or Enter to only move it",
+            })
+        } else if self.copy_target == Some(CopyTarget::Coordinates) {
+            Some("COPY: pick the point to copy its coordinates (' again: color)")
+        } else if self.copy_target == Some(CopyTarget::Color) {
+            Some("COLOR: pick the point to copy its color")
+        } else if self.pick_mode == PickMode::SelectTextStart {
+            Some("SELECT TEXT: pick the start (double-clicks the word)")
+        } else if self.pick_mode == PickMode::SelectTextEnd {
+            Some("SELECT TEXT: pick the end (shift-click)")
+        } else if self.pick_mode == PickMode::Scroll {
+            Some("SCROLL: pick the point to scroll at")
+        } else {
+            None
+        };
+        if let Some(banner) = armed_banner {
+            painter.text(
+                screen_rect.center_top() + egui::vec2(0.0, 24.0),
+                egui::Align2::CENTER_TOP,
+                banner,
+                egui::FontId::proportional(18.0),
+                egui::Color32::YELLOW,
+            );
+        }
+        if self.display_mode == grid::DisplayMode::Bisect {
+            self.paint_bisect(painter, screen_rect);
+            return;
+        }
+        let theme = &self.config.appearance;
+        let main_cell_bg_color = self.active_profile.cell_color(theme.main_cell_fill);
+        let line_stroke = theme.grid_stroke;
+        let text_color = theme.label_color;
+        let preview_highlight_color = theme.preview_fill;
+        let filtered_out_bg_color = theme.filtered_fill;
+        let matching_stroke = theme.match_stroke;
+
+        if self.display_mode == grid::DisplayMode::SubGrid {
+            // One dim layer around the picked main cell; the other main cells and their labels
+            // only cost tessellation and pull the eye away from the sub-grid
+            if let Some(picked) = self.selection.selected_levels().first() {
+                paint_dimmed_outside(painter, screen_rect, picked.rect, theme.dimmed_fill);
+                painter.rect_filled(picked.rect, 0.0, main_cell_bg_color);
+                painter.rect_stroke(picked.rect, 0.0, line_stroke);
+                if theme.double_outline {
+                    paint_double_outline(painter, picked.rect, egui::Stroke::new(matching_stroke.width, theme.accent));
+                }
+            }
+        } else if !self.selection.main_rects().is_empty() {
+            for (index, rect) in self.selection.main_rects().iter().enumerate() {
+                let mut current_bg_color = main_cell_bg_color;
+                // Once a prefix is typed only the cells it can still reach keep their labels
+                let mut remaining_label = None;
+                if !self.selection.is_main_cell_pickable(index) {
+                    current_bg_color = filtered_out_bg_color;
+                } else if self.display_mode == grid::DisplayMode::MainGrid
+                    && let Some(prefix) = &self.previewed_prefix
+                {
+                    match self.selection.main_labels().get(index).and_then(|label| label.strip_prefix(prefix.as_str())) {
+                        Some(rest) => {
+                            current_bg_color = preview_highlight_color;
+                            remaining_label = Some((prefix.as_str(), rest));
+                        }
+                        None => current_bg_color = filtered_out_bg_color,
+                    }
+                }
+                painter.rect_filled(*rect, 0.0, current_bg_color);
+                painter.rect_stroke(*rect, 0.0, line_stroke);
+                if let Some((typed, rest)) = remaining_label {
+                    if theme.double_outline {
+                        paint_double_outline(painter, *rect, matching_stroke);
+                    } else {
+                        painter.rect_stroke(rect.shrink(0.5), 0.0, matching_stroke);
+                    }
+                    paint_filtered_label(painter, *rect, typed, rest, theme, self.config.theme.label_outline);
+                } else if current_bg_color != filtered_out_bg_color && index < self.selection.main_labels().len() {
+                    let (anchor, align, font_size) = grid::label_anchor(*rect, screen_rect, self.config.theme.label_position, rect.height().min(rect.width()) * 0.4 * theme.label_scale);
+                    paint_label(painter, anchor, align, &self.selection.main_labels()[index], label_font::label_font(font_size), text_color, self.config.theme.label_outline);
+                }
+                if self.config.debug.enabled && self.show_cell_coordinates
+                    && let Some(global_center) = self.to_global_point(painter.ctx(), rect.center())
+                {
+                    let coordinates = format!("{:.1},{:.1}", global_center.x, global_center.y);
+                    painter.text(rect.left_top() + egui::vec2(2.0, 1.0), egui::Align2::LEFT_TOP, coordinates, egui::FontId::monospace(9.0), egui::Color32::LIGHT_GREEN);
+                }
+            }
+        } else if self.display_mode == grid::DisplayMode::MainGrid {
+             painter.text(screen_rect.center(), egui::Align2::CENTER_CENTER, "Waiting for layout...", egui::FontId::default(), text_color);
+        }
+
+        if self.display_mode == grid::DisplayMode::ActionPrompt
+            && let Some(point) = self.action_prompt_point
+        {
+            let actions = &self.config.actions;
+            let mut legend = format!(
+                "{} plain   {} right   {} middle   {} cmd-click   {} ctrl-click   {} double",
+                actions.plain_key, actions.right_key, actions.middle_key, actions.cmd_click_key, actions.control_click_key, actions.double_key
+            );
+            if let Some(combo) = &self.config.action_shortcut {
+                legend.push_str(&format!("   {} click, then {}", actions.shortcut_key, combo.label()));
+            }
+            let legend_font = egui::FontId::proportional(16.0);
+            let galley = painter.layout_no_wrap(legend, legend_font, egui::Color32::WHITE);
+            let screen = screen_rect;
+            let mut legend_pos = point + egui::vec2(-galley.size().x / 2.0, 24.0);
+            if legend_pos.y + galley.size().y > screen.max.y {
+                legend_pos.y = point.y - 24.0 - galley.size().y;
+            }
+            legend_pos.x = legend_pos.x.clamp(screen.min.x, (screen.max.x - galley.size().x).max(screen.min.x));
+            let legend_rect = egui::Rect::from_min_size(legend_pos, galley.size()).expand(8.0);
+            painter.circle_stroke(point, 6.0, egui::Stroke::new(2.0, egui::Color32::YELLOW));
+            painter.rect_filled(legend_rect, 6.0, egui::Color32::from_rgba_unmultiplied(20, 20, 20, 220));
+            painter.galley(legend_pos, galley, egui::Color32::WHITE);
+        }
+
+        if self.display_mode == grid::DisplayMode::SubGrid {
+            // The dim layer above leaves the picked main cell clear; deeper levels dim around their own cell
+            if let Some(active_cell) = self.selection.selected_levels().last().filter(|_| self.selection.level() > 1) {
+                paint_dimmed_outside(painter, screen_rect, active_cell.rect, theme.shade);
+            }
+            if self.selection.sub_rects().is_empty() {
+                 if let Some(active_cell) = self.selection.selected_levels().last() {
+                     let selected_rect = active_cell.rect;
+                     painter.text(selected_rect.center(), egui::Align2::CENTER_CENTER, "Waiting for sub-layout...", egui::FontId::proportional(selected_rect.height() * 0.15), egui::Color32::YELLOW);
+                }
+            } else {
+                let sub_cell_bg_color = self.active_profile.sub_cell_color(theme.sub_cell_fill);
+                let sub_text_color = theme.sub_label_color;
+                let remembered = self.remembered_sub_cell();
+                // With a capture, the sub-grid goes over the magnified cell and the cell itself is left clear
+                let lens = self.selection.selected_levels().last().and_then(|active_cell| {
+                    let texture = self.magnifier.texture_for(active_cell.rect)?;
+                    let lens = magnifier::lens_rect(active_cell.rect, self.config.magnifier.zoom, screen_rect);
+                    painter.rect_stroke(active_cell.rect, 0.0, egui::Stroke::new(2.0, theme.accent));
+                    painter.image(texture.id(), lens, egui::Rect::from_min_max(egui::Pos2::ZERO, egui::pos2(1.0, 1.0)), egui::Color32::WHITE);
+                    painter.rect_stroke(lens, 0.0, egui::Stroke::new(2.0, theme.accent));
+                    Some((active_cell.rect, lens))
+                });
+                let radial = self.refinement_layout().is_radial(self.selection.level());
+                if let Some(active_cell) = self.selection.selected_levels().last().filter(|_| radial && lens.is_none()) {
+                    // Faint rings so the spokes read as directions from the center
+                    let rings = self.config.grid.radial_rings.max(1);
+                    let ring_gap = active_cell.rect.width().min(active_cell.rect.height()) / 2.0 / rings as f32;
+                    for ring in 1..=rings {
+                        painter.circle_stroke(active_cell.rect.center(), ring_gap * ring as f32, egui::Stroke::new(1.0, line_stroke.color.gamma_multiply(0.5)));
+                    }
+                }
+                for (index, rect) in self.selection.sub_rects().iter().enumerate() {
+                    let rect = match lens {
+                        Some((cell, lens)) => magnifier::to_lens(*rect, cell, lens),
+                        None => *rect,
+                    };
+                    if radial {
+                        if lens.is_none() {
+                            painter.circle_filled(rect.center(), rect.width() / 2.0, sub_cell_bg_color);
+                        }
+                        painter.circle_stroke(rect.center(), rect.width() / 2.0, line_stroke);
+                    } else {
+                        if lens.is_none() {
+                            painter.rect_filled(rect, 0.0, sub_cell_bg_color);
+                        }
+                        painter.rect_stroke(rect, 0.0, line_stroke);
+                    }
+                    if remembered == Some(index) {
+                        // Enter picks this one again
+                        painter.rect_stroke(rect.shrink(2.0), 0.0, egui::Stroke::new(2.0, egui::Color32::from_rgba_unmultiplied(120, 200, 255, 180)));
+                    }
+                    if index < self.selection.sub_labels().len() {
+                        // Third-level cells can be tiny; keep their labels legible even if they overflow the cell
+                        let label_scale = if self.selection.sub_labels()[index].len() > 1 { 0.35 } else { 0.5 };
+                        let font_size = (rect.height().min(rect.width()) * label_scale).max(9.0) * theme.label_scale;
+                        let (anchor, align, font_size) = grid::label_anchor(rect, screen_rect, self.config.theme.sub_label_position, font_size);
+                        // Over the raw capture there's no cell fill behind the label, so it always gets an outline
+                        let outline = self.config.theme.label_outline || lens.is_some();
+                        paint_label(painter, anchor, align, &self.selection.sub_labels()[index], label_font::label_font(font_size), sub_text_color, outline);
+                    }
+                }
+            }
+            if self.magnifier.notice_pending() {
+                paint_hint_pill(painter, screen_rect, magnifier::PERMISSION_NOTICE.to_string());
+            }
+            if let Some((rect, _)) = self.fuzzy_flash.filter(|(_, picked_at)| picked_at.elapsed() < Duration::from_millis(FUZZY_FLASH_MS)) {
+                painter.rect_stroke(rect, 0.0, egui::Stroke::new(3.0, egui::Color32::from_rgb(255, 170, 0)));
+            }
+        }
+    }
+
+    /// Captures the cell being refined for the lens while in the sub-grid, and drops the capture otherwise.
+    /// Only the home display's window is captured below, so the lens is off on other displays.
+    #[cfg(target_os = "macos")]
+    fn update_magnifier(&mut self, ctx: &egui::Context, frame: Option<&eframe::Frame>) {
+        let cell = self.selection.selected_levels().last().map(|cell| cell.rect).filter(|_| {
+            self.config.magnifier.enabled
+                && self.display_mode == grid::DisplayMode::SubGrid
+                && self.selected_secondary_display().is_none()
+                && self.eframe_control.is_visible.load(AtomicOrdering::SeqCst)
+        });
+        let Some(cell) = cell else {
+            self.magnifier.notice_seen();
+            self.magnifier.clear();
+            return;
+        };
+        let (Some(window_number), Some(global_min)) = (ns_window_from_frame(frame).and_then(macos::window_number), self.to_global_point(ctx, cell.min)) else {
+            return;
+        };
+        self.magnifier.update(ctx, cell, egui::Rect::from_min_size(global_min, cell.size()), window_number, &self.config.magnifier);
+    }
+
+    /// Converts a point local to the active display's overlay into global screen coordinates.
+    fn to_global_point(&self, ctx: &egui::Context, window_relative_point: egui::Pos2) -> Option<egui::Pos2> {
+        let current_viewport_origin = match self.selected_secondary_display() {
+            Some(display) => Some(display.frame.min),
+            None => ctx.input(|i| i.viewport().outer_rect).map(|rect| rect.min),
+        };
+        current_viewport_origin.map(|origin| origin + window_relative_point.to_vec2())
+    }
+
+    /// Global point for posting mouse events, centered on the physical pixel under the logical point.
+    fn to_click_point(&self, ctx: &egui::Context, window_relative_point: egui::Pos2) -> Option<egui::Pos2> {
+        let scale_factor = match self.selected_secondary_display() {
+            Some(display) => display.scale_factor,
+            None => ctx.pixels_per_point(),
+        };
+        self.to_global_point(ctx, window_relative_point).map(|point| click::pixel_center(point, scale_factor))
+    }
+
+    fn enter_scroll_mode(&mut self, ctx: &egui::Context, window_relative_point: egui::Pos2) {
+        self.pick_mode = PickMode::Click;
+        let Some(global_point) = self.to_click_point(ctx, window_relative_point) else {
+            eprintln!("Failed to get window rect for scroll at {:?}", window_relative_point);
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            return;
+        };
+        if let Err(e) = click::warp_cursor(click::to_cg_point(global_point)) {
+            eprintln!("Failed to move mouse: {:?}", e);
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            return;
+        }
+        println!("Entering scroll mode at {:?}", global_point);
+        self.scroll_point = Some(window_relative_point);
+        self.scroll_repeater.reset();
+        self.display_mode = grid::DisplayMode::Scroll;
+    }
+
+    fn enter_pointer_mode(&mut self) {
+        println!("Entering pointer mode");
+        self.clear_typed();
+        self.previewed_prefix = None;
+        self.eframe_control.pointer_keys.release_all();
+        self.pointer_motion = Some(PointerMotion::start(
+            self.eframe_control.pointer_keys.clone(),
+            self.lshift_key_is_pressed.clone(),
+            self.config.pointer.clone(),
+        ));
+        self.display_mode = grid::DisplayMode::Pointer;
+    }
+
+    /// Labels the frontmost app's clickable elements; stays on the grid when it exposes none (common in Electron apps).
+    fn enter_hint_mode(&mut self, ctx: &egui::Context) {
+        let rects = self.clickable_element_rects(ctx);
+        if rects.is_empty() {
+            println!("Frontmost app exposes no clickable elements, staying on the grid");
+            return;
+        }
+        let labels = match grid::generate_labels(rects.len(), &self.config.label_alphabets.sub_chars, 1) {
+            Ok(labels) => labels,
+            Err(e) => {
+                eprintln!("Failed to label hints: {}", e);
+                return;
+            }
+        };
+        println!("Entering hint mode with {} elements", rects.len());
+        self.clear_typed();
+        self.previewed_prefix = None;
+        self.hint_labels = labels;
+        self.hint_rects = rects;
+        self.display_mode = grid::DisplayMode::Hints;
+    }
+
+    /// Window-relative frames of the clickable elements in the frontmost app's focused window,
+    /// top to bottom, left to right. Elements centered off this display are dropped.
+    fn clickable_element_rects(&self, ctx: &egui::Context) -> Vec<egui::Rect> {
+        #[cfg(target_os = "macos")]
+        {
+            let Some(window) = self.previous_frontmost_pid.and_then(ax::focused_window) else {
+                return Vec::new();
+            };
+            let Some(offset) = self.to_global_point(ctx, egui::Pos2::ZERO).map(|origin| origin.to_vec2()) else {
+                return Vec::new();
+            };
+            let mut rects: Vec<egui::Rect> = ax::collect_clickable_elements(&window).into_iter()
+                .map(|(frame, _)| egui::Rect::from_min_size(
+                    egui::pos2(frame.origin.x as f32, frame.origin.y as f32) - offset,
+                    egui::vec2(frame.size.width as f32, frame.size.height as f32),
+                ))
+                .filter(|rect| self.last_layout_screen_rect.contains(rect.center()))
+                .collect();
+            rects.sort_by(|a, b| a.min.y.total_cmp(&b.min.y).then(a.min.x.total_cmp(&b.min.x)));
+            rects
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = ctx;
+            Vec::new()
+        }
+    }
+
+    fn clear_hints(&mut self) {
+        self.hint_labels.clear();
+        self.hint_rects.clear();
+        self.requested_hint_mode = None;
+        #[cfg(target_os = "macos")]
+        {
+            self.dock_items.clear();
+            self.picker_windows.clear();
+        }
+    }
+
+    /// Labels the Dock's items, which live in the Dock process rather than the frontmost app.
+    /// Stays on the grid when the Dock can't be read or is on another display.
+    fn enter_dock_mode(&mut self, ctx: &egui::Context) {
+        #[cfg(target_os = "macos")]
+        {
+            let items = macos::dock_pid().map(ax::dock_items).unwrap_or_default();
+            let offset = self.to_global_point(ctx, egui::Pos2::ZERO).map(|origin| origin.to_vec2()).unwrap_or_default();
+            let frames: Vec<egui::Rect> = items.iter()
+                .map(|(frame, _)| egui::Rect::from_min_size(
+                    egui::pos2(frame.origin.x as f32, frame.origin.y as f32) - offset,
+                    egui::vec2(frame.size.width as f32, frame.size.height as f32),
+                ))
+                .collect();
+            let rects = dock::place_dock_hints(&frames, self.last_layout_screen_rect);
+            if rects.is_empty() {
+                println!("No Dock items on this display ({} found), staying on the grid", items.len());
+                return;
+            }
+            let labels = match grid::generate_labels(rects.len(), &self.config.label_alphabets.sub_chars, 1) {
+                Ok(labels) => labels,
+                Err(e) => {
+                    eprintln!("Failed to label Dock items: {}", e);
+                    return;
+                }
+            };
+            println!("Entering dock mode with {} items along the {:?} edge", rects.len(), dock::dock_edge(&frames, self.last_layout_screen_rect));
+            self.clear_typed();
+            self.previewed_prefix = None;
+            self.hint_labels = labels;
+            self.hint_rects = rects;
+            self.dock_items = items.into_iter().map(|(_, item)| item).collect();
+            self.display_mode = grid::DisplayMode::DockHints;
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = ctx;
+            println!("Dock mode needs macOS Accessibility");
+        }
+    }
+
+    /// Lists the displays once for the prompt's pins rather than on every repaint.
+    fn open_bookmark_prompt(&mut self, prompt: BookmarkPrompt) {
+        self.bookmark_prompt = Some(prompt);
+        self.bookmark_displays = displays::active_displays();
+    }
+
+    /// Handles the character typed after the bookmark key.
+    fn handle_bookmark_prompt(&mut self, ctx: &egui::Context) {
+        let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+        for (key, typed_char) in presses {
+            let Some(prompt) = self.bookmark_prompt else {
+                break;
+            };
+            if self.config.key_bindings.is(key, Action::Bookmark) {
+                if prompt == BookmarkPrompt::Recall {
+                    self.bookmark_prompt = Some(BookmarkPrompt::SaveCursor);
+                }
+                continue;
+            }
+            if self.config.key_bindings.is(key, Action::Back) {
+                self.bookmark_prompt = match prompt {
+                    BookmarkPrompt::SaveCursor | BookmarkPrompt::SavePoint(_) => Some(BookmarkPrompt::Delete),
+                    BookmarkPrompt::Recall | BookmarkPrompt::Delete => None,
+                };
+                continue;
+            }
+            let Some(name) = typed_char else {
+                continue;
+            };
+            self.bookmark_prompt = None;
+            let displays = std::mem::take(&mut self.bookmark_displays);
+            let app = self.frontmost_bundle_id.clone();
+            match prompt {
+                BookmarkPrompt::Recall => match self.bookmarks.get(name, app.as_deref(), &displays) {
+                    Ok((global_point, kind, namespace)) => {
+                        println!("Recalling {} bookmark '{}' at {:?} ({})", namespace.label(), name, global_point, kind.label());
+                        self.sub_grid_click_kind = Some(kind);
+                        let offset = self.to_global_point(ctx, egui::Pos2::ZERO).unwrap_or_default().to_vec2();
+                        self.clear_typed();
+                        self.previewed_prefix = None;
+                        self.select_point(ctx, global_point - offset);
+                    }
+                    Err(e) => println!("{}", e),
+                },
+                BookmarkPrompt::SaveCursor | BookmarkPrompt::SavePoint(_) => {
+                    let point = match prompt {
+                        BookmarkPrompt::SavePoint(point) => Some(point),
+                        _ => click::current_cursor_location().map(|point| egui::pos2(point.x as f32, point.y as f32)),
+                    };
+                    let kind = self.sub_grid_click_kind.unwrap_or(self.default_click_kind);
+                    // Shift+name saves to the global namespace; without a known frontmost app there's no other
+                    let namespace = match app {
+                        Some(bundle_id) if !ctx.input(|i| i.modifiers.shift) => Namespace::App(bundle_id),
+                        _ => Namespace::Global,
+                    };
+                    let saved = point.ok_or_else(|| "Cursor position unavailable".to_string())
+                        .and_then(|point| self.bookmarks.set(name, namespace.clone(), point, kind, &displays));
+                    match saved {
+                        Ok(()) => {
+                            println!("Saved {} bookmark '{}' at {:?}", namespace.label(), name, point);
+                            self.bookmark_flash = Some((format!("Saved bookmark {} ({})", name, namespace.label()), Instant::now()));
+                        }
+                        Err(e) => eprintln!("Failed to save bookmark '{}': {}", name, e),
+                    }
+                }
+                BookmarkPrompt::Delete => {
+                    if let Some(namespace) = self.bookmarks.delete(name, app.as_deref()) {
+                        println!("Deleted {} bookmark '{}'", namespace.label(), name);
+                        self.bookmark_flash = Some((format!("Deleted bookmark {} ({})", name, namespace.label()), Instant::now()));
+                    } else {
+                        println!("No bookmark '{}'", name);
+                    }
+                }
+            }
+            break;
+        }
+    }
+
+    /// Handles the character typed after the quick jump key.
+    fn handle_quick_jump(&mut self, ctx: &egui::Context) {
+        let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+        for (key, typed_char) in presses {
+            if self.config.key_bindings.is(key, Action::Back) || self.config.key_bindings.is(key, Action::QuickJump) {
+                self.key_prompt = None;
+                break;
+            }
+            let Some(target) = typed_char.and_then(|c| self.config.quick_jump.target_for_char(c)) else {
+                continue;
+            };
+            self.key_prompt = None;
+            // Window-relative, so the whole display: the menu bar is what most of these are for
+            let display_rect = match self.selected_secondary_display() {
+                Some(display) => egui::Rect::from_min_size(egui::Pos2::ZERO, display.frame.size()),
+                None => ctx.screen_rect(),
+            };
+            let point = target.point_in(display_rect, self.config.quick_jump.inset_px);
+            println!("Quick jump to {:?} at {:?}", target, point);
+            self.clear_typed();
+            self.previewed_prefix = None;
+            self.select_point(ctx, point);
+            break;
+        }
+    }
+
+    fn handle_window_snap(&mut self, ctx: &egui::Context) {
+        let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+        for (key, typed_char) in presses {
+            if self.config.key_bindings.is(key, Action::Back) || self.config.key_bindings.is(key, Action::QuickJump) {
+                self.key_prompt = None;
+                break;
+            }
+            let Some(preset) = typed_char.and_then(|c| self.config.window_snap.preset_for_char(c)) else {
+                continue;
+            };
+            self.key_prompt = None;
+            self.two_point.reset();
+            self.snap_window(&preset);
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            break;
+        }
+    }
+
+    /// Labels every on-screen window, on all displays, front to back.
+    fn enter_window_mode(&mut self, ctx: &egui::Context) {
+        #[cfg(target_os = "macos")]
+        {
+            let windows = macos::on_screen_windows(self.config.window_picker.min_side as f64);
+            if windows.is_empty() {
+                println!("No windows to label, staying on the grid");
+                return;
+            }
+            let labels = match grid::generate_labels(windows.len(), &self.config.label_alphabets.sub_chars, 1) {
+                Ok(labels) => labels,
+                Err(e) => {
+                    eprintln!("Failed to label windows: {}", e);
+                    return;
+                }
+            };
+            let offset = self.to_global_point(ctx, egui::Pos2::ZERO).map(|origin| origin.to_vec2()).unwrap_or_default();
+            println!("Entering window mode with {} windows", windows.len());
+            self.clear_typed();
+            self.previewed_prefix = None;
+            self.hint_labels = labels;
+            self.hint_rects = windows.iter()
+                .map(|window| egui::Rect::from_min_size(
+                    egui::pos2(window.bounds.origin.x as f32, window.bounds.origin.y as f32) - offset,
+                    egui::vec2(window.bounds.size.width as f32, window.bounds.size.height as f32),
+                ))
+                .collect();
+            self.picker_windows = windows;
+            self.display_mode = grid::DisplayMode::WindowHints;
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = ctx;
+            println!("Window mode needs macOS");
+        }
+    }
+
+    /// Focuses the picked window, or moves the cursor to its center, then hides.
+    fn pick_window(&mut self, index: usize) {
+        #[cfg(target_os = "macos")]
+        if let Some(window) = self.picker_windows.get(index) {
+            let center = CGPoint::new(
+                window.bounds.origin.x + window.bounds.size.width / 2.0,
+                window.bounds.origin.y + window.bounds.size.height / 2.0,
+            );
+            match self.config.window_picker.action {
+                WindowPickAction::Focus => {
+                    println!("Focusing {} window at {:?}", window.owner, window.bounds);
+                    // Raising only orders the window within its app; activating brings the app forward
+                    ax::raise_window(window.pid, window.bounds);
+                    if !macos::activate_app(window.pid) {
+                        eprintln!("Failed to activate {}", window.owner);
+                    }
+                    self.previous_frontmost_pid = None;
+                }
+                WindowPickAction::MoveCursor => {
+                    println!("Moving cursor to the center of the {} window", window.owner);
+                    if let Err(e) = click::warp_cursor(center) {
+                        eprintln!("{}", e);
+                    }
+                }
+            }
+            self.clear_hints();
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+        }
+        #[cfg(not(target_os = "macos"))]
+        let _ = index;
+    }
+
+    /// Presses the Dock item rather than clicking it, so an auto-hidden Dock needn't be revealed.
+    fn press_dock_item(&mut self, index: usize) {
+        #[cfg(target_os = "macos")]
+        if let Some(item) = self.dock_items.get(index) {
+            println!("Pressing Dock item {:?}", item.title().unwrap_or_default());
+            if item.press() {
+                // The pressed item decides which app gets focus
+                self.previous_frontmost_pid = None;
+                self.clear_hints();
+                self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            }
+        }
+        #[cfg(not(target_os = "macos"))]
+        let _ = index;
+    }
+
+    /// Labels the frontmost app's menu bar titles; picking one opens its menu and labels its items.
+    fn enter_menu_mode(&mut self, ctx: &egui::Context) {
+        #[cfg(target_os = "macos")]
+        {
+            let Some(walker) = self.previous_frontmost_pid.and_then(MenuWalker::start) else {
+                println!("Frontmost app has no menu bar to label, staying on the grid");
+                return;
+            };
+            println!("Entering menu mode");
+            self.menu_walker = Some(walker);
+            self.clear_typed();
+            self.previewed_prefix = None;
+            self.display_mode = grid::DisplayMode::MenuHints;
+            self.label_menu_entries(ctx);
+        }
+        #[cfg(not(target_os = "macos"))]
+        {
+            let _ = ctx;
+            println!("Menu mode needs macOS Accessibility");
+        }
+    }
+
+    /// Turns the walker's current entries into hints.
+    #[cfg(target_os = "macos")]
+    fn label_menu_entries(&mut self, ctx: &egui::Context) {
+        let Some(walker) = &self.menu_walker else {
+            return;
+        };
+        let offset = self.to_global_point(ctx, egui::Pos2::ZERO).map(|origin| origin.to_vec2()).unwrap_or_default();
+        let rects: Vec<egui::Rect> = walker.entries().iter()
+            .map(|(frame, _)| egui::Rect::from_min_size(
+                egui::pos2(frame.origin.x as f32, frame.origin.y as f32) - offset,
+                egui::vec2(frame.size.width as f32, frame.size.height as f32),
+            ))
+            .collect();
+        self.hint_labels = grid::generate_labels(rects.len(), &self.config.label_alphabets.sub_chars, 1).unwrap_or_else(|e| {
+            eprintln!("Failed to label menu entries: {}", e);
+            Vec::new()
+        });
+        self.hint_rects = rects;
+        self.clear_typed();
+    }
+
+    fn choose_menu_entry(&mut self, index: usize) {
+        #[cfg(target_os = "macos")]
+        match self.menu_walker.as_mut().and_then(|walker| walker.choose(index)) {
+            Some(MenuStep::Opened) => {
+                self.hint_labels.clear();
+                self.hint_rects.clear();
+                self.menu_opened_at = Some(Instant::now());
+            }
+            Some(MenuStep::Activated) => {
+                println!("Menu item activated, hiding");
+                self.menu_walker = None;
+                self.clear_hints();
+                self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            }
+            None => {}
+        }
+        #[cfg(not(target_os = "macos"))]
+        let _ = index;
+    }
+
+    /// Labels a menu pressed open once its entries can be read; backs out if it never shows.
+    fn poll_opened_menu(&mut self, ctx: &egui::Context) {
+        let Some(opened_at) = self.menu_opened_at else {
+            return;
+        };
+        if opened_at.elapsed() < Duration::from_millis(MENU_OPEN_DELAY_MS) {
+            return;
+        }
+        #[cfg(target_os = "macos")]
+        if self.menu_walker.as_mut().is_some_and(|walker| walker.refresh()) {
+            self.menu_opened_at = None;
+            self.label_menu_entries(ctx);
+            return;
+        }
+        #[cfg(not(target_os = "macos"))]
+        let _ = ctx;
+        if opened_at.elapsed() >= Duration::from_millis(MENU_OPEN_TIMEOUT_MS) {
+            eprintln!("Menu didn't open within {} ms", MENU_OPEN_TIMEOUT_MS);
+            self.menu_opened_at = None;
+            self.go_back();
+        }
+    }
+
+    /// Closes every menu menu mode opened, e.g. when the overlay hides.
+    fn close_menus(&mut self) {
+        self.menu_opened_at = None;
+        #[cfg(target_os = "macos")]
+        if let Some(mut walker) = self.menu_walker.take() {
+            walker.close_all();
+        }
+    }
+
+    fn enter_bisect_mode(&mut self) {
+        println!("Entering bisect mode");
+        self.clear_typed();
+        self.previewed_prefix = None;
+        self.bisect_regions.reset(self.last_layout_screen_rect);
+        self.display_mode = grid::DisplayMode::Bisect;
+    }
+
+    /// Copies the global coordinate of the point as "x,y" instead of clicking it.
+    fn copy_point_coordinates(&mut self, ctx: &egui::Context, window_relative_point: egui::Pos2) {
+        self.copy_target = None;
+        // The point a click would be posted at, so the copy can be checked against the click
+        let Some(click_point) = self.to_click_point(ctx, window_relative_point) else {
+            eprintln!("Failed to get window rect for copy at {:?}", window_relative_point);
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            return;
+        };
+        let text = click::format_point(click_point);
+        if !clipboard::copy(&text) {
+            eprintln!("Failed to copy {} to the clipboard", text);
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            return;
+        }
+        println!("Copied {} to the clipboard", text);
+        self.copy_flash = Some((text, window_relative_point, Instant::now()));
+    }
+
+    /// Copy key: off, then coordinates, then color, then off again.
+    fn cycle_copy_target(&mut self) {
+        self.copy_target = match self.copy_target {
+            None => Some(CopyTarget::Coordinates),
+            Some(CopyTarget::Coordinates) => Some(CopyTarget::Color),
+            Some(CopyTarget::Color) => None,
+        };
+        println!("Copy target {:?}", self.copy_target);
+    }
+
+    /// The overlay would be read instead of what is under it, so the color is sampled from the
+    /// click sequence once the window is gone, and the grid comes back to show it.
+    fn sample_color_after_hide(&mut self, ctx: &egui::Context, window_relative_point: egui::Pos2) {
+        self.copy_target = None;
+        let Some(global_point) = self.to_global_point(ctx, window_relative_point) else {
+            eprintln!("Failed to get window rect for color at {:?}", window_relative_point);
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            return;
+        };
+        println!("Sampling color at {:?}, hiding app", global_point);
+        self.step_hide_phase(HideEvent::Queue(AfterHide::SampleColor { point: global_point }));
+        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+    }
+
+    /// Reads the color under the global `point` and copies its hex value.
+    fn sample_color(&self, point: egui::Pos2) -> Option<SampledColor> {
+        #[cfg(target_os = "macos")]
+        let sampled = color_sample::sample(point);
+        #[cfg(not(target_os = "macos"))]
+        let sampled: Option<SampledColor> = None;
+        let Some(color) = sampled else {
+            eprintln!("Failed to read the color at {:?}", point);
+            return None;
+        };
+        println!("Sampled {} at {:?}", color.summary(), point);
+        if !clipboard::copy(&color.hex()) {
+            eprintln!("Failed to copy {} to the clipboard", color.hex());
+        }
+        Some(color)
+    }
+
+    /// Window-relative point the sub-grid's confirm_center key or fine adjust's confirm key would
+    /// click, for the coordinate readout.
+    fn target_point(&self, ctx: &egui::Context) -> Option<egui::Pos2> {
+        match self.display_mode {
+            grid::DisplayMode::FineAdjust { point, .. } => Some(point),
+            grid::DisplayMode::SubGrid if self.config.grid.confirm_center_action == ConfirmCenterAction::SubCell => {
+                self.sub_cell_to_confirm(ctx).and_then(|index| self.selection.sub_rects().get(index)).map(|rect| rect.center())
+            }
+            grid::DisplayMode::SubGrid => self.selection.selected_levels().last().map(|cell| cell.rect.center()),
+            _ => None,
+        }
+    }
+
+    /// Sub-cell under the mouse pointer, or the one holding the active cell's center.
+    fn sub_cell_to_confirm(&self, ctx: &egui::Context) -> Option<usize> {
+        let pointer = click::current_cursor_location()
+            .zip(self.to_global_point(ctx, egui::Pos2::ZERO))
+            .map(|(cursor, origin)| egui::pos2(cursor.x as f32, cursor.y as f32) - origin.to_vec2());
+        let center = self.selection.selected_levels().last()?.rect.center();
+        pointer.and_then(|point| self.selection.sub_rects().iter().position(|rect| rect.contains(point)))
+            .or_else(|| self.selection.sub_rects().iter().position(|rect| rect.contains(center)))
+    }
+
+    fn tutorial_lines(&self) -> Vec<String> {
+        let keys = &self.config.key_bindings;
+        tutorial::lines(&self.config.taps, keys.key_for(Action::Back), keys.key_for(Action::Cancel))
+    }
+
+    /// Between a pick or hide and the overlay being fully gone. A show in this window would either
+    /// be read as the toggle that hides the overlay or cancel the click that is about to land.
+    fn is_hiding(&self) -> bool {
+        self.hide_phase.action().is_some()
+            || (self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) && self.eframe_control.hide_requested.load(AtomicOrdering::SeqCst))
+    }
+
+    /// Runs the tap latched by `is_hiding`, once the overlay is hidden and the click sequence reset.
+    fn run_pending_show(&mut self, ctx: &egui::Context) {
+        let Some((action, cursor_pos)) = self.pending_show.take() else {
+            return;
+        };
+        if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
+            println!("Dropping held {:?}, the grid is already up again", action);
+            return;
+        }
+        println!("Running {:?} held back while the overlay hid", action);
+        self.run_tap_action(ctx, action, cursor_pos);
+    }
+
+    /// Moves the hide phase on by `event`, returning the queued work if it is due now.
+    fn step_hide_phase(&mut self, event: HideEvent) -> Option<AfterHide> {
+        let (phase, due) = self.hide_phase.step(event, Duration::from_millis(HIDE_TO_CLICK_MS));
+        self.hide_phase = phase;
+        due
+    }
+
+    fn exit_passthrough_modes(&mut self) {
+        self.copy_target = None;
+        self.two_point.reset();
+        self.measurement = None;
+        self.sampled_color = None;
+        self.copy_flash = None;
+        // The end of a text selection outlives the hide between its two picks
+        if self.pick_mode != PickMode::SelectTextEnd {
+            self.pick_mode = PickMode::Click;
+        }
+        self.scroll_point = None;
+        self.scroll_repeater.reset();
+        self.pointer_motion = None;
+        #[cfg(target_os = "macos")]
+        {
+            self.mouse_passthrough_guard = None;
+        }
+    }
+
+    fn perform_mouse_click(&mut self, _ctx: &egui::Context, window_relative_point: egui::Pos2, kind: ClickKind) {
+        if let Some(global_click_point) = self.to_click_point(_ctx, window_relative_point) {
+
+            println!("Preparing click at {:?}", global_click_point);
+
+            if self.headless {
+                println!("Headless: not moving the mouse");
+            } else if self.config.click.glide_ms > 0 {
+                // The glide runs once the overlay is gone, so the hover effects it triggers land on the app
+                println!("Mouse will glide to ({}, {})", global_click_point.x, global_click_point.y);
+            } else if let Err(e) = click::warp_cursor(click::to_cg_point(global_click_point)) {
+                eprintln!("Failed to move mouse: {:?}", e);
+                self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+                self.step_hide_phase(HideEvent::Cancel);
+                return;
+            } else {
+                println!("Mouse moved to ({}, {})", global_click_point.x, global_click_point.y);
+            }
+            
+            self.step_hide_phase(HideEvent::Queue(AfterHide::Click { point: global_click_point, kind, then_keys: None }));
+            println!("{} click queued, hiding app", kind.label());
+
+        } else {
+            eprintln!("Failed to get window rect for click at {:?}", window_relative_point);
+            self.step_hide_phase(HideEvent::Cancel);
+        }
+        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+    }
+
+    /// Clicks `window_relative_point` with the overlay still up. The overlay lets mouse events
+    /// through until it hides, so the click reaches the app underneath.
+    fn rapid_fire_click(&mut self, ctx: &egui::Context, frame: Option<&eframe::Frame>, window_relative_point: egui::Pos2) {
+        let Some(global_point) = self.to_click_point(ctx, window_relative_point) else {
+            eprintln!("Failed to get window rect for rapid fire click at {:?}", window_relative_point);
+            return;
+        };
+        #[cfg(target_os = "macos")]
+        if self.mouse_passthrough_guard.is_none() {
+            self.mouse_passthrough_guard = ns_window_from_frame(frame)
+                .and_then(|ns_window| macos::IgnoreMouseEventsGuard::new(ns_window, Duration::ZERO));
+        }
+        #[cfg(not(target_os = "macos"))]
+        let _ = frame;
+        let kind = self.sub_grid_click_kind.unwrap_or(self.default_click_kind);
+        let point = click::to_cg_point(global_point);
+        if self.headless {
+            println!("Headless: rapid fire {} click at {:?} not posted", kind.label(), global_point);
+            return;
+        }
+        if let Err(e) = click::warp_cursor(point) {
+            eprintln!("Failed to move mouse: {:?}", e);
+        }
+        click::post_click(point, kind, Duration::from_millis(self.config.click.hold_ms));
+        feedback::play(Cue::ClickPosted, &self.config.feedback);
+        self.last_click = Some((point, kind));
+        // The click has landed and made the app under it key; take the keyboard back for the next label
+        self.focus_requested = true;
+        ctx.request_repaint();
+    }
+
+    /// Posts a click queued for after the hide, with the pointer already at `point`. A held click
+    /// only presses here; its release is due once the hold is over.
+    fn post_queued_click(&mut self, point: egui::Pos2, kind: ClickKind, then_keys: Option<KeyCombo>) {
+        let hold = Duration::from_millis(self.config.click.hold_ms);
+        if hold.is_zero() {
+            click::post_click(click::to_cg_point(point), kind, hold);
+            self.queued_click_landed(point, kind, then_keys);
+        } else {
+            click::post_button(click::to_cg_point(point), kind, 1, true);
+            let release = AfterHide::Release { point, kind, click_state: 1, then_keys };
+            self.step_hide_phase(HideEvent::Continue { action: release, due: self.clock.now() + hold });
+        }
+    }
+
+    fn queued_click_landed(&mut self, point: egui::Pos2, kind: ClickKind, then_keys: Option<KeyCombo>) {
+        let click_point_cg = click::to_cg_point(point);
+        feedback::play(Cue::ClickPosted, &self.config.feedback);
+        self.last_click = Some((click_point_cg, kind));
+        // The first click of a text selection comes back for the second; a marker would sit in its way
+        if self.config.feedback.click_marker && self.pick_mode != PickMode::SelectTextEnd {
+            self.click_marker = Some(ClickMarker::new(point));
+        }
+        if let Some(combo) = then_keys {
+            let due = self.clock.now() + Duration::from_millis(self.config.actions.shortcut_delay_ms);
+            self.step_hide_phase(HideEvent::Continue { action: AfterHide::Keys { combo }, due });
+        }
+    }
+
+    /// A left click that, once it has landed and given the app focus, sends `combo` to it.
+    fn perform_click_then_keys(&mut self, ctx: &egui::Context, window_relative_point: egui::Pos2, combo: KeyCombo) {
+        self.perform_mouse_click(ctx, window_relative_point, ClickKind::Left);
+        if let HidePhase::Queued(AfterHide::Click { point, kind, .. }) = self.hide_phase {
+            println!("{} queued after the click", combo.label());
+            self.step_hide_phase(HideEvent::Queue(AfterHide::Click { point, kind, then_keys: Some(combo) }));
+        }
+    }
+
+    /// First pick: remember it and start over for the second. Second pick: capture, measure or place the window.
+    fn pick_two_point(&mut self, ctx: &egui::Context, purpose: TwoPointPurpose, window_relative_point: egui::Pos2) {
+        let Some(global_point) = self.to_global_point(ctx, window_relative_point) else {
+            eprintln!("Failed to get window rect for {} point at {:?}", purpose.label(), window_relative_point);
+            self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            return;
+        };
+        self.clear_typed();
+        self.selection.reset();
+        self.previewed_prefix = None;
+        self.display_mode = grid::DisplayMode::MainGrid;
+        let Some((first, second)) = self.two_point.pick(global_point) else {
+            println!("First {} point at {:?}", purpose.label(), global_point);
+            return;
+        };
+        match purpose {
+            TwoPointPurpose::Screenshot => {
+                println!("Screenshot region {:?} to {:?}, hiding app", first, second);
+                // Captured from the click sequence, once the overlay is gone
+                self.step_hide_phase(HideEvent::Queue(AfterHide::Screenshot { region: egui::Rect::from_two_pos(first, second) }));
+                self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            }
+            TwoPointPurpose::Measure => {
+                let displays = displays::active_displays();
+                let scale_factor = displays::display_containing(&displays, first)
+                    .map(|index| displays[index].scale_factor)
+                    .unwrap_or_else(|| ctx.pixels_per_point());
+                let measurement = Measurement { start: first, end: second, scale_factor };
+                let summary = measurement.summary();
+                println!("Measured {}", summary);
+                if !clipboard::copy(&summary) {
+                    eprintln!("Failed to copy the measurement to the clipboard");
+                }
+                self.measurement = Some(measurement);
+            }
+            TwoPointPurpose::PlaceWindow => {
+                self.place_window(first, Some(second));
+                self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            }
+        }
+    }
+
+    /// Moves the frontmost app's focused window so its top-left is at the global point `origin`, and
+    /// resizes it to reach `corner` when given and the app allows it. It is kept on `origin`'s display.
+    fn place_window(&self, origin: egui::Pos2, corner: Option<egui::Pos2>) {
+        #[cfg(target_os = "macos")]
+        {
+            let Some((window, frame, resizable)) = self.window_to_place() else {
+                return;
+            };
+            let target = match corner {
+                Some(corner) if resizable => egui::Rect::from_two_pos(origin, corner),
+                Some(_) => {
+                    println!("The focused window can't be resized, only moving it");
+                    egui::Rect::from_min_size(origin, frame.size())
+                }
+                None => egui::Rect::from_min_size(origin, frame.size()),
+            };
+            set_window_frame(&window, frame, target, resizable);
+        }
+        #[cfg(not(target_os = "macos"))]
+        println!("Placing windows needs macOS ({:?}, {:?})", origin, corner);
+    }
+
+    /// Fits the frontmost app's focused window to `preset` on the display it is mostly on, inside
+    /// the part the menu bar and Dock leave free. A window that can't be resized is only moved.
+    fn snap_window(&self, preset: &SnapPreset) {
+        #[cfg(target_os = "macos")]
+        {
+            let Some((window, frame, resizable)) = self.window_to_place() else {
+                return;
+            };
+            let displays = displays::active_displays();
+            let Some(index) = displays::display_containing(&displays, frame.center())
+                .or_else(|| displays::display_containing(&displays, frame.min))
+            else {
+                eprintln!("The focused window isn't on any display");
+                return;
+            };
+            let mut target = preset.rect_in(window_snap::visible_frame(displays[index].frame));
+            if !resizable {
+                println!("The focused window can't be resized, only moving it");
+                target = egui::Rect::from_min_size(target.min, frame.size());
+            }
+            println!("Snapping the focused window to the {}", preset.name);
+            set_window_frame(&window, frame, target, resizable);
+        }
+        #[cfg(not(target_os = "macos"))]
+        println!("Snapping windows needs macOS ({})", preset.name);
+    }
+
+    /// The frontmost app's focused window, its frame in global points, and whether it can be resized.
+    #[cfg(target_os = "macos")]
+    fn window_to_place(&self) -> Option<(ax::AXElement, egui::Rect, bool)> {
+        let Some(window) = self.previous_frontmost_pid.and_then(ax::focused_window) else {
+            eprintln!("No focused window to place");
+            return None;
+        };
+        let Some(frame) = window.frame() else {
+            eprintln!("Couldn't read the focused window's frame");
+            return None;
+        };
+        let frame = egui::Rect::from_min_size(
+            egui::pos2(frame.origin.x as f32, frame.origin.y as f32),
+            egui::vec2(frame.size.width as f32, frame.size.height as f32),
+        );
+        let resizable = window.is_settable("AXSize");
+        Some((window, frame, resizable))
+    }
+
+    /// Queues moving the held drag to the point, once the overlay is gone like a click. The user's
+    /// release of the button drops it there.
+    fn perform_drop(&mut self, ctx: &egui::Context, window_relative_point: egui::Pos2) {
+        // No cursor warp: the drag's own move events carry the cursor there
+        match self.to_click_point(ctx, window_relative_point) {
+            Some(point) => {
+                println!("Drop queued at {:?}, hiding app", point);
+                self.step_hide_phase(HideEvent::Queue(AfterHide::Drop { point }));
+            }
+            None => {
+                eprintln!("Failed to get window rect for drop at {:?}", window_relative_point);
+                self.step_hide_phase(HideEvent::Cancel);
+                self.pick_mode = PickMode::Click;
+            }
+        }
+        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+    }
+}
+
+impl eframe::App for MouselessApp {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        self.run_frame(ctx, Some(frame));
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, overlay_opacity::STORAGE_KEY, &self.overlay_opacity);
+        eframe::set_value(storage, tutorial::STORAGE_KEY, &self.tutorial_seen);
+    }
+
+    /// Only the overlay opacity and the tutorial flag are kept; egui's own state starts fresh every run.
+    fn persist_egui_memory(&self) -> bool {
+        false
+    }
+
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        [0.0, 0.0, 0.0, 0.0]
+    }
+}
+
+impl MouselessApp {
+    /// One frame of `update`. `frame` is None when driven headlessly, with no native window to set up.
+    fn run_frame(&mut self, ctx: &egui::Context, frame: Option<&eframe::Frame>) {
+        while let Ok(event) = self.event_rx.try_recv() {
+            match event {
+                GlobalEvent::TapGesture { taps, action, cursor_pos } => {
+                    println!("App received {}-tap gesture ({:?})", taps, action);
+                    if self.is_hiding() {
+                        println!("Overlay still hiding, holding {:?} until it's gone", action);
+                        self.pending_show = Some((action, cursor_pos));
+                    } else if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
+                        println!("Overlay already visible, hiding instead");
+                        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+                    } else {
+                        self.run_tap_action(ctx, action, cursor_pos);
+                    }
+                }
+                GlobalEvent::Relaunched => {
+                    // Opening the app again reads as asking for it, so it doesn't toggle like a tap
+                    if self.is_hiding() {
+                        self.pending_show = Some((self.config.taps.relaunch, None));
+                    } else if !self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
+                        self.run_tap_action(ctx, self.config.taps.relaunch, None);
+                    }
+                }
+                GlobalEvent::AppearanceChanged { appearance, accessibility } => {
+                    if !self.config.theme.follows_system() {
+                        continue;
+                    }
+                    match self.config.theme.resolve(appearance, accessibility) {
+                        Ok(theme) => {
+                            println!("System appearance is now {:?}, switching theme", appearance);
+                            self.config.appearance = theme;
+                            ctx.request_repaint();
+                        }
+                        Err(e) => eprintln!("Keeping the current theme: {}", e),
+                    }
+                }
+                GlobalEvent::EscapePressed { force_hide } => {
+                    println!("App received EscapePressed (force hide: {})", force_hide);
+                    self.pending_show = None;
+                    if let Some(action) = self.hide_phase.action() {
+                        // A pick already on its way out is taken back, not left to fire after the cancel
+                        println!("Escape cancels queued {:?}", action);
+                        feedback::play(Cue::Cancelled, &self.config.feedback);
+                        self.step_hide_phase(HideEvent::Cancel);
+                        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+                        continue;
+                    }
+                    if force_hide || !self.go_back() {
+                        if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
+                            feedback::play(Cue::Cancelled, &self.config.feedback);
+                        }
+                        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+                    }
+                }
+                GlobalEvent::DropGridRequested { cursor_pos } => {
+                    if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) || self.hide_phase.is_waiting() {
+                        continue;
+                    }
+                    println!("App received drop grid request at {:?}", cursor_pos);
+                    self.show_grid(ctx, Some(cursor_pos));
+                    self.pick_mode = PickMode::Drop;
+                }
+                GlobalEvent::LeftButtonPressed { location } => {
+                    // Not acted on yet; logged so mouse activations can be checked against the tap
+                    if self.config.debug.enabled {
+                        println!("Left button pressed at {:?}", location);
+                    }
+                }
+                GlobalEvent::MouseMoved { location, delta } => {
+                    let threshold = self.config.dismiss.mouse_move_px;
+                    if threshold <= 0.0 || self.eframe_control.hide_requested.load(AtomicOrdering::SeqCst) {
+                        continue;
+                    }
+                    self.mouse_drift += delta;
+                    if self.mouse_drift.length() > threshold {
+                        println!("Mouse moved by hand to {:?} ({:.0} px), hiding without clicking", location, self.mouse_drift.length());
+                        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+                    }
+                }
+                GlobalEvent::LeftButtonReleased => {
+                    // Released before the drag was moved: it already ended where the cursor was
+                    if self.pick_mode == PickMode::Drop {
+                        match self.hide_phase.action() {
+                            None => println!("Left button released before a drop target was picked, hiding"),
+                            Some(action) => {
+                                println!("Left button released before the drag reached the target, dropping queued {:?}", action);
+                                self.step_hide_phase(HideEvent::Cancel);
+                            }
+                        }
+                        self.pick_mode = PickMode::Click;
+                        self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+                    }
+                }
+            }
+        }
+
+        let now = Instant::now();
+        if let Some(self_test) = &self.self_test {
+            if self_test.is_done() {
+                println!("{}", self_test.report());
+                self.self_test = None;
+                ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                return;
+            }
+            if self_test.should_show(now) && !self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) && !self.hide_phase.is_waiting() {
+                if let Some(self_test) = self.self_test.as_mut() {
+                    self_test.mark_shown(now);
+                }
+                self.show_grid(ctx, None);
+            }
+        }
+        if self.tutorial_pending && !self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) && !self.is_hiding() {
+            println!("First run, showing the tutorial");
+            self.tutorial_pending = false;
+            self.tutorial_seen = true;
+            self.show_grid(ctx, None);
+            self.tutorial_visible = true;
+        }
+
+        let hide_req = self.eframe_control.hide_requested.load(AtomicOrdering::SeqCst);
+        if hide_req {
+            if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) {
+                // Keep painting the overlay as it was while it fades out; input waits until it's gone
+                let fade_out_started_at = *self.fade_out_started_at.get_or_insert_with(|| self.clock.now());
+                let opacity = self.fade_opacity(ctx, false);
+                if opacity > 0.0 {
+                    self.paint_displays(ctx, opacity);
+                    ctx.request_repaint();
+                    return;
+                }
+                self.fade_out_started_at = None;
+                println!("Hiding window");
+                self.eframe_control.overlay_rendered.store(false, AtomicOrdering::SeqCst);
+                self.eframe_control.is_visible.store(false, AtomicOrdering::SeqCst);
+                ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+                self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
+                self.clear_typed();
+                self.selection.reset();
+                self.previewed_prefix = None;
+                self.display_mode = grid::DisplayMode::MainGrid;
+                self.action_prompt_point = None;
+                self.action_prompt_started_at = None;
+                self.tutorial_visible = false;
+                self.overlay_displays.clear();
+                self.home_display_index = None;
+                self.selected_display_index = None;
+                self.exit_passthrough_modes();
+                self.close_menus();
+                println!("Hide initiated");
+                self.step_hide_phase(HideEvent::Hidden { fade_started_at: fade_out_started_at });
+                if !self.hide_phase.is_waiting() {
+                    self.pick_mode = PickMode::Click;
+                    self.restore_previous_focus();
+                    self.run_pending_show(ctx);
+                }
+                return;
+            }
+            else if hide_req { 
+                 self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
+                 if let Some(action) = self.hide_phase.action().filter(|_| !self.hide_phase.is_waiting()) {
+                    println!("Clearing queued {:?}", action);
+                    self.step_hide_phase(HideEvent::Cancel);
+                 }
+                 self.fade_out_started_at = None;
+                 self.previewed_prefix = None;
+            }
+        }
+
+        if self.hide_phase.is_waiting() {
+            let now = self.clock.now();
+            if let Some(action) = self.step_hide_phase(HideEvent::Tick(now)) {
+                if !matches!(action, AfterHide::Glide { .. }) {
+                    println!("Performing {:?}", action);
+                }
+
+                // Held until the whole sequence is done, so steps due later still pass through the overlay
+                #[cfg(target_os = "macos")]
+                if self.mouse_passthrough_guard.is_none() {
+                    self.mouse_passthrough_guard = ns_window_from_frame(frame).and_then(|ns_window| {
+                        macos::IgnoreMouseEventsGuard::new(ns_window, Duration::from_millis(self.config.click.restore_mouse_delay_ms))
+                    });
+                }
+
+                let mut sampled_color = None;
+                match action {
+                    AfterHide::Screenshot { region } => screenshot::capture(region, &self.config.screenshot),
+                    AfterHide::SampleColor { point } => sampled_color = self.sample_color(point).map(|color| (color, point)),
+                    AfterHide::Drop { point } if self.headless => println!("Headless: drop at {:?} not posted", point),
+                    AfterHide::Drop { point } => click::post_drop(click::to_cg_point(point)),
+                    AfterHide::Click { point, kind, then_keys } => {
+                        let click_point_cg = click::to_cg_point(point);
+                        let click_kind = if kind == ClickKind::Left && self.lshift_key_is_pressed.load(AtomicOrdering::SeqCst) {
+                            println!("Using right click (shift held)");
+                            ClickKind::Right
+                        } else {
+                            kind
+                        };
+                        if self.headless {
+                            println!("Headless: {} click at {:?} not posted", click_kind.label(), point);
+                        } else if let Some(self_test) = self.self_test.as_mut() {
+                            click::post_mouse_move(click_point_cg);
+                            self_test.mark_posted(Instant::now());
+                        } else if self.config.click.glide_ms > 0
+                            && let Some(cursor) = click::current_cursor_location()
+                        {
+                            let from = egui::pos2(cursor.x as f32, cursor.y as f32);
+                            let glide = AfterHide::Glide { from, started_at: now, point, kind: click_kind, then_keys };
+                            self.step_hide_phase(HideEvent::Continue { action: glide, due: now });
+                        } else {
+                            if self.config.click.glide_ms > 0
+                                && let Err(e) = click::warp_cursor(click_point_cg)
+                            {
+                                eprintln!("Failed to move mouse: {:?}", e);
+                            }
+                            self.post_queued_click(point, click_kind, then_keys);
+                        }
+                    }
+                    AfterHide::Glide { from, started_at, point, kind, then_keys } => {
+                        let glide = Duration::from_millis(self.config.click.glide_ms);
+                        let progress = now.duration_since(started_at).as_secs_f64() / glide.as_secs_f64();
+                        click::post_mouse_move(click::glide_point(click::to_cg_point(from), click::to_cg_point(point), progress));
+                        if progress < 1.0 {
+                            self.step_hide_phase(HideEvent::Continue { action, due: now + click::GLIDE_STEP });
+                        } else {
+                            self.post_queued_click(point, kind, then_keys);
+                        }
+                    }
+                    AfterHide::Release { point, kind, click_state, then_keys } => {
+                        click::post_button(click::to_cg_point(point), kind, click_state, false);
+                        if click_state < kind.click_count() {
+                            click::post_button(click::to_cg_point(point), kind, click_state + 1, true);
+                            let release = AfterHide::Release { point, kind, click_state: click_state + 1, then_keys };
+                            let due = now + Duration::from_millis(self.config.click.hold_ms);
+                            self.step_hide_phase(HideEvent::Continue { action: release, due });
+                        } else {
+                            println!("Posted held {} click at ({}, {})", kind.label(), point.x, point.y);
+                            self.queued_click_landed(point, kind, then_keys);
+                        }
+                    }
+                    AfterHide::Keys { combo } => click::post_key_combo(&combo),
+                }
+                if !self.hide_phase.is_waiting() {
+                    #[cfg(target_os = "macos")]
+                    {
+                        self.mouse_passthrough_guard = None;
+                    }
+                    if self.pick_mode == PickMode::Drop {
+                        self.pick_mode = PickMode::Click;
+                    }
+                    self.previewed_prefix = None;
+                    self.clear_typed();
+                    self.selection.reset();
+                    self.display_mode = grid::DisplayMode::MainGrid;
+                    self.eframe_control.hide_requested.store(false, AtomicOrdering::SeqCst);
+                    self.restore_previous_focus();
+                    println!("Click sequence complete");
+                    if self.pick_mode == PickMode::SelectTextEnd {
+                        println!("Showing grid again for the end of the text selection");
+                        let cursor_pos = click::current_cursor_location().map(|point| egui::pos2(point.x as f32, point.y as f32));
+                        self.show_grid(ctx, cursor_pos);
+                    }
+                    if let Some(sampled_color) = sampled_color {
+                        println!("Showing grid again for the sampled color");
+                        let cursor_pos = click::current_cursor_location().map(|point| egui::pos2(point.x as f32, point.y as f32));
+                        self.show_grid(ctx, cursor_pos);
+                        self.sampled_color = Some(sampled_color);
+                    }
+                    self.run_pending_show(ctx);
+                }
+            }
+            if let Some(due) = self.hide_phase.due() {
+                ctx.request_repaint_after(due.saturating_duration_since(self.clock.now()));
+            }
+        }
+
+        if let Some(marker) = self.click_marker
+            && !marker.show(ctx, Duration::from_millis(self.config.feedback.click_marker_ms))
+        {
+            self.click_marker = None;
+        }
+
+        if !self.eframe_control.is_visible.load(AtomicOrdering::SeqCst) && !self.hide_phase.is_waiting() {
+            // Park the fade at fully hidden so the next show starts from transparent
+            self.fade_opacity(ctx, false);
+            // Events wake the UI as they're sent; only the self-test runs on a timer while hidden
+            let idle_ms = if self.self_test.is_some() { SELF_TEST_IDLE_REPAINT_MS } else { HIDDEN_IDLE_REPAINT_MS };
+            ctx.request_repaint_after(Duration::from_millis(idle_ms));
+            return;
+        }
+
+        #[cfg(target_os = "macos")]
+        if !self.macos_panel_properties_set {
+            if let Some(window_ptr) = ns_window_from_frame(frame) {
+                let applied_style_mask = unsafe {
+                    let collection_behavior = 
+                        NSWindowCollectionBehavior::CanJoinAllSpaces |
+                        NSWindowCollectionBehavior::FullScreenAuxiliary |
+                        NSWindowCollectionBehavior::Stationary;
+                    let _: () = msg_send![window_ptr, setCollectionBehavior: collection_behavior];
+                    let current_style_mask: NSWindowStyleMask = msg_send![window_ptr, styleMask];
+                    let new_style_mask = current_style_mask.bits() | NSNONACTIVATING_PANEL_MASK as usize;
+                    let _: () = msg_send![window_ptr, setStyleMask: NSWindowStyleMask::from_bits_truncate(new_style_mask)];
+                    let applied_style_mask: NSWindowStyleMask = msg_send![window_ptr, styleMask];
+                    applied_style_mask
+                };
+                // Only done once the style reads back as non-activating; otherwise retried next frame
+                if applied_style_mask.bits() & NSNONACTIVATING_PANEL_MASK as usize != 0 {
+                    println!("Configured window as non-activating panel");
+                    self.macos_panel_properties_set = true;
+                } else {
+                    eprintln!("Window style {:#x} isn't non-activating yet, retrying", applied_style_mask.bits());
+                }
+            }
+            self.panel_setup_frames += 1;
+            if self.macos_panel_properties_set {
+                self.panel_setup_frames = 0;
+            } else if self.panel_setup_frames >= PANEL_SETUP_MAX_FRAMES {
+                eprintln!("Couldn't make the window a non-activating panel in {} frames, focusing it anyway", PANEL_SETUP_MAX_FRAMES);
+                self.macos_panel_properties_set = true;
+            } else {
+                ctx.request_repaint();
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        let focus_ready = self.macos_panel_properties_set;
+        #[cfg(not(target_os = "macos"))]
+        let focus_ready = true;
+        if self.focus_requested && focus_ready {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+            self.focus_requested = false;
+        }
+
+        #[cfg(target_os = "macos")]
+        if (self.config.displays.skip_menu_bar || self.config.displays.block_notch_cells)
+            && let Some(insets) = ns_window_from_frame(frame).and_then(macos::screen_top_insets)
+        {
+            self.screen_top_insets = insets;
+        }
+
+        let current_content_rect = self.active_layout_rect(ctx);
+        if !current_content_rect.is_finite() || current_content_rect.width() < 1.0 || current_content_rect.height() < 1.0 {
+            self.wait_for_layout(ctx, current_content_rect);
+            return;
+        }
+        if self.layout_wait.take().is_some() {
+            println!("Screen rect usable again: {:?}", current_content_rect);
+        }
+        if self.selection.main_rects().is_empty() || self.last_layout_screen_rect != current_content_rect {
+            println!("Recalculating layout");
+            let (cols, rows) = self.main_grid_dims(current_content_rect);
+            let alphabet = self.config.grid.label_alphabet();
+            let (labels, rects) = grid::generate_main_grid_layout(cols, rows, current_content_rect, &self.config.label_alphabets, alphabet.as_deref()).unwrap_or_else(|e| {
+                eprintln!("Failed to lay out main grid: {}", e);
+                (Vec::new(), Vec::new())
+            });
+            let (labels, rects) = grid::exclude_cells(labels, rects, &self.excluded_regions(self.to_global_point(ctx, egui::Pos2::ZERO)));
+            self.last_layout_screen_rect = current_content_rect;
+            // Main cells under the notch are left unlabeled when displays.block_notch_cells is set.
+            // The notch is only known for this window's display, not for secondary ones
+            let notch = self.screen_top_insets.notch_rect().filter(|_| self.selected_secondary_display().is_none());
+            let blocked_main_cells = match notch.filter(|_| self.config.displays.block_notch_cells) {
+                Some(notch) => rects.iter().enumerate()
+                    .filter(|(_, rect)| rect.intersects(notch))
+                    .map(|(index, _)| index)
+                    .collect(),
+                None => Vec::new(),
+            };
+            self.selection.set_main_grid(labels, rects, blocked_main_cells);
+
+            if self.display_mode == grid::DisplayMode::SubGrid {
+                self.relayout_levels();
+            }
+            if !self.bisect_regions.is_empty() {
+                self.bisect_regions.reset(current_content_rect);
+            }
+        }
+        let resume_requested = std::mem::take(&mut self.resume_last_region_requested);
+        match self.requested_hint_mode.take() {
+            Some(grid::DisplayMode::Hints) => self.enter_hint_mode(ctx),
+            Some(grid::DisplayMode::MenuHints) => self.enter_menu_mode(ctx),
+            Some(grid::DisplayMode::DockHints) => self.enter_dock_mode(ctx),
+            Some(grid::DisplayMode::WindowHints) => self.enter_window_mode(ctx),
+            _ if resume_requested => self.resume_last_region(),
+            _ => {}
+        }
+
+        if self.display_mode == grid::DisplayMode::MainGrid && !self.selection.main_rects().is_empty()
+            && self.self_test.as_ref().is_some_and(|self_test| self_test.should_select(Instant::now()))
+        {
+            let target = self.selection.main_rects()[self.selection.main_rects().len() / 2].center();
+            if let Some(self_test) = self.self_test.as_mut() {
+                self_test.mark_queued(Instant::now());
+            }
+            self.perform_mouse_click(ctx, target, ClickKind::Left);
+        }
+
+        if self.tutorial_visible {
+            // Any key only dismisses the card, so the grid under it can be tried right away
+            if ctx.input(|i| i.events.iter().any(|event| matches!(event, egui::Event::Key { pressed: true, .. }))) {
+                println!("Tutorial dismissed");
+                self.tutorial_visible = false;
+            }
+        } else if let Some((_, _, copied_at)) = &self.copy_flash {
+            if copied_at.elapsed() >= Duration::from_millis(COPY_FLASH_MS) {
+                self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+            }
+        } else if self.measurement.is_some() || self.sampled_color.is_some() {
+            // Keys wait for Escape while the readout is up
+        } else if self.bookmark_prompt.is_some() {
+            self.handle_bookmark_prompt(ctx);
+        } else if self.key_prompt == Some(KeyPrompt::QuickJump) {
+            self.handle_quick_jump(ctx);
+        } else if self.key_prompt == Some(KeyPrompt::WindowSnap) {
+            self.handle_window_snap(ctx);
+        } else if self.display_mode == grid::DisplayMode::MainGrid {
+            let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+            for (key, typed_char) in presses {
+                if self.all_displays_active() && self.selection.typed().is_empty()
+                    && let Some(display_number) = key_to_display_number(key)
+                {
+                    self.select_display(display_number);
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::ExtraLevel) {
+                    self.extra_level_toggled = !self.extra_level_toggled;
+                    println!("Grid levels for this selection: {}", self.total_levels());
+                    continue;
+                }
+                if self.config.debug.enabled && self.config.key_bindings.is(key, Action::DebugCoordinates) {
+                    self.show_cell_coordinates = !self.show_cell_coordinates;
+                    println!("Cell coordinates {}", if self.show_cell_coordinates { "shown" } else { "hidden" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::ForgetSubCells) {
+                    self.forget_sub_cells();
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::EnterPointerMode) {
+                    self.enter_pointer_mode();
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::EnterBisectMode) {
+                    self.enter_bisect_mode();
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::EnterHintMode) {
+                    self.enter_hint_mode(ctx);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::EnterMenuMode) {
+                    self.enter_menu_mode(ctx);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::EnterDockMode) {
+                    self.enter_dock_mode(ctx);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::EnterWindowMode) {
+                    self.enter_window_mode(ctx);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::SelectText) {
+                    let armed = self.pick_mode.toggle(PickMode::SelectTextStart);
+                    println!("Text selection {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::CopyCoordinates) {
+                    self.cycle_copy_target();
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::Bookmark) && self.selection.typed().is_empty() {
+                    self.open_bookmark_prompt(BookmarkPrompt::Recall);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::Screenshot) {
+                    let armed = self.two_point.toggle(TwoPointPurpose::Screenshot);
+                    println!("Screenshot {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::PlaceWindow) {
+                    let armed = self.two_point.toggle(TwoPointPurpose::PlaceWindow);
+                    println!("Window placement {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::Confirm) && self.two_point.purpose() == Some(TwoPointPurpose::PlaceWindow)
+                    && let Some(origin) = self.two_point.first()
+                {
+                    self.two_point.reset();
+                    self.place_window(origin, None);
+                    self.eframe_control.hide_requested.store(true, AtomicOrdering::SeqCst);
+                    break;
+                }
+                let opacity_steps = if self.config.key_bindings.is(key, Action::OverlayMoreOpaque) {
+                    1
+                } else if self.config.key_bindings.is(key, Action::OverlayFainter) {
+                    -1
+                } else {
+                    0
+                };
+                if opacity_steps != 0 {
+                    if self.overlay_opacity.adjust(opacity_steps) {
+                        println!("Overlay opacity {}%", self.overlay_opacity.percent());
+                    }
+                    self.opacity_flash = Some(Instant::now());
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::Measure) {
+                    let armed = self.two_point.toggle(TwoPointPurpose::Measure);
+                    println!("Measure {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::QuickJump) && self.selection.typed().is_empty()
+                    && self.two_point.purpose() == Some(TwoPointPurpose::PlaceWindow) && self.two_point.first().is_none()
+                {
+                    // While placing a window the quick jump key offers the snap presets instead
+                    self.key_prompt = Some(KeyPrompt::WindowSnap);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::QuickJump) && self.selection.typed().is_empty() {
+                    self.key_prompt = Some(KeyPrompt::QuickJump);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::EnterScrollMode) {
+                    let armed = self.pick_mode.toggle(PickMode::Scroll);
+                    println!("Scroll mode {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::Back) {
+                    self.selection.pop_typed();
+                    self.previewed_prefix = (!self.selection.typed().is_empty()).then(|| self.selection.typed().to_string());
+                    println!("Backspace, key buffer now {:?}", self.selection.typed());
+                    continue;
+                }
+                if let Some(char_code) = typed_char {
+                    // Labels are prefix-free, so an exact match is final whatever its length
+                    let adjacency = self.config.fuzzy.enabled.then_some(&self.config.key_adjacency);
+                    match self.selection.type_char(char_code, adjacency) {
+                        Step::Picked(index) => {
+                            self.previewed_prefix = None;
+                            self.pick_cell(ctx, index, self.selection.main_rects()[index]);
+                        }
+                        Step::Pending => self.previewed_prefix = Some(self.selection.typed().to_string()),
+                        Step::Corrected(index) => {
+                            println!("Taking adjacent-key match {:?}", self.selection.main_labels()[index]);
+                            self.previewed_prefix = None;
+                            let rect = self.selection.main_rects()[index];
+                            if self.config.fuzzy.flash {
+                                self.fuzzy_flash = Some((rect, Instant::now()));
+                            }
+                            self.pick_cell(ctx, index, rect);
+                        }
+                        Step::Reset => self.previewed_prefix = None,
+                    }
+                }
+            }
+        } else if self.display_mode == grid::DisplayMode::SubGrid {
+            self.previewed_prefix = None;
+            let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+            for (key, typed_char) in presses {
+                if self.config.key_bindings.is(key, Action::Back) {
+                    if !self.selection.typed().is_empty() {
+                        self.selection.clear_typed();
+                        continue;
+                    }
+                    if self.sub_grid_click_kind.take().is_some() {
+                        println!("Click button prefix cleared");
+                        continue;
+                    }
+                    self.go_back();
+                    break;
+                }
+                // Checked before the bindings: the prefix keys may be bound to main grid actions
+                let prefix_kind = match typed_char.or_else(|| keybindings::key_label_char(key)) {
+                    Some(c) if c == self.config.actions.right_prefix_key => Some(ClickKind::Right),
+                    Some(c) if c == self.config.actions.middle_prefix_key => Some(ClickKind::Middle),
+                    _ => None,
+                };
+                if let Some(kind) = prefix_kind.filter(|_| self.selection.typed().is_empty()) {
+                    self.sub_grid_click_kind = Some(kind);
+                    println!("Next pick: {} click", kind.label());
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::SelectText) {
+                    let armed = self.pick_mode.toggle(PickMode::SelectTextStart);
+                    println!("Text selection {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::CopyCoordinates) {
+                    self.cycle_copy_target();
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::DenserSubGrid) {
+                    self.change_sub_grid_density(1);
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::SparserSubGrid) {
+                    self.change_sub_grid_density(-1);
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::FineAdjust) {
+                    let armed = self.pick_mode.toggle(PickMode::FineAdjust);
+                    println!("Fine adjust {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::ExtraLevel) {
+                    self.extra_level_toggled = !self.extra_level_toggled;
+                    println!("Grid levels for this selection: {}", self.total_levels());
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::ConfirmCenter) {
+                    match self.config.grid.confirm_center_action {
+                        ConfirmCenterAction::CellCenter => {
+                            if let Some(active_cell) = self.selection.selected_levels().last().copied() {
+                                self.select_point(ctx, active_cell.rect.center());
+                                break;
+                            }
+                        }
+                        ConfirmCenterAction::SubCell => {
+                            if let Some(sub_idx) = self.sub_cell_to_confirm(ctx) {
+                                println!("Picking sub-cell {} for confirm_center", sub_idx);
+                                self.clear_typed();
+                                self.pick_cell(ctx, sub_idx, self.selection.sub_rects()[sub_idx]);
+                                break;
+                            }
+                        }
+                        ConfirmCenterAction::Ignore => continue,
+                    }
+                }
+                if self.config.key_bindings.is(key, Action::Confirm)
+                    && let Some(sub_idx) = self.remembered_sub_cell()
+                {
+                    println!("Picking remembered sub-cell {}", sub_idx);
+                    self.selection.clear_typed();
+                    self.pick_cell(ctx, sub_idx, self.selection.sub_rects()[sub_idx]);
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::ForgetSubCells) {
+                    self.forget_sub_cells();
+                    continue;
+                }
+                if let Some(char_code) = typed_char {
+                    // Labels are one letter, or two once the sub-grid outgrows the alphabet
+                    match self.selection.type_char(char_code, None) {
+                        Step::Picked(sub_idx) if self.rapid_fire && self.selection.level() + 1 >= self.total_levels() => {
+                            // The grid stays as it is, so the next label can follow right away
+                            self.remember_sub_cell(sub_idx);
+                            self.rapid_fire_click(ctx, frame, self.selection.sub_rects()[sub_idx].center());
+                        }
+                        Step::Picked(sub_idx) => {
+                            self.remember_sub_cell(sub_idx);
+                            self.pick_cell(ctx, sub_idx, self.selection.sub_rects()[sub_idx]);
+                            break;
+                        }
+                        Step::Pending | Step::Corrected(_) | Step::Reset => {}
+                    }
+                }
+            }
+        } else if self.display_mode == grid::DisplayMode::Bisect {
+            if self.bisect_regions.is_empty() {
+                self.bisect_regions.reset(self.last_layout_screen_rect);
+            }
+            let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+            for (key, typed_char) in presses {
+                if self.config.key_bindings.is(key, Action::Back) {
+                    self.go_back();
+                    break;
+                }
+                if self.config.key_bindings.is(key, Action::SelectText) {
+                    let armed = self.pick_mode.toggle(PickMode::SelectTextStart);
+                    println!("Text selection {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::CopyCoordinates) {
+                    self.cycle_copy_target();
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::EnterScrollMode) {
+                    let armed = self.pick_mode.toggle(PickMode::Scroll);
+                    println!("Scroll mode {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if self.config.key_bindings.is(key, Action::FineAdjust) {
+                    let armed = self.pick_mode.toggle(PickMode::FineAdjust);
+                    println!("Fine adjust {}", if armed { "armed" } else { "disarmed" });
+                    continue;
+                }
+                if (self.config.key_bindings.is(key, Action::Confirm) || self.config.key_bindings.is(key, Action::ConfirmCenter))
+                    && let Some(region) = self.bisect_regions.current()
+                {
+                    self.select_point(ctx, region.center());
+                    break;
+                }
+                if let Some(quadrant) = typed_char.and_then(|c| self.config.bisect.quadrant_for_char(c))
+                    && self.bisect_regions.zoom(quadrant)
+                {
+                    println!("Bisect {:?}, depth {}, region {:?}", quadrant, self.bisect_regions.depth(), self.bisect_regions.current());
+                }
+            }
+        } else if matches!(
+            self.display_mode,
+            grid::DisplayMode::Hints | grid::DisplayMode::WindowHints | grid::DisplayMode::DockHints | grid::DisplayMode::MenuHints
+        ) {
+            if self.display_mode == grid::DisplayMode::MenuHints {
+                self.poll_opened_menu(ctx);
+            }
+            if let Some(index) = self.type_hint_label(ctx) {
+                match self.display_mode {
+                    grid::DisplayMode::Hints => {
+                        println!("Picked hint {}", self.hint_labels[index]);
+                        self.select_point(ctx, self.hint_rects[index].center());
+                    }
+                    grid::DisplayMode::WindowHints => self.pick_window(index),
+                    grid::DisplayMode::DockHints => self.press_dock_item(index),
+                    grid::DisplayMode::MenuHints => self.choose_menu_entry(index),
+                    _ => {}
+                }
+            }
+        } else if self.display_mode == grid::DisplayMode::Pointer {
+            #[cfg(target_os = "macos")]
+            if self.mouse_passthrough_guard.is_none() {
+                // Clicks at the cursor must reach the app underneath, not the overlay
+                self.mouse_passthrough_guard = ns_window_from_frame(frame)
+                    .and_then(|ns_window| macos::IgnoreMouseEventsGuard::new(ns_window, Duration::ZERO));
+            }
+            let click_requested = ctx.input(|i| i.events.iter().any(|event| {
+                matches!(event, egui::Event::Key { key, pressed: true, repeat: false, .. } if self.config.key_bindings.is(*key, Action::ConfirmCenter))
+            }));
+            if click_requested
+                && let Some(cursor) = click::current_cursor_location()
+            {
+                let kind = if self.lshift_key_is_pressed.load(AtomicOrdering::SeqCst) { ClickKind::Right } else { ClickKind::Left };
+                click::post_click(cursor, kind, Duration::from_millis(self.config.click.hold_ms));
+                self.last_click = Some((cursor, kind));
+            }
+        } else if self.display_mode == grid::DisplayMode::Scroll {
+            #[cfg(target_os = "macos")]
+            if self.mouse_passthrough_guard.is_none() {
+                // Scroll events go to the window under the cursor, which would otherwise be the overlay
+                self.mouse_passthrough_guard = ns_window_from_frame(frame)
+                    .and_then(|ns_window| macos::IgnoreMouseEventsGuard::new(ns_window, Duration::ZERO));
+            }
+            let (held_direction, pressed_steps, pressed_pages) = ctx.input(|i| {
+                let mut held_direction = egui::Vec2::ZERO;
+                if i.key_down(egui::Key::ArrowUp) { held_direction.y -= 1.0; }
+                if i.key_down(egui::Key::ArrowDown) { held_direction.y += 1.0; }
+                if i.key_down(egui::Key::ArrowLeft) { held_direction.x -= 1.0; }
+                if i.key_down(egui::Key::ArrowRight) { held_direction.x += 1.0; }
+                let mut pressed_steps = egui::Vec2::ZERO;
+                for event in &i.events {
+                    if let egui::Event::Key { key, pressed: true, repeat: false, .. } = event {
+                        match key {
+                            egui::Key::ArrowUp => pressed_steps.y -= 1.0,
+                            egui::Key::ArrowDown => pressed_steps.y += 1.0,
+                            egui::Key::ArrowLeft => pressed_steps.x -= 1.0,
+                            egui::Key::ArrowRight => pressed_steps.x += 1.0,
+                            _ => {}
+                        }
+                    }
+                }
+                let mut pressed_pages = 0.0;
+                for event in &i.events {
+                    if let egui::Event::Key { key, pressed: true, .. } = event {
+                        match key {
+                            egui::Key::PageUp => pressed_pages -= 1.0,
+                            egui::Key::PageDown => pressed_pages += 1.0,
+                            _ => {}
+                        }
+                    }
+                }
+                (held_direction, pressed_steps, pressed_pages)
+            });
+            if pressed_steps != egui::Vec2::ZERO {
+                scroll::post_scroll(pressed_steps * self.config.scroll.step_px);
+            }
+            if pressed_pages != 0.0 {
+                let page_px = ctx.screen_rect().height() * self.config.scroll.page_fraction;
+                scroll::post_scroll(egui::vec2(0.0, pressed_pages * page_px));
+            }
+            if let Some(delta) = self.scroll_repeater.tick(held_direction, Instant::now(), &self.config.scroll) {
+                scroll::post_scroll(delta);
+            }
+        } else if let grid::DisplayMode::FineAdjust { point, origin } = self.display_mode {
+            let step = self.config.fine_adjust.step_px;
+            let shift_step = self.config.fine_adjust.shift_step_px;
+            let mut adjusted = point;
+            let mut commit = false;
+            let mut save_bookmark = false;
+            let mut copy = false;
+            ctx.input(|i| {
+                for event in &i.events {
+                    if let egui::Event::Key { key, pressed: true, modifiers, .. } = event {
+                        let amount = if modifiers.shift { shift_step } else { step };
+                        match key {
+                            egui::Key::ArrowUp => adjusted.y -= amount,
+                            egui::Key::ArrowDown => adjusted.y += amount,
+                            egui::Key::ArrowLeft => adjusted.x -= amount,
+                            egui::Key::ArrowRight => adjusted.x += amount,
+                            _ if self.config.key_bindings.is(*key, Action::Confirm) => commit = true,
+                            _ if self.config.key_bindings.is(*key, Action::Bookmark) => save_bookmark = true,
+                            _ if self.config.key_bindings.is(*key, Action::CopyCoordinates) => copy = true,
+                            _ => {}
+                        }
+                    }
+                }
+            });
+            adjusted = self.last_layout_screen_rect.clamp(adjusted);
+            if save_bookmark
+                && let Some(point) = self.to_global_point(ctx, adjusted)
+            {
+                self.open_bookmark_prompt(BookmarkPrompt::SavePoint(point));
+            }
+            if copy {
+                self.display_mode = self.selection_stage();
+                self.copy_point_coordinates(ctx, adjusted);
+            } else if commit {
+                println!("Fine adjust committed at {:?} (moved {:?})", adjusted, adjusted - origin);
+                self.display_mode = self.selection_stage();
+                self.commit_point(ctx, adjusted);
+            } else if adjusted != point {
+                self.display_mode = grid::DisplayMode::FineAdjust { point: adjusted, origin };
+            }
+        } else if self.display_mode == grid::DisplayMode::ActionPrompt {
+            if let Some(point) = self.action_prompt_point {
+                let mut chosen_kind = None;
+                let presses = ctx.input(|i| key_presses(&i.events, &self.config.key_bindings));
+                for (key, typed_char) in presses {
+                    if self.config.key_bindings.is(key, Action::Back) {
+                        self.go_back();
+                        break;
+                    }
+                    if self.config.key_bindings.is(key, Action::Confirm) || self.config.key_bindings.is(key, Action::ConfirmCenter) {
+                        chosen_kind = Some(ClickKind::Left);
+                        break;
+                    }
+                    if let Some(kind) = typed_char.and_then(|c| self.action_for_char(c)) {
+                        chosen_kind = Some(kind);
+                        break;
+                    }
+                    if let Some(combo) = self.config.action_shortcut.filter(|_| typed_char == Some(self.config.actions.shortcut_key)) {
+                        self.action_prompt_point = None;
+                        self.action_prompt_started_at = None;
+                        self.perform_click_then_keys(ctx, point, combo);
+                        break;
+                    }
+                }
+                let timed_out = self.action_prompt_started_at
+                    .is_some_and(|started| started.elapsed() >= Duration::from_millis(self.config.actions.timeout_ms));
+                if chosen_kind.is_none() && timed_out {
+                    println!("Action prompt timed out, using plain click");
+                    chosen_kind = Some(ClickKind::Left);
+                }
+                if let Some(kind) = chosen_kind {
+                    self.action_prompt_point = None;
+                    self.action_prompt_started_at = None;
+                    self.perform_mouse_click(ctx, point, kind);
+                }
+            } else {
+                self.display_mode = grid::DisplayMode::MainGrid;
+            }
+        }
+
+        #[cfg(target_os = "macos")]
+        self.update_magnifier(ctx, frame);
+        let opacity = self.fade_opacity(ctx, self.eframe_control.is_visible.load(AtomicOrdering::SeqCst));
+        self.paint_displays(ctx, opacity);
+        if self.eframe_control.is_visible.load(AtomicOrdering::SeqCst)
+            && !self.eframe_control.overlay_rendered.swap(true, AtomicOrdering::SeqCst)
+        {
+            println!("Overlay rendered");
+        }
+        ctx.request_repaint();
+    }
+}
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+    use std::sync::mpsc::{channel, Sender};
+
+    use super::*;
+    use crate::theme::{DisplayAccessibility, SystemAppearance};
+
+    const SCREEN: egui::Rect = egui::Rect { min: egui::Pos2::ZERO, max: egui::pos2(1440.0, 900.0) };
+    /// What egui assumes a headless frame takes; the hide phase's clock moves on by as much.
+    const FRAME: Duration = Duration::from_micros(16_667);
+    /// Enough frames for the fade-out and the wait before a queued click.
+    const MAX_HIDE_FRAMES: usize = 200;
+
+    /// Time that only moves when the harness runs a frame.
+    #[derive(Clone)]
+    struct ManualClock(Arc<Mutex<Instant>>);
+
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    struct Harness {
+        ctx: egui::Context,
+        tx: Sender<GlobalEvent>,
+        clock: ManualClock,
+        app: MouselessApp,
+    }
+
+    impl Harness {
+        fn new(config: Config) -> Self {
+            let ctx = egui::Context::default();
+            let (tx, rx) = channel();
+            let clock = ManualClock(Arc::new(Mutex::new(Instant::now())));
+            let mut app = MouselessApp::headless(&ctx, SCREEN, rx, config);
+            app.clock = Box::new(clock.clone());
+            Self { ctx, tx, clock, app }
+        }
+
+        fn frame(&mut self, events: Vec<egui::Event>) {
+            self.painted(events);
+        }
+
+        /// Runs a frame and returns the shapes it painted, with nested lists flattened.
+        fn painted(&mut self, events: Vec<egui::Event>) -> Vec<egui::Shape> {
+            let mut input = egui::RawInput { screen_rect: Some(SCREEN), events, ..Default::default() };
+            // Window-relative picks become global points through the viewport's outer rect
+            input.viewports.entry(egui::ViewportId::ROOT).or_default().outer_rect = Some(SCREEN);
+            let app = &mut self.app;
+            let output = self.ctx.run(input, |ctx| app.headless_frame(ctx));
+            self.clock.advance(FRAME);
+            let mut shapes = Vec::new();
+            let mut pending: Vec<egui::Shape> = output.shapes.into_iter().map(|clipped| clipped.shape).collect();
+            while let Some(shape) = pending.pop() {
+                match shape {
+                    egui::Shape::Vec(nested) => pending.extend(nested),
+                    shape => shapes.push(shape),
+                }
+            }
+            shapes
+        }
+
+        fn send(&self, event: GlobalEvent) {
+            self.tx.send(event).unwrap();
+        }
+
+        fn tap_show_grid(&self) {
+            self.send(GlobalEvent::TapGesture { taps: 2, action: TapAction::ShowGrid, cursor_pos: None });
+        }
+
+        /// Shows the grid and runs the frame that lays it out.
+        fn show(&mut self) {
+            self.tap_show_grid();
+            self.frame(Vec::new());
+            assert!(self.is_visible());
+            assert!(!self.app.selection.main_rects().is_empty(), "the first visible frame lays out the main grid");
+        }
+
+        fn is_visible(&self) -> bool {
+            self.app.eframe_control.is_visible.load(AtomicOrdering::SeqCst)
+        }
+
+        /// Types a main label, then a sub-grid label, one frame each, returning the sub-cell picked.
+        fn pick(&mut self, main_index: usize, sub_index: usize) -> egui::Rect {
+            let main_label = self.app.selection.main_labels()[main_index].clone();
+            self.frame(typed(&main_label));
+            assert_eq!(self.app.display_mode, grid::DisplayMode::SubGrid);
+            assert_eq!(self.app.selection.selected_levels()[0].index, main_index);
+            let sub_rect = self.app.selection.sub_rects()[sub_index];
+            let sub_label = self.app.selection.sub_labels()[sub_index].clone();
+            self.frame(typed(&sub_label));
+            sub_rect
+        }
+    }
+
+    /// The defaults, minus snapping to accessibility elements: on a real Mac that would move the
+    /// click to whatever app sits under the picked cell.
+    fn test_config() -> Config {
+        let mut config = Config::default();
+        config.click.snap_to_elements = false;
+        config
+    }
+
+    /// Key presses with the text each one types, as winit reports typing: labels are matched on the
+    /// Text event, the key alone only stands in when it produced none.
+    fn typed(label: &str) -> Vec<egui::Event> {
+        label
+            .chars()
+            .flat_map(|c| {
+                let key = egui::Key::from_name(&c.to_string()).expect("label characters are keys");
+                [
+                    egui::Event::Key { key, physical_key: None, pressed: true, repeat: false, modifiers: egui::Modifiers::NONE },
+                    egui::Event::Text(c.to_ascii_lowercase().to_string()),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn typed_labels_queue_a_click_at_the_picked_sub_cell() {
+        let mut harness = Harness::new(test_config());
+        harness.show();
+        let sub_rect = harness.pick(5, 3);
+        let (point, kind) = harness.app.pending_click().expect("a click should be queued");
+        assert_eq!(kind, ClickKind::Left);
+        assert_eq!(point, click::pixel_center(SCREEN.min + sub_rect.center().to_vec2(), harness.ctx.pixels_per_point()));
+        assert!(harness.app.eframe_control.hide_requested.load(AtomicOrdering::SeqCst));
+    }
+
+    #[test]
+    fn a_label_that_matches_nothing_queues_nothing() {
+        let mut harness = Harness::new(test_config());
+        harness.show();
+        let unused = harness.app.config.label_alphabets.all_chars().into_iter()
+            .find(|c| !harness.app.selection.main_labels().iter().any(|label| label.starts_with(*c)));
+        let Some(unused) = unused else {
+            return;
+        };
+        harness.frame(typed(&unused.to_string()));
+        assert_eq!(harness.app.display_mode, grid::DisplayMode::MainGrid);
+        assert_eq!(harness.app.selection.typed(), "");
+        assert!(harness.app.pending_click().is_none());
+    }
+
+    #[test]
+    fn the_right_click_prefix_changes_the_queued_click() {
+        let mut harness = Harness::new(test_config());
+        harness.show();
+        let main_label = harness.app.selection.main_labels()[0].clone();
+        harness.frame(typed(&main_label));
+        assert_eq!(harness.app.config.actions.right_prefix_key, ';');
+        // Semicolon is bound to bisect mode too; in the sub-grid the prefix comes first
+        let mut events = vec![
+            egui::Event::Key { key: egui::Key::Semicolon, physical_key: None, pressed: true, repeat: false, modifiers: egui::Modifiers::NONE },
+            egui::Event::Text(";".to_string()),
+        ];
+        events.extend(typed(&harness.app.selection.sub_labels()[0].clone()));
+        harness.frame(events);
+        let (_, kind) = harness.app.pending_click().expect("a click should be queued");
+        assert_eq!(kind, ClickKind::Right);
+    }
+
+    #[test]
+    fn the_copy_key_copies_the_picked_point_instead_of_clicking() {
+        let mut harness = Harness::new(test_config());
+        harness.show();
+        clipboard::take_copied();
+        harness.frame(vec![
+            egui::Event::Key { key: egui::Key::Quote, physical_key: None, pressed: true, repeat: false, modifiers: egui::Modifiers::NONE },
+            egui::Event::Text("'".to_string()),
+        ]);
+        assert_eq!(harness.app.copy_target, Some(CopyTarget::Coordinates));
+        let sub_rect = harness.pick(4, 1);
+        let point = click::pixel_center(SCREEN.min + sub_rect.center().to_vec2(), harness.ctx.pixels_per_point());
+        assert_eq!(clipboard::take_copied(), vec![click::format_point(point)]);
+        assert!(harness.app.pending_click().is_none());
+        assert_eq!(harness.app.copy_target, None);
+    }
+
+    #[test]
+    fn the_sub_grid_frame_paints_only_the_picked_main_cell() {
+        fn texts(shapes: &[egui::Shape]) -> Vec<String> {
+            shapes.iter().filter_map(|shape| match shape {
+                egui::Shape::Text(text) => Some(text.galley.text().to_string()),
+                _ => None,
+            }).collect()
+        }
+        let mut harness = Harness::new(test_config());
+        harness.show();
+        let main_grid = harness.painted(Vec::new());
+        let main_labels = harness.app.selection.main_labels().to_vec();
+        let main_texts = texts(&main_grid);
+        assert!(main_labels.iter().all(|label| main_texts.contains(label)), "the main grid paints every main label");
+
+        harness.frame(typed(&main_labels[5]));
+        assert_eq!(harness.app.display_mode, grid::DisplayMode::SubGrid);
+        let sub_grid = harness.painted(Vec::new());
+        assert!(
+            sub_grid.len() * 4 < main_grid.len(),
+            "the sub-grid frame painted {} shapes, the main grid {}", sub_grid.len(), main_grid.len()
+        );
+        let sub_texts = texts(&sub_grid);
+        for (index, label) in main_labels.iter().enumerate().filter(|(index, _)| *index != 5) {
+            assert!(!sub_texts.contains(label), "main cell {} label {} painted under the sub-grid", index, label);
+        }
+    }
+
+    #[test]
+    fn minus_fades_the_main_grid_and_thins_the_sub_grid() {
+        let minus = || vec![
+            egui::Event::Key { key: egui::Key::Minus, physical_key: None, pressed: true, repeat: false, modifiers: egui::Modifiers::NONE },
+            egui::Event::Text("-".to_string()),
+        ];
+        let mut config = test_config();
+        config.theme.hud = false;
+        let mut harness = Harness::new(config);
+        harness.show();
+        let (percent, sub_grid_dims) = (harness.app.overlay_opacity.percent(), harness.app.sub_grid_dims);
+        let shapes = harness.painted(minus());
+        assert!(harness.app.overlay_opacity.percent() < percent);
+        assert_eq!(harness.app.sub_grid_dims, sub_grid_dims);
+        let flash = format!("Opacity {}%", harness.app.overlay_opacity.percent());
+        let shapes = [shapes, harness.painted(Vec::new())].concat();
+        assert!(
+            shapes.iter().any(|shape| matches!(shape, egui::Shape::Text(text) if text.galley.text() == flash)),
+            "the new opacity flashes with the HUD off"
+        );
+
+        let main_label = harness.app.selection.main_labels()[0].clone();
+        harness.frame(typed(&main_label));
+        let percent = harness.app.overlay_opacity.percent();
+        harness.frame(minus());
+        assert_eq!(harness.app.overlay_opacity.percent(), percent);
+        assert_eq!(harness.app.sub_grid_dims, (sub_grid_dims.0 - 1, sub_grid_dims.1 - 1));
+    }
+
+    #[test]
+    fn a_show_during_the_hide_waits_for_the_click_then_shows_the_grid() {
+        let mut harness = Harness::new(test_config());
+        harness.show();
+        harness.pick(2, 0);
+        assert!(harness.app.pending_click().is_some());
+        assert!(harness.app.is_hiding());
+
+        // Tapped again while the overlay fades out: neither a toggle nor a cancel of the click
+        harness.tap_show_grid();
+        harness.frame(Vec::new());
+        assert!(harness.app.pending_show.is_some());
+        assert!(harness.app.pending_click().is_some());
+
+        let mut hid_with_click_queued = false;
+        for _ in 0..MAX_HIDE_FRAMES {
+            if !harness.is_visible() && harness.app.pending_click().is_some() {
+                hid_with_click_queued = true;
+            }
+            if harness.app.pending_click().is_none() {
+                break;
+            }
+            harness.frame(Vec::new());
+        }
+        assert!(hid_with_click_queued, "the overlay should hide before the click runs");
+        assert!(harness.app.pending_click().is_none(), "the click should have run");
+        assert!(harness.app.pending_show.is_none());
+        assert!(harness.is_visible(), "the held tap should show the grid once the click is done");
+        assert_eq!(harness.app.display_mode, grid::DisplayMode::MainGrid);
+        assert!(!harness.app.eframe_control.hide_requested.load(AtomicOrdering::SeqCst));
+    }
+
+    #[test]
+    fn escape_during_the_hide_drops_the_held_show() {
+        let mut harness = Harness::new(test_config());
+        harness.show();
+        harness.pick(2, 0);
+        harness.tap_show_grid();
+        harness.frame(Vec::new());
+        assert!(harness.app.pending_show.is_some());
+
+        harness.send(GlobalEvent::EscapePressed { force_hide: false });
+        harness.frame(Vec::new());
+        assert!(harness.app.pending_show.is_none());
+        assert!(harness.app.pending_click().is_none());
+        for _ in 0..MAX_HIDE_FRAMES {
+            if !harness.is_visible() {
+                break;
+            }
+            harness.frame(Vec::new());
+        }
+        assert!(!harness.is_visible());
+        assert!(harness.app.pending_show.is_none());
+    }
+
+    #[test]
+    fn auto_theme_follows_light_to_dark() {
+        let mut config = test_config();
+        assert!(config.theme.follows_system());
+        let accessibility = DisplayAccessibility::default();
+        let light = config.theme.resolve(SystemAppearance::Light, accessibility).unwrap();
+        let dark = config.theme.resolve(SystemAppearance::Dark, accessibility).unwrap();
+        assert_ne!(light, dark);
+        config.appearance = light;
+        let mut harness = Harness::new(config);
+
+        harness.send(GlobalEvent::AppearanceChanged { appearance: SystemAppearance::Dark, accessibility });
+        harness.frame(Vec::new());
+        assert_eq!(harness.app.config.appearance, dark);
+
+        harness.send(GlobalEvent::AppearanceChanged { appearance: SystemAppearance::Light, accessibility });
+        harness.frame(Vec::new());
+        assert_eq!(harness.app.config.appearance, light);
+
+        // Increase contrast picks the high contrast theme whichever appearance comes with it
+        let increase_contrast = DisplayAccessibility { increase_contrast: true, reduce_transparency: false };
+        harness.send(GlobalEvent::AppearanceChanged { appearance: SystemAppearance::Light, accessibility: increase_contrast });
+        harness.frame(Vec::new());
+        assert_eq!(harness.app.config.appearance, harness.app.config.theme.resolve(SystemAppearance::Dark, increase_contrast).unwrap());
+        assert_ne!(harness.app.config.appearance, light);
+    }
+
+    #[test]
+    fn a_named_theme_ignores_appearance_changes() {
+        let mut config = test_config();
+        config.theme.name = "light".to_string();
+        config.appearance = Theme::light();
+        let mut harness = Harness::new(config);
+        harness.send(GlobalEvent::AppearanceChanged { appearance: SystemAppearance::Dark, accessibility: DisplayAccessibility::default() });
+        harness.frame(Vec::new());
+        assert_eq!(harness.app.config.appearance, Theme::light());
+    }
+
+    /// Median time to run and tessellate one frame on a 4K display (1920x1080 points at 2x), on
+    /// the main grid and under the sub-grid. Timing only: run it with
+    /// `cargo test --release frame_cost -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn frame_cost() {
+        const FRAMES: usize = 500;
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1920.0, 1080.0));
+        let ctx = egui::Context::default();
+        ctx.set_pixels_per_point(2.0);
+        let (tx, rx) = channel();
+        let mut app = MouselessApp::headless(&ctx, screen, rx, test_config());
+        let frame = |app: &mut MouselessApp, events: Vec<egui::Event>| {
+            let input = egui::RawInput { screen_rect: Some(screen), events, ..Default::default() };
+            let output = ctx.run(input, |ctx| app.headless_frame(ctx));
+            let shapes = output.shapes.len();
+            ctx.tessellate(output.shapes, output.pixels_per_point);
+            shapes
+        };
+        let measure = |app: &mut MouselessApp, name: &str| {
+            let mut shapes = 0;
+            let mut times: Vec<Duration> = (0..FRAMES)
+                .map(|_| {
+                    let started = Instant::now();
+                    shapes = frame(app, Vec::new());
+                    started.elapsed()
+                })
+                .collect();
+            times.sort();
+            println!("{name}: {shapes} shapes, median {:?}, p95 {:?}", times[FRAMES / 2], times[FRAMES * 95 / 100]);
+        };
+
+        tx.send(GlobalEvent::TapGesture { taps: 2, action: TapAction::ShowGrid, cursor_pos: None }).unwrap();
+        frame(&mut app, Vec::new());
+        measure(&mut app, "main grid");
+        let label = app.selection.main_labels()[app.selection.main_labels().len() / 2].clone();
+        frame(&mut app, typed(&label));
+        assert_eq!(app.display_mode, grid::DisplayMode::SubGrid);
+        measure(&mut app, "sub-grid");
+    }
+}
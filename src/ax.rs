@@ -0,0 +1,378 @@
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use core_foundation::array::{CFArrayGetCount, CFArrayGetTypeID, CFArrayGetValueAtIndex, CFArrayRef};
+use core_foundation::base::{CFGetTypeID, CFRelease, CFRetain, CFType, CFTypeRef, TCFType};
+use core_foundation::boolean::CFBoolean;
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::string::{CFString, CFStringRef};
+use core_graphics::geometry::{CGPoint, CGRect, CGSize};
+
+/// Roles that do what the user meant when clicked at their center.
+const CLICKABLE_ROLES: [&str; 10] = [
+    "AXButton", "AXLink", "AXCheckBox", "AXRadioButton", "AXMenuItem",
+    "AXMenuBarItem", "AXMenuButton", "AXPopUpButton", "AXDisclosureTriangle", "AXComboBox",
+];
+/// Text inputs get hints too; clicking one focuses it.
+const TEXT_INPUT_ROLES: [&str; 2] = ["AXTextField", "AXTextArea"];
+/// Limits for walking a window's accessibility tree, which gets huge in web views.
+const MAX_HINT_DEPTH: usize = 30;
+const MAX_HINT_ELEMENTS: usize = 400;
+const HINT_WALK_TIMEOUT_MS: u64 = 300;
+/// Parents walked up from the element under the point, e.g. from a button's text to the button.
+const MAX_PARENT_DEPTH: usize = 3;
+/// Longest the click waits for an answer; a hung app must not hold it up.
+const QUERY_TIMEOUT_MS: u64 = 50;
+
+type AXUIElementRef = *const c_void;
+type AXError = i32;
+const AX_ERROR_SUCCESS: AXError = 0;
+const AX_VALUE_CG_POINT_TYPE: u32 = 1;
+const AX_VALUE_CG_SIZE_TYPE: u32 = 2;
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn AXUIElementCreateSystemWide() -> AXUIElementRef;
+    fn AXUIElementCreateApplication(pid: i32) -> AXUIElementRef;
+    fn AXUIElementSetMessagingTimeout(element: AXUIElementRef, timeout_secs: f32) -> AXError;
+    fn AXUIElementCopyElementAtPosition(element: AXUIElementRef, x: f32, y: f32, hit: *mut AXUIElementRef) -> AXError;
+    fn AXUIElementCopyAttributeValue(element: AXUIElementRef, attribute: CFStringRef, value: *mut CFTypeRef) -> AXError;
+    fn AXUIElementSetAttributeValue(element: AXUIElementRef, attribute: CFStringRef, value: CFTypeRef) -> AXError;
+    fn AXUIElementIsAttributeSettable(element: AXUIElementRef, attribute: CFStringRef, settable: *mut u8) -> AXError;
+    fn AXUIElementPerformAction(element: AXUIElementRef, action: CFStringRef) -> AXError;
+    fn AXValueGetValue(value: CFTypeRef, value_type: u32, value_ptr: *mut c_void) -> bool;
+    fn AXValueCreate(value_type: u32, value_ptr: *const c_void) -> CFTypeRef;
+    fn AXIsProcessTrusted() -> bool;
+    fn AXIsProcessTrustedWithOptions(options: CFDictionaryRef) -> bool;
+    static kAXTrustedCheckOptionPrompt: CFStringRef;
+}
+
+/// Owned AXUIElementRef, released on drop.
+pub struct AXElement(AXUIElementRef);
+
+impl Drop for AXElement {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0) }
+    }
+}
+
+impl Clone for AXElement {
+    fn clone(&self) -> Self {
+        unsafe { CFRetain(self.0) };
+        AXElement(self.0)
+    }
+}
+
+impl AXElement {
+    fn application(pid: i32) -> Option<AXElement> {
+        let element = unsafe { AXUIElementCreateApplication(pid) };
+        if element.is_null() {
+            return None;
+        }
+        unsafe { AXUIElementSetMessagingTimeout(element, QUERY_TIMEOUT_MS as f32 / 1000.0) };
+        Some(AXElement(element))
+    }
+
+    /// Takes over the reference an attribute copy returned.
+    fn from_attribute(value: CFType) -> AXElement {
+        let element = AXElement(value.as_CFTypeRef());
+        std::mem::forget(value);
+        element
+    }
+
+    fn attribute(&self, name: &str) -> Option<CFType> {
+        let name = CFString::new(name);
+        let mut value: CFTypeRef = ptr::null();
+        let error = unsafe { AXUIElementCopyAttributeValue(self.0, name.as_concrete_TypeRef(), &mut value) };
+        if error != AX_ERROR_SUCCESS || value.is_null() {
+            return None;
+        }
+        Some(unsafe { CFType::wrap_under_create_rule(value) })
+    }
+
+    /// Whether the app lets `name` be changed, e.g. AXSize on a window that can't be resized.
+    pub fn is_settable(&self, name: &str) -> bool {
+        let name = CFString::new(name);
+        let mut settable = 0u8;
+        let error = unsafe { AXUIElementIsAttributeSettable(self.0, name.as_concrete_TypeRef(), &mut settable) };
+        error == AX_ERROR_SUCCESS && settable != 0
+    }
+
+    fn set_value(&self, name: &str, value_type: u32, value_ptr: *const c_void) -> bool {
+        let value = unsafe { AXValueCreate(value_type, value_ptr) };
+        if value.is_null() {
+            return false;
+        }
+        let value = unsafe { CFType::wrap_under_create_rule(value) };
+        let attribute = CFString::new(name);
+        let error = unsafe { AXUIElementSetAttributeValue(self.0, attribute.as_concrete_TypeRef(), value.as_CFTypeRef()) };
+        if error != AX_ERROR_SUCCESS {
+            eprintln!("Setting {} failed with AXError {}", name, error);
+        }
+        error == AX_ERROR_SUCCESS
+    }
+
+    /// Moves a window so its top-left is at `origin`, in global top-left-origin coordinates.
+    pub fn set_position(&self, origin: CGPoint) -> bool {
+        self.set_value("AXPosition", AX_VALUE_CG_POINT_TYPE, &origin as *const CGPoint as *const c_void)
+    }
+
+    pub fn set_size(&self, size: CGSize) -> bool {
+        self.set_value("AXSize", AX_VALUE_CG_SIZE_TYPE, &size as *const CGSize as *const c_void)
+    }
+
+    /// Moves a window to `origin` and, when `size` is given, resizes it. Size, then position, then
+    /// size again: the first size may be refused while the window would still hang off the display
+    /// at its old position.
+    pub fn set_frame(&self, origin: CGPoint, size: Option<CGSize>) {
+        if let Some(size) = size {
+            self.set_size(size);
+        }
+        self.set_position(origin);
+        if let Some(size) = size {
+            self.set_size(size);
+        }
+    }
+
+    fn string_attribute(&self, name: &str) -> Option<String> {
+        self.attribute(name)?.downcast::<CFString>().map(|value| value.to_string())
+    }
+
+    fn role(&self) -> Option<String> {
+        self.string_attribute("AXRole")
+    }
+
+    fn subrole(&self) -> Option<String> {
+        self.string_attribute("AXSubrole")
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.string_attribute("AXTitle")
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.attribute("AXEnabled").and_then(|value| value.downcast::<CFBoolean>()).is_none_or(bool::from)
+    }
+
+    fn perform_action(&self, action: &str) -> bool {
+        let action_name = CFString::new(action);
+        let error = unsafe { AXUIElementPerformAction(self.0, action_name.as_concrete_TypeRef()) };
+        if error != AX_ERROR_SUCCESS {
+            eprintln!("{} failed with AXError {}", action, error);
+        }
+        error == AX_ERROR_SUCCESS
+    }
+
+    /// Activates the element the way a click would; menus and menu items open or run.
+    pub fn press(&self) -> bool {
+        self.perform_action("AXPress")
+    }
+
+    /// Closes an open menu.
+    pub fn cancel(&self) -> bool {
+        self.perform_action("AXCancel")
+    }
+
+    fn parent(&self) -> Option<AXElement> {
+        self.attribute("AXParent").map(AXElement::from_attribute)
+    }
+
+    fn children(&self) -> Vec<AXElement> {
+        self.elements("AXChildren")
+    }
+
+    /// Elements of an array-valued attribute such as AXChildren or AXWindows.
+    fn elements(&self, name: &str) -> Vec<AXElement> {
+        let Some(elements) = self.attribute(name) else {
+            return Vec::new();
+        };
+        unsafe {
+            if CFGetTypeID(elements.as_CFTypeRef()) != CFArrayGetTypeID() {
+                return Vec::new();
+            }
+            let array = elements.as_CFTypeRef() as CFArrayRef;
+            (0..CFArrayGetCount(array))
+                .map(|index| CFArrayGetValueAtIndex(array, index))
+                .filter(|child| !child.is_null())
+                .map(|child| {
+                    // Array items are borrowed
+                    CFRetain(child);
+                    AXElement(child)
+                })
+                .collect()
+        }
+    }
+
+    /// Frame in global top-left-origin coordinates, like CGEvent locations.
+    pub fn frame(&self) -> Option<CGRect> {
+        let mut origin = CGPoint::new(0.0, 0.0);
+        let mut size = CGSize::new(0.0, 0.0);
+        let position = self.attribute("AXPosition")?;
+        let extent = self.attribute("AXSize")?;
+        let read = unsafe {
+            AXValueGetValue(position.as_CFTypeRef(), AX_VALUE_CG_POINT_TYPE, &mut origin as *mut CGPoint as *mut c_void)
+                && AXValueGetValue(extent.as_CFTypeRef(), AX_VALUE_CG_SIZE_TYPE, &mut size as *mut CGSize as *mut c_void)
+        };
+        read.then(|| CGRect::new(&origin, &size))
+    }
+
+    fn center(&self) -> Option<CGPoint> {
+        self.frame().map(|frame| CGPoint::new(frame.origin.x + frame.size.width / 2.0, frame.origin.y + frame.size.height / 2.0))
+    }
+}
+
+/// Whether this process is in the Accessibility list, which the event tap and every AX call need.
+pub fn is_process_trusted() -> bool {
+    unsafe { AXIsProcessTrusted() }
+}
+
+/// Asks the system to show its prompt pointing the user at the Accessibility list. Shown once per
+/// launch at most; returns whether the process is already trusted.
+pub fn prompt_for_trust() -> bool {
+    let key = unsafe { CFString::wrap_under_get_rule(kAXTrustedCheckOptionPrompt) };
+    let options = CFDictionary::from_CFType_pairs(&[(key.as_CFType(), CFBoolean::true_value().as_CFType())]);
+    unsafe { AXIsProcessTrustedWithOptions(options.as_concrete_TypeRef()) }
+}
+
+/// Focused window of the app with `pid`.
+pub fn focused_window(pid: i32) -> Option<AXElement> {
+    AXElement::application(pid)?.attribute("AXFocusedWindow").map(AXElement::from_attribute)
+}
+
+/// Menu bar of the app with `pid`.
+pub fn menu_bar(pid: i32) -> Option<AXElement> {
+    AXElement::application(pid)?.attribute("AXMenuBar").map(AXElement::from_attribute)
+}
+
+/// The menu a menu bar item or menu item opens, if it has one.
+pub fn submenu(item: &AXElement) -> Option<AXElement> {
+    item.children().into_iter().find(|child| child.role().as_deref() == Some("AXMenu"))
+}
+
+/// Enabled, titled entries of a menu bar or open menu with their frames; separators have no title.
+pub fn menu_entries(menu: &AXElement) -> Vec<(CGRect, AXElement)> {
+    menu.children().into_iter()
+        .filter(|entry| entry.is_enabled() && entry.title().is_some_and(|title| !title.is_empty()))
+        .filter_map(|entry| {
+            entry.frame()
+                .filter(|frame| frame.size.width > 0.0 && frame.size.height > 0.0)
+                .map(|frame| (frame, entry))
+        })
+        .collect()
+}
+
+/// Items of the Dock with `dock_pid` (apps, folders, minimized windows, the Trash) with their frames,
+/// in Dock order. Separators are skipped. An auto-hidden Dock still reports frames, off the screen edge.
+pub fn dock_items(dock_pid: i32) -> Vec<(CGRect, AXElement)> {
+    let Some(dock) = AXElement::application(dock_pid) else {
+        return Vec::new();
+    };
+    dock.children().into_iter()
+        .filter(|child| child.role().as_deref() == Some("AXList"))
+        .flat_map(|list| list.children())
+        .filter(|item| item.role().as_deref() == Some("AXDockItem") && item.subrole().as_deref() != Some("AXSeparatorDockItem"))
+        .filter_map(|item| {
+            item.frame()
+                .filter(|frame| frame.size.width > 0.0 && frame.size.height > 0.0)
+                .map(|frame| (frame, item))
+        })
+        .collect()
+}
+
+/// Raises the window of the app with `pid` whose frame is `bounds`. Window server listings have
+/// no handle Accessibility understands, so the window is matched by its frame.
+pub fn raise_window(pid: i32, bounds: CGRect) -> bool {
+    let Some(app) = AXElement::application(pid) else {
+        return false;
+    };
+    let same_frame = |frame: &CGRect| {
+        (frame.origin.x - bounds.origin.x).abs() < 2.0 && (frame.origin.y - bounds.origin.y).abs() < 2.0
+            && (frame.size.width - bounds.size.width).abs() < 2.0 && (frame.size.height - bounds.size.height).abs() < 2.0
+    };
+    match app.elements("AXWindows").into_iter().find(|window| window.frame().is_some_and(|frame| same_frame(&frame))) {
+        Some(window) => window.perform_action("AXRaise"),
+        None => {
+            println!("No accessible window of pid {} at {:?}", pid, bounds);
+            false
+        }
+    }
+}
+
+/// Clickable elements and text inputs in `window` with a non-empty frame. Children of a clickable
+/// element are skipped (a button's label is not a second target). The walk stops at
+/// MAX_HINT_DEPTH levels, MAX_HINT_ELEMENTS results or HINT_WALK_TIMEOUT_MS, whichever comes first.
+pub fn collect_clickable_elements(window: &AXElement) -> Vec<(CGRect, AXElement)> {
+    let started = Instant::now();
+    let mut found = Vec::new();
+    let mut pending = vec![(window.clone(), 0)];
+    while let Some((element, depth)) = pending.pop() {
+        if found.len() >= MAX_HINT_ELEMENTS || started.elapsed() >= Duration::from_millis(HINT_WALK_TIMEOUT_MS) {
+            println!("Stopped collecting hint elements after {} in {:?}", found.len(), started.elapsed());
+            break;
+        }
+        let role = element.role().unwrap_or_default();
+        if CLICKABLE_ROLES.contains(&role.as_str()) || TEXT_INPUT_ROLES.contains(&role.as_str()) {
+            if let Some(frame) = element.frame().filter(|frame| frame.size.width > 0.0 && frame.size.height > 0.0) {
+                found.push((frame, element));
+            }
+            continue;
+        }
+        if depth < MAX_HINT_DEPTH {
+            pending.extend(element.children().into_iter().map(|child| (child, depth + 1)));
+        }
+    }
+    found
+}
+
+/// Center of the clickable element at `point` (or the button/link containing it) when that center
+/// is within `max_distance` points. Hit-tests inside the app with `pid`, so the overlay itself is
+/// never found; without a pid the system-wide element is used. Gives up after QUERY_TIMEOUT_MS.
+pub fn clickable_near(pid: Option<i32>, point: CGPoint, max_distance: f64) -> Option<CGPoint> {
+    let (tx, rx) = channel();
+    // AX calls block on the target app; a hung one is left to time out on this thread
+    thread::spawn(move || {
+        let _ = tx.send(query_clickable(pid, point));
+    });
+    let Ok(found) = rx.recv_timeout(Duration::from_millis(QUERY_TIMEOUT_MS)) else {
+        println!("Accessibility query took over {} ms, not snapping", QUERY_TIMEOUT_MS);
+        return None;
+    };
+    let (role, center) = found?;
+    let distance = (center.x - point.x).hypot(center.y - point.y);
+    if distance > max_distance {
+        println!("{} at ({:.0}, {:.0}) is {:.0} pt away, not snapping", role, center.x, center.y, distance);
+        return None;
+    }
+    println!("Snapping click to {} at ({:.0}, {:.0}), {:.0} pt from the cell center", role, center.x, center.y, distance);
+    Some(center)
+}
+
+fn query_clickable(pid: Option<i32>, point: CGPoint) -> Option<(String, CGPoint)> {
+    let root = match pid {
+        Some(pid) => AXElement::application(pid)?,
+        None => {
+            let system_wide = unsafe { AXUIElementCreateSystemWide() };
+            if system_wide.is_null() {
+                return None;
+            }
+            unsafe { AXUIElementSetMessagingTimeout(system_wide, QUERY_TIMEOUT_MS as f32 / 1000.0) };
+            AXElement(system_wide)
+        }
+    };
+    let mut hit: AXUIElementRef = ptr::null();
+    let error = unsafe { AXUIElementCopyElementAtPosition(root.0, point.x as f32, point.y as f32, &mut hit) };
+    if error != AX_ERROR_SUCCESS || hit.is_null() {
+        return None;
+    }
+    let mut element = AXElement(hit);
+    for _ in 0..=MAX_PARENT_DEPTH {
+        if let Some(role) = element.role().filter(|role| CLICKABLE_ROLES.contains(&role.as_str())) {
+            return element.center().map(|center| (role, center));
+        }
+        element = element.parent()?;
+    }
+    None
+}
@@ -0,0 +1,116 @@
+use eframe::egui;
+use serde::Deserialize;
+
+/// Regions narrower than this are not split any further.
+pub const MIN_REGION_SIZE: f32 = 2.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BisectConfig {
+    /// Start in bisect mode instead of the labeled grid when the overlay is shown.
+    pub default_mode: bool,
+    pub top_left_key: char,
+    pub top_right_key: char,
+    pub bottom_left_key: char,
+    pub bottom_right_key: char,
+}
+
+impl Default for BisectConfig {
+    fn default() -> Self {
+        Self {
+            default_mode: false,
+            top_left_key: 'U',
+            top_right_key: 'I',
+            bottom_left_key: 'J',
+            bottom_right_key: 'K',
+        }
+    }
+}
+
+impl BisectConfig {
+    pub fn quadrant_for_char(&self, char_code: char) -> Option<Quadrant> {
+        Quadrant::ALL.into_iter().find(|quadrant| self.key_for(*quadrant) == char_code)
+    }
+
+    pub fn key_for(&self, quadrant: Quadrant) -> char {
+        match quadrant {
+            Quadrant::TopLeft => self.top_left_key,
+            Quadrant::TopRight => self.top_right_key,
+            Quadrant::BottomLeft => self.bottom_left_key,
+            Quadrant::BottomRight => self.bottom_right_key,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Quadrant {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Quadrant {
+    pub const ALL: [Quadrant; 4] = [Quadrant::TopLeft, Quadrant::TopRight, Quadrant::BottomLeft, Quadrant::BottomRight];
+
+    pub fn of(&self, region: egui::Rect) -> egui::Rect {
+        let center = region.center();
+        match self {
+            Quadrant::TopLeft => egui::Rect::from_min_max(region.min, center),
+            Quadrant::TopRight => egui::Rect::from_min_max(egui::pos2(center.x, region.min.y), egui::pos2(region.max.x, center.y)),
+            Quadrant::BottomLeft => egui::Rect::from_min_max(egui::pos2(region.min.x, center.y), egui::pos2(center.x, region.max.y)),
+            Quadrant::BottomRight => egui::Rect::from_min_max(center, region.max),
+        }
+    }
+}
+
+/// Stack of zoomed regions; the first entry is the whole layout rect, the last is the active one.
+#[derive(Debug, Default)]
+pub struct BisectRegions {
+    regions: Vec<egui::Rect>,
+}
+
+impl BisectRegions {
+    pub fn reset(&mut self, root: egui::Rect) {
+        self.regions = vec![root];
+    }
+
+    pub fn clear(&mut self) {
+        self.regions.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    pub fn current(&self) -> Option<egui::Rect> {
+        self.regions.last().copied()
+    }
+
+    /// Number of zooms applied on top of the root region.
+    pub fn depth(&self) -> usize {
+        self.regions.len().saturating_sub(1)
+    }
+
+    /// Returns false once the region is too small to split.
+    pub fn zoom(&mut self, quadrant: Quadrant) -> bool {
+        let Some(current) = self.current() else {
+            return false;
+        };
+        if current.width() < MIN_REGION_SIZE && current.height() < MIN_REGION_SIZE {
+            return false;
+        }
+        self.regions.push(quadrant.of(current));
+        true
+    }
+
+    /// Un-zooms one level. Returns false at the root region.
+    pub fn back(&mut self) -> bool {
+        if self.regions.len() > 1 {
+            self.regions.pop();
+            true
+        } else {
+            false
+        }
+    }
+}
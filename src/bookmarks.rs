@@ -0,0 +1,425 @@
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::click::ClickKind;
+use crate::config;
+use crate::displays::{self, OverlayDisplay};
+#[cfg(target_os = "macos")]
+use crate::macos;
+
+const BOOKMARKS_FILE_NAME: &str = "bookmarks.json";
+/// Format version this build writes. Bump it and add a step to `migrate` when the format changes.
+const BOOKMARKS_VERSION: u32 = 2;
+
+/// What the next typed character does after the bookmark key.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BookmarkPrompt {
+    /// Pick the bookmark with that name.
+    Recall,
+    /// Save the cursor position under that name.
+    SaveCursor,
+    /// Save this global point (the fine adjust target) under that name.
+    SavePoint(egui::Pos2),
+    /// Delete the bookmark with that name.
+    Delete,
+}
+
+/// Which set of bookmarks a name lives in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Namespace {
+    /// Only visible while the app with this bundle id is frontmost.
+    App(String),
+    Global,
+}
+
+impl Namespace {
+    pub fn label(&self) -> &str {
+        match self {
+            Namespace::App(bundle_id) => bundle_id,
+            Namespace::Global => "global",
+        }
+    }
+}
+
+/// A saved position, kept as a fraction of its display so it survives resolution changes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// CGDisplayCreateUUIDFromDisplayID, which unlike the display id survives reboots and reconnects.
+    pub display_uuid: String,
+    /// x, y within the display, 0.0-1.0.
+    pub fraction: [f32; 2],
+    /// Click made when the bookmark is recalled.
+    pub click_kind: ClickKind,
+}
+
+/// Global bookmarks plus each app's own.
+#[derive(Debug, Default, Clone, PartialEq)]
+struct BookmarkSets {
+    global: BTreeMap<char, Bookmark>,
+    /// Bundle id -> that app's bookmarks.
+    apps: BTreeMap<String, BTreeMap<char, Bookmark>>,
+}
+
+impl BookmarkSets {
+    fn marks(&self, namespace: &Namespace) -> Option<&BTreeMap<char, Bookmark>> {
+        match namespace {
+            Namespace::App(bundle_id) => self.apps.get(bundle_id),
+            Namespace::Global => Some(&self.global),
+        }
+    }
+
+    /// The bookmark `name` means while `app` is frontmost: the app's own bookmark, else the global one.
+    fn resolve(&self, name: char, app: Option<&str>) -> Option<(Namespace, &Bookmark)> {
+        let namespaces = app.map(|bundle_id| Namespace::App(bundle_id.to_string())).into_iter().chain([Namespace::Global]);
+        for namespace in namespaces {
+            if let Some(bookmark) = self.marks(&namespace).and_then(|marks| marks.get(&name)) {
+                return Some((namespace, bookmark));
+            }
+        }
+        None
+    }
+
+    /// Every name that resolves while `app` is frontmost, with what it resolves to.
+    fn visible(&self, app: Option<&str>) -> Vec<(char, Namespace, &Bookmark)> {
+        let app_names = app.and_then(|bundle_id| self.apps.get(bundle_id)).into_iter().flat_map(|marks| marks.keys());
+        let mut names: Vec<char> = app_names.chain(self.global.keys()).copied().collect();
+        names.sort();
+        names.dedup();
+        names.into_iter()
+            .filter_map(|name| self.resolve(name, app).map(|(namespace, bookmark)| (name, namespace, bookmark)))
+            .collect()
+    }
+
+    fn insert(&mut self, namespace: Namespace, name: char, bookmark: Bookmark) {
+        match namespace {
+            Namespace::App(bundle_id) => self.apps.entry(bundle_id).or_default().insert(name, bookmark),
+            Namespace::Global => self.global.insert(name, bookmark),
+        };
+    }
+
+    fn remove(&mut self, namespace: &Namespace, name: char) -> bool {
+        match namespace {
+            Namespace::App(bundle_id) => {
+                let Some(marks) = self.apps.get_mut(bundle_id) else {
+                    return false;
+                };
+                let removed = marks.remove(&name).is_some();
+                if marks.is_empty() {
+                    self.apps.remove(bundle_id);
+                }
+                removed
+            }
+            Namespace::Global => self.global.remove(&name).is_some(),
+        }
+    }
+
+    fn all(&self) -> impl Iterator<Item = (char, &Bookmark)> {
+        self.global.iter().chain(self.apps.values().flatten()).map(|(name, bookmark)| (*name, bookmark))
+    }
+}
+
+/// Layout of bookmarks.json.
+#[derive(Debug, Serialize, Deserialize)]
+struct BookmarksFile {
+    version: u32,
+    #[serde(default)]
+    global: BTreeMap<char, Bookmark>,
+    #[serde(default)]
+    apps: BTreeMap<String, BTreeMap<char, Bookmark>>,
+}
+
+/// Positions saved under single-character names, kept next to the config so they survive restarts.
+/// Bookmarks on displays that aren't connected are kept; they just can't be recalled until the
+/// display comes back.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    sets: BookmarkSets,
+    /// Set when the file exists but couldn't be read, so saving doesn't overwrite what the user had.
+    read_only: bool,
+    /// Where they're saved; None when there's no config directory.
+    path: Option<PathBuf>,
+}
+
+fn bookmarks_path() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join(BOOKMARKS_FILE_NAME))
+}
+
+#[cfg(target_os = "macos")]
+fn display_uuid(display: &OverlayDisplay) -> String {
+    macos::display_uuid(display.id).unwrap_or_else(|| display.id.to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn display_uuid(display: &OverlayDisplay) -> String {
+    display.id.to_string()
+}
+
+/// Brings bookmarks.json as written by any earlier version up to the current format.
+fn migrate(mut value: serde_json::Value) -> Result<BookmarkSets, String> {
+    let version = value.get("version").and_then(|version| version.as_u64())
+        .ok_or_else(|| "no format version".to_string())?;
+    if version > BOOKMARKS_VERSION as u64 {
+        return Err(format!("format version {} is newer than this build understands ({})", version, BOOKMARKS_VERSION));
+    }
+    if version < 2 {
+        // Version 1 had no app namespaces: its bookmarks all become global
+        if let Some(object) = value.as_object_mut() {
+            let marks = object.remove("bookmarks").unwrap_or_else(|| serde_json::Value::Object(Default::default()));
+            object.insert("global".to_string(), marks);
+        }
+    }
+    let file: BookmarksFile = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    Ok(BookmarkSets { global: file.global, apps: file.apps })
+}
+
+/// Parses the contents of bookmarks.json, migrating older formats.
+fn from_json(contents: &str) -> Result<BookmarkSets, String> {
+    serde_json::from_str(contents).map_err(|e| e.to_string()).and_then(migrate)
+}
+
+fn to_json(sets: &BookmarkSets) -> Result<String, String> {
+    let file = BookmarksFile { version: BOOKMARKS_VERSION, global: sets.global.clone(), apps: sets.apps.clone() };
+    serde_json::to_string_pretty(&file).map_err(|e| e.to_string())
+}
+
+impl Bookmarks {
+    pub fn load() -> Self {
+        bookmarks_path().map(Self::load_from).unwrap_or_default()
+    }
+
+    fn load_from(path: PathBuf) -> Self {
+        if !path.exists() {
+            return Self { path: Some(path), ..Self::default() };
+        }
+        let loaded = fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|contents| from_json(&contents));
+        match loaded {
+            Ok(sets) => {
+                let connected: Vec<String> = displays::active_displays().iter().map(display_uuid).collect();
+                let unavailable: String = sets.all()
+                    .filter(|(_, bookmark)| !connected.contains(&bookmark.display_uuid))
+                    .map(|(name, _)| name)
+                    .collect();
+                if !unavailable.is_empty() {
+                    println!("Bookmarks on displays that are not connected: {}", unavailable);
+                }
+                Self { sets, read_only: false, path: Some(path) }
+            }
+            Err(e) => {
+                eprintln!("Ignoring bookmarks in {} (left untouched): {}", path.display(), e);
+                Self { sets: BookmarkSets::default(), read_only: true, path: Some(path) }
+            }
+        }
+    }
+
+    /// Saves the global `point` under `name` in `namespace`, replacing any bookmark with that name there.
+    pub fn set(&mut self, name: char, namespace: Namespace, point: egui::Pos2, click_kind: ClickKind, displays: &[OverlayDisplay]) -> Result<(), String> {
+        let display = displays::display_containing(displays, point)
+            .map(|index| &displays[index])
+            .ok_or_else(|| format!("({:.0}, {:.0}) is not on any display", point.x, point.y))?;
+        let fraction = (point - display.frame.min) / display.frame.size();
+        self.sets.insert(namespace, name, Bookmark { display_uuid: display_uuid(display), fraction: [fraction.x, fraction.y], click_kind });
+        self.save();
+        Ok(())
+    }
+
+    /// Global point, click kind and namespace of the bookmark `name` means while `app` is frontmost.
+    pub fn get(&self, name: char, app: Option<&str>, displays: &[OverlayDisplay]) -> Result<(egui::Pos2, ClickKind, Namespace), String> {
+        let (namespace, bookmark) = self.sets.resolve(name, app).ok_or_else(|| format!("No bookmark '{}'", name))?;
+        let point = point_on(bookmark, displays)
+            .ok_or_else(|| format!("Bookmark '{}' is on display {}, which is not connected", name, bookmark.display_uuid))?;
+        Ok((point, bookmark.click_kind, namespace))
+    }
+
+    /// Every bookmark visible while `app` is frontmost and on a connected display, with its global point, by name.
+    pub fn list(&self, app: Option<&str>, displays: &[OverlayDisplay]) -> Vec<(char, egui::Pos2, Namespace)> {
+        self.sets.visible(app).into_iter()
+            .filter_map(|(name, namespace, bookmark)| point_on(bookmark, displays).map(|point| (name, point, namespace)))
+            .collect()
+    }
+
+    /// Names visible while `app` is frontmost whose display isn't connected.
+    pub fn unavailable(&self, app: Option<&str>, displays: &[OverlayDisplay]) -> Vec<char> {
+        self.sets.visible(app).into_iter()
+            .filter(|(_, _, bookmark)| point_on(bookmark, displays).is_none())
+            .map(|(name, _, _)| name)
+            .collect()
+    }
+
+    /// Deletes the bookmark `name` means while `app` is frontmost, returning where it was.
+    pub fn delete(&mut self, name: char, app: Option<&str>) -> Option<Namespace> {
+        let (namespace, _) = self.sets.resolve(name, app)?;
+        self.sets.remove(&namespace, name);
+        self.save();
+        Some(namespace)
+    }
+    /// Writes a temporary file next to bookmarks.json and renames it over the old one, so a crash
+    /// mid-write leaves either the old file or the new one, never half of each.
+    fn save(&self) {
+        let Some(path) = self.path.as_deref() else {
+            return;
+        };
+        if self.read_only {
+            eprintln!("Not saving bookmarks: {} could not be read at startup", path.display());
+            return;
+        }
+        let temp_path = path.with_extension("json.tmp");
+        let saved = to_json(&self.sets).and_then(|contents| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            let mut file = File::create(&temp_path).map_err(|e| e.to_string())?;
+            file.write_all(contents.as_bytes()).and_then(|_| file.sync_all()).map_err(|e| e.to_string())?;
+            fs::rename(&temp_path, path).map_err(|e| e.to_string())
+        });
+        if let Err(e) = saved {
+            eprintln!("Failed to save bookmarks to {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn point_on(bookmark: &Bookmark, displays: &[OverlayDisplay]) -> Option<egui::Pos2> {
+    let display = displays.iter().find(|display| display_uuid(display) == bookmark.display_uuid)?;
+    let [x, y] = bookmark.fraction;
+    Some(display.frame.min + egui::vec2(x, y) * display.frame.size())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+
+    fn mark(x: f32, click_kind: ClickKind) -> Bookmark {
+        Bookmark { display_uuid: "display-a".to_string(), fraction: [x, 0.5], click_kind }
+    }
+
+    fn sample_sets() -> BookmarkSets {
+        let mut sets = BookmarkSets::default();
+        sets.insert(Namespace::Global, 'a', mark(0.1, ClickKind::Left));
+        sets.insert(Namespace::Global, 'b', mark(0.2, ClickKind::Right));
+        sets.insert(Namespace::App("com.apple.Safari".to_string()), 'a', mark(0.3, ClickKind::Double));
+        sets
+    }
+
+    /// A file name in the temp directory that no other test run uses.
+    fn temp_bookmarks_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("mouseless-{}-{}.json", name, process::id()))
+    }
+
+    #[test]
+    fn bookmarks_round_trip_through_json() {
+        let sets = sample_sets();
+        let json = to_json(&sets).unwrap();
+        assert!(json.contains("\"version\": 2"), "{}", json);
+        assert_eq!(from_json(&json).unwrap(), sets);
+    }
+
+    #[test]
+    fn version_one_bookmarks_become_global() {
+        let json = r#"{
+            "version": 1,
+            "bookmarks": {
+                "a": { "display_uuid": "display-a", "fraction": [0.1, 0.5], "click_kind": "left" },
+                "b": { "display_uuid": "display-a", "fraction": [0.2, 0.5], "click_kind": "right" }
+            }
+        }"#;
+        let sets = from_json(json).unwrap();
+        assert_eq!(sets.global[&'a'], mark(0.1, ClickKind::Left));
+        assert_eq!(sets.global[&'b'], mark(0.2, ClickKind::Right));
+        assert!(sets.apps.is_empty());
+        assert_eq!(from_json(r#"{ "version": 1 }"#).unwrap(), BookmarkSets::default());
+    }
+
+    #[test]
+    fn a_newer_or_missing_version_is_rejected() {
+        let error = from_json(r#"{ "version": 3, "global": {} }"#).unwrap_err();
+        assert!(error.contains("newer"), "{}", error);
+        assert!(from_json(r#"{ "global": {} }"#).is_err());
+        assert!(from_json("not json").is_err());
+    }
+
+    #[test]
+    fn a_file_from_a_newer_version_is_left_untouched() {
+        let path = temp_bookmarks_path("newer-bookmarks");
+        let contents = r#"{ "version": 3, "global": {}, "future": true }"#;
+        fs::write(&path, contents).unwrap();
+        let mut bookmarks = Bookmarks::load_from(path.clone());
+        assert!(bookmarks.read_only);
+
+        let display = OverlayDisplay {
+            id: 1,
+            frame: egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1000.0, 800.0)),
+            scale_factor: 1.0,
+            is_primary: true,
+        };
+        bookmarks.set('a', Namespace::Global, egui::pos2(100.0, 100.0), ClickKind::Left, &[display]).unwrap();
+        bookmarks.delete('a', None);
+        let left = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(left, contents);
+    }
+
+    #[test]
+    fn a_missing_file_is_created_on_the_first_save() {
+        let path = temp_bookmarks_path("new-bookmarks");
+        let _ = fs::remove_file(&path);
+        let mut bookmarks = Bookmarks::load_from(path.clone());
+        assert!(!bookmarks.read_only);
+        bookmarks.sets = sample_sets();
+        bookmarks.save();
+        let saved = fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|contents| from_json(&contents));
+        let _ = fs::remove_file(&path);
+        assert_eq!(saved.unwrap(), sample_sets());
+    }
+
+    const SAFARI: &str = "com.apple.Safari";
+
+    #[test]
+    fn an_app_bookmark_shadows_the_global_one() {
+        let sets = sample_sets();
+        assert_eq!(sets.resolve('a', Some(SAFARI)), Some((Namespace::App(SAFARI.to_string()), &mark(0.3, ClickKind::Double))));
+        assert_eq!(sets.resolve('a', None), Some((Namespace::Global, &mark(0.1, ClickKind::Left))));
+        assert_eq!(sets.resolve('a', Some("com.apple.Terminal")), Some((Namespace::Global, &mark(0.1, ClickKind::Left))));
+        // Names the app doesn't have fall through to the global set
+        assert_eq!(sets.resolve('b', Some(SAFARI)), Some((Namespace::Global, &mark(0.2, ClickKind::Right))));
+        assert_eq!(sets.resolve('c', Some(SAFARI)), None);
+    }
+
+    #[test]
+    fn visible_lists_each_name_once_as_it_resolves() {
+        let mut sets = sample_sets();
+        sets.insert(Namespace::App(SAFARI.to_string()), 'z', mark(0.4, ClickKind::Left));
+        let visible: Vec<(char, Namespace)> = sets.visible(Some(SAFARI)).into_iter().map(|(name, namespace, _)| (name, namespace)).collect();
+        let safari = Namespace::App(SAFARI.to_string());
+        assert_eq!(visible, vec![('a', safari.clone()), ('b', Namespace::Global), ('z', safari)]);
+        let visible: Vec<char> = sets.visible(None).into_iter().map(|(name, _, _)| name).collect();
+        assert_eq!(visible, vec!['a', 'b']);
+    }
+
+    #[test]
+    fn removing_an_app_bookmark_uncovers_the_global_one() {
+        let mut sets = sample_sets();
+        let safari = Namespace::App(SAFARI.to_string());
+        assert!(sets.remove(&safari, 'a'));
+        assert_eq!(sets.resolve('a', Some(SAFARI)), Some((Namespace::Global, &mark(0.1, ClickKind::Left))));
+        assert!(!sets.remove(&safari, 'a'));
+    }
+
+    #[test]
+    fn removing_an_apps_last_bookmark_drops_its_entry() {
+        let mut sets = sample_sets();
+        let safari = Namespace::App(SAFARI.to_string());
+        sets.insert(safari.clone(), 'z', mark(0.4, ClickKind::Left));
+        assert!(sets.remove(&safari, 'a'));
+        assert!(sets.apps.contains_key(SAFARI));
+        assert!(sets.remove(&safari, 'z'));
+        assert!(!sets.apps.contains_key(SAFARI));
+        assert!(!sets.remove(&Namespace::App("com.apple.Terminal".to_string()), 'a'));
+        assert_eq!(sets.global.len(), 2);
+    }
+}
@@ -0,0 +1,296 @@
+use std::thread;
+use std::time::Duration;
+
+use core_graphics::event::{CGEvent, CGEventFlags, CGEventTapLocation, CGEventType, CGMouseButton, EventField};
+use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+use core_graphics::display::CGDisplay;
+use core_graphics::geometry::CGPoint;
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::keybindings::KeyCombo;
+
+/// Time between the interpolated moves of a glide, about one per 120 Hz frame.
+pub const GLIDE_STEP: Duration = Duration::from_millis(8);
+
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventSourceButtonState(state_id: CGEventSourceStateID, button: CGMouseButton) -> bool;
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClickKind {
+    Left,
+    Right,
+    Middle,
+    CommandLeft,
+    /// Left button with Control held, for apps that open context menus on control-click but not right-click.
+    ControlLeft,
+    ShiftLeft,
+    Double,
+}
+
+impl ClickKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClickKind::Left => "left",
+            ClickKind::Right => "right",
+            ClickKind::Middle => "middle",
+            ClickKind::CommandLeft => "cmd-click",
+            ClickKind::ControlLeft => "ctrl-click",
+            ClickKind::ShiftLeft => "shift-click",
+            ClickKind::Double => "double",
+        }
+    }
+
+    fn mouse_events(&self) -> (CGEventType, CGEventType, CGMouseButton) {
+        match self {
+            ClickKind::Right => (CGEventType::RightMouseDown, CGEventType::RightMouseUp, CGMouseButton::Right),
+            ClickKind::Middle => (CGEventType::OtherMouseDown, CGEventType::OtherMouseUp, CGMouseButton::Center),
+            ClickKind::Left | ClickKind::CommandLeft | ClickKind::ControlLeft | ClickKind::ShiftLeft | ClickKind::Double => {
+                (CGEventType::LeftMouseDown, CGEventType::LeftMouseUp, CGMouseButton::Left)
+            }
+        }
+    }
+
+    fn flags(&self) -> CGEventFlags {
+        match self {
+            ClickKind::CommandLeft => CGEventFlags::CGEventFlagCommand,
+            ClickKind::ControlLeft => CGEventFlags::CGEventFlagControl,
+            ClickKind::ShiftLeft => CGEventFlags::CGEventFlagShift,
+            _ => CGEventFlags::CGEventFlagNull,
+        }
+    }
+
+    pub fn click_count(&self) -> i64 {
+        match self {
+            ClickKind::Double => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Posts the click's down and up events. With a `hold` the button stays down that long between
+/// them, on a thread of its own so the caller carries on meanwhile.
+pub fn post_click(point: CGPoint, kind: ClickKind, hold: Duration) {
+    if hold.is_zero() {
+        post_held_click(point, kind, hold);
+    } else {
+        thread::spawn(move || post_held_click(point, kind, hold));
+    }
+}
+
+fn post_held_click(point: CGPoint, kind: ClickKind, hold: Duration) {
+    for click_state in 1..=kind.click_count() {
+        post_button(point, kind, click_state, true);
+        if !hold.is_zero() {
+            thread::sleep(hold);
+        }
+        post_button(point, kind, click_state, false);
+    }
+    println!("Posted {} click at ({}, {})", kind.label(), point.x, point.y);
+}
+
+/// Posts one press or release of the click's button. `click_state` counts the clicks of a double click.
+pub fn post_button(point: CGPoint, kind: ClickKind, click_state: i64, pressed: bool) {
+    let event_source = match CGEventSource::new(CGEventSourceStateID::Private) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to create event source: {:?}", e);
+            return;
+        }
+    };
+    let (down_type, up_type, button) = kind.mouse_events();
+    let event_type = if pressed { down_type } else { up_type };
+    post_mouse_event(&event_source, event_type, point, button, kind.flags(), click_state);
+}
+
+/// Presses and releases `combo` with its modifiers held, for whichever app has key focus.
+pub fn post_key_combo(combo: &KeyCombo) {
+    let event_source = match CGEventSource::new(CGEventSourceStateID::Private) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to create event source: {:?}", e);
+            return;
+        }
+    };
+    let mut flags = CGEventFlags::CGEventFlagNull;
+    for (held, flag) in [
+        (combo.command, CGEventFlags::CGEventFlagCommand),
+        (combo.shift, CGEventFlags::CGEventFlagShift),
+        (combo.option, CGEventFlags::CGEventFlagAlternate),
+        (combo.control, CGEventFlags::CGEventFlagControl),
+    ] {
+        if held {
+            flags |= flag;
+        }
+    }
+    for key_down in [true, false] {
+        match CGEvent::new_keyboard_event(event_source.clone(), combo.key_code as u16, key_down) {
+            Ok(event) => {
+                // The flags alone carry the modifiers; no separate modifier key events are needed
+                event.set_flags(flags);
+                event.post(CGEventTapLocation::HID);
+            }
+            Err(_) => eprintln!("Failed to create key event for {}", combo.label()),
+        }
+    }
+    println!("Posted {}", combo.label());
+}
+
+/// Centers a logical point on the physical pixel under it. On a 2x display a point covers four
+/// pixels; aiming at a pixel center keeps any later rounding from landing on a neighbouring one.
+pub fn pixel_center(point: egui::Pos2, scale_factor: f32) -> egui::Pos2 {
+    if scale_factor <= 0.0 {
+        return point;
+    }
+    let snap = |coord: f32| ((coord * scale_factor).floor() + 0.5) / scale_factor;
+    egui::pos2(snap(point.x), snap(point.y))
+}
+
+/// "x,y" as posted, without trailing zeros: "1234,567" or "1234.25,567.75" on a 2x display.
+pub fn format_point(point: egui::Pos2) -> String {
+    let format = |coord: f32| {
+        let text = format!("{:.2}", coord);
+        text.trim_end_matches('0').trim_end_matches('.').to_string()
+    };
+    format!("{},{}", format(point.x), format(point.y))
+}
+
+pub fn to_cg_point(point: egui::Pos2) -> CGPoint {
+    CGPoint::new(point.x as f64, point.y as f64)
+}
+
+/// Puts the cursor exactly at `point` (no integer rounding), then posts a move so hover effects follow.
+pub fn warp_cursor(point: CGPoint) -> Result<(), String> {
+    CGDisplay::warp_mouse_cursor_position(point).map_err(|e| format!("CGWarpMouseCursorPosition failed: {:?}", e))?;
+    post_mouse_move(point);
+    Ok(())
+}
+
+/// Where a glide from `from` to `to` is once `progress` (0.0-1.0) of its time has passed. It eases
+/// in and out; posting a move there every `GLIDE_STEP` fires hover effects along the way and lets
+/// a recording show the pointer travel.
+pub fn glide_point(from: CGPoint, to: CGPoint, progress: f64) -> CGPoint {
+    let t = progress.clamp(0.0, 1.0);
+    let eased = t * t * (3.0 - 2.0 * t);
+    CGPoint::new(from.x + (to.x - from.x) * eased, from.y + (to.y - from.y) * eased)
+}
+
+pub fn current_cursor_location() -> Option<CGPoint> {
+    let event_source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState).ok()?;
+    CGEvent::new(event_source).ok().map(|event| event.location())
+}
+
+/// Drags the held left button to `point`. No up is posted: the physical button is still down, and
+/// releasing it is what drops there. If it was let go already, the drag is over and nothing moves.
+pub fn post_drop(point: CGPoint) {
+    if !unsafe { CGEventSourceButtonState(CGEventSourceStateID::HIDSystemState, CGMouseButton::Left) } {
+        println!("Left button already released, not moving the drag to ({}, {})", point.x, point.y);
+        return;
+    }
+    let event_source = match CGEventSource::new(CGEventSourceStateID::Private) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to create event source: {:?}", e);
+            return;
+        }
+    };
+    post_mouse_event(&event_source, CGEventType::LeftMouseDragged, point, CGMouseButton::Left, CGEventFlags::CGEventFlagNull, 1);
+    println!("Dragged to ({}, {}), releasing the left button drops there", point.x, point.y);
+}
+
+/// Moves the pointer with a synthetic event (unlike a warp, this triggers hover effects).
+pub fn post_mouse_move(point: CGPoint) {
+    let event_source = match CGEventSource::new(CGEventSourceStateID::Private) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("Failed to create event source: {:?}", e);
+            return;
+        }
+    };
+    post_mouse_event(&event_source, CGEventType::MouseMoved, point, CGMouseButton::Left, CGEventFlags::CGEventFlagNull, 0);
+}
+
+fn post_mouse_event(
+    event_source: &CGEventSource,
+    event_type: CGEventType,
+    point: CGPoint,
+    button: CGMouseButton,
+    flags: CGEventFlags,
+    click_state: i64,
+) {
+    match CGEvent::new_mouse_event(event_source.clone(), event_type, point, button) {
+        Ok(event) => {
+            // Double clicks are recognized by the click state counter, not by timing alone
+            event.set_integer_value_field(EventField::MOUSE_EVENT_CLICK_STATE, click_state);
+            if flags != CGEventFlags::CGEventFlagNull {
+                event.set_flags(flags);
+            }
+            event.post(CGEventTapLocation::HID);
+        }
+        Err(_) => {
+            eprintln!("Failed to create {:?} event", event_type);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_snap_to_the_pixel_center_on_a_1x_display() {
+        assert_eq!(pixel_center(egui::pos2(10.0, 20.7), 1.0), egui::pos2(10.5, 20.5));
+    }
+
+    #[test]
+    fn points_snap_to_one_of_four_pixels_on_a_2x_display() {
+        assert_eq!(pixel_center(egui::pos2(10.0, 20.3), 2.0), egui::pos2(10.25, 20.25));
+        assert_eq!(pixel_center(egui::pos2(10.6, 20.5), 2.0), egui::pos2(10.75, 20.75));
+        assert_eq!(pixel_center(egui::pos2(10.25, 20.75), 2.0), egui::pos2(10.25, 20.75));
+    }
+
+    #[test]
+    fn points_stay_on_their_pixel_at_fractional_scales() {
+        for scale_factor in [1.25f32, 1.5, 1.75, 2.5] {
+            for step in 0..200 {
+                let coord = step as f32 * 0.37;
+                let snapped = pixel_center(egui::pos2(coord, coord), scale_factor).x;
+                let pixel = (coord * scale_factor).floor();
+                assert!(
+                    (snapped * scale_factor - (pixel + 0.5)).abs() < 1e-3,
+                    "{} at {}x went to {}, off the center of pixel {}", coord, scale_factor, snapped, pixel
+                );
+            }
+        }
+        assert_eq!(pixel_center(egui::pos2(10.0, 10.5), 1.5), egui::pos2(15.5 / 1.5, 15.5 / 1.5));
+    }
+
+    #[test]
+    fn an_unknown_scale_leaves_the_point_alone() {
+        assert_eq!(pixel_center(egui::pos2(10.3, 20.7), 0.0), egui::pos2(10.3, 20.7));
+    }
+
+    #[test]
+    fn a_glide_eases_from_its_start_to_its_end() {
+        let (from, to) = (CGPoint::new(100.0, 50.0), CGPoint::new(300.0, 450.0));
+        let at = |progress| {
+            let point = glide_point(from, to, progress);
+            (point.x, point.y)
+        };
+        assert_eq!(at(0.0), (100.0, 50.0));
+        assert_eq!(at(0.5), (200.0, 250.0));
+        assert_eq!(at(1.0), (300.0, 450.0));
+        assert_eq!(at(1.5), (300.0, 450.0));
+        assert!(at(0.1).0 - 100.0 < 0.1 * 200.0, "the glide starts slower than a straight run");
+    }
+
+    #[test]
+    fn points_format_without_trailing_zeros() {
+        assert_eq!(format_point(egui::pos2(1234.0, 567.0)), "1234,567");
+        assert_eq!(format_point(egui::pos2(1234.25, 567.75)), "1234.25,567.75");
+        assert_eq!(format_point(egui::pos2(0.5, 10.0)), "0.5,10");
+    }
+}
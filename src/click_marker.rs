@@ -0,0 +1,59 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+
+/// Radius the ring grows to; the marker window is just big enough to hold it.
+const MAX_RADIUS: f32 = 22.0;
+const MIN_RADIUS: f32 = 3.0;
+const RING_WIDTH: f32 = 2.5;
+
+/// A ring shown at a posted click, in global points.
+#[derive(Debug, Clone, Copy)]
+pub struct ClickMarker {
+    point: egui::Pos2,
+    started_at: Instant,
+}
+
+impl ClickMarker {
+    pub fn new(point: egui::Pos2) -> Self {
+        Self { point, started_at: Instant::now() }
+    }
+
+    /// 0.0-1.0 through the animation, None once it has run for `duration`.
+    fn progress(&self, now: Instant, duration: Duration) -> Option<f32> {
+        let elapsed = now.saturating_duration_since(self.started_at);
+        (elapsed < duration).then(|| elapsed.as_secs_f32() / duration.as_secs_f32().max(f32::EPSILON))
+    }
+
+    /// Paints the marker in its own small viewport that ignores the mouse from creation, so it can't
+    /// take the events meant for what was just clicked. Returns false once the animation is over;
+    /// the viewport closes as soon as it is no longer shown.
+    pub fn show(&self, ctx: &egui::Context, duration: Duration) -> bool {
+        let Some(progress) = self.progress(Instant::now(), duration) else {
+            return false;
+        };
+        let size = egui::Vec2::splat(2.0 * (MAX_RADIUS + RING_WIDTH));
+        let builder = egui::ViewportBuilder::default()
+            .with_title("Mouseless Click Marker")
+            .with_position(self.point - size / 2.0)
+            .with_inner_size(size)
+            .with_transparent(true)
+            .with_decorations(false)
+            .with_always_on_top()
+            .with_mouse_passthrough(true)
+            .with_active(false);
+        ctx.show_viewport_immediate(egui::ViewportId::from_hash_of("mouseless-click-marker"), builder, |marker_ctx, _class| {
+            egui::CentralPanel::default()
+                .frame(egui::Frame::none().fill(egui::Color32::TRANSPARENT))
+                .show(marker_ctx, |ui| {
+                    let radius = MIN_RADIUS + (MAX_RADIUS - MIN_RADIUS) * progress;
+                    let alpha = ((1.0 - progress) * 230.0) as u8;
+                    let center = marker_ctx.screen_rect().center();
+                    ui.painter().circle_stroke(center, radius, egui::Stroke::new(RING_WIDTH, egui::Color32::from_rgba_unmultiplied(255, 200, 0, alpha)));
+                    ui.painter().circle_filled(center, 2.0, egui::Color32::from_rgba_unmultiplied(255, 60, 60, alpha));
+                });
+        });
+        ctx.request_repaint();
+        true
+    }
+}
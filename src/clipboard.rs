@@ -0,0 +1,35 @@
+#[cfg(test)]
+use std::cell::RefCell;
+
+#[cfg(all(target_os = "macos", not(test)))]
+use crate::macos;
+
+#[cfg(test)]
+thread_local! {
+    static COPIED: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Puts `text` on the clipboard; false if the pasteboard refused it. Under test nothing reaches the
+/// system clipboard, the text is only kept for `take_copied`.
+pub fn copy(text: &str) -> bool {
+    #[cfg(test)]
+    {
+        COPIED.with(|copied| copied.borrow_mut().push(text.to_string()));
+        true
+    }
+    #[cfg(all(target_os = "macos", not(test)))]
+    {
+        macos::copy_to_clipboard(text)
+    }
+    #[cfg(all(not(target_os = "macos"), not(test)))]
+    {
+        println!("No clipboard on this platform, not copying {}", text);
+        true
+    }
+}
+
+/// Everything copied on this thread since the last call, oldest first.
+#[cfg(test)]
+pub fn take_copied() -> Vec<String> {
+    COPIED.with(|copied| copied.take())
+}
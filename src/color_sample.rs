@@ -0,0 +1,75 @@
+use eframe::egui;
+
+#[cfg(target_os = "macos")]
+use core_graphics::base::{kCGBitmapByteOrder32Big, kCGImageAlphaPremultipliedLast};
+#[cfg(target_os = "macos")]
+use core_graphics::color_space::{kCGColorSpaceDisplayP3, kCGColorSpaceSRGB, CGColorSpace};
+#[cfg(target_os = "macos")]
+use core_graphics::context::CGContext;
+#[cfg(target_os = "macos")]
+use core_graphics::display::CGDisplay;
+#[cfg(target_os = "macos")]
+use core_graphics::geometry::{CGPoint, CGRect, CGSize};
+#[cfg(target_os = "macos")]
+use core_graphics::image::CGImage;
+#[cfg(target_os = "macos")]
+use core_foundation::string::CFStringRef;
+
+#[cfg(target_os = "macos")]
+use crate::displays;
+
+/// Color of one on-screen pixel, converted out of the display's own color space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SampledColor {
+    pub srgb: [u8; 3],
+    /// The same pixel in Display P3, which keeps colors a wide-gamut display shows outside sRGB.
+    pub display_p3: Option<[u8; 3]>,
+}
+
+impl SampledColor {
+    /// sRGB as "#RRGGBB", the form that gets copied.
+    pub fn hex(&self) -> String {
+        let [r, g, b] = self.srgb;
+        format!("#{:02X}{:02X}{:02X}", r, g, b)
+    }
+
+    pub fn summary(&self) -> String {
+        let [r, g, b] = self.srgb;
+        let mut summary = format!("{}  rgb({}, {}, {})", self.hex(), r, g, b);
+        if let Some([r, g, b]) = self.display_p3 {
+            summary.push_str(&format!("  ·  P3 {:.3} {:.3} {:.3}", r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0));
+        }
+        summary
+    }
+
+    pub fn color32(&self) -> egui::Color32 {
+        let [r, g, b] = self.srgb;
+        egui::Color32::from_rgb(r, g, b)
+    }
+}
+
+/// Reads the pixel under the global `point` (top-left origin, points). Whatever is on screen
+/// there is read, so the overlay has to be hidden first. On a Retina display the point covers
+/// several pixels, which come out blended.
+#[cfg(target_os = "macos")]
+pub fn sample(point: egui::Pos2) -> Option<SampledColor> {
+    let displays = displays::active_displays();
+    let display = &displays[displays::display_containing(&displays, point)?];
+    let local = point - display.frame.min;
+    let rect = CGRect::new(&CGPoint::new(local.x.floor() as f64, local.y.floor() as f64), &CGSize::new(1.0, 1.0));
+    let image = CGDisplay::new(display.id).image_for_rect(rect)?;
+    let srgb = convert_pixel(&image, unsafe { kCGColorSpaceSRGB })?;
+    let display_p3 = convert_pixel(&image, unsafe { kCGColorSpaceDisplayP3 });
+    Some(SampledColor { srgb, display_p3 })
+}
+
+/// Draws a 1x1 image into a 1x1 bitmap in the named color space and reads the pixel back,
+/// letting CoreGraphics do the conversion from the display's color space.
+#[cfg(target_os = "macos")]
+fn convert_pixel(image: &CGImage, color_space_name: CFStringRef) -> Option<[u8; 3]> {
+    let color_space = CGColorSpace::create_with_name(color_space_name)?;
+    let mut context = CGContext::create_bitmap_context(None, 1, 1, 8, 4, &color_space, kCGImageAlphaPremultipliedLast | kCGBitmapByteOrder32Big);
+    context.draw_image(CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(1.0, 1.0)), image);
+    let pixel = context.data();
+    Some([pixel[0], pixel[1], pixel[2]])
+}
@@ -0,0 +1,541 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::bisect::BisectConfig;
+use crate::feedback::FeedbackConfig;
+use crate::grid::{self, FuzzyConfig, LabelAlphabets, LabelConfig, LabelStyle};
+use crate::keybindings::{KeyBindingConfig, KeyBindings, KeyCombo};
+use crate::magnifier::MagnifierConfig;
+use crate::pointer_motion::PointerConfig;
+use crate::profiles::ProfileConfig;
+use crate::quick_jump::QuickJumpConfig;
+use crate::screenshot::ScreenshotConfig;
+use crate::scroll::ScrollConfig;
+use crate::tap_gesture::TapConfig;
+use crate::theme::{self, Theme, ThemeConfig};
+use crate::window_picker::WindowPickerConfig;
+use crate::window_snap::WindowSnapConfig;
+
+pub const CONFIG_DIR_NAME: &str = "mouseless";
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+const SUB_GRID_FILE_NAME: &str = "sub_grid.toml";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub actions: ActionConfig,
+    pub click: ClickConfig,
+    pub drop_grid: DropGridConfig,
+    pub dismiss: DismissConfig,
+    pub displays: DisplayConfig,
+    pub feedback: FeedbackConfig,
+    pub keys: KeyBindingConfig,
+    pub scroll: ScrollConfig,
+    pub fine_adjust: FineAdjustConfig,
+    pub pointer: PointerConfig,
+    pub bisect: BisectConfig,
+    pub grid: GridConfig,
+    pub labels: LabelConfig,
+    pub fuzzy: FuzzyConfig,
+    pub magnifier: MagnifierConfig,
+    pub taps: TapConfig,
+    pub window_picker: WindowPickerConfig,
+    pub window_snap: WindowSnapConfig,
+    pub quick_jump: QuickJumpConfig,
+    pub screenshot: ScreenshotConfig,
+    pub theme: ThemeConfig,
+    pub debug: DebugConfig,
+    /// Keyed by bundle identifier, plus "default" for every other app.
+    pub profiles: HashMap<String, ProfileConfig>,
+    /// Resolved from `labels` at load time.
+    #[serde(skip)]
+    pub label_alphabets: LabelAlphabets,
+    /// Resolved from `fuzzy` at load time, empty while fuzzy matching is off.
+    #[serde(skip)]
+    pub key_adjacency: HashMap<char, Vec<char>>,
+    /// Resolved from `keys` and validated against the label alphabet at load time.
+    #[serde(skip)]
+    pub key_bindings: KeyBindings,
+    /// Resolved from `theme` at load time.
+    #[serde(skip)]
+    pub appearance: Theme,
+    /// Resolved from `actions.shortcut` at load time.
+    #[serde(skip)]
+    pub action_shortcut: Option<KeyCombo>,
+}
+
+/// Post-selection action stage: after the sub-grid cell is chosen, a single key picks the click preset.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ActionConfig {
+    pub enabled: bool,
+    pub timeout_ms: u64,
+    pub plain_key: char,
+    pub right_key: char,
+    pub middle_key: char,
+    pub cmd_click_key: char,
+    pub control_click_key: char,
+    pub double_key: char,
+    /// Typed in the sub-grid before a label: that pick right-clicks (or middle-clicks), skipping the prompt.
+    pub right_prefix_key: char,
+    pub middle_prefix_key: char,
+    /// Left-clicks, then sends `shortcut` to the app that now has focus.
+    pub shortcut_key: char,
+    /// Key combination such as "cmd+w"; empty leaves `shortcut_key` unused.
+    pub shortcut: String,
+    /// Wait between the click and the shortcut, so the clicked app has taken focus.
+    pub shortcut_delay_ms: u64,
+}
+
+impl Default for ActionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            timeout_ms: 800,
+            plain_key: 'C',
+            right_key: 'R',
+            middle_key: 'M',
+            cmd_click_key: 'T',
+            control_click_key: 'X',
+            double_key: 'D',
+            right_prefix_key: ';',
+            middle_prefix_key: '/',
+            shortcut_key: 'K',
+            shortcut: String::new(),
+            shortcut_delay_ms: 150,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClickConfig {
+    /// Wait after the mouse-up before the overlay accepts mouse events again.
+    pub restore_mouse_delay_ms: u64,
+    /// Move the final point onto the center of a button or link near it, found through Accessibility.
+    pub snap_to_elements: bool,
+    /// How long the button stays down between mouse-down and mouse-up, for press-and-hold controls.
+    pub hold_ms: u64,
+    /// Glide the pointer to the target over this long before clicking instead of jumping there.
+    /// 0 jumps; anything else delays every click by as much.
+    pub glide_ms: u64,
+}
+
+impl Default for ClickConfig {
+    fn default() -> Self {
+        Self { restore_mouse_delay_ms: 30, snap_to_elements: true, hold_ms: 0, glide_ms: 0 }
+    }
+}
+
+impl ClickConfig {
+    /// Longer holds are almost certainly a typo, and would keep the button down for as long.
+    const MAX_HOLD_MS: u64 = 5_000;
+
+    fn validate(&self) -> Result<(), String> {
+        if self.hold_ms > Self::MAX_HOLD_MS {
+            return Err(format!("click.hold_ms must be at most {}, got {}", Self::MAX_HOLD_MS, self.hold_ms));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DismissConfig {
+    /// Hide the overlay, without clicking, once the physical mouse has drifted this far from where
+    /// it was when the overlay appeared. Jitter that goes back and forth cancels out. 0 turns it off.
+    pub mouse_move_px: f32,
+}
+
+impl Default for DismissConfig {
+    fn default() -> Self {
+        Self { mouse_move_px: 12.0 }
+    }
+}
+
+/// Holding the physical left button still shows the grid to pick where the drag is released.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DropGridConfig {
+    pub enabled: bool,
+    /// How long the button must stay down before the grid appears.
+    pub hold_ms: u64,
+    /// Moving further than this while holding is an ordinary drag; no grid.
+    pub slop_px: f64,
+}
+
+impl Default for DropGridConfig {
+    fn default() -> Self {
+        Self { enabled: false, hold_ms: 400, slop_px: 6.0 }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Show the grid on every display at once; a number key picks the display before the label.
+    pub all_displays: bool,
+    /// Start the main grid below the menu bar (and notch) instead of at the top edge.
+    pub skip_menu_bar: bool,
+    /// Leave main cells that overlap the notch unlabeled so they can't be picked.
+    pub block_notch_cells: bool,
+    /// Lay the main grid over the focused window's frame instead of the whole display. The whole
+    /// display is used when the window's frame can't be read or isn't on this display.
+    pub focused_window: bool,
+    /// Screen areas the grid leaves alone, e.g. a floating always-on-top window.
+    pub exclude: Vec<ExcludedRegion>,
+}
+
+/// A rect in global screen points (top-left origin, like the rest of the config).
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ExcludedRegion {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl ExcludedRegion {
+    pub fn rect(&self) -> egui::Rect {
+        egui::Rect::from_min_size(egui::pos2(self.x, self.y), egui::vec2(self.width.max(0.0), self.height.max(0.0)))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct DebugConfig {
+    /// Enables the debug_coordinates key, which labels each main cell with its global center.
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FineAdjustConfig {
+    /// Always nudge before clicking instead of only after the fine_adjust key.
+    pub always: bool,
+    pub step_px: f32,
+    pub shift_step_px: f32,
+}
+
+impl Default for FineAdjustConfig {
+    fn default() -> Self {
+        Self { always: false, step_px: 2.0, shift_step_px: 10.0 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct GridConfig {
+    /// 2: main grid + sub-grid. 3: one more grid inside the picked sub-grid cell.
+    pub levels: usize,
+    /// Main grid size when `auto` is off. Past 12x12 labels come from `label_alphabet`.
+    pub main_cols: usize,
+    pub main_rows: usize,
+    /// Characters for generated main grid labels, e.g. "asdfghjkl". Empty keeps the row/column scheme.
+    pub label_alphabet: String,
+    pub sub_grid_cols: usize,
+    pub sub_grid_rows: usize,
+    /// Write sub-grid size changes made with the density keys back to the config file.
+    pub persist_sub_grid: bool,
+    /// Highlight the sub-cell last picked in a main cell; Enter picks it again.
+    pub remember_sub_cells: bool,
+    /// Open the sub-grid of the last picked main cell on every show; Shift held while activating does it once.
+    pub resume_last_region: bool,
+    /// Pick main grid columns and rows from the display's aspect ratio instead of the fixed 12x12.
+    pub auto: bool,
+    /// Roughly how many main grid cells `auto` aims for.
+    pub target_cells: usize,
+    /// Shape the sub-grid after the picked main cell, keeping about sub_grid_cols x sub_grid_rows cells.
+    pub auto_sub_grid: bool,
+    pub third_level_cols: usize,
+    pub third_level_rows: usize,
+    /// A picked main cell narrower or shorter than this many points is clicked at its center instead
+    /// of getting a sub-grid, which would only have slivers for cells. 0 always refines.
+    pub min_refine_side: f32,
+    /// What the confirm_center key (Space) clicks in the sub-grid.
+    pub confirm_center_action: ConfirmCenterAction,
+    /// "grid" or "radial": labelled points on rings around the picked main cell's center.
+    pub sub_grid_shape: grid::SubGridShape,
+    pub radial_rings: usize,
+    pub radial_spokes: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfirmCenterAction {
+    /// The center of the cell the sub-grid was opened in.
+    #[default]
+    CellCenter,
+    /// The sub-cell under the mouse pointer, or the one at the cell's center when the pointer is
+    /// elsewhere. Picked like a typed label, so a third level opens inside it.
+    SubCell,
+    /// The key does nothing in the sub-grid.
+    #[serde(rename = "none")]
+    Ignore,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            levels: 2,
+            main_cols: grid::MAIN_GRID_COLS,
+            main_rows: grid::MAIN_GRID_ROWS,
+            label_alphabet: String::new(),
+            sub_grid_cols: grid::SUB_GRID_COLS,
+            sub_grid_rows: grid::SUB_GRID_ROWS,
+            persist_sub_grid: false,
+            remember_sub_cells: true,
+            resume_last_region: false,
+            auto: false,
+            target_cells: 140,
+            auto_sub_grid: false,
+            third_level_cols: 3,
+            third_level_rows: 3,
+            min_refine_side: 40.0,
+            confirm_center_action: ConfirmCenterAction::CellCenter,
+            sub_grid_shape: grid::SubGridShape::Grid,
+            radial_rings: 2,
+            radial_spokes: 8,
+        }
+    }
+}
+
+impl GridConfig {
+    pub fn label_alphabet(&self) -> Option<Vec<char>> {
+        if self.label_alphabet.is_empty() {
+            None
+        } else {
+            Some(self.label_alphabet.chars().collect())
+        }
+    }
+
+    fn validate(&self, alphabets: &LabelAlphabets) -> Result<(), String> {
+        if !(2..=3).contains(&self.levels) {
+            return Err(format!("grid.levels must be 2 or 3, got {}", self.levels));
+        }
+        let alphabet = self.label_alphabet().unwrap_or_else(|| alphabets.sub_chars.clone());
+        grid::validate_alphabet("grid.label_alphabet", &alphabet, 2)?;
+        let main_cells = self.main_cols * self.main_rows;
+        let fits_row_column_labels = self.label_alphabet.is_empty()
+            && self.main_rows <= alphabets.first_chars.len()
+            && self.main_cols <= alphabets.second_chars.len();
+        let needs_generated_labels = alphabets.style == LabelStyle::Letters && !fits_row_column_labels;
+        if main_cells == 0 {
+            return Err(format!("grid.main_cols and grid.main_rows must be at least 1, got {}x{}", self.main_cols, self.main_rows));
+        }
+        if needs_generated_labels && grid::label_len_for(main_cells, alphabet.len(), 2) > grid::MAX_LABEL_LEN {
+            let row_column_note = if self.label_alphabet.is_empty() {
+                format!(
+                    "row/column labels cover up to {} columns x {} rows; past that ",
+                    alphabets.second_chars.len(), alphabets.first_chars.len()
+                )
+            } else {
+                String::new()
+            };
+            return Err(format!(
+                "grid.main_cols x grid.main_rows is {}x{} = {} cells, but {}labels of up to {} characters from the {} in \"{}\" cover at most {} cells. Use a smaller grid or a longer grid.label_alphabet",
+                self.main_cols, self.main_rows, main_cells, row_column_note, grid::MAX_LABEL_LEN, alphabet.len(),
+                alphabet.iter().collect::<String>(), alphabet.len().pow(grid::MAX_LABEL_LEN as u32)
+            ));
+        }
+        if self.target_cells == 0 {
+            return Err("grid.target_cells must be at least 1".to_string());
+        }
+        let sub_grid_cells = self.sub_grid_cols * self.sub_grid_rows;
+        if sub_grid_cells == 0 || sub_grid_cells > alphabets.max_sub_grid_cells() {
+            return Err(format!(
+                "grid.sub_grid_cols x grid.sub_grid_rows must be between 1 and {} cells, got {}",
+                alphabets.max_sub_grid_cells(), sub_grid_cells
+            ));
+        }
+        let radial_points = 1 + self.radial_rings * self.radial_spokes;
+        if self.sub_grid_shape == grid::SubGridShape::Radial
+            && (self.radial_rings == 0 || self.radial_spokes == 0 || radial_points > alphabets.max_sub_grid_cells())
+        {
+            return Err(format!(
+                "grid.radial_rings x grid.radial_spokes + 1 must be between 2 and {} points, got {}",
+                alphabets.max_sub_grid_cells(), radial_points
+            ));
+        }
+        let third_level_cells = self.third_level_cols * self.third_level_rows;
+        if third_level_cells == 0 || third_level_cells > alphabets.max_single_char_cells() {
+            return Err(format!(
+                "grid.third_level_cols x grid.third_level_rows must be between 1 and {} cells, got {}",
+                alphabets.max_single_char_cells(), third_level_cells
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join(CONFIG_DIR_NAME))
+}
+
+/// Sub-grid size changed with the density keys, kept next to the config file so the file
+/// itself is never rewritten.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSubGrid {
+    cols: usize,
+    rows: usize,
+}
+
+/// Writes the sub-grid size to sub_grid.toml, which overrides [grid] while persist_sub_grid is on.
+pub fn save_sub_grid_dims(cols: usize, rows: usize) -> Result<(), String> {
+    let dir = config_dir().ok_or_else(|| "HOME not set".to_string())?;
+    let path = dir.join(SUB_GRID_FILE_NAME);
+    let contents = toml::to_string(&SavedSubGrid { cols, rows })
+        .map_err(|e| format!("Failed to serialize sub-grid size: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// The sub-grid size saved by `save_sub_grid_dims`, if there is one the labels can cover.
+fn load_sub_grid_dims(alphabets: &LabelAlphabets) -> Option<(usize, usize)> {
+    let path = config_dir()?.join(SUB_GRID_FILE_NAME);
+    if !path.exists() {
+        return None;
+    }
+    let loaded = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| toml::from_str::<SavedSubGrid>(&contents).map_err(|e| e.to_string()))
+        .and_then(|saved| {
+            let cells = saved.cols * saved.rows;
+            if cells == 0 || cells > alphabets.max_sub_grid_cells() {
+                return Err(format!("{}x{} is not between 1 and {} cells", saved.cols, saved.rows, alphabets.max_sub_grid_cells()));
+            }
+            Ok((saved.cols, saved.rows))
+        });
+    loaded.map_err(|e| eprintln!("Ignoring saved sub-grid size in {}: {}", path.display(), e)).ok()
+}
+
+impl Config {
+    /// The default config, with the theme for the current system appearance.
+    fn defaults() -> Self {
+        let mut config = Self::default();
+        config.appearance = config.theme.resolve(theme::system_appearance(), theme::system_display_accessibility()).expect("the default theme resolves");
+        config
+    }
+
+    /// Checks [grid] and every profile's grid against what the label scheme can label, so a grid
+    /// too big for its labels stops the app at startup instead of leaving cells unlabeled.
+    fn validate_grid_sizes(&self) -> Result<(), String> {
+        self.grid.validate(&self.label_alphabets)?;
+        for (bundle_id, profile) in &self.profiles {
+            profile.apply_to(&self.grid).validate(&self.label_alphabets)
+                .map_err(|e| format!("profiles.{:?}: {}", bundle_id, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn load() -> Result<Self, String> {
+        let Some(path) = config_dir().map(|dir| dir.join(CONFIG_FILE_NAME)) else {
+            println!("HOME not set, using default config");
+            return Ok(Self::defaults());
+        };
+        if !path.exists() {
+            println!("No config at {}, using defaults", path.display());
+            return Ok(Self::defaults());
+        }
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+        let mut config: Config = toml::from_str(&contents)
+            .map_err(|e| format!("Failed to parse config {}: {}", path.display(), e))?;
+        config.normalize();
+        config.label_alphabets = config.labels.resolve()
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        let default_sub_grid = (grid::SUB_GRID_COLS, grid::SUB_GRID_ROWS);
+        if config.label_alphabets.style == LabelStyle::Numbers && (config.grid.sub_grid_cols, config.grid.sub_grid_rows) == default_sub_grid {
+            // Numeric sub-grids default to the numpad shape unless a size was configured
+            config.grid.sub_grid_cols = grid::NUMPAD_SIDE;
+            config.grid.sub_grid_rows = grid::NUMPAD_SIDE;
+        }
+        if config.grid.persist_sub_grid
+            && let Some((cols, rows)) = load_sub_grid_dims(&config.label_alphabets)
+        {
+            config.grid.sub_grid_cols = cols;
+            config.grid.sub_grid_rows = rows;
+        }
+        config.validate_grid_sizes()
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        if config.fuzzy.enabled {
+            config.key_adjacency = config.fuzzy.resolve()
+                .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        }
+        let sub_chars = &config.label_alphabets.sub_chars;
+        if let Some(prefix) = [config.actions.right_prefix_key, config.actions.middle_prefix_key].into_iter().find(|c| sub_chars.contains(c)) {
+            return Err(format!("Invalid config {}: click prefix key '{}' is also a sub-grid label character", path.display(), prefix));
+        }
+        let mut label_chars = config.label_alphabets.all_chars();
+        label_chars.extend(config.grid.label_alphabet().unwrap_or_default());
+        if let Some(digit) = label_chars.iter().find(|c| c.is_ascii_digit() && **c != '0').filter(|_| config.displays.all_displays) {
+            return Err(format!(
+                "Invalid config {}: label character '{}' is also a display number while displays.all_displays is on",
+                path.display(), digit
+            ));
+        }
+        if !config.actions.shortcut.trim().is_empty() {
+            config.action_shortcut = Some(KeyCombo::parse(&config.actions.shortcut)
+                .map_err(|e| format!("Invalid config {}: actions.shortcut: {}", path.display(), e))?);
+        }
+        config.appearance = config.theme.resolve(theme::system_appearance(), theme::system_display_accessibility())
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        config.window_snap.validate()
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        config.click.validate()
+            .map_err(|e| format!("Invalid config {}: {}", path.display(), e))?;
+        config.key_bindings = KeyBindings::from_config(&config.keys, &label_chars)
+            .map_err(|e| format!("Invalid key bindings in {}: {}", path.display(), e))?;
+        println!("Loaded config from {}", path.display());
+        Ok(config)
+    }
+
+    /// Key chars are compared against the uppercase chars produced by key_to_char.
+    fn normalize(&mut self) {
+        let actions = &mut self.actions;
+        for key in [
+            &mut actions.plain_key,
+            &mut actions.right_key,
+            &mut actions.middle_key,
+            &mut actions.cmd_click_key,
+            &mut actions.control_click_key,
+            &mut actions.double_key,
+            &mut actions.right_prefix_key,
+            &mut actions.middle_prefix_key,
+            &mut actions.shortcut_key,
+        ] {
+            *key = key.to_ascii_uppercase();
+        }
+        self.grid.label_alphabet = self.grid.label_alphabet.to_ascii_uppercase();
+        let bisect = &mut self.bisect;
+        for key in [
+            &mut bisect.top_left_key,
+            &mut bisect.top_right_key,
+            &mut bisect.bottom_left_key,
+            &mut bisect.bottom_right_key,
+        ] {
+            *key = key.to_ascii_uppercase();
+        }
+        let quick_jump = &mut self.quick_jump;
+        for key in [
+            &mut quick_jump.center_key,
+            &mut quick_jump.left_key,
+            &mut quick_jump.bottom_key,
+            &mut quick_jump.top_key,
+            &mut quick_jump.right_key,
+            &mut quick_jump.top_left_key,
+            &mut quick_jump.top_right_key,
+            &mut quick_jump.bottom_left_key,
+            &mut quick_jump.bottom_right_key,
+        ] {
+            *key = key.to_ascii_uppercase();
+        }
+        for preset in &mut self.window_snap.presets {
+            preset.key = preset.key.to_ascii_uppercase();
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use eframe::egui;
+
+use crate::click;
+use crate::displays;
+#[cfg(target_os = "macos")]
+use crate::macos;
+
+pub const DIAGNOSE_FLAG: &str = "--diagnose";
+/// Frames the diagnostic window stays open; the maximized size can take a few to arrive.
+const DIAGNOSE_FRAMES: u32 = 30;
+
+/// `--diagnose`: print the screen arrangement and what egui sees, then exit.
+pub fn requested(args: &[String]) -> bool {
+    args.iter().any(|arg| arg == DIAGNOSE_FLAG)
+}
+
+pub fn run() -> Result<(), String> {
+    print_screens();
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_transparent(true)
+            .with_decorations(false)
+            .with_always_on_top()
+            .with_maximized(true)
+            .with_title("Mouseless Diagnostics"),
+        ..Default::default()
+    };
+    eframe::run_native("Mouseless Diagnostics", native_options, Box::new(|_cc| Ok(Box::new(DiagnoseApp::default()))))
+        .map_err(|e| format!("Eframe error: {:?}", e))
+}
+
+/// Each screen as AppKit and display_info report it, and which one holds the cursor.
+fn print_screens() {
+    let cursor = click::current_cursor_location().map(|point| egui::pos2(point.x as f32, point.y as f32));
+    println!("Cursor (global, top-left origin): {:?}", cursor);
+    #[cfg(target_os = "macos")]
+    {
+        let screens = macos::screens();
+        // AppKit's y axis points up from the bottom of the menu bar screen
+        let primary_height = screens.first().map(|screen| screen.frame.size.height).unwrap_or(0.0);
+        for (index, screen) in screens.iter().enumerate() {
+            let frame = screen.frame;
+            let top_left_frame = egui::Rect::from_min_size(
+                egui::pos2(frame.origin.x as f32, (primary_height - frame.origin.y - frame.size.height) as f32),
+                egui::vec2(frame.size.width as f32, frame.size.height as f32),
+            );
+            println!(
+                "NSScreen {}: AppKit frame ({}, {}) {}x{}, top-left frame {:?}, backing scale {}{}",
+                index, frame.origin.x, frame.origin.y, frame.size.width, frame.size.height, top_left_frame, screen.scale_factor,
+                if cursor.is_some_and(|cursor| top_left_frame.contains(cursor)) { "  <- cursor" } else { "" }
+            );
+        }
+    }
+    for (index, display) in displays::active_displays().iter().enumerate() {
+        println!(
+            "Display {} (id {}): frame {:?}, scale {}{}{}",
+            index + 1, display.id, display.frame, display.scale_factor,
+            if display.is_primary { ", primary" } else { "" },
+            if cursor.is_some_and(|cursor| display.frame.contains(cursor)) { "  <- cursor" } else { "" }
+        );
+    }
+}
+
+/// Prints the overlay-sized window's screen_rect and scale whenever they change, then closes.
+#[derive(Default)]
+struct DiagnoseApp {
+    frames: u32,
+    last_printed: Option<String>,
+}
+
+impl eframe::App for DiagnoseApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let report = ctx.input(|i| format!(
+            "egui screen_rect {:?}, pixels_per_point {}, outer_rect {:?}, native_pixels_per_point {:?}",
+            i.screen_rect(), i.pixels_per_point(), i.viewport().outer_rect, i.viewport().native_pixels_per_point
+        ));
+        if self.last_printed.as_ref() != Some(&report) {
+            println!("Frame {}: {}", self.frames, report);
+            self.last_printed = Some(report);
+        }
+        self.frames += 1;
+        if self.frames >= DIAGNOSE_FRAMES {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        ctx.request_repaint();
+    }
+
+    fn clear_color(&self, _visuals: &egui::Visuals) -> [f32; 4] {
+        [0.0, 0.0, 0.0, 0.0]
+    }
+}
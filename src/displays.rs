@@ -0,0 +1,67 @@
+use std::cmp::Ordering;
+
+use display_info::DisplayInfo;
+use eframe::egui;
+
+#[derive(Debug, Clone)]
+pub struct OverlayDisplay {
+    pub id: u32,
+    /// Global frame in points, top-left origin (same space as egui's outer_rect).
+    pub frame: egui::Rect,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+/// All connected displays ordered left to right (then top to bottom), so display numbers
+/// follow the physical arrangement.
+pub fn active_displays() -> Vec<OverlayDisplay> {
+    match DisplayInfo::all() {
+        Ok(infos) => {
+            let mut displays: Vec<OverlayDisplay> = infos
+                .into_iter()
+                .map(|info| OverlayDisplay {
+                    id: info.id,
+                    frame: egui::Rect::from_min_size(
+                        egui::pos2(info.x as f32, info.y as f32),
+                        egui::vec2(info.width as f32, info.height as f32),
+                    ),
+                    scale_factor: info.scale_factor,
+                    is_primary: info.is_primary,
+                })
+                .collect();
+            displays.sort_by(|a, b| {
+                a.frame.min.x.partial_cmp(&b.frame.min.x)
+                    .unwrap_or(Ordering::Equal)
+                    .then(a.frame.min.y.partial_cmp(&b.frame.min.y).unwrap_or(Ordering::Equal))
+            });
+            displays
+        }
+        Err(e) => {
+            eprintln!("Failed to enumerate displays: {:?}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Strip at the top of a display that clicks can't usefully land on, in display-local points.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScreenTopInsets {
+    /// Menu bar height; on notched displays this also covers the camera housing.
+    pub menu_bar: f32,
+    /// Left and right edge of the notch, if the display has one.
+    pub notch: Option<(f32, f32)>,
+}
+
+impl ScreenTopInsets {
+    pub fn notch_rect(&self) -> Option<egui::Rect> {
+        self.notch.map(|(left, right)| egui::Rect::from_min_max(egui::pos2(left, 0.0), egui::pos2(right, self.menu_bar)))
+    }
+}
+
+pub fn display_containing(displays: &[OverlayDisplay], point: egui::Pos2) -> Option<usize> {
+    displays.iter().position(|display| display.frame.contains(point))
+}
+
+pub fn primary_display(displays: &[OverlayDisplay]) -> Option<usize> {
+    displays.iter().position(|display| display.is_primary)
+}
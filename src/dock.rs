@@ -0,0 +1,55 @@
+use eframe::egui;
+
+/// Screen edge the Dock is pinned to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DockEdge {
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Edge the Dock items line up along: a wide strip is a bottom Dock, a tall one sits on the side
+/// of the screen its center is on.
+pub fn dock_edge(items: &[egui::Rect], screen_rect: egui::Rect) -> Option<DockEdge> {
+    let strip = items.iter().copied().reduce(|union, rect| union.union(rect))?;
+    Some(if strip.width() >= strip.height() {
+        DockEdge::Bottom
+    } else if strip.center().x < screen_rect.center().x {
+        DockEdge::Left
+    } else {
+        DockEdge::Right
+    })
+}
+
+/// Where to put the hints for Dock items with these frames (window-relative, in Dock order).
+/// An auto-hidden Dock reports its items just past the screen edge, so the strip is moved in until
+/// it touches the edge. Magnified items are shrunk to the smallest item's thickness around their
+/// centers so neighbouring hints don't cover each other. Empty when the Dock is on another display.
+pub fn place_dock_hints(items: &[egui::Rect], screen_rect: egui::Rect) -> Vec<egui::Rect> {
+    let Some(edge) = dock_edge(items, screen_rect) else {
+        return Vec::new();
+    };
+    let strip = items.iter().copied().reduce(|union, rect| union.union(rect)).unwrap_or(egui::Rect::NOTHING);
+    let (shift, thickness) = match edge {
+        DockEdge::Bottom => (
+            egui::vec2(0.0, (screen_rect.max.y - strip.max.y).min(0.0)),
+            items.iter().map(|rect| rect.height()).fold(f32::INFINITY, f32::min),
+        ),
+        DockEdge::Left => (
+            egui::vec2((screen_rect.min.x - strip.min.x).max(0.0), 0.0),
+            items.iter().map(|rect| rect.width()).fold(f32::INFINITY, f32::min),
+        ),
+        DockEdge::Right => (
+            egui::vec2((screen_rect.max.x - strip.max.x).min(0.0), 0.0),
+            items.iter().map(|rect| rect.width()).fold(f32::INFINITY, f32::min),
+        ),
+    };
+    let placed: Vec<egui::Rect> = items.iter()
+        .map(|rect| egui::Rect::from_center_size(rect.center() + shift, egui::vec2(thickness, thickness)))
+        .collect();
+    if placed.iter().all(|rect| screen_rect.contains(rect.center())) {
+        placed
+    } else {
+        Vec::new()
+    }
+}
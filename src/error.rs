@@ -0,0 +1,40 @@
+use std::fmt;
+
+/// Why mouseless couldn't start or stopped.
+#[derive(thiserror::Error)]
+pub enum AppError {
+    #[error("mouseless isn't allowed to use Accessibility; add it in System Settings > Privacy & Security > Accessibility and launch it again")]
+    AccessibilityNotGranted,
+    #[error("failed to create the event tap: {0}")]
+    EventTap(String),
+    #[error("{0}")]
+    Config(String),
+    #[error("{0}")]
+    Diagnose(String),
+    #[error("eframe error: {0}")]
+    Eframe(#[from] eframe::Error),
+}
+
+/// Why the event tap couldn't start. Sent back from the listener thread, so unlike AppError it
+/// has to be Send.
+#[derive(Debug)]
+pub enum TapError {
+    AccessibilityNotGranted,
+    Failed(String),
+}
+
+impl From<TapError> for AppError {
+    fn from(error: TapError) -> Self {
+        match error {
+            TapError::AccessibilityNotGranted => AppError::AccessibilityNotGranted,
+            TapError::Failed(reason) => AppError::EventTap(reason),
+        }
+    }
+}
+
+// main prints a returned error with Debug; the message is what the user needs to see
+impl fmt::Debug for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
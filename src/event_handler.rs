@@ -1,61 +1,260 @@
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
-use std::time::{Instant, Duration};
 use std::ptr;
-use std::sync::mpsc::Sender;
-use std::cell::Cell;
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, SendError, Sender};
+use std::sync::OnceLock;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::collections::HashSet;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use core_foundation::runloop::{kCFRunLoopCommonModes, CFRunLoopSource, CFRunLoop};
-use core_foundation::mach_port::CFMachPortCreateRunLoopSource;
+use core_foundation::mach_port::{CFMachPortCreateRunLoopSource, CFMachPortRef};
 use core_foundation::base::TCFType;
 use core_graphics::event::{
     CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement, CGEventTapProxy, CGEventType,
     CGEventFlags, CGEvent, EventField, CGEventTap
 };
+use core_graphics::geometry::CGPoint;
 use mouse_rs::Mouse;
 
-pub const RCMD_TAP_DURATION_MS: u128 = 100;
-pub const RCMD_DOUBLE_TAP_MAX_DELAY_MS: u128 = 200; // Max delay between releases for a double tap
+use crate::config::DropGridConfig;
+use crate::error::TapError;
+use crate::pointer_motion::HeldDirections;
+use crate::tap_gesture::{ActivationKey, TapAction, TapInput};
+use crate::theme::{DisplayAccessibility, SystemAppearance};
+#[cfg(target_os = "macos")]
+use crate::ax;
+
 pub const RIGHT_COMMAND_KEY_CODE: i64 = 54;
+pub const RIGHT_OPTION_KEY_CODE: i64 = 61;
+pub const CAPS_LOCK_KEY_CODE: i64 = 57;
+pub const FN_KEY_CODE: i64 = 63;
 pub const LEFT_SHIFT_KEY_CODE: i64 = 56;
+pub const RIGHT_SHIFT_KEY_CODE: i64 = 60;
 pub const ESCAPE_KEY_CODE: i64 = 53;
 
+#[link(name = "CoreGraphics", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventTapEnable(tap: CFMachPortRef, enable: bool);
+}
+
 #[derive(Debug)]
 pub enum GlobalEvent {
-    PotentialSingleRCmdTap { tap_time: Instant, cursor_pos: Option<eframe::egui::Pos2> },
-    RCmdDoubleTap,
-    CancelPendingRCmdTap
+    /// The activation key tapped `taps` times in a row.
+    TapGesture { taps: usize, action: TapAction, cursor_pos: Option<eframe::egui::Pos2> },
+    /// Escape while the overlay is visible; the UI decides whether that backs out one level or hides.
+    EscapePressed { force_hide: bool },
+    /// The physical left button has been held still for [drop_grid] hold_ms.
+    DropGridRequested { cursor_pos: eframe::egui::Pos2 },
+    /// The left button went down, physical or posted, while the overlay was visible. Not sent while
+    /// it's hidden, so ordinary clicks don't wake the UI.
+    LeftButtonPressed { location: eframe::egui::Pos2 },
+    /// The left button came up while the overlay was visible.
+    LeftButtonReleased,
+    /// The physical pointer moved while the overlay was on screen, by `delta`. Not sent while it's
+    /// hidden, so normal mouse use doesn't flood the channel, nor for moves mouseless posts itself.
+    MouseMoved { location: eframe::egui::Pos2, delta: eframe::egui::Vec2 },
+    /// mouseless was launched again while this instance was running.
+    Relaunched,
+    /// The system switched between light and dark mode; `accessibility` is read at the same time.
+    AppearanceChanged { appearance: SystemAppearance, accessibility: DisplayAccessibility },
+}
+
+/// Sends events to the UI and wakes it, so the UI doesn't have to poll for them while hidden.
+#[derive(Clone)]
+pub struct EventSender {
+    tx: Sender<GlobalEvent>,
+    ctx: Arc<OnceLock<eframe::egui::Context>>,
+}
+
+impl EventSender {
+    pub fn new(tx: Sender<GlobalEvent>) -> Self {
+        Self { tx, ctx: Arc::new(OnceLock::new()) }
+    }
+
+    /// Hands over the UI's context once eframe has made it. Events sent before then are read on
+    /// the first frame.
+    pub fn attach(&self, ctx: eframe::egui::Context) {
+        let _ = self.ctx.set(ctx);
+    }
+
+    pub fn send(&self, event: GlobalEvent) -> Result<(), SendError<GlobalEvent>> {
+        self.tx.send(event)?;
+        if let Some(ctx) = self.ctx.get() {
+            ctx.request_repaint();
+        }
+        Ok(())
+    }
 }
 
 pub struct EventTapSharedState {
-    pub event_tx: Sender<GlobalEvent>,
+    pub event_tx: EventSender,
+    pub tap_tx: Sender<TapInput>,
     pub app_is_visible: Arc<AtomicBool>,
-    pub eframe_hide_requested_by_listener: Arc<AtomicBool>,
+    pub overlay_rendered: Arc<AtomicBool>,
+    pub cancel_key_code: i64,
+    pub pointer_keys: Arc<HeldDirections>,
     pub lshift_key_is_pressed: Arc<AtomicBool>,
+    pub activation_key: ActivationKey,
+    pub drop_grid: DropGridConfig,
+}
+
+fn to_pos2(point: CGPoint) -> eframe::egui::Pos2 {
+    eframe::egui::pos2(point.x as f32, point.y as f32)
 }
 
 fn is_modifier_key_code(key_code: i64) -> bool {
-    matches!(key_code, 54 | 55 | 56 | 57 | 58 | 59 | 60 | 61 | 62 | 63)
+    matches!(key_code, 54..=63)
+}
+
+fn is_shift_key_code(key_code: i64) -> bool {
+    key_code == LEFT_SHIFT_KEY_CODE || key_code == RIGHT_SHIFT_KEY_CODE
+}
+
+/// Device-dependent flag bit (NX_DEVICE*KEYMASK) that tells left and right modifiers apart.
+fn device_mask_for_key_code(key_code: i64) -> Option<u64> {
+    match key_code {
+        54 => Some(0x0000_0010), // right command
+        55 => Some(0x0000_0008), // left command
+        56 => Some(0x0000_0002), // left shift
+        58 => Some(0x0000_0020), // left option
+        59 => Some(0x0000_0001), // left control
+        60 => Some(0x0000_0004), // right shift
+        61 => Some(0x0000_0040), // right option
+        62 => Some(0x0000_2000), // right control
+        63 => Some(0x0080_0000), // fn / globe (NX_SECONDARYFNMASK)
+        _ => None,
+    }
+}
+
+/// Modifier keys currently held, tracked per key code rather than inferred from the
+/// aggregate CGEventFlagCommand/Shift bits which both sides of the keyboard share.
+#[derive(Default)]
+struct PressedModifiers {
+    key_codes: HashSet<i64>,
+}
+
+impl PressedModifiers {
+    /// Applies a FlagsChanged event and returns whether `key_code` is down afterwards.
+    fn update(&mut self, key_code: i64, flags: CGEventFlags) -> bool {
+        let is_down = match device_mask_for_key_code(key_code) {
+            Some(mask) => flags.bits() & mask != 0,
+            // No device bit for this key (caps lock): each event toggles it
+            None => !self.key_codes.contains(&key_code),
+        };
+        if is_down {
+            self.key_codes.insert(key_code);
+        } else {
+            self.key_codes.remove(&key_code);
+        }
+        is_down
+    }
+
+    fn is_down(&self, key_code: i64) -> bool {
+        self.key_codes.contains(&key_code)
+    }
 }
 
-pub fn global_event_listener_thread(shared_state: EventTapSharedState) {
+/// Physical left button input for the drop grid's hold timer.
+enum HoldInput {
+    /// Pressed at this point with the overlay hidden; a drop grid unless it ends within the hold.
+    Pressed(CGPoint),
+    /// Released, dragged past the slop, or pressed on the overlay.
+    Ended,
+}
+
+/// Times every press on one thread, so the tap callback never waits and no thread is started per press.
+fn drop_hold_thread(input_rx: Receiver<HoldInput>, event_tx: EventSender, hold: Duration) {
+    let mut held: Option<(CGPoint, Instant)> = None;
+    loop {
+        let input = match held {
+            Some((_, deadline)) => match input_rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(input) => Some(input),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            },
+            None => match input_rx.recv() {
+                Ok(input) => Some(input),
+                Err(_) => return,
+            },
+        };
+        match input {
+            Some(HoldInput::Pressed(origin)) => held = Some((origin, Instant::now() + hold)),
+            Some(HoldInput::Ended) => held = None,
+            None => {
+                if let Some((origin, _)) = held.take() {
+                    println!("Left button held at ({}, {}), requesting drop grid", origin.x, origin.y);
+                    let _ = event_tx.send(GlobalEvent::DropGridRequested { cursor_pos: to_pos2(origin) });
+                }
+            }
+        }
+    }
+}
+
+/// Runs the event tap on this thread until the app exits. Whether the tap could be created is
+/// reported on `ready_tx` first, so startup can stop on a missing permission.
+pub fn global_event_listener_thread(shared_state: EventTapSharedState, ready_tx: Sender<Result<(), TapError>>) {
     println!("Global event listener started");
-    let rcmd_press_start_time: Cell<Option<Instant>> = Cell::new(None);
-    let first_tap_release_time_for_double_tap: Cell<Option<Instant>> = Cell::new(None);
+    #[cfg(target_os = "macos")]
+    if !ax::is_process_trusted() {
+        let _ = ready_tx.send(Err(TapError::AccessibilityNotGranted));
+        return;
+    }
+    let pressed_modifiers: RefCell<PressedModifiers> = RefCell::new(PressedModifiers::default());
+    let activation_key_code = shared_state.activation_key.key_code();
+    println!("Activation key: {:?}", shared_state.activation_key);
+    let activation_key = shared_state.activation_key;
+    // Caps lock's tracked state is the lock, not the key being held
+    let activation_key_is_held = move |pressed: &PressedModifiers| activation_key != ActivationKey::CapsLock && pressed.is_down(activation_key_code);
+    // Where the physical left button went down, while it may still become a drop grid
+    let held_left_button: RefCell<Option<CGPoint>> = RefCell::new(None);
+    let hold_tx = shared_state.drop_grid.enabled.then(|| {
+        let (hold_tx, hold_rx) = channel();
+        let event_tx = shared_state.event_tx.clone();
+        let hold = Duration::from_millis(shared_state.drop_grid.hold_ms);
+        thread::spawn(move || drop_hold_thread(hold_rx, event_tx, hold));
+        hold_tx
+    });
+    let send_hold = move |input: HoldInput| {
+        if let Some(hold_tx) = &hold_tx {
+            let _ = hold_tx.send(input);
+        }
+    };
     let current_run_loop = CFRunLoop::get_current();
+    // Set once the tap exists, so the callback can switch it back on
+    let tap_port: Rc<Cell<Option<CFMachPortRef>>> = Rc::new(Cell::new(None));
+    let callback_tap_port = Rc::clone(&tap_port);
 
     let callback_closure = move |_proxy: CGEventTapProxy, event_type: CGEventType, event: &CGEvent| -> Option<CGEvent> {
-        if shared_state.app_is_visible.load(AtomicOrdering::SeqCst) {
-            match event_type {
-                CGEventType::KeyDown => {
-                    let key_code = event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
-                    if key_code == ESCAPE_KEY_CODE {
-                        println!("Escape pressed, hiding app");
-                        shared_state.eframe_hide_requested_by_listener.store(true, AtomicOrdering::SeqCst);
-                        return None;
-                    }
-                }
-                _ => {}
+        // A tap that filters events is switched off when a callback runs long; every key and click
+        // would then go by unseen until it's re-enabled
+        if matches!(event_type, CGEventType::TapDisabledByTimeout) {
+            if let Some(port) = callback_tap_port.get() {
+                unsafe { CGEventTapEnable(port, true) };
+                println!("Event tap timed out, re-enabled it");
+            }
+            return Some(event.clone());
+        }
+
+        if matches!(event_type, CGEventType::KeyDown | CGEventType::KeyUp) {
+            let key_code = event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
+            shared_state.pointer_keys.update_from_key(key_code, matches!(event_type, CGEventType::KeyDown));
+        }
+
+        // The cancel key is the only event the tap swallows, and only once the overlay is really on
+        // screen; a stale visible flag must not eat another app's Escape
+        if matches!(event_type, CGEventType::KeyDown)
+            && shared_state.app_is_visible.load(AtomicOrdering::SeqCst)
+            && shared_state.overlay_rendered.load(AtomicOrdering::SeqCst)
+        {
+            let key_code = event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
+            if key_code == shared_state.cancel_key_code {
+                let force_hide = event.get_flags().contains(CGEventFlags::CGEventFlagShift);
+                println!("Cancel key pressed (force hide: {}), forwarding to app", force_hide);
+                let _ = shared_state.event_tx.send(GlobalEvent::EscapePressed { force_hide });
+                return None;
             }
         }
 
@@ -63,87 +262,79 @@ pub fn global_event_listener_thread(shared_state: EventTapSharedState) {
             CGEventType::FlagsChanged => {
                 let flags = event.get_flags();
                 let key_code = event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
+                let key_is_down = pressed_modifiers.borrow_mut().update(key_code, flags);
 
-                if key_code == RIGHT_COMMAND_KEY_CODE {
-                    if flags.contains(CGEventFlags::CGEventFlagCommand) { // RCMD Pressed
-                        if rcmd_press_start_time.get().is_none() {
-                            rcmd_press_start_time.set(Some(Instant::now()));
-                        }
-                        // If RCMD is pressed while a double-tap is being awaited (first_tap_release_time is Some),
-                        // it means the user didn't release RCMD cleanly between taps, or it's a new press after timeout.
-                        // We should reset the double-tap expectation.
-                        // This case is less about a "clean" second press and more about interrupting a pending double tap state.
-                        if first_tap_release_time_for_double_tap.get().is_some() && rcmd_press_start_time.get().is_some() {
-                            // If a new press starts *while* we are waiting for a second tap (first_tap_release_time_for_double_tap is Some)
-                            // it's complex. For now, let's assume a new press *after* a first tap's release should not immediately clear state.
-                            // The release logic will handle timeouts.
-                            // The key is that rcmd_press_start_time is for the CURRENT press.
-                        }
-                    } else { // RCMD Released
-                        let current_release_time = Instant::now();
-                        if let Some(press_time) = rcmd_press_start_time.take() { 
-                            if press_time.elapsed() < Duration::from_millis(RCMD_TAP_DURATION_MS as u64) {
-                                let cursor_pos = match Mouse::new().get_position() {
-                                    Ok(point) => Some(eframe::egui::pos2(point.x as f32, point.y as f32)),
-                                    Err(_) => None,
-                                };
-
-                                if let Some(prev_release_time) = first_tap_release_time_for_double_tap.take() {
-                                    if current_release_time.duration_since(prev_release_time) < Duration::from_millis(RCMD_DOUBLE_TAP_MAX_DELAY_MS as u64) {
-                                        println!("RCmd Double Tap detected by listener.");
-                                        let _ = shared_state.event_tx.send(GlobalEvent::RCmdDoubleTap);
-                                        return None;
-                                    } else {
-                                        println!("Second RCmd tap too late for double. Treating as new first potential tap.");
-                                        first_tap_release_time_for_double_tap.set(Some(current_release_time));
-                                        let _ = shared_state.event_tx.send(GlobalEvent::PotentialSingleRCmdTap { tap_time: current_release_time, cursor_pos });
-                                        return None;
-                                    }
-                                } else {
-                                    println!("First RCmd tap release detected by listener.");
-                                    first_tap_release_time_for_double_tap.set(Some(current_release_time));
-                                    let _ = shared_state.event_tx.send(GlobalEvent::PotentialSingleRCmdTap { tap_time: current_release_time, cursor_pos });
-                                    return None;
-                                }
-                            } else {
-                                println!("RCmd held too long, not a tap. Cancelling pending sequence.");
-                                if first_tap_release_time_for_double_tap.take().is_some() {
-                                    let _ = shared_state.event_tx.send(GlobalEvent::CancelPendingRCmdTap);
-                                }
-                            }
-                        }
-                    }
-                } else if key_code == LEFT_SHIFT_KEY_CODE {
-                    if flags.contains(CGEventFlags::CGEventFlagShift) {
-                        if !shared_state.lshift_key_is_pressed.load(AtomicOrdering::SeqCst) {
-                             shared_state.lshift_key_is_pressed.store(true, AtomicOrdering::SeqCst);
-                             println!("Left shift pressed");
-                        }
+                // The flags describe the whole modifier state, so shift is tracked from its bit no matter
+                // which key this particular event was reported for
+                let shift_down = flags.contains(CGEventFlags::CGEventFlagShift);
+                if shared_state.lshift_key_is_pressed.swap(shift_down, AtomicOrdering::SeqCst) != shift_down {
+                    println!("Shift {}", if shift_down { "pressed" } else { "released" });
+                }
+
+                if key_code == activation_key_code {
+                    let cursor_pos = || match Mouse::new().get_position() {
+                        Ok(point) => Some(eframe::egui::pos2(point.x as f32, point.y as f32)),
+                        Err(_) => None,
+                    };
+                    if shared_state.activation_key == ActivationKey::CapsLock {
+                        // No separate release event: the toggle is the whole tap
+                        let _ = shared_state.tap_tx.send(TapInput::Press);
+                        let _ = shared_state.tap_tx.send(TapInput::Release(cursor_pos()));
+                    } else if key_is_down {
+                        let _ = shared_state.tap_tx.send(TapInput::Press);
                     } else {
-                        if shared_state.lshift_key_is_pressed.load(AtomicOrdering::SeqCst) {
-                            shared_state.lshift_key_is_pressed.store(false, AtomicOrdering::SeqCst);
-                            println!("Left shift released");
-                        }
-                    }
-                } else {
-                    if rcmd_press_start_time.get().is_some() {
-                        println!("Other modifier changed while RCmd pressed, cancelling pending RCmd tap sequence.");
-                        rcmd_press_start_time.set(None);
-                        if first_tap_release_time_for_double_tap.take().is_some() {
-                            let _ = shared_state.event_tx.send(GlobalEvent::CancelPendingRCmdTap);
-                        }
+                        let _ = shared_state.tap_tx.send(TapInput::Release(cursor_pos()));
                     }
+                } else if is_shift_key_code(key_code) {
+                    // Shift is used to pick the click button, so it never interrupts a tap
+                } else if activation_key_is_held(&pressed_modifiers.borrow()) {
+                    let _ = shared_state.tap_tx.send(TapInput::Interrupt);
                 }
             }
             CGEventType::KeyDown => {
                 let key_code = event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
-                
-                if rcmd_press_start_time.get().is_some() && !is_modifier_key_code(key_code) && key_code != RIGHT_COMMAND_KEY_CODE {
-                    println!("Non-modifier key pressed while RCmd held, cancelling pending RCmd tap sequence.");
-                    rcmd_press_start_time.set(None);
-                    if first_tap_release_time_for_double_tap.take().is_some() {
-                        let _ = shared_state.event_tx.send(GlobalEvent::CancelPendingRCmdTap);
-                    }
+                if !is_modifier_key_code(key_code) && activation_key_is_held(&pressed_modifiers.borrow()) {
+                    let _ = shared_state.tap_tx.send(TapInput::Interrupt);
+                }
+            }
+            CGEventType::LeftMouseDown => {
+                if shared_state.app_is_visible.load(AtomicOrdering::SeqCst) {
+                    let _ = shared_state.event_tx.send(GlobalEvent::LeftButtonPressed { location: to_pos2(event.location()) });
+                }
+                // A press on the overlay itself is never the start of a drag to drop
+                if shared_state.drop_grid.enabled && !shared_state.app_is_visible.load(AtomicOrdering::SeqCst) {
+                    let origin = event.location();
+                    *held_left_button.borrow_mut() = Some(origin);
+                    send_hold(HoldInput::Pressed(origin));
+                } else {
+                    send_hold(HoldInput::Ended);
+                }
+            }
+            CGEventType::LeftMouseDragged => {
+                let location = event.location();
+                let moved_too_far = held_left_button.borrow().is_some_and(|origin| {
+                    (location.x - origin.x).hypot(location.y - origin.y) > shared_state.drop_grid.slop_px
+                });
+                if moved_too_far {
+                    *held_left_button.borrow_mut() = None;
+                    send_hold(HoldInput::Ended);
+                }
+            }
+            CGEventType::LeftMouseUp => {
+                *held_left_button.borrow_mut() = None;
+                send_hold(HoldInput::Ended);
+                if shared_state.app_is_visible.load(AtomicOrdering::SeqCst) {
+                    let _ = shared_state.event_tx.send(GlobalEvent::LeftButtonReleased);
+                }
+            }
+            CGEventType::MouseMoved => {
+                let posted_by_us = event.get_integer_value_field(EventField::EVENT_SOURCE_UNIX_PROCESS_ID) == std::process::id() as i64;
+                if !posted_by_us && shared_state.app_is_visible.load(AtomicOrdering::SeqCst) && shared_state.overlay_rendered.load(AtomicOrdering::SeqCst) {
+                    let delta = eframe::egui::vec2(
+                        event.get_double_value_field(EventField::MOUSE_EVENT_DELTA_X) as f32,
+                        event.get_double_value_field(EventField::MOUSE_EVENT_DELTA_Y) as f32,
+                    );
+                    let _ = shared_state.event_tx.send(GlobalEvent::MouseMoved { location: to_pos2(event.location()), delta });
                 }
             }
             _ => {}
@@ -154,8 +345,12 @@ pub fn global_event_listener_thread(shared_state: EventTapSharedState) {
     let tap_result = CGEventTap::new(
         CGEventTapLocation::HID,        
         CGEventTapPlacement::HeadInsertEventTap, 
-        CGEventTapOptions::ListenOnly,  
-        vec![CGEventType::KeyDown, CGEventType::KeyUp, CGEventType::FlagsChanged], 
+        CGEventTapOptions::Default,
+        vec![
+            CGEventType::KeyDown, CGEventType::KeyUp, CGEventType::FlagsChanged,
+            CGEventType::LeftMouseDown, CGEventType::LeftMouseDragged, CGEventType::LeftMouseUp,
+            CGEventType::MouseMoved,
+        ],
         callback_closure,
     );
 
@@ -165,7 +360,7 @@ pub fn global_event_listener_thread(shared_state: EventTapSharedState) {
                 let mach_port_ref = tap.mach_port.as_concrete_TypeRef();
                 let source = CFMachPortCreateRunLoopSource(ptr::null_mut(), mach_port_ref, 0);
                 if source.is_null() {
-                    eprintln!("Failed to create run loop source");
+                    let _ = ready_tx.send(Err(TapError::Failed("no run loop source for the tap".to_string())));
                     return;
                 }
                 let cf_run_loop_source = CFRunLoopSource::wrap_under_get_rule(source);
@@ -173,13 +368,15 @@ pub fn global_event_listener_thread(shared_state: EventTapSharedState) {
                 current_run_loop.add_source(&cf_run_loop_source, kCFRunLoopCommonModes);
                 
                 tap.enable();
+                tap_port.set(Some(mach_port_ref));
             }
             println!("Event tap enabled");
+            let _ = ready_tx.send(Ok(()));
             CFRunLoop::run_current(); 
             println!("Event loop exited"); 
         }
         Err(e) => {
-            eprintln!("Failed to create event tap: {:?}", e);
+            let _ = ready_tx.send(Err(TapError::Failed(format!("{:?}", e))));
         }
     }
 } 
\ No newline at end of file
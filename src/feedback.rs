@@ -0,0 +1,167 @@
+use serde::Deserialize;
+
+#[cfg(target_os = "macos")]
+use std::collections::HashMap;
+#[cfg(target_os = "macos")]
+use std::sync::OnceLock;
+#[cfg(target_os = "macos")]
+use std::sync::mpsc::{self, Sender};
+#[cfg(target_os = "macos")]
+use std::thread;
+
+#[cfg(target_os = "macos")]
+use core_foundation::base::{CFType, TCFType};
+#[cfg(target_os = "macos")]
+use core_foundation::boolean::CFBoolean;
+#[cfg(target_os = "macos")]
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+#[cfg(target_os = "macos")]
+use core_foundation::string::CFString;
+#[cfg(target_os = "macos")]
+use core_foundation::url::{CFURL, CFURLRef};
+
+/// Where the named system sounds live.
+#[cfg(target_os = "macos")]
+const SYSTEM_SOUNDS_DIR: &str = "/System/Library/Sounds";
+
+#[cfg(target_os = "macos")]
+#[link(name = "AudioToolbox", kind = "framework")]
+unsafe extern "C" {
+    fn AudioServicesCreateSystemSoundID(url: CFURLRef, sound_id: *mut u32) -> i32;
+    fn AudioServicesPlaySystemSound(sound_id: u32);
+}
+
+#[cfg(target_os = "macos")]
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn CGSessionCopyCurrentDictionary() -> CFDictionaryRef;
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FeedbackConfig {
+    /// After a click lands, show a ring expanding from the clicked point.
+    pub click_marker: bool,
+    pub click_marker_ms: u64,
+    /// Short system sounds for the cues below; each can be turned off on its own.
+    pub sound: bool,
+    pub sound_on_show: bool,
+    pub sound_on_sub_grid: bool,
+    pub sound_on_click: bool,
+    pub sound_on_cancel: bool,
+    /// No sounds at all while the screen is being shared, so they don't reach the other side.
+    pub silent_while_sharing: bool,
+}
+
+impl Default for FeedbackConfig {
+    fn default() -> Self {
+        Self {
+            click_marker: false,
+            click_marker_ms: 300,
+            sound: false,
+            sound_on_show: true,
+            sound_on_sub_grid: true,
+            sound_on_click: true,
+            sound_on_cancel: true,
+            silent_while_sharing: false,
+        }
+    }
+}
+
+/// Moments that can play a sound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cue {
+    GridShown,
+    SubGridEntered,
+    ClickPosted,
+    Cancelled,
+}
+
+impl Cue {
+    fn enabled(&self, config: &FeedbackConfig) -> bool {
+        config.sound
+            && match self {
+                Cue::GridShown => config.sound_on_show,
+                Cue::SubGridEntered => config.sound_on_sub_grid,
+                Cue::ClickPosted => config.sound_on_click,
+                Cue::Cancelled => config.sound_on_cancel,
+            }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn sound_name(&self) -> &'static str {
+        match self {
+            Cue::GridShown => "Pop",
+            Cue::SubGridEntered => "Tink",
+            Cue::ClickPosted => "Morse",
+            Cue::Cancelled => "Bottle",
+        }
+    }
+}
+
+/// Plays `cue` if it's turned on. Never blocks: the sound is handed to a player thread, and
+/// AudioServicesPlaySystemSound itself returns before the sound has played. System sounds go
+/// through the alert volume, so a muted Mac stays quiet. With `silent_while_sharing`, the player
+/// thread checks for screen sharing before each sound, so the check never runs on the UI thread.
+pub fn play(cue: Cue, config: &FeedbackConfig) {
+    if !cue.enabled(config) {
+        return;
+    }
+    #[cfg(target_os = "macos")]
+    if player().send((cue, config.silent_while_sharing)).is_err() {
+        eprintln!("Sound player thread is gone, not playing {:?}", cue);
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn player() -> &'static Sender<(Cue, bool)> {
+    static PLAYER: OnceLock<Sender<(Cue, bool)>> = OnceLock::new();
+    PLAYER.get_or_init(|| {
+        let (cue_tx, cue_rx) = mpsc::channel::<(Cue, bool)>();
+        thread::spawn(move || {
+            // Sound ids are created on first use and kept; a sound that failed stays None
+            let mut sound_ids: HashMap<Cue, Option<u32>> = HashMap::new();
+            for (cue, silent_while_sharing) in cue_rx {
+                if silent_while_sharing && screen_is_shared() {
+                    continue;
+                }
+                let sound_id = *sound_ids.entry(cue).or_insert_with(|| create_sound_id(cue.sound_name()));
+                if let Some(sound_id) = sound_id {
+                    unsafe { AudioServicesPlaySystemSound(sound_id) };
+                }
+            }
+        });
+        cue_tx
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn create_sound_id(name: &str) -> Option<u32> {
+    let path = format!("{}/{}.aiff", SYSTEM_SOUNDS_DIR, name);
+    let Some(url) = CFURL::from_path(&path, false) else {
+        eprintln!("Bad sound path {}", path);
+        return None;
+    };
+    let mut sound_id = 0;
+    let status = unsafe { AudioServicesCreateSystemSoundID(url.as_concrete_TypeRef(), &mut sound_id) };
+    if status != 0 {
+        eprintln!("Failed to load {} (status {})", path, status);
+        return None;
+    }
+    Some(sound_id)
+}
+
+/// Whether the login session's screen is being shared, as Screen Sharing and Remote Management
+/// report it. Apps that capture the screen themselves, like video calls, don't set this.
+#[cfg(target_os = "macos")]
+fn screen_is_shared() -> bool {
+    let session = unsafe { CGSessionCopyCurrentDictionary() };
+    if session.is_null() {
+        return false;
+    }
+    let session: CFDictionary<CFString, CFType> = unsafe { CFDictionary::wrap_under_create_rule(session) };
+    session
+        .find(CFString::from_static_string("CGSSessionScreenIsShared"))
+        .and_then(|shared| shared.downcast::<CFBoolean>())
+        .is_some_and(bool::from)
+}
@@ -1,73 +1,895 @@
-use eframe::egui;
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+
+use crate::keybindings;
 
 pub const MAIN_GRID_COLS: usize = 12;
 pub const MAIN_GRID_ROWS: usize = 12;
 pub const SUB_GRID_COLS: usize = 5;
 pub const SUB_GRID_ROWS: usize = 5;
 
+pub const MAIN_FIRST_CHARS: [char; 12] = ['A', 'S', 'D', 'F', 'G', 'H', 'J', 'K', 'L', 'Q', 'W', 'E'];
+pub const MAIN_SECOND_CHARS: [char; 12] = ['H', 'J', 'K', 'L', 'Q', 'W', 'E', 'R', 'T', 'Y', 'A', 'S'];
+pub const SUB_GRID_CHARS: [char; 26] = [
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M',
+    'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
+];
+
+// Colemak and Dvorak presets start from their home rows, then the easiest reaches above them
+const COLEMAK_FIRST_CHARS: [char; 12] = ['A', 'R', 'S', 'T', 'D', 'H', 'N', 'E', 'I', 'O', 'W', 'F'];
+const COLEMAK_SECOND_CHARS: [char; 12] = ['N', 'E', 'I', 'O', 'H', 'L', 'U', 'Y', 'A', 'R', 'S', 'T'];
+const COLEMAK_SUB_CHARS: [char; 26] = [
+    'A', 'R', 'S', 'T', 'D', 'H', 'N', 'E', 'I', 'O', 'Q', 'W', 'F',
+    'P', 'G', 'J', 'L', 'U', 'Y', 'Z', 'X', 'C', 'V', 'B', 'K', 'M',
+];
+const DVORAK_FIRST_CHARS: [char; 12] = ['A', 'O', 'E', 'U', 'I', 'D', 'H', 'T', 'N', 'S', 'P', 'G'];
+const DVORAK_SECOND_CHARS: [char; 12] = ['H', 'T', 'N', 'S', 'D', 'I', 'U', 'E', 'O', 'A', 'C', 'R'];
+const DVORAK_SUB_CHARS: [char; 26] = [
+    'A', 'O', 'E', 'U', 'I', 'D', 'H', 'T', 'N', 'S', 'P', 'Y', 'F',
+    'G', 'C', 'R', 'L', 'Q', 'J', 'K', 'X', 'B', 'M', 'W', 'V', 'Z',
+];
+// Left half of a QWERTY keyboard, so the right hand can stay on the trackpad
+const LEFT_HAND_FIRST_CHARS: [char; 12] = ['A', 'S', 'D', 'F', 'G', 'Q', 'W', 'E', 'R', 'T', 'Z', 'X'];
+const LEFT_HAND_SECOND_CHARS: [char; 12] = ['F', 'D', 'S', 'A', 'G', 'R', 'E', 'W', 'Q', 'T', 'V', 'C'];
+const LEFT_HAND_SUB_CHARS: [char; 15] = ['A', 'S', 'D', 'F', 'G', 'Q', 'W', 'E', 'R', 'T', 'Z', 'X', 'C', 'V', 'B'];
+
+/// Numeric sub-grids of this size are laid out like a numpad.
+pub const NUMPAD_SIDE: usize = 3;
+const NUMPAD_DIGITS: [char; 9] = ['7', '8', '9', '4', '5', '6', '1', '2', '3'];
+const DIGIT_CHARS: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+
+// Key rows of a QWERTY keyboard, each shifted half a key right of the one above
+const QWERTY_ROWS: [&str; 4] = ["1234567890-=", "QWERTYUIOP[]", "ASDFGHJKL;'", "ZXCVBNM,./"];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelStyle {
+    Letters,
+    /// Zero-padded cell numbers on the main grid, numpad digits in a 3x3 sub-grid.
+    Numbers,
+}
+
+/// `[labels]` config section: which characters the grid labels are made of.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LabelConfig {
+    pub style: LabelStyle,
+    /// "qwerty", "colemak", "dvorak" or "left-hand-only". Only used by the letters style.
+    pub preset: String,
+    /// Main grid row characters; empty uses the preset's.
+    pub first_chars: Vec<char>,
+    /// Main grid column characters; empty uses the preset's.
+    pub second_chars: Vec<char>,
+    /// Sub-grid characters; empty uses the preset's.
+    pub sub_chars: Vec<char>,
+}
+
+impl Default for LabelConfig {
+    fn default() -> Self {
+        Self {
+            style: LabelStyle::Letters,
+            preset: "qwerty".to_string(),
+            first_chars: Vec::new(),
+            second_chars: Vec::new(),
+            sub_chars: Vec::new(),
+        }
+    }
+}
+
+impl LabelConfig {
+    /// Applies the overrides on top of the named preset and checks every alphabet is typeable and free of repeats.
+    pub fn resolve(&self) -> Result<LabelAlphabets, String> {
+        let preset = LabelAlphabets::preset(&self.preset).ok_or_else(|| format!(
+            "labels.preset must be one of qwerty, colemak, dvorak, left-hand-only, got {:?}",
+            self.preset
+        ))?;
+        let pick = |configured: &Vec<char>, fallback: Vec<char>| {
+            if configured.is_empty() {
+                fallback
+            } else {
+                configured.iter().map(|c| c.to_ascii_uppercase()).collect()
+            }
+        };
+        let alphabets = LabelAlphabets {
+            style: self.style,
+            first_chars: pick(&self.first_chars, preset.first_chars),
+            second_chars: pick(&self.second_chars, preset.second_chars),
+            sub_chars: pick(&self.sub_chars, preset.sub_chars),
+        };
+        validate_alphabet("labels.first_chars", &alphabets.first_chars, 1)?;
+        validate_alphabet("labels.second_chars", &alphabets.second_chars, 1)?;
+        validate_alphabet("labels.sub_chars", &alphabets.sub_chars, 2)?;
+        Ok(alphabets)
+    }
+}
+
+/// `[fuzzy]` config section: accepting a main grid label whose last key was a neighbour of the intended one.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct FuzzyConfig {
+    pub enabled: bool,
+    /// Outline the picked cell briefly so a corrected typo doesn't go unnoticed.
+    pub flash: bool,
+    /// Neighbours per key, replacing the QWERTY ones for the keys listed.
+    pub adjacent_keys: HashMap<char, Vec<char>>,
+}
+
+impl Default for FuzzyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            flash: true,
+            adjacent_keys: HashMap::new(),
+        }
+    }
+}
+
+impl FuzzyConfig {
+    /// The QWERTY adjacency map with the configured overrides applied, all uppercase.
+    pub fn resolve(&self) -> Result<HashMap<char, Vec<char>>, String> {
+        let mut adjacency = qwerty_adjacency();
+        for (key, neighbours) in &self.adjacent_keys {
+            let name = format!("fuzzy.adjacent_keys.{}", key);
+            let key = key.to_ascii_uppercase();
+            let neighbours: Vec<char> = neighbours.iter().map(|c| c.to_ascii_uppercase()).collect();
+            validate_alphabet(&name, &[key], 1)?;
+            validate_alphabet(&name, &neighbours, 0)?;
+            adjacency.insert(key, neighbours);
+        }
+        Ok(adjacency)
+    }
+}
+
+/// Keys touching each key on a QWERTY keyboard: both sides, and the two overlapping keys above and below.
+pub fn qwerty_adjacency() -> HashMap<char, Vec<char>> {
+    let rows: Vec<Vec<char>> = QWERTY_ROWS.iter().map(|row| row.chars().collect()).collect();
+    let mut adjacency = HashMap::new();
+    for (r, row) in rows.iter().enumerate() {
+        for (i, key) in row.iter().enumerate() {
+            let mut neighbours = Vec::new();
+            let mut add = |row: Option<&Vec<char>>, index: Option<usize>| {
+                if let Some(c) = row.zip(index).and_then(|(row, index)| row.get(index)) {
+                    neighbours.push(*c);
+                }
+            };
+            add(Some(row), i.checked_sub(1));
+            add(Some(row), Some(i + 1));
+            let above = r.checked_sub(1).map(|r| &rows[r]);
+            add(above, Some(i));
+            add(above, Some(i + 1));
+            let below = rows.get(r + 1);
+            add(below, i.checked_sub(1));
+            add(below, Some(i));
+            adjacency.insert(*key, neighbours);
+        }
+    }
+    adjacency
+}
+
+/// Checks a label alphabet only has typeable, distinct characters and at least `min_len` of them.
+pub fn validate_alphabet(name: &str, chars: &[char], min_len: usize) -> Result<(), String> {
+    if let Some(c) = chars.iter().find(|c| !keybindings::is_label_char(**c)) {
+        return Err(format!("{} contains '{}', which can't be typed as a label key", name, c));
+    }
+    if let Some(c) = chars.iter().enumerate().find(|(i, c)| chars[..*i].contains(c)).map(|(_, c)| c) {
+        return Err(format!("{} contains '{}' more than once", name, c));
+    }
+    if chars.len() < min_len {
+        return Err(format!("{} needs at least {} characters, got {}", name, min_len, chars.len()));
+    }
+    Ok(())
+}
+
+/// Resolved label characters: row + column characters for the main grid, single characters for the sub-grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabelAlphabets {
+    pub style: LabelStyle,
+    pub first_chars: Vec<char>,
+    pub second_chars: Vec<char>,
+    pub sub_chars: Vec<char>,
+}
+
+impl Default for LabelAlphabets {
+    fn default() -> Self {
+        Self::preset("qwerty").expect("qwerty preset exists")
+    }
+}
+
+impl LabelAlphabets {
+    pub fn preset(name: &str) -> Option<Self> {
+        let (first_chars, second_chars, sub_chars): (&[char], &[char], &[char]) = match name {
+            "qwerty" => (&MAIN_FIRST_CHARS[..], &MAIN_SECOND_CHARS[..], &SUB_GRID_CHARS[..]),
+            "colemak" => (&COLEMAK_FIRST_CHARS[..], &COLEMAK_SECOND_CHARS[..], &COLEMAK_SUB_CHARS[..]),
+            "dvorak" => (&DVORAK_FIRST_CHARS[..], &DVORAK_SECOND_CHARS[..], &DVORAK_SUB_CHARS[..]),
+            "left-hand-only" => (&LEFT_HAND_FIRST_CHARS[..], &LEFT_HAND_SECOND_CHARS[..], &LEFT_HAND_SUB_CHARS[..]),
+            _ => return None,
+        };
+        Some(Self { style: LabelStyle::Letters, first_chars: first_chars.to_vec(), second_chars: second_chars.to_vec(), sub_chars: sub_chars.to_vec() })
+    }
+
+    /// Every character that can appear in a main or sub grid label.
+    pub fn all_chars(&self) -> Vec<char> {
+        if self.style == LabelStyle::Numbers {
+            return DIGIT_CHARS.to_vec();
+        }
+        let mut chars: Vec<char> = Vec::new();
+        for c in self.first_chars.iter().chain(self.second_chars.iter()).chain(self.sub_chars.iter()) {
+            if !chars.contains(c) {
+                chars.push(*c);
+            }
+        }
+        chars
+    }
+
+    /// Largest sub-grid that still gets one- or two-character labels.
+    pub fn max_sub_grid_cells(&self) -> usize {
+        match self.style {
+            LabelStyle::Letters => self.sub_chars.len() * self.sub_chars.len(),
+            LabelStyle::Numbers => 99,
+        }
+    }
+
+    /// Largest grid whose labels are all a single character, as used for the third level.
+    pub fn max_single_char_cells(&self) -> usize {
+        match self.style {
+            LabelStyle::Letters => self.sub_chars.len(),
+            LabelStyle::Numbers => NUMPAD_DIGITS.len(),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum DisplayMode {
     MainGrid,
     SubGrid,
+    ActionPrompt,
+    Scroll,
+    /// Steering the real cursor with h/j/k/l; the overlay only shows a HUD.
+    Pointer,
+    /// Zooming into quadrants of a shrinking region; the region stack lives in the app.
+    Bisect,
+    /// Nudging the candidate point with the arrow keys before committing it.
+    FineAdjust { point: egui::Pos2, origin: egui::Pos2 },
+    /// Typing the label of one of the frontmost app's clickable elements instead of a grid cell.
+    Hints,
+    /// Typing the label of a menu bar title or menu item; picks open menus instead of clicking.
+    MenuHints,
+    /// Typing the label of a Dock item; picks press the item instead of clicking it.
+    DockHints,
+    /// Typing the label of an on-screen window to focus it or move the cursor to it.
+    WindowHints,
 }
 
-pub fn generate_main_grid_layout(num_cols: usize, num_rows: usize, screen_rect: egui::Rect) -> (Vec<String>, Vec<egui::Rect>) {
-    let mut labels = Vec::with_capacity(num_rows * num_cols);
-    let first_chars = ['A', 'S', 'D', 'F', 'G', 'H', 'J', 'K', 'L', 'Q', 'W', 'E'];
-    let second_chars = ['H', 'J', 'K', 'L', 'Q', 'W', 'E', 'R', 'T', 'Y', 'A', 'S'];
+/// A cell picked at one refinement level, kept by index so it can be re-derived after a relayout.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SelectedCell {
+    pub index: usize,
+    pub rect: egui::Rect,
+}
 
-    assert!(num_rows <= first_chars.len(), "Not enough unique first characters for the number of rows.");
-    assert!(num_cols <= second_chars.len(), "Not enough unique second characters for the number of columns.");
+/// Longest main grid label `generate_labels` is allowed to produce.
+pub const MAX_LABEL_LEN: usize = 3;
 
-    for r in 0..num_rows {
-        for c in 0..num_cols {
-            let char1 = first_chars[r];
-            let char2 = second_chars[c];
-            labels.push(format!("{}{}", char1, char2));
-        }
+/// Number of characters needed to give `count` cells distinct labels over `alphabet_len` characters.
+pub fn label_len_for(count: usize, alphabet_len: usize, min_len: usize) -> usize {
+    let mut len = min_len.max(1);
+    while alphabet_len.saturating_pow(len as u32) < count {
+        len += 1;
     }
+    len
+}
 
-    let mut rects = Vec::with_capacity(num_rows * num_cols);
-    if screen_rect.width() > 1.0 && screen_rect.height() > 1.0 {
-        let cell_width = screen_rect.width() / num_cols as f32;
-        let cell_height = screen_rect.height() / num_rows as f32;
-        for i in 0..num_rows {
-            for j in 0..num_cols {
+/// Shortest labels over `alphabet` (at least `min_len` long) for `count` cells. All labels have
+/// the same length, which makes the set prefix-free: a typed buffer equal to a label is final.
+/// Each label is `index` written in base `alphabet.len()`, so no two cells can share one.
+pub fn generate_labels(count: usize, alphabet: &[char], min_len: usize) -> Result<Vec<String>, String> {
+    let base = alphabet.len();
+    if base < 2 {
+        return Err(format!("label alphabet needs at least 2 characters, got {}", base));
+    }
+    if let Some(c) = alphabet.iter().enumerate().find(|(i, c)| alphabet[..*i].contains(c)).map(|(_, c)| c) {
+        return Err(format!("label alphabet contains '{}' more than once", c));
+    }
+    let len = label_len_for(count, base, min_len);
+    if len > MAX_LABEL_LEN {
+        return Err(format!(
+            "{} cells need {}-character labels over {} characters, at most {} are supported",
+            count, len, base, MAX_LABEL_LEN
+        ));
+    }
+    let labels: Vec<String> = (0..count)
+        .map(|index| {
+            let mut digits = vec![alphabet[0]; len];
+            let mut rest = index;
+            for digit in digits.iter_mut().rev() {
+                *digit = alphabet[rest % base];
+                rest /= base;
+            }
+            digits.into_iter().collect()
+        })
+        .collect();
+    debug_assert_labels_unique(&labels, count);
+    Ok(labels)
+}
+
+fn debug_assert_labels_unique(labels: &[String], count: usize) {
+    debug_assert_eq!(labels.len(), count, "expected one label per cell");
+    debug_assert!(
+        labels.iter().enumerate().all(|(i, label)| !labels[..i].contains(label)),
+        "generated duplicate grid labels"
+    );
+}
+
+/// Err naming the first label that appears twice. With repeats the first cell wins every
+/// match and the other can never be picked.
+pub fn ensure_labels_unique(labels: &[String]) -> Result<(), String> {
+    let mut seen = HashSet::with_capacity(labels.len());
+    match labels.iter().find(|label| !seen.insert(label.as_str())) {
+        Some(duplicate) => Err(format!("grid label {:?} was generated twice; pick a larger alphabet or fewer cells", duplicate)),
+        None => Ok(()),
+    }
+}
+
+/// 1-based cell numbers zero-padded to a shared width (at least two digits), e.g. 001-144.
+/// A fixed width keeps the set prefix-free, just like the letter labels.
+pub fn generate_numeric_labels(count: usize, min_width: usize) -> Vec<String> {
+    let width = count.to_string().len().max(min_width);
+    let labels: Vec<String> = (1..=count).map(|number| format!("{:0width$}", number, width = width)).collect();
+    debug_assert_labels_unique(&labels, count);
+    labels
+}
+
+/// `cols` x `rows` equal cells covering `rect`, row by row. Empty while the rect is still degenerate.
+fn layout_cell_rects(rect: egui::Rect, cols: usize, rows: usize) -> Vec<egui::Rect> {
+    let mut rects = Vec::with_capacity(cols * rows);
+    if rect.width() > 1.0 && rect.height() > 1.0 {
+        let cell_width = rect.width() / cols as f32;
+        let cell_height = rect.height() / rows as f32;
+        for r in 0..rows {
+            for c in 0..cols {
                 rects.push(egui::Rect::from_min_size(
-                    screen_rect.min + egui::vec2(j as f32 * cell_width, i as f32 * cell_height),
+                    rect.min + egui::vec2(c as f32 * cell_width, r as f32 * cell_height),
                     egui::vec2(cell_width, cell_height)
                 ));
             }
         }
     }
-    (labels, rects)
+    rects
+}
+
+/// `alphabet` None keeps the row + column character scheme as long as the grid fits it,
+/// and falls back to generated labels over the sub-grid characters past that.
+pub fn generate_main_grid_layout(num_cols: usize, num_rows: usize, screen_rect: egui::Rect, alphabets: &LabelAlphabets, alphabet: Option<&[char]>) -> Result<(Vec<String>, Vec<egui::Rect>), String> {
+    if num_cols == 0 || num_rows == 0 {
+        return Err(format!("main grid needs at least one column and row, got {}x{}", num_cols, num_rows));
+    }
+    if alphabets.style == LabelStyle::Numbers {
+        return Ok((generate_numeric_labels(num_rows * num_cols, 2), layout_cell_rects(screen_rect, num_cols, num_rows)));
+    }
+    let fits_row_column_labels = num_rows <= alphabets.first_chars.len() && num_cols <= alphabets.second_chars.len();
+    let labels = match alphabet {
+        // Both alphabets are free of repeats, so every row/column pair is distinct
+        None if fits_row_column_labels => {
+            let mut labels = Vec::with_capacity(num_rows * num_cols);
+            for first in alphabets.first_chars.iter().take(num_rows) {
+                for second in alphabets.second_chars.iter().take(num_cols) {
+                    labels.push(format!("{}{}", first, second));
+                }
+            }
+            labels
+        }
+        _ => generate_labels(num_rows * num_cols, alphabet.unwrap_or(&alphabets.sub_chars), 2)?,
+    };
+    // Every scheme above is unique by construction; checked anyway since a repeat would leave a cell unreachable
+    ensure_labels_unique(&labels)?;
+    Ok((labels, layout_cell_rects(screen_rect, num_cols, num_rows)))
+}
+
+/// Drops the cells lying entirely inside one of `excluded` (in the rects' coordinates) along with
+/// their labels: nothing is painted there and the labels match nothing. Cells only partly covered
+/// stay. The rest keep their labels, so they stay unique and don't move when a region changes.
+pub fn exclude_cells(labels: Vec<String>, rects: Vec<egui::Rect>, excluded: &[egui::Rect]) -> (Vec<String>, Vec<egui::Rect>) {
+    if excluded.is_empty() || labels.len() != rects.len() {
+        return (labels, rects);
+    }
+    labels.into_iter()
+        .zip(rects)
+        .filter(|(_, rect)| !excluded.iter().any(|region| region.contains_rect(*rect)))
+        .unzip()
 }
 
-pub fn generate_sub_grid_layout(main_cell_rect: egui::Rect, num_cols: usize, num_rows: usize) -> (Vec<String>, Vec<egui::Rect>) {
-    let mut labels = Vec::new();
-    let sub_grid_chars = [
-        'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M',
-        'N', 'O', 'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z',
-    ];
-    let total_cells = num_cols * num_rows;
-    for i in 0..total_cells {
-        if i < sub_grid_chars.len() {
-            labels.push(sub_grid_chars[i].to_string());
+/// Columns and rows for about `target_cells` near-square cells in an area of `screen_size`.
+/// Each side is capped by `max_cols`/`max_rows` (the label alphabets) and the total by `max_labels`;
+/// when a cap is hit the other side is recomputed from the aspect ratio so cells stay square.
+pub fn compute_auto_dims(screen_size: egui::Vec2, target_cells: usize, max_cols: usize, max_rows: usize, max_labels: usize) -> (usize, usize) {
+    let max_cols = max_cols.max(1);
+    let max_rows = max_rows.max(1);
+    if screen_size.x <= 0.0 || screen_size.y <= 0.0 {
+        return (max_cols, max_rows);
+    }
+    let aspect = screen_size.x / screen_size.y;
+    let mut rows = (target_cells.max(1) as f32 / aspect).sqrt();
+    let mut cols = rows * aspect;
+    if cols > max_cols as f32 {
+        cols = max_cols as f32;
+        rows = cols / aspect;
+    }
+    if rows > max_rows as f32 {
+        rows = max_rows as f32;
+        cols = (rows * aspect).min(max_cols as f32);
+    }
+    let mut cols = (cols.round() as usize).clamp(1, max_cols);
+    let mut rows = (rows.round() as usize).clamp(1, max_rows);
+    while cols * rows > max_labels.max(1) {
+        // Drop whichever side leaves the cells closest to square
+        if cols as f32 / rows as f32 > aspect { cols -= 1 } else { rows -= 1 }
+    }
+    (cols, rows)
+}
+
+/// Single characters from the sub-grid alphabet while they suffice, two-character labels past that.
+/// Numeric labels use numpad digits for a 3x3 grid (7 = top-left) and cell numbers otherwise.
+pub fn generate_sub_grid_layout(main_cell_rect: egui::Rect, num_cols: usize, num_rows: usize, alphabets: &LabelAlphabets) -> Result<(Vec<String>, Vec<egui::Rect>), String> {
+    if num_cols == 0 || num_rows == 0 {
+        return Err(format!("sub-grid needs at least one column and row, got {}x{}", num_cols, num_rows));
+    }
+    let labels = match alphabets.style {
+        LabelStyle::Numbers if num_cols == NUMPAD_SIDE && num_rows == NUMPAD_SIDE => {
+            NUMPAD_DIGITS.iter().map(|c| c.to_string()).collect()
         }
+        LabelStyle::Numbers => generate_numeric_labels(num_cols * num_rows, 1),
+        LabelStyle::Letters => generate_labels(num_cols * num_rows, &alphabets.sub_chars, 1)?,
+    };
+    Ok((labels, layout_cell_rects(main_cell_rect, num_cols, num_rows)))
+}
+
+/// How the level below a picked main cell is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubGridShape {
+    #[default]
+    Grid,
+    /// Points on rings around the cell's center, one per spoke, for off-axis targets.
+    Radial,
+}
+
+/// Marker squares never get smaller than this, so their labels stay readable.
+const MIN_RADIAL_MARKER_SIDE: f32 = 12.0;
+
+/// The center of `cell_rect` plus `spokes` points on each of `rings` evenly spaced circles, the
+/// outermost touching the cell's shorter side. Spokes start straight up and go clockwise, ring by
+/// ring from the inside. Each point gets a square marker centered on it, so picking one clicks the
+/// point like any other cell. Labels are as for a sub-grid of the same size.
+pub fn generate_radial_layout(cell_rect: egui::Rect, rings: usize, spokes: usize, alphabets: &LabelAlphabets) -> Result<(Vec<String>, Vec<egui::Rect>), String> {
+    if rings == 0 || spokes == 0 {
+        return Err(format!("radial sub-grid needs at least one ring and spoke, got {} and {}", rings, spokes));
     }
-    labels.truncate(total_cells);
-    let mut rects = Vec::with_capacity(total_cells);
-    if main_cell_rect.width() > 1.0 && main_cell_rect.height() > 1.0 {
-        let cell_width = main_cell_rect.width() / num_cols as f32;
-        let cell_height = main_cell_rect.height() / num_rows as f32;
-        for r in 0..num_rows {
-            for c in 0..num_cols {
-                rects.push(egui::Rect::from_min_size(
-                    main_cell_rect.min + egui::vec2(c as f32 * cell_width, r as f32 * cell_height),
-                    egui::vec2(cell_width, cell_height)
-                ));
+    let count = 1 + rings * spokes;
+    let labels = match alphabets.style {
+        LabelStyle::Numbers => generate_numeric_labels(count, 1),
+        LabelStyle::Letters => generate_labels(count, &alphabets.sub_chars, 1)?,
+    };
+    let center = cell_rect.center();
+    let ring_gap = cell_rect.width().min(cell_rect.height()) / 2.0 / rings as f32;
+    // Neighbouring markers on the inner ring are closest; keep them from overlapping
+    let inner_chord = 2.0 * ring_gap * (std::f32::consts::PI / spokes as f32).sin();
+    let marker_side = (ring_gap.min(if spokes > 1 { inner_chord } else { ring_gap }) * 0.9).max(MIN_RADIAL_MARKER_SIDE);
+    let marker = |point: egui::Pos2| egui::Rect::from_center_size(point, egui::Vec2::splat(marker_side));
+    let mut rects = Vec::with_capacity(count);
+    rects.push(marker(center));
+    for ring in 1..=rings {
+        let radius = ring_gap * ring as f32;
+        for spoke in 0..spokes {
+            let angle = std::f32::consts::TAU * spoke as f32 / spokes as f32;
+            rects.push(marker(center + radius * egui::vec2(angle.sin(), -angle.cos())));
+        }
+    }
+    Ok((labels, rects))
+}
+
+/// Where a label sits in its cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LabelPosition {
+    #[default]
+    Center,
+    TopLeft,
+    BottomRight,
+    /// The corner facing away from the screen's center, so labels gather toward the screen edges.
+    Auto,
+}
+
+/// Corner labels are this much smaller than centered ones, so they stay inside their cell.
+const CORNER_LABEL_SCALE: f32 = 0.7;
+/// Gap between a corner label and the cell's edges.
+const CORNER_LABEL_INSET: f32 = 3.0;
+
+/// Anchor point, alignment and font size for a label of `font_size` in `rect` at `position`. A cell
+/// too small to fit the label in a corner without clipping keeps it centered.
+pub fn label_anchor(rect: egui::Rect, screen_rect: egui::Rect, position: LabelPosition, font_size: f32) -> (egui::Pos2, egui::Align2, f32) {
+    let centered = (rect.center(), egui::Align2::CENTER_CENTER, font_size);
+    let corner_font_size = font_size * CORNER_LABEL_SCALE;
+    // A label roughly as wide as it is tall needs room for itself plus the inset on both sides
+    if rect.width().min(rect.height()) < 2.0 * (corner_font_size + CORNER_LABEL_INSET) {
+        return centered;
+    }
+    let (right, bottom) = match position {
+        LabelPosition::Center => return centered,
+        LabelPosition::TopLeft => (false, false),
+        LabelPosition::BottomRight => (true, true),
+        LabelPosition::Auto => (rect.center().x > screen_rect.center().x, rect.center().y > screen_rect.center().y),
+    };
+    let inner = rect.shrink(CORNER_LABEL_INSET);
+    let x = if right { inner.max.x } else { inner.min.x };
+    let y = if bottom { inner.max.y } else { inner.min.y };
+    let align = egui::Align2([
+        if right { egui::Align::Max } else { egui::Align::Min },
+        if bottom { egui::Align::Max } else { egui::Align::Min },
+    ]);
+    (egui::pos2(x, y), align, corner_font_size)
+}
+
+/// What the characters typed so far select among a level's labels.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LabelMatch {
+    /// The typed text is a whole label; labels are prefix-free, so this is final.
+    Exact(usize),
+    /// The typed text starts at least one label, so more input is needed.
+    Prefix,
+    /// Nothing starts with the typed text.
+    None,
+}
+
+/// Matches `typed` against the labels `is_pickable` accepts.
+pub fn match_label(labels: &[String], typed: &str, is_pickable: impl Fn(usize) -> bool) -> LabelMatch {
+    let mut pickable = labels.iter().enumerate().filter(|(index, _)| is_pickable(*index));
+    if let Some((index, _)) = pickable.clone().find(|(_, label)| label.as_str() == typed) {
+        LabelMatch::Exact(index)
+    } else if pickable.any(|(_, label)| label.starts_with(typed)) {
+        LabelMatch::Prefix
+    } else {
+        LabelMatch::None
+    }
+}
+
+/// The one label `typed` spells if its last key is swapped for a neighbour of it, for a slipped finger.
+/// Only the second and later keys are corrected, and an ambiguous correction gives None.
+pub fn fuzzy_match_label(labels: &[String], typed: &str, is_pickable: impl Fn(usize) -> bool, adjacency: &HashMap<char, Vec<char>>) -> Option<usize> {
+    let mut prefix = typed.to_string();
+    let last = prefix.pop()?;
+    if prefix.is_empty() {
+        return None;
+    }
+    let mut candidates = adjacency.get(&last)?.iter().filter_map(|neighbour| {
+        let corrected = format!("{}{}", prefix, neighbour);
+        match match_label(labels, &corrected, &is_pickable) {
+            LabelMatch::Exact(index) => Some(index),
+            _ => None,
+        }
+    });
+    let index = candidates.next()?;
+    candidates.next().is_none().then_some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRESETS: [&str; 4] = ["qwerty", "colemak", "dvorak", "left-hand-only"];
+
+    fn alphabet(len: usize) -> Vec<char> {
+        ('A'..='Z').take(len).collect()
+    }
+
+    /// No label starts another, which also rules out two equal labels.
+    fn assert_prefix_free(labels: &[String]) {
+        for (i, label) in labels.iter().enumerate() {
+            for (j, other) in labels.iter().enumerate() {
+                assert!(i == j || !other.starts_with(label.as_str()), "{:?} is a prefix of {:?}", label, other);
+            }
+        }
+    }
+
+    #[test]
+    fn generated_labels_are_unique_and_prefix_free() {
+        for base in 2..=8 {
+            let chars = alphabet(base);
+            for count in [1, base - 1, base, base + 1, base * base - 1, base * base, base * base + 1, base.pow(3)] {
+                for min_len in 1..=2 {
+                    let labels = generate_labels(count, &chars, min_len).unwrap();
+                    assert_eq!(labels.len(), count, "{} labels over {} characters", count, base);
+                    assert!(labels.iter().flat_map(|label| label.chars()).all(|c| chars.contains(&c)));
+                    assert!(ensure_labels_unique(&labels).is_ok());
+                    assert_prefix_free(&labels);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn preset_sub_grid_labels_are_prefix_free() {
+        for name in PRESETS {
+            let alphabets = LabelAlphabets::preset(name).unwrap();
+            for count in [alphabets.max_single_char_cells(), alphabets.max_sub_grid_cells()] {
+                let labels = generate_labels(count, &alphabets.sub_chars, 1).unwrap();
+                assert_prefix_free(&labels);
+            }
+        }
+    }
+
+    #[test]
+    fn main_grid_labels_are_prefix_free_in_both_schemes() {
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1440.0, 900.0));
+        for name in PRESETS {
+            let alphabets = LabelAlphabets::preset(name).unwrap();
+            let rows = alphabets.first_chars.len();
+            let cols = alphabets.second_chars.len();
+            // Row + column pairs, then one row too many for them
+            for (cols, rows) in [(cols, rows), (cols, rows + 1)] {
+                let (labels, rects) = generate_main_grid_layout(cols, rows, screen, &alphabets, None).unwrap();
+                assert_eq!(labels.len(), rects.len());
+                assert_prefix_free(&labels);
+            }
+        }
+    }
+
+    #[test]
+    fn alphabets_that_cannot_label_are_rejected() {
+        assert!(generate_labels(4, &['A'], 1).is_err());
+        assert!(generate_labels(4, &['A', 'B', 'A'], 1).is_err());
+    }
+
+    #[test]
+    fn label_length_steps_up_just_past_each_power_of_the_alphabet() {
+        for base in [2usize, 3, 8, 10] {
+            for k in 1..=3u32 {
+                let exact = base.pow(k);
+                assert_eq!(label_len_for(exact, base, 1), k as usize, "{} cells over {} characters", exact, base);
+                assert_eq!(label_len_for(exact + 1, base, 1), k as usize + 1, "{} cells over {} characters", exact + 1, base);
             }
         }
     }
-    (labels, rects)
-} 
\ No newline at end of file
+
+    #[test]
+    fn label_length_respects_the_minimum() {
+        assert_eq!(label_len_for(1, 8, 2), 2);
+        assert_eq!(label_len_for(64, 8, 2), 2);
+        assert_eq!(label_len_for(65, 8, 2), 3);
+        assert_eq!(label_len_for(0, 8, 0), 1);
+    }
+
+    #[test]
+    fn generated_labels_have_the_length_for_their_count() {
+        let chars = alphabet(4);
+        for (count, len) in [(4, 1), (5, 2), (16, 2), (17, 3), (64, 3)] {
+            let labels = generate_labels(count, &chars, 1).unwrap();
+            assert!(labels.iter().all(|label| label.len() == len), "{} cells should get {}-character labels", count, len);
+        }
+        // The last label of an exact power uses the last character in every place
+        assert_eq!(generate_labels(16, &chars, 1).unwrap().last().unwrap(), "DD");
+    }
+
+    #[test]
+    fn counts_past_the_longest_label_are_rejected() {
+        let chars = alphabet(4);
+        let longest = 4usize.pow(MAX_LABEL_LEN as u32);
+        assert_eq!(generate_labels(longest, &chars, 1).unwrap().len(), longest);
+        let error = generate_labels(longest + 1, &chars, 1).unwrap_err();
+        assert!(error.contains("at most 3"), "{}", error);
+    }
+
+    #[test]
+    fn a_repeated_label_is_named_in_the_error() {
+        let labels: Vec<String> = ["AS", "AD", "AF", "AD", "AF"].iter().map(|label| label.to_string()).collect();
+        let error = ensure_labels_unique(&labels).unwrap_err();
+        assert!(error.contains("\"AD\""), "the first repeat should be named: {}", error);
+        assert!(!error.contains("\"AF\""), "{}", error);
+        assert!(ensure_labels_unique(&labels[..3]).is_ok());
+        assert!(ensure_labels_unique(&[]).is_ok());
+    }
+
+    /// About 200 cells with room in every cap.
+    fn auto_dims(width: f32, height: f32) -> (usize, usize) {
+        compute_auto_dims(egui::vec2(width, height), 200, 26, 26, 676)
+    }
+
+    /// Cells within 15% of square.
+    fn assert_near_square(width: f32, height: f32, (cols, rows): (usize, usize)) {
+        let cell_aspect = (width / cols as f32) / (height / rows as f32);
+        assert!((0.85..=1.15).contains(&cell_aspect), "{}x{} cells on {}x{} are {:.2}:1", cols, rows, width, height, cell_aspect);
+    }
+
+    #[test]
+    fn auto_dims_keep_cells_square_across_aspect_ratios() {
+        for (width, height, dims) in [
+            (1920.0, 1080.0, (19, 11)), // 16:9
+            (1440.0, 900.0, (18, 11)),  // 16:10
+            (1080.0, 1920.0, (11, 19)), // portrait
+            (3440.0, 1440.0, (22, 9)),  // ultrawide
+        ] {
+            assert_eq!(auto_dims(width, height), dims, "{}x{}", width, height);
+            assert_near_square(width, height, dims);
+        }
+    }
+
+    #[test]
+    fn auto_dims_recompute_the_other_side_at_a_cap() {
+        // Too few columns for an ultrawide: rows shrink with them
+        assert_eq!(compute_auto_dims(egui::vec2(3440.0, 1440.0), 200, 12, 26, 676), (12, 5));
+        // Too few rows for a portrait display: so do columns
+        assert_eq!(compute_auto_dims(egui::vec2(1080.0, 1920.0), 200, 26, 10, 676), (6, 10));
+    }
+
+    #[test]
+    fn auto_dims_stay_within_the_label_count() {
+        let dims = compute_auto_dims(egui::vec2(1920.0, 1080.0), 200, 26, 26, 150);
+        assert_eq!(dims, (16, 9));
+        assert_near_square(1920.0, 1080.0, dims);
+    }
+
+    #[test]
+    fn auto_dims_fall_back_to_the_caps_without_a_screen_size() {
+        assert_eq!(compute_auto_dims(egui::Vec2::ZERO, 200, 12, 8, 96), (12, 8));
+    }
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    fn qwerty_neighbours_touch_the_key() {
+        let adjacency = qwerty_adjacency();
+        assert_eq!(adjacency[&'D'], vec!['S', 'F', 'E', 'R', 'X', 'C']);
+        assert_eq!(adjacency[&'E'], vec!['W', 'R', '3', '4', 'S', 'D']);
+    }
+
+    #[test]
+    fn a_slipped_last_key_picks_the_one_label_it_neighbours() {
+        let adjacency = qwerty_adjacency();
+        // E is next to S: "AE" was meant as "AS"
+        assert_eq!(fuzzy_match_label(&labels(&["AS", "AG", "AH"]), "AE", |_| true, &adjacency), Some(0));
+        assert_eq!(fuzzy_match_label(&labels(&["QWE", "QWJ"]), "QWR", |_| true, &adjacency), Some(0));
+    }
+
+    #[test]
+    fn an_ambiguous_correction_picks_nothing() {
+        let adjacency = qwerty_adjacency();
+        // E is next to both S and D
+        assert_eq!(fuzzy_match_label(&labels(&["AS", "AD", "AG"]), "AE", |_| true, &adjacency), None);
+        // Unless only one of them can be picked
+        assert_eq!(fuzzy_match_label(&labels(&["AS", "AD", "AG"]), "AE", |index| index != 0, &adjacency), Some(1));
+    }
+
+    #[test]
+    fn the_first_key_is_never_corrected() {
+        let adjacency = qwerty_adjacency();
+        assert_eq!(fuzzy_match_label(&labels(&["S", "G"]), "E", |_| true, &adjacency), None);
+        // W is next to E, but only the last key is swapped
+        assert_eq!(fuzzy_match_label(&labels(&["ES", "EG"]), "WS", |_| true, &adjacency), None);
+    }
+
+    #[test]
+    fn no_correction_without_neighbours_or_a_pickable_label() {
+        let adjacency = qwerty_adjacency();
+        assert_eq!(fuzzy_match_label(&labels(&["AS"]), "", |_| true, &adjacency), None);
+        assert_eq!(fuzzy_match_label(&labels(&["AS"]), "A!", |_| true, &adjacency), None);
+        assert_eq!(fuzzy_match_label(&labels(&["AS", "AG"]), "AE", |index| index != 0, &adjacency), None);
+        assert_eq!(fuzzy_match_label(&labels(&["AS", "AG"]), "AE", |_| true, &HashMap::new()), None);
+    }
+
+    #[test]
+    fn configured_neighbours_replace_the_qwerty_ones() {
+        let config = FuzzyConfig { adjacent_keys: HashMap::from([('e', vec!['g'])]), ..FuzzyConfig::default() };
+        let adjacency = config.resolve().unwrap();
+        assert_eq!(adjacency[&'E'], vec!['G']);
+        assert_eq!(fuzzy_match_label(&labels(&["AS", "AG"]), "AE", |_| true, &adjacency), Some(1));
+    }
+
+    fn numbers() -> LabelAlphabets {
+        LabelAlphabets { style: LabelStyle::Numbers, ..LabelAlphabets::default() }
+    }
+
+    #[test]
+    fn numeric_labels_share_a_zero_padded_width() {
+        assert_eq!(generate_numeric_labels(9, 2), labels(&["01", "02", "03", "04", "05", "06", "07", "08", "09"]));
+        let labels = generate_numeric_labels(100, 2);
+        assert_eq!((labels[0].as_str(), labels[99].as_str()), ("001", "100"));
+        assert_eq!(generate_numeric_labels(12, 1)[0], "01");
+        assert_eq!(generate_numeric_labels(5, 1), vec!["1", "2", "3", "4", "5"]);
+        for count in [9, 10, 99, 100, 144] {
+            assert_prefix_free(&generate_numeric_labels(count, 1));
+        }
+    }
+
+    #[test]
+    fn numeric_main_grid_numbers_cells_row_by_row() {
+        let screen = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1200.0, 1200.0));
+        let (labels, rects) = generate_main_grid_layout(12, 12, screen, &numbers(), None).unwrap();
+        assert_eq!(labels.len(), 144);
+        assert_eq!((labels[0].as_str(), labels[143].as_str()), ("001", "144"));
+        // The first cell of the second row
+        assert_eq!(labels[12], "013");
+        assert_eq!(rects[12].min, egui::pos2(0.0, 100.0));
+    }
+
+    #[test]
+    fn a_numeric_three_by_three_sub_grid_is_laid_out_like_a_numpad() {
+        let cell = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(90.0, 90.0));
+        let (labels, rects) = generate_sub_grid_layout(cell, NUMPAD_SIDE, NUMPAD_SIDE, &numbers()).unwrap();
+        let rect_of = |label: &str| rects[labels.iter().position(|l| l == label).unwrap()];
+        assert_eq!(rect_of("7").min, egui::pos2(0.0, 0.0));
+        assert_eq!(rect_of("5").center(), cell.center());
+        assert_eq!(rect_of("3").max, cell.max);
+        assert_eq!(rect_of("2").center(), egui::pos2(45.0, 75.0));
+    }
+
+    #[test]
+    fn other_numeric_sub_grids_number_their_cells() {
+        let cell = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(100.0, 100.0));
+        let (labels, _) = generate_sub_grid_layout(cell, 2, 2, &numbers()).unwrap();
+        assert_eq!(labels, vec!["1", "2", "3", "4"]);
+        let (labels, _) = generate_sub_grid_layout(cell, 4, 4, &numbers()).unwrap();
+        assert_eq!((labels[0].as_str(), labels[15].as_str()), ("01", "16"));
+        let (labels, rects) = generate_radial_layout(cell, 2, 8, &numbers()).unwrap();
+        assert_eq!((labels.len(), rects.len()), (17, 17));
+        assert_prefix_free(&labels);
+    }
+
+    #[test]
+    fn numeric_alphabets_are_digits() {
+        let alphabets = numbers();
+        assert_eq!(alphabets.all_chars(), ('0'..='9').collect::<Vec<_>>());
+        assert_eq!(alphabets.max_single_char_cells(), 9);
+        assert_eq!(alphabets.max_sub_grid_cells(), 99);
+        let config = LabelConfig { style: LabelStyle::Numbers, ..LabelConfig::default() };
+        assert_eq!(config.resolve().unwrap().style, LabelStyle::Numbers);
+    }
+
+    const SCREEN: egui::Rect = egui::Rect { min: egui::Pos2::ZERO, max: egui::pos2(1000.0, 1000.0) };
+
+    fn cell_at(x: f32, y: f32, side: f32) -> egui::Rect {
+        egui::Rect::from_min_size(egui::pos2(x, y), egui::Vec2::splat(side))
+    }
+
+    #[test]
+    fn centered_labels_keep_their_size() {
+        let cell = cell_at(100.0, 100.0, 100.0);
+        assert_eq!(label_anchor(cell, SCREEN, LabelPosition::Center, 20.0), (egui::pos2(150.0, 150.0), egui::Align2::CENTER_CENTER, 20.0));
+    }
+
+    #[test]
+    fn corner_labels_sit_inset_and_smaller() {
+        let cell = cell_at(100.0, 100.0, 100.0);
+        assert_eq!(label_anchor(cell, SCREEN, LabelPosition::TopLeft, 20.0), (egui::pos2(103.0, 103.0), egui::Align2::LEFT_TOP, 14.0));
+        assert_eq!(label_anchor(cell, SCREEN, LabelPosition::BottomRight, 20.0), (egui::pos2(197.0, 197.0), egui::Align2::RIGHT_BOTTOM, 14.0));
+    }
+
+    #[test]
+    fn auto_labels_face_away_from_the_screen_center() {
+        let anchor = |x, y| {
+            let (point, align, _) = label_anchor(cell_at(x, y, 100.0), SCREEN, LabelPosition::Auto, 20.0);
+            (point, align)
+        };
+        assert_eq!(anchor(0.0, 0.0), (egui::pos2(3.0, 3.0), egui::Align2::LEFT_TOP));
+        assert_eq!(anchor(900.0, 0.0), (egui::pos2(997.0, 3.0), egui::Align2::RIGHT_TOP));
+        assert_eq!(anchor(0.0, 900.0), (egui::pos2(3.0, 997.0), egui::Align2::LEFT_BOTTOM));
+        assert_eq!(anchor(900.0, 900.0), (egui::pos2(997.0, 997.0), egui::Align2::RIGHT_BOTTOM));
+        // A cell centered on the screen's center counts as top-left
+        assert_eq!(anchor(450.0, 450.0), (egui::pos2(453.0, 453.0), egui::Align2::LEFT_TOP));
+    }
+
+    #[test]
+    fn a_cell_too_small_for_a_corner_label_keeps_it_centered() {
+        // A 14pt corner label needs 2 * (14 + 3) = 34pt
+        let fits = cell_at(0.0, 0.0, 34.0);
+        assert_eq!(label_anchor(fits, SCREEN, LabelPosition::TopLeft, 20.0).1, egui::Align2::LEFT_TOP);
+        let too_small = cell_at(0.0, 0.0, 33.0);
+        for position in [LabelPosition::TopLeft, LabelPosition::BottomRight, LabelPosition::Auto] {
+            assert_eq!(label_anchor(too_small, SCREEN, position, 20.0), (too_small.center(), egui::Align2::CENTER_CENTER, 20.0));
+        }
+        // Only the shorter side counts
+        let narrow = egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(200.0, 30.0));
+        assert_eq!(label_anchor(narrow, SCREEN, LabelPosition::BottomRight, 20.0).1, egui::Align2::CENTER_CENTER);
+    }
+}
@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+
+use crate::grid::{self, LabelAlphabets, LabelMatch, SelectedCell, SubGridShape};
+
+/// Result of feeding one typed character to a `GridController`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Step {
+    /// The character started or extended a label prefix.
+    Pending,
+    /// The typed label picked this cell of the active level.
+    Picked(usize),
+    /// The typed label matched nothing, but swapping mistyped characters for adjacent keys picked this cell.
+    Corrected(usize),
+    /// The typed text matched nothing and was discarded.
+    Reset,
+}
+
+/// How the levels below the main grid are laid out. Level 1 is the sub-grid inside a main cell,
+/// every deeper level uses `deeper_dims`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefinementLayout {
+    /// With `auto_sub_grid` only the product counts, as the number of sub-cells to aim for.
+    pub sub_grid_dims: (usize, usize),
+    pub auto_sub_grid: bool,
+    pub sub_grid_shape: SubGridShape,
+    pub radial_rings: usize,
+    pub radial_spokes: usize,
+    pub deeper_dims: (usize, usize),
+    pub alphabets: LabelAlphabets,
+}
+
+impl RefinementLayout {
+    /// Only the sub-grid can be radial; deeper levels are grids inside the picked point's marker.
+    pub fn is_radial(&self, depth: usize) -> bool {
+        depth == 1 && self.sub_grid_shape == SubGridShape::Radial
+    }
+
+    /// Columns and rows of the grid laid out inside `cell_rect` at `depth`.
+    pub fn dims(&self, depth: usize, cell_rect: egui::Rect) -> (usize, usize) {
+        if depth > 1 {
+            return self.deeper_dims;
+        }
+        if !self.auto_sub_grid {
+            return self.sub_grid_dims;
+        }
+        let (cols, rows) = self.sub_grid_dims;
+        let sub_chars = self.alphabets.sub_chars.len();
+        grid::compute_auto_dims(cell_rect.size(), cols * rows, sub_chars, sub_chars, self.alphabets.max_sub_grid_cells())
+    }
+
+    pub fn layout(&self, depth: usize, cell_rect: egui::Rect) -> Result<(Vec<String>, Vec<egui::Rect>), String> {
+        if self.is_radial(depth) {
+            return grid::generate_radial_layout(cell_rect, self.radial_rings, self.radial_spokes, &self.alphabets);
+        }
+        let (cols, rows) = self.dims(depth, cell_rect);
+        grid::generate_sub_grid_layout(cell_rect, cols, rows, &self.alphabets)
+    }
+}
+
+/// Label selection state without any windowing or OS calls: the main grid, the cells picked below
+/// it and the label typed so far. Characters go in, picked cells come out; whether a pick descends
+/// or ends the selection is up to the caller.
+#[derive(Debug, Clone, Default)]
+pub struct GridController {
+    main_labels: Vec<String>,
+    main_rects: Vec<egui::Rect>,
+    /// Main cells that keep their label but can't be picked.
+    blocked_main_cells: Vec<usize>,
+    /// Cells picked so far, outermost first: the main cell, then one per level below it.
+    selected_levels: Vec<SelectedCell>,
+    sub_labels: Vec<String>,
+    sub_rects: Vec<egui::Rect>,
+    typed: String,
+}
+
+impl GridController {
+    pub fn new(screen_rect: egui::Rect, main_dims: (usize, usize), alphabets: &LabelAlphabets) -> Result<Self, String> {
+        let (labels, rects) = grid::generate_main_grid_layout(main_dims.0, main_dims.1, screen_rect, alphabets, None)?;
+        let mut controller = Self::default();
+        controller.set_main_grid(labels, rects, Vec::new());
+        Ok(controller)
+    }
+
+    /// Replaces the main grid. Picked levels are kept by index; `relayout` re-derives them.
+    pub fn set_main_grid(&mut self, labels: Vec<String>, rects: Vec<egui::Rect>, blocked_main_cells: Vec<usize>) {
+        self.main_labels = labels;
+        self.main_rects = rects;
+        self.blocked_main_cells = blocked_main_cells;
+    }
+
+    /// Drops the main grid so the next frame lays it out again.
+    pub fn clear_main_grid(&mut self) {
+        self.main_labels.clear();
+        self.main_rects.clear();
+        self.blocked_main_cells.clear();
+    }
+
+    pub fn main_labels(&self) -> &[String] {
+        &self.main_labels
+    }
+
+    pub fn main_rects(&self) -> &[egui::Rect] {
+        &self.main_rects
+    }
+
+    pub fn is_main_cell_pickable(&self, index: usize) -> bool {
+        index < self.main_rects.len() && !self.blocked_main_cells.contains(&index)
+    }
+
+    /// Zero-based level whose labels are being typed; 0 is the main grid.
+    pub fn level(&self) -> usize {
+        self.selected_levels.len()
+    }
+
+    pub fn selected_levels(&self) -> &[SelectedCell] {
+        &self.selected_levels
+    }
+
+    /// Labels of the active level below the main grid; empty while on the main grid.
+    pub fn sub_labels(&self) -> &[String] {
+        &self.sub_labels
+    }
+
+    pub fn sub_rects(&self) -> &[egui::Rect] {
+        &self.sub_rects
+    }
+
+    /// Labels of whichever level is active, the main grid included.
+    pub fn labels(&self) -> &[String] {
+        if self.selected_levels.is_empty() { &self.main_labels } else { &self.sub_labels }
+    }
+
+    pub fn rects(&self) -> &[egui::Rect] {
+        if self.selected_levels.is_empty() { &self.main_rects } else { &self.sub_rects }
+    }
+
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    pub fn clear_typed(&mut self) {
+        self.typed.clear();
+    }
+
+    /// Drops the last typed character; false if nothing was typed.
+    pub fn pop_typed(&mut self) -> bool {
+        self.typed.pop().is_some()
+    }
+
+    /// Matches `c` appended to the typed text against the active level. A pick or a miss clears the
+    /// typed text. With `adjacency`, a miss on the main grid falls back to an adjacent-key match.
+    pub fn type_char(&mut self, c: char, adjacency: Option<&HashMap<char, Vec<char>>>) -> Step {
+        self.typed.push(c);
+        let on_main_grid = self.selected_levels.is_empty();
+        let pickable = |index: usize| if on_main_grid { self.is_main_cell_pickable(index) } else { index < self.sub_rects.len() };
+        match grid::match_label(self.labels(), &self.typed, pickable) {
+            LabelMatch::Exact(index) => {
+                self.typed.clear();
+                Step::Picked(index)
+            }
+            LabelMatch::Prefix => Step::Pending,
+            LabelMatch::None => {
+                let corrected = adjacency
+                    .filter(|_| on_main_grid)
+                    .and_then(|adjacency| grid::fuzzy_match_label(&self.main_labels, &self.typed, pickable, adjacency));
+                self.typed.clear();
+                corrected.map_or(Step::Reset, Step::Corrected)
+            }
+        }
+    }
+
+    /// Makes cell `index` of the active level the next picked level and lays out the level inside it.
+    pub fn descend(&mut self, index: usize, layout: &RefinementLayout) -> Result<(), String> {
+        let rect = *self.rects().get(index).ok_or_else(|| format!("no cell {} at level {}", index, self.level()))?;
+        self.selected_levels.push(SelectedCell { index, rect });
+        self.typed.clear();
+        self.layout_active_level(layout)
+    }
+
+    /// Leaves the active level for the one above it. Returns false when already at the main grid.
+    pub fn ascend(&mut self, layout: &RefinementLayout) -> Result<bool, String> {
+        self.typed.clear();
+        if self.selected_levels.pop().is_none() {
+            return Ok(false);
+        }
+        self.layout_active_level(layout)?;
+        Ok(true)
+    }
+
+    /// Back to the main grid with nothing typed.
+    pub fn reset(&mut self) {
+        self.typed.clear();
+        self.selected_levels.clear();
+        self.sub_labels.clear();
+        self.sub_rects.clear();
+    }
+
+    /// Re-derives the picked rects from their indices, after the main grid or `layout` changed.
+    /// Levels whose cell no longer exists are dropped.
+    pub fn relayout(&mut self, layout: &RefinementLayout) -> Result<(), String> {
+        let indices: Vec<usize> = self.selected_levels.iter().map(|cell| cell.index).collect();
+        self.selected_levels.clear();
+        let mut level_rects = self.main_rects.clone();
+        for (depth, index) in indices.into_iter().enumerate() {
+            let Some(rect) = level_rects.get(index).copied() else {
+                break;
+            };
+            self.selected_levels.push(SelectedCell { index, rect });
+            level_rects = layout.layout(depth + 1, rect).map(|(_, rects)| rects).unwrap_or_default();
+        }
+        self.layout_active_level(layout)
+    }
+
+    fn layout_active_level(&mut self, layout: &RefinementLayout) -> Result<(), String> {
+        self.sub_labels.clear();
+        self.sub_rects.clear();
+        if let Some(cell) = self.selected_levels.last() {
+            (self.sub_labels, self.sub_rects) = layout.layout(self.selected_levels.len(), cell.rect)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn screen() -> egui::Rect {
+        egui::Rect::from_min_size(egui::Pos2::ZERO, egui::vec2(1600.0, 900.0))
+    }
+
+    fn layout(alphabets: &LabelAlphabets) -> RefinementLayout {
+        RefinementLayout {
+            sub_grid_dims: (5, 5),
+            auto_sub_grid: false,
+            sub_grid_shape: SubGridShape::Grid,
+            radial_rings: 2,
+            radial_spokes: 8,
+            deeper_dims: (3, 3),
+            alphabets: alphabets.clone(),
+        }
+    }
+
+    fn type_label(controller: &mut GridController, label: &str) -> Step {
+        label.chars().map(|c| controller.type_char(c, None)).last().expect("label is not empty")
+    }
+
+    #[test]
+    fn typing_a_main_label_picks_its_cell() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let label = controller.main_labels()[7].clone();
+        assert_eq!(controller.type_char(label.chars().next().unwrap(), None), Step::Pending);
+        assert_eq!(controller.typed(), &label[..1]);
+        assert_eq!(type_label(&mut controller, &label[1..]), Step::Picked(7));
+        assert_eq!(controller.typed(), "");
+    }
+
+    #[test]
+    fn descending_lays_out_the_sub_grid_inside_the_picked_cell() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let main_rect = controller.main_rects()[3];
+        controller.descend(3, &layout(&alphabets)).unwrap();
+        assert_eq!(controller.level(), 1);
+        assert_eq!(controller.selected_levels(), &[SelectedCell { index: 3, rect: main_rect }]);
+        assert_eq!(controller.sub_rects().len(), 25);
+        assert!(controller.sub_rects().iter().all(|rect| main_rect.expand(0.5).contains_rect(*rect)));
+        let label = controller.sub_labels()[12].clone();
+        assert_eq!(type_label(&mut controller, &label), Step::Picked(12));
+        assert_eq!(controller.rects()[12].center(), main_rect.center());
+    }
+
+    #[test]
+    fn deeper_levels_use_their_own_dims() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        controller.descend(0, &layout(&alphabets)).unwrap();
+        controller.descend(0, &layout(&alphabets)).unwrap();
+        assert_eq!(controller.level(), 2);
+        assert_eq!(controller.sub_rects().len(), 9);
+    }
+
+    #[test]
+    fn a_miss_resets_the_typed_text() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let first = controller.main_labels()[0].chars().next().unwrap();
+        assert_eq!(controller.type_char(first, None), Step::Pending);
+        assert_eq!(controller.type_char('!', None), Step::Reset);
+        assert_eq!(controller.typed(), "");
+    }
+
+    #[test]
+    fn a_miss_on_the_main_grid_can_be_corrected_through_adjacent_keys() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let label = controller.main_labels()[5].clone();
+        let mut chars = label.chars();
+        let (first, second) = (chars.next().unwrap(), chars.next().unwrap());
+        let adjacency = HashMap::from([('!', vec![second])]);
+        controller.type_char(first, Some(&adjacency));
+        assert_eq!(controller.type_char('!', Some(&adjacency)), Step::Corrected(5));
+        assert_eq!(controller.typed(), "");
+    }
+
+    #[test]
+    fn blocked_main_cells_cant_be_picked() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let (labels, rects) = (controller.main_labels().to_vec(), controller.main_rects().to_vec());
+        let label = labels[2].clone();
+        controller.set_main_grid(labels, rects, vec![2]);
+        assert!(!controller.is_main_cell_pickable(2));
+        assert_eq!(type_label(&mut controller, &label), Step::Reset);
+    }
+
+    #[test]
+    fn backing_out_returns_to_the_level_above() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let layout = layout(&alphabets);
+        controller.descend(4, &layout).unwrap();
+        controller.descend(6, &layout).unwrap();
+        let sub_rects_of_main_cell = {
+            let mut copy = controller.clone();
+            copy.ascend(&layout).unwrap();
+            copy.sub_rects().to_vec()
+        };
+        assert!(controller.ascend(&layout).unwrap());
+        assert_eq!(controller.level(), 1);
+        assert_eq!(controller.sub_rects(), sub_rects_of_main_cell.as_slice());
+        assert!(controller.ascend(&layout).unwrap());
+        assert!(controller.sub_rects().is_empty());
+        assert_eq!(controller.labels(), controller.main_labels());
+        assert!(!controller.ascend(&layout).unwrap());
+    }
+
+    #[test]
+    fn relayout_follows_a_new_main_grid_and_drops_vanished_levels() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let layout = layout(&alphabets);
+        controller.descend(8, &layout).unwrap();
+        let (labels, rects) = grid::generate_main_grid_layout(10, 6, screen().translate(egui::vec2(0.0, 30.0)), &alphabets, None).unwrap();
+        let moved = rects[8];
+        controller.set_main_grid(labels, rects, Vec::new());
+        controller.relayout(&layout).unwrap();
+        assert_eq!(controller.selected_levels(), &[SelectedCell { index: 8, rect: moved }]);
+
+        let (labels, rects) = grid::generate_main_grid_layout(2, 2, screen(), &alphabets, None).unwrap();
+        controller.set_main_grid(labels, rects, Vec::new());
+        controller.relayout(&layout).unwrap();
+        assert_eq!(controller.level(), 0);
+        assert!(controller.sub_rects().is_empty());
+    }
+
+    #[test]
+    fn reset_returns_to_the_main_grid_with_nothing_typed() {
+        let alphabets = LabelAlphabets::default();
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        controller.descend(1, &layout(&alphabets)).unwrap();
+        controller.type_char(controller.sub_labels()[0].chars().next().unwrap(), None);
+        controller.reset();
+        assert_eq!(controller.level(), 0);
+        assert_eq!(controller.typed(), "");
+        assert_eq!(controller.rects(), controller.main_rects());
+    }
+
+    #[test]
+    fn a_show_after_a_click_picks_the_same_cells_again() {
+        let alphabets = LabelAlphabets::default();
+        let layout = layout(&alphabets);
+        let mut controller = GridController::new(screen(), (10, 6), &alphabets).unwrap();
+        let main_label = controller.main_labels()[12].clone();
+        let pick = |controller: &mut GridController| {
+            assert_eq!(type_label(controller, &main_label), Step::Picked(12));
+            controller.descend(12, &layout).unwrap();
+            let sub_label = controller.sub_labels()[3].clone();
+            assert_eq!(type_label(controller, &sub_label), Step::Picked(3));
+            controller.rects()[3]
+        };
+        let first = pick(&mut controller);
+        // The overlay hides after the click and the next show starts over on the main grid
+        controller.reset();
+        assert_eq!(controller.level(), 0);
+        assert!(controller.sub_labels().is_empty());
+        assert_eq!(pick(&mut controller), first);
+    }
+}
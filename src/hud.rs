@@ -0,0 +1,67 @@
+use eframe::egui;
+
+const PADDING: egui::Vec2 = egui::vec2(12.0, 6.0);
+const BOTTOM_MARGIN: f32 = 12.0;
+/// When flipped to the top the HUD sits below the armed-mode banner.
+const TOP_MARGIN: f32 = 56.0;
+const SEPARATOR: &str = "   ·   ";
+
+/// Colors and size of the HUD, from [theme].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HudStyle {
+    pub font_size: f32,
+    pub text_color: egui::Color32,
+    pub background: egui::Color32,
+}
+
+impl Default for HudStyle {
+    fn default() -> Self {
+        Self {
+            font_size: 13.0,
+            text_color: egui::Color32::WHITE,
+            background: egui::Color32::from_rgba_unmultiplied(20, 20, 20, 170),
+        }
+    }
+}
+
+/// Small status pill for overlay state (mode, typed keys, click kind, ...). Callers push
+/// one segment per piece of state and paint it once per frame.
+#[derive(Debug, Default)]
+pub struct Hud {
+    segments: Vec<String>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, segment: impl Into<String>) -> &mut Self {
+        self.segments.push(segment.into());
+        self
+    }
+
+    /// Paints bottom-center, or top-center if that would cover `avoid` (the region being targeted).
+    pub fn paint(&self, painter: &egui::Painter, screen_rect: egui::Rect, avoid: Option<egui::Rect>, style: &HudStyle) {
+        if self.segments.is_empty() {
+            return;
+        }
+        let galley = painter.layout_no_wrap(self.segments.join(SEPARATOR), egui::FontId::monospace(style.font_size), style.text_color);
+        let hud_rect = placement(screen_rect, galley.size() + 2.0 * PADDING, avoid);
+        painter.rect_filled(hud_rect, hud_rect.height() / 2.0, style.background);
+        painter.galley(hud_rect.center() - galley.size() / 2.0, galley, style.text_color);
+    }
+}
+
+/// Where a HUD of `size` goes on `screen_rect`: bottom-center unless it would overlap `avoid`.
+pub fn placement(screen_rect: egui::Rect, size: egui::Vec2, avoid: Option<egui::Rect>) -> egui::Rect {
+    let bottom = egui::Rect::from_center_size(
+        screen_rect.center_bottom() - egui::vec2(0.0, BOTTOM_MARGIN + size.y / 2.0),
+        size,
+    );
+    if avoid.is_some_and(|avoid| avoid.intersects(bottom)) {
+        egui::Rect::from_center_size(screen_rect.center_top() + egui::vec2(0.0, TOP_MARGIN + size.y / 2.0), size)
+    } else {
+        bottom
+    }
+}
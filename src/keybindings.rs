@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// Logical actions that can be bound to a key, independent of the label alphabet.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Action {
+    /// Sub-grid: click the center of the selected main cell, or what grid.confirm_center_action picks.
+    ConfirmCenter,
+    /// Accept the current selection (plain click in the action prompt).
+    Confirm,
+    /// Back out one level, or hide from the main grid. Handled in the event tap.
+    Cancel,
+    /// Undo the last typed character, or leave the current stage.
+    Back,
+    /// Arm scroll mode: the next picked point becomes the scroll target instead of a click.
+    EnterScrollMode,
+    /// Sub-grid: nudge the picked point with the arrow keys before it is clicked.
+    FineAdjust,
+    /// Main grid: switch to continuous h/j/k/l pointer movement.
+    EnterPointerMode,
+    /// Main grid: switch to quadrant bisection.
+    EnterBisectMode,
+    /// Arm copy mode: the next picked point is copied as "x,y" instead of clicked. Pressed again,
+    /// the color under the point is copied instead.
+    CopyCoordinates,
+    /// Flip between two and three grid levels for the current selection.
+    ExtraLevel,
+    /// Sub-grid: one more row and column.
+    DenserSubGrid,
+    /// Sub-grid: one less row and column.
+    SparserSubGrid,
+    /// Main grid: a more opaque overlay.
+    OverlayMoreOpaque,
+    /// Main grid: a fainter overlay.
+    OverlayFainter,
+    /// Arm text selection: double-click the first pick, then shift-click a second pick.
+    SelectText,
+    /// Show each main cell's global center (only with debug.enabled).
+    DebugCoordinates,
+    /// Forget every remembered sub-grid pick.
+    ForgetSubCells,
+    /// Main grid: label the frontmost app's clickable elements instead.
+    EnterHintMode,
+    /// Main grid: label the frontmost app's menu bar and walk its menus.
+    EnterMenuMode,
+    /// Main grid: label the Dock's items.
+    EnterDockMode,
+    /// Main grid: label every on-screen window.
+    EnterWindowMode,
+    /// Main grid: recall a bookmark by name (twice: save the cursor). Fine adjust: save the target.
+    Bookmark,
+    /// Main grid: the next character picks the display's center, an edge midpoint or a corner.
+    QuickJump,
+    /// Main grid: the next two picks are opposite corners of a region to screenshot.
+    Screenshot,
+    /// Main grid: place the frontmost window. The first pick is its new top-left, the second its
+    /// opposite corner.
+    PlaceWindow,
+    /// Main grid: the next two picks are the ends of a distance to measure.
+    Measure,
+}
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::ConfirmCenter => "confirm_center",
+            Action::Confirm => "confirm",
+            Action::Cancel => "cancel",
+            Action::Back => "back",
+            Action::EnterScrollMode => "scroll_mode",
+            Action::FineAdjust => "fine_adjust",
+            Action::EnterPointerMode => "pointer_mode",
+            Action::EnterBisectMode => "bisect_mode",
+            Action::CopyCoordinates => "copy_coordinates",
+            Action::ExtraLevel => "extra_level",
+            Action::DenserSubGrid => "denser_sub_grid",
+            Action::SparserSubGrid => "sparser_sub_grid",
+            Action::OverlayMoreOpaque => "overlay_more_opaque",
+            Action::OverlayFainter => "overlay_fainter",
+            Action::SelectText => "select_text",
+            Action::DebugCoordinates => "debug_coordinates",
+            Action::ForgetSubCells => "forget_sub_cells",
+            Action::EnterHintMode => "hint_mode",
+            Action::EnterMenuMode => "menu_mode",
+            Action::EnterDockMode => "dock_mode",
+            Action::EnterWindowMode => "window_mode",
+            Action::Bookmark => "bookmark",
+            Action::QuickJump => "quick_jump",
+            Action::Screenshot => "screenshot",
+            Action::PlaceWindow => "place_window",
+            Action::Measure => "measure",
+        }
+    }
+
+    /// The sub-grid density keys only act in the sub-grid and the opacity keys only on the main
+    /// grid, so each pair can be bound to the same key.
+    fn may_share_key_with(&self, other: Action) -> bool {
+        matches!(
+            (self, other),
+            (Action::DenserSubGrid, Action::OverlayMoreOpaque)
+                | (Action::OverlayMoreOpaque, Action::DenserSubGrid)
+                | (Action::SparserSubGrid, Action::OverlayFainter)
+                | (Action::OverlayFainter, Action::SparserSubGrid)
+        )
+    }
+}
+
+/// `[keys]` config section: key names as understood by egui::Key::from_name ("Space", "Enter", "Semicolon", "A", ...).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KeyBindingConfig {
+    pub confirm_center: String,
+    pub confirm: String,
+    pub cancel: String,
+    pub back: String,
+    pub scroll_mode: String,
+    pub fine_adjust: String,
+    pub pointer_mode: String,
+    pub bisect_mode: String,
+    pub copy_coordinates: String,
+    pub extra_level: String,
+    pub denser_sub_grid: String,
+    pub sparser_sub_grid: String,
+    pub overlay_more_opaque: String,
+    pub overlay_fainter: String,
+    pub select_text: String,
+    pub debug_coordinates: String,
+    pub forget_sub_cells: String,
+    pub hint_mode: String,
+    pub menu_mode: String,
+    pub dock_mode: String,
+    pub window_mode: String,
+    pub bookmark: String,
+    pub quick_jump: String,
+    pub screenshot: String,
+    pub place_window: String,
+    pub measure: String,
+}
+
+impl Default for KeyBindingConfig {
+    fn default() -> Self {
+        Self {
+            confirm_center: "Space".to_string(),
+            confirm: "Enter".to_string(),
+            cancel: "Escape".to_string(),
+            back: "Backspace".to_string(),
+            scroll_mode: "Tab".to_string(),
+            fine_adjust: "Period".to_string(),
+            pointer_mode: "Slash".to_string(),
+            bisect_mode: "Semicolon".to_string(),
+            copy_coordinates: "Quote".to_string(),
+            extra_level: "Comma".to_string(),
+            denser_sub_grid: "Equals".to_string(),
+            sparser_sub_grid: "Minus".to_string(),
+            overlay_more_opaque: "Equals".to_string(),
+            overlay_fainter: "Minus".to_string(),
+            select_text: "Backslash".to_string(),
+            debug_coordinates: "Backtick".to_string(),
+            forget_sub_cells: "Delete".to_string(),
+            hint_mode: "OpenBracket".to_string(),
+            menu_mode: "CloseBracket".to_string(),
+            dock_mode: "Home".to_string(),
+            window_mode: "End".to_string(),
+            bookmark: "Colon".to_string(),
+            quick_jump: "Questionmark".to_string(),
+            screenshot: "Pipe".to_string(),
+            place_window: "PageUp".to_string(),
+            measure: "Plus".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    keys: HashMap<Action, egui::Key>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self::from_config(&KeyBindingConfig::default(), &[]).expect("default key bindings are valid")
+    }
+}
+
+/// Keys that can double as label keys, with the character they stand for in labels.
+const LABEL_KEYS: [(egui::Key, char); 47] = {
+    use egui::Key;
+    [
+        (Key::A, 'A'), (Key::B, 'B'), (Key::C, 'C'), (Key::D, 'D'), (Key::E, 'E'), (Key::F, 'F'),
+        (Key::G, 'G'), (Key::H, 'H'), (Key::I, 'I'), (Key::J, 'J'), (Key::K, 'K'), (Key::L, 'L'),
+        (Key::M, 'M'), (Key::N, 'N'), (Key::O, 'O'), (Key::P, 'P'), (Key::Q, 'Q'), (Key::R, 'R'),
+        (Key::S, 'S'), (Key::T, 'T'), (Key::U, 'U'), (Key::V, 'V'), (Key::W, 'W'), (Key::X, 'X'),
+        (Key::Y, 'Y'), (Key::Z, 'Z'),
+        (Key::Num0, '0'), (Key::Num1, '1'), (Key::Num2, '2'), (Key::Num3, '3'), (Key::Num4, '4'),
+        (Key::Num5, '5'), (Key::Num6, '6'), (Key::Num7, '7'), (Key::Num8, '8'), (Key::Num9, '9'),
+        (Key::Semicolon, ';'), (Key::Quote, '\''), (Key::Comma, ','), (Key::Period, '.'),
+        (Key::Slash, '/'), (Key::OpenBracket, '['), (Key::CloseBracket, ']'), (Key::Minus, '-'),
+        (Key::Equals, '='), (Key::Backtick, '`'), (Key::Backslash, '\\'),
+    ]
+};
+
+/// Character a key types when it doubles as a label key, e.g. Key::A -> 'A', Key::Semicolon -> ';'.
+pub fn key_label_char(key: egui::Key) -> Option<char> {
+    LABEL_KEYS.iter().find(|(label_key, _)| *label_key == key).map(|(_, c)| *c)
+}
+
+/// Whether `c` (uppercase for letters) can be typed as part of a label.
+pub fn is_label_char(c: char) -> bool {
+    LABEL_KEYS.iter().any(|(_, label_char)| *label_char == c)
+}
+
+impl KeyBindings {
+    /// Parses the configured key names and rejects bindings that collide with each other
+    /// or with a character used in grid labels (which would make those labels untypeable).
+    pub fn from_config(config: &KeyBindingConfig, label_chars: &[char]) -> Result<Self, String> {
+        let entries = [
+            (Action::ConfirmCenter, &config.confirm_center),
+            (Action::Confirm, &config.confirm),
+            (Action::Cancel, &config.cancel),
+            (Action::Back, &config.back),
+            (Action::EnterScrollMode, &config.scroll_mode),
+            (Action::FineAdjust, &config.fine_adjust),
+            (Action::EnterPointerMode, &config.pointer_mode),
+            (Action::EnterBisectMode, &config.bisect_mode),
+            (Action::CopyCoordinates, &config.copy_coordinates),
+            (Action::ExtraLevel, &config.extra_level),
+            (Action::DenserSubGrid, &config.denser_sub_grid),
+            (Action::SparserSubGrid, &config.sparser_sub_grid),
+            (Action::OverlayMoreOpaque, &config.overlay_more_opaque),
+            (Action::OverlayFainter, &config.overlay_fainter),
+            (Action::SelectText, &config.select_text),
+            (Action::DebugCoordinates, &config.debug_coordinates),
+            (Action::ForgetSubCells, &config.forget_sub_cells),
+            (Action::EnterHintMode, &config.hint_mode),
+            (Action::EnterMenuMode, &config.menu_mode),
+            (Action::EnterDockMode, &config.dock_mode),
+            (Action::EnterWindowMode, &config.window_mode),
+            (Action::Bookmark, &config.bookmark),
+            (Action::QuickJump, &config.quick_jump),
+            (Action::Screenshot, &config.screenshot),
+            (Action::PlaceWindow, &config.place_window),
+            (Action::Measure, &config.measure),
+        ];
+        let mut keys: HashMap<Action, egui::Key> = HashMap::new();
+        for (action, key_name) in entries {
+            let key = egui::Key::from_name(key_name)
+                .ok_or_else(|| format!("Unknown key {:?} for keys.{}", key_name, action.name()))?;
+            if let Some(label_char) = key_label_char(key).filter(|c| label_chars.contains(c)) {
+                return Err(format!(
+                    "keys.{} = {:?} conflicts with the label character '{}'",
+                    action.name(), key_name, label_char
+                ));
+            }
+            if action == Action::Cancel && mac_key_code(key).is_none() {
+                return Err(format!("keys.cancel = {:?} is not a key the event tap can watch", key_name));
+            }
+            if let Some((other, _)) = keys.iter().find(|(other, bound)| **bound == key && !action.may_share_key_with(**other)) {
+                return Err(format!(
+                    "keys.{} and keys.{} are both bound to {:?}",
+                    other.name(), action.name(), key_name
+                ));
+            }
+            keys.insert(action, key);
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn key_for(&self, action: Action) -> Option<egui::Key> {
+        self.keys.get(&action).copied()
+    }
+
+    pub fn action_for(&self, key: egui::Key) -> Option<Action> {
+        self.keys.iter().find(|(_, bound)| **bound == key).map(|(action, _)| *action)
+    }
+
+    pub fn is(&self, key: egui::Key, action: Action) -> bool {
+        self.key_for(action) == Some(key)
+    }
+
+    /// Virtual key code for the event tap, which sees raw keycodes rather than egui keys.
+    pub fn mac_key_code(&self, action: Action) -> Option<i64> {
+        self.key_for(action).and_then(mac_key_code)
+    }
+}
+
+/// ANSI virtual key codes (kVK_*) for the keys that can be bound.
+pub fn mac_key_code(key: egui::Key) -> Option<i64> {
+    use egui::Key;
+    let code = match key {
+        Key::A => 0, Key::S => 1, Key::D => 2, Key::F => 3, Key::H => 4, Key::G => 5,
+        Key::Z => 6, Key::X => 7, Key::C => 8, Key::V => 9, Key::B => 11, Key::Q => 12,
+        Key::W => 13, Key::E => 14, Key::R => 15, Key::Y => 16, Key::T => 17,
+        Key::Num1 => 18, Key::Num2 => 19, Key::Num3 => 20, Key::Num4 => 21, Key::Num6 => 22,
+        Key::Num5 => 23, Key::Equals => 24, Key::Num9 => 25, Key::Num7 => 26, Key::Minus => 27,
+        Key::Num8 => 28, Key::Num0 => 29, Key::CloseBracket => 30, Key::O => 31, Key::U => 32,
+        Key::OpenBracket => 33, Key::I => 34, Key::P => 35, Key::Enter => 36, Key::L => 37,
+        Key::J => 38, Key::Quote => 39, Key::K => 40, Key::Semicolon => 41, Key::Backslash => 42,
+        Key::Comma => 43, Key::Slash => 44, Key::N => 45, Key::M => 46, Key::Period => 47,
+        Key::Tab => 48, Key::Space => 49, Key::Backtick => 50, Key::Backspace => 51,
+        Key::Escape => 53,
+        Key::Home => 115, Key::PageUp => 116, Key::Delete => 117, Key::End => 119, Key::PageDown => 121,
+        Key::ArrowLeft => 123, Key::ArrowRight => 124, Key::ArrowDown => 125, Key::ArrowUp => 126,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// A key with modifiers to send to another app, e.g. "cmd+w" or "cmd+shift+t".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    pub key: egui::Key,
+    /// ANSI virtual key code of `key`.
+    pub key_code: i64,
+    pub command: bool,
+    pub shift: bool,
+    pub option: bool,
+    pub control: bool,
+}
+
+impl KeyCombo {
+    /// Modifiers joined with "+", then the key as egui::Key::from_name understands it. Modifiers
+    /// are cmd/command, shift, opt/option/alt and ctrl/control, in any case and order.
+    pub fn parse(combo: &str) -> Result<KeyCombo, String> {
+        let mut parts: Vec<&str> = combo.split('+').map(str::trim).collect();
+        let key_name = parts.pop().filter(|name| !name.is_empty()).ok_or_else(|| format!("{:?} has no key", combo))?;
+        let key = egui::Key::from_name(key_name).ok_or_else(|| format!("unknown key {:?} in {:?}", key_name, combo))?;
+        let key_code = mac_key_code(key).ok_or_else(|| format!("{:?} can't be sent as a key press", key_name))?;
+        let mut parsed = KeyCombo { key, key_code, command: false, shift: false, option: false, control: false };
+        for modifier in parts {
+            let flag = match modifier.to_ascii_lowercase().as_str() {
+                "cmd" | "command" => &mut parsed.command,
+                "shift" => &mut parsed.shift,
+                "opt" | "option" | "alt" => &mut parsed.option,
+                "ctrl" | "control" => &mut parsed.control,
+                other => return Err(format!("unknown modifier {:?} in {:?}", other, combo)),
+            };
+            *flag = true;
+        }
+        Ok(parsed)
+    }
+
+    /// "⌘⇧T" style, for legends.
+    pub fn label(&self) -> String {
+        let mut label = String::new();
+        for (held, symbol) in [(self.control, '⌃'), (self.option, '⌥'), (self.shift, '⇧'), (self.command, '⌘')] {
+            if held {
+                label.push(symbol);
+            }
+        }
+        label.push_str(self.key.symbol_or_name());
+        label
+    }
+}
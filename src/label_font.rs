@@ -0,0 +1,52 @@
+use std::fs;
+use std::sync::Arc;
+
+use eframe::egui;
+
+use crate::screenshot;
+use crate::theme::ThemeConfig;
+
+/// Font family every grid label is painted in; always registered, with or without a custom font.
+const LABEL_FONT_FAMILY: &str = "mouseless-labels";
+const CUSTOM_FONT_NAME: &str = "mouseless-custom";
+/// Leading bytes of TrueType, OpenType (CFF), old Mac TrueType and font collection files.
+const FONT_MAGIC: [&[u8]; 4] = [&[0x00, 0x01, 0x00, 0x00], b"OTTO", b"true", b"ttcf"];
+
+pub fn label_font(size: f32) -> egui::FontId {
+    egui::FontId::new(size, egui::FontFamily::Name(LABEL_FONT_FAMILY.into()))
+}
+
+/// Registers the label font family: the file at `font_path` if it loads, then egui's bundled
+/// fonts of `font_family` for any glyph it lacks. A font that fails to load is logged and skipped,
+/// never fatal.
+pub fn install(ctx: &egui::Context, config: &ThemeConfig) {
+    let mut fonts = egui::FontDefinitions::default();
+    let fallback_family = if config.font_family.eq_ignore_ascii_case("monospace") {
+        egui::FontFamily::Monospace
+    } else {
+        egui::FontFamily::Proportional
+    };
+    let mut label_fonts = fonts.families.get(&fallback_family).cloned().unwrap_or_default();
+    if !config.font_path.trim().is_empty() {
+        match load_font_file(config.font_path.trim()) {
+            Ok(bytes) => {
+                println!("Using {} for grid labels", config.font_path);
+                fonts.font_data.insert(CUSTOM_FONT_NAME.to_string(), Arc::new(egui::FontData::from_owned(bytes)));
+                label_fonts.insert(0, CUSTOM_FONT_NAME.to_string());
+            }
+            Err(e) => eprintln!("Ignoring theme.font_path: {}", e),
+        }
+    }
+    fonts.families.insert(egui::FontFamily::Name(LABEL_FONT_FAMILY.into()), label_fonts);
+    ctx.set_fonts(fonts);
+}
+
+/// Reads a font file, rejecting anything that isn't one up front: egui panics on a font it can't parse.
+fn load_font_file(path: &str) -> Result<Vec<u8>, String> {
+    let path = screenshot::expand_home(path);
+    let bytes = fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if !FONT_MAGIC.iter().any(|magic| bytes.starts_with(magic)) {
+        return Err(format!("{} isn't a TTF, OTF or TTC file", path.display()));
+    }
+    Ok(bytes)
+}
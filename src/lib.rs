@@ -0,0 +1,6 @@
+//! Platform-independent core of mouseless: grid layout, label generation and key bindings.
+//! Build it without the overlay and its macOS dependencies with `--no-default-features`.
+
+pub mod grid;
+pub mod grid_controller;
+pub mod keybindings;
@@ -0,0 +1,355 @@
+use std::thread;
+use std::time::Duration;
+
+use std::ffi::c_void;
+
+use core_foundation::base::{CFRelease, CFTypeRef, TCFType};
+use core_foundation::dictionary::{CFDictionary, CFDictionaryRef};
+use core_foundation::number::{CFNumber, CFNumberRef};
+use core_foundation::string::{CFString, CFStringRef};
+use core_graphics::geometry::CGRect;
+use core_graphics::window::{self, kCGNullWindowID, kCGWindowBounds, kCGWindowLayer, kCGWindowListExcludeDesktopElements, kCGWindowListOptionOnScreenOnly, kCGWindowOwnerName, kCGWindowOwnerPID};
+use objc::{msg_send, sel, sel_impl, class};
+use objc::runtime::{Object, BOOL, YES};
+
+use crate::displays::ScreenTopInsets;
+
+const NS_APPLICATION_ACTIVATE_IGNORING_OTHER_APPS: usize = 1 << 1;
+const DOCK_BUNDLE_ID: &str = "com.apple.dock";
+/// UTI behind NSPasteboardTypeString.
+#[cfg(not(test))]
+const PASTEBOARD_TYPE_STRING: &str = "public.utf8-plain-text";
+/// Posted to every app when the system switches between light and dark.
+const INTERFACE_THEME_CHANGED: &str = "AppleInterfaceThemeChangedNotification";
+const CF_NOTIFICATION_DELIVER_IMMEDIATELY: isize = 4;
+
+type NotificationCallback = extern "C" fn(CFTypeRef, *mut c_void, CFStringRef, *const c_void, CFDictionaryRef);
+
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn CGDisplayCreateUUIDFromDisplayID(display: u32) -> CFTypeRef;
+    fn CFUUIDCreateString(allocator: CFTypeRef, uuid: CFTypeRef) -> CFStringRef;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+unsafe extern "C" {
+    fn CFNotificationCenterGetDistributedCenter() -> CFTypeRef;
+    fn CFNotificationCenterAddObserver(
+        center: CFTypeRef,
+        observer: *const c_void,
+        callback: NotificationCallback,
+        name: CFStringRef,
+        object: *const c_void,
+        suspension_behavior: isize,
+    );
+}
+
+/// Process id of the app that currently owns the menu bar / keyboard focus.
+pub fn frontmost_app_pid() -> Option<i32> {
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        if workspace.is_null() {
+            return None;
+        }
+        let app: *mut Object = msg_send![workspace, frontmostApplication];
+        if app.is_null() {
+            return None;
+        }
+        let pid: i32 = msg_send![app, processIdentifier];
+        Some(pid)
+    }
+}
+
+/// Bundle identifier of the frontmost app, e.g. "com.apple.Safari".
+pub fn frontmost_app_bundle_id() -> Option<String> {
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        if workspace.is_null() {
+            return None;
+        }
+        let app: *mut Object = msg_send![workspace, frontmostApplication];
+        if app.is_null() {
+            return None;
+        }
+        let bundle_id: *mut Object = msg_send![app, bundleIdentifier];
+        if bundle_id.is_null() {
+            return None;
+        }
+        let utf8: *const std::os::raw::c_char = msg_send![bundle_id, UTF8String];
+        if utf8.is_null() {
+            return None;
+        }
+        Some(std::ffi::CStr::from_ptr(utf8).to_string_lossy().into_owned())
+    }
+}
+
+/// CFString is toll-free bridged with NSString, so it can be passed wherever AppKit wants one.
+fn ns_string(string: &CFString) -> *mut Object {
+    string.as_concrete_TypeRef() as *mut Object
+}
+
+/// Process id of the Dock, which owns the Dock's accessibility tree.
+pub fn dock_pid() -> Option<i32> {
+    unsafe {
+        let bundle_id = CFString::new(DOCK_BUNDLE_ID);
+        let apps: *mut Object = msg_send![class!(NSRunningApplication), runningApplicationsWithBundleIdentifier: ns_string(&bundle_id)];
+        if apps.is_null() {
+            return None;
+        }
+        let count: usize = msg_send![apps, count];
+        if count == 0 {
+            return None;
+        }
+        let app: *mut Object = msg_send![apps, objectAtIndex: 0usize];
+        let pid: i32 = msg_send![app, processIdentifier];
+        Some(pid)
+    }
+}
+
+/// An on-screen window as the window server lists it.
+pub struct WindowInfo {
+    pub pid: i32,
+    pub owner: String,
+    /// Global top-left-origin coordinates, like CGEvent locations.
+    pub bounds: CGRect,
+}
+
+/// Normal-layer windows on screen, front to back, on every display. Our own windows and any
+/// narrower or shorter than `min_side` are left out.
+pub fn on_screen_windows(min_side: f64) -> Vec<WindowInfo> {
+    let Some(windows) = window::copy_window_info(kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements, kCGNullWindowID) else {
+        return Vec::new();
+    };
+    let own_pid = own_pid();
+    windows.iter()
+        .filter_map(|item| unsafe {
+            let info: CFDictionary = CFDictionary::wrap_under_get_rule(*item as CFDictionaryRef);
+            let value = |key: CFStringRef| info.find(key as *const c_void).map(|value| *value);
+            let number = |key: CFStringRef| value(key).and_then(|value| CFNumber::wrap_under_get_rule(value as CFNumberRef).to_i64());
+            if number(kCGWindowLayer)? != 0 {
+                return None;
+            }
+            let pid = number(kCGWindowOwnerPID)? as i32;
+            let bounds = CGRect::from_dict_representation(&CFDictionary::wrap_under_get_rule(value(kCGWindowBounds)? as CFDictionaryRef))?;
+            let owner = value(kCGWindowOwnerName).map(|name| CFString::wrap_under_get_rule(name as CFStringRef).to_string()).unwrap_or_default();
+            Some(WindowInfo { pid, owner, bounds })
+        })
+        .filter(|window| window.pid != own_pid && window.bounds.size.width >= min_side && window.bounds.size.height >= min_side)
+        .collect()
+}
+
+/// An NSScreen as AppKit reports it.
+pub struct ScreenInfo {
+    /// AppKit coordinates: origin at the bottom-left of the first screen, y pointing up.
+    pub frame: CGRect,
+    /// `frame` without the menu bar and the Dock.
+    pub visible_frame: CGRect,
+    pub scale_factor: f64,
+}
+
+/// Every NSScreen, the one with the menu bar first.
+pub fn screens() -> Vec<ScreenInfo> {
+    unsafe {
+        let screens: *mut Object = msg_send![class!(NSScreen), screens];
+        if screens.is_null() {
+            return Vec::new();
+        }
+        let count: usize = msg_send![screens, count];
+        (0..count)
+            .map(|index| {
+                let screen: *mut Object = msg_send![screens, objectAtIndex: index];
+                let frame: CGRect = msg_send![screen, frame];
+                let visible_frame: CGRect = msg_send![screen, visibleFrame];
+                let scale_factor: f64 = msg_send![screen, backingScaleFactor];
+                ScreenInfo { frame, visible_frame, scale_factor }
+            })
+            .collect()
+    }
+}
+
+/// Stable UUID of a display. Display ids can change between reboots and reconnects; this doesn't.
+pub fn display_uuid(display_id: u32) -> Option<String> {
+    unsafe {
+        let uuid = CGDisplayCreateUUIDFromDisplayID(display_id);
+        if uuid.is_null() {
+            return None;
+        }
+        let string = CFUUIDCreateString(std::ptr::null(), uuid);
+        CFRelease(uuid);
+        if string.is_null() {
+            return None;
+        }
+        Some(CFString::wrap_under_create_rule(string).to_string())
+    }
+}
+
+pub fn own_pid() -> i32 {
+    std::process::id() as i32
+}
+
+pub fn activate_app(pid: i32) -> bool {
+    unsafe {
+        let app: *mut Object = msg_send![class!(NSRunningApplication), runningApplicationWithProcessIdentifier: pid];
+        if app.is_null() {
+            eprintln!("No running application with pid {}", pid);
+            return false;
+        }
+        let activated: BOOL = msg_send![app, activateWithOptions: NS_APPLICATION_ACTIVATE_IGNORING_OTHER_APPS];
+        activated == YES
+    }
+}
+
+/// Replaces the general pasteboard contents with `text`. Tests copy through clipboard's capture instead.
+#[cfg(not(test))]
+pub fn copy_to_clipboard(text: &str) -> bool {
+    unsafe {
+        let pasteboard: *mut Object = msg_send![class!(NSPasteboard), generalPasteboard];
+        if pasteboard.is_null() {
+            return false;
+        }
+        let _: isize = msg_send![pasteboard, clearContents];
+        let text = CFString::new(text);
+        let pasteboard_type = CFString::new(PASTEBOARD_TYPE_STRING);
+        let copied: BOOL = msg_send![pasteboard, setString: ns_string(&text) forType: ns_string(&pasteboard_type)];
+        copied == YES
+    }
+}
+
+/// Whether the system is in dark mode. Read from the global AppleInterfaceStyle default rather than
+/// NSApp's effectiveAppearance: that would create NSApp before winit sets up its own, and it only
+/// catches up with a change some time after the change notification arrives.
+pub fn interface_style_is_dark() -> bool {
+    unsafe {
+        let defaults: *mut Object = msg_send![class!(NSUserDefaults), standardUserDefaults];
+        if defaults.is_null() {
+            return false;
+        }
+        let key = CFString::new("AppleInterfaceStyle");
+        let style: *mut Object = msg_send![defaults, stringForKey: ns_string(&key)];
+        if style.is_null() {
+            return false;
+        }
+        let utf8: *const std::os::raw::c_char = msg_send![style, UTF8String];
+        !utf8.is_null() && std::ffi::CStr::from_ptr(utf8).to_bytes() == b"Dark"
+    }
+}
+
+/// "Increase contrast" and "Reduce transparency" from System Settings > Accessibility > Display.
+pub fn display_accessibility_options() -> (bool, bool) {
+    unsafe {
+        let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+        if workspace.is_null() {
+            return (false, false);
+        }
+        let increase_contrast: BOOL = msg_send![workspace, accessibilityDisplayShouldIncreaseContrast];
+        let reduce_transparency: BOOL = msg_send![workspace, accessibilityDisplayShouldReduceTransparency];
+        (increase_contrast == YES, reduce_transparency == YES)
+    }
+}
+
+extern "C" fn interface_theme_changed(_center: CFTypeRef, observer: *mut c_void, _name: CFStringRef, _object: *const c_void, _info: CFDictionaryRef) {
+    let on_change = unsafe { &*(observer as *const Box<dyn Fn() + Send>) };
+    on_change();
+}
+
+/// Calls `on_change` on the main thread's run loop whenever the system switches between light and
+/// dark. Must be called on the main thread; the observer stays registered for the life of the app.
+pub fn observe_interface_theme_changes(on_change: Box<dyn Fn() + Send>) {
+    let observer = Box::into_raw(Box::new(on_change));
+    let name = CFString::new(INTERFACE_THEME_CHANGED);
+    unsafe {
+        CFNotificationCenterAddObserver(
+            CFNotificationCenterGetDistributedCenter(),
+            observer as *const c_void,
+            interface_theme_changed,
+            name.as_concrete_TypeRef(),
+            std::ptr::null(),
+            CF_NOTIFICATION_DELIVER_IMMEDIATELY,
+        );
+    }
+}
+
+// Only `top` is read, but the layout has to match AppKit's struct
+#[allow(dead_code)]
+#[repr(C)]
+struct NSEdgeInsets {
+    top: f64,
+    left: f64,
+    bottom: f64,
+    right: f64,
+}
+
+/// Window server id of `ns_window`, as the CGWindowList functions take it.
+pub fn window_number(ns_window: *mut Object) -> Option<u32> {
+    if ns_window.is_null() {
+        return None;
+    }
+    let number: isize = unsafe { msg_send![ns_window, windowNumber] };
+    u32::try_from(number).ok().filter(|number| *number > 0)
+}
+
+/// Menu bar and notch of the screen `ns_window` is on. The notch needs macOS 12's safe area APIs.
+pub fn screen_top_insets(ns_window: *mut Object) -> Option<ScreenTopInsets> {
+    if ns_window.is_null() {
+        return None;
+    }
+    unsafe {
+        let screen: *mut Object = msg_send![ns_window, screen];
+        if screen.is_null() {
+            return None;
+        }
+        let frame: CGRect = msg_send![screen, frame];
+        let visible: CGRect = msg_send![screen, visibleFrame];
+        // AppKit frames are bottom-left based, so the menu bar is what visibleFrame leaves off the top
+        let mut menu_bar = (frame.origin.y + frame.size.height) - (visible.origin.y + visible.size.height);
+        let mut notch = None;
+        let has_safe_area: BOOL = msg_send![screen, respondsToSelector: sel!(safeAreaInsets)];
+        if has_safe_area == YES {
+            let insets: NSEdgeInsets = msg_send![screen, safeAreaInsets];
+            menu_bar = menu_bar.max(insets.top);
+            if insets.top > 0.0 {
+                let left_area: CGRect = msg_send![screen, auxiliaryTopLeftArea];
+                let right_area: CGRect = msg_send![screen, auxiliaryTopRightArea];
+                let notch_left = left_area.origin.x + left_area.size.width - frame.origin.x;
+                let notch_right = right_area.origin.x - frame.origin.x;
+                if left_area.size.width > 0.0 && right_area.size.width > 0.0 && notch_right > notch_left {
+                    notch = Some((notch_left as f32, notch_right as f32));
+                }
+            }
+        }
+        Some(ScreenTopInsets { menu_bar: menu_bar.max(0.0) as f32, notch })
+    }
+}
+
+/// Makes a window click-through for as long as the guard lives. The restore runs in Drop
+/// so an early return or failed event post can't leave the overlay ignoring the mouse.
+pub struct IgnoreMouseEventsGuard {
+    ns_window: *mut Object,
+    restore_delay: Duration,
+}
+
+impl IgnoreMouseEventsGuard {
+    pub fn new(ns_window: *mut Object, restore_delay: Duration) -> Option<Self> {
+        if ns_window.is_null() {
+            return None;
+        }
+        unsafe {
+            let _: () = msg_send![ns_window, setIgnoresMouseEvents: true];
+        }
+        println!("Window set to ignore mouse events");
+        Some(Self { ns_window, restore_delay })
+    }
+}
+
+impl Drop for IgnoreMouseEventsGuard {
+    fn drop(&mut self) {
+        // Give the window server time to deliver the trailing synthetic events before the overlay can intercept again
+        if !self.restore_delay.is_zero() {
+            thread::sleep(self.restore_delay);
+        }
+        unsafe {
+            let _: () = msg_send![self.ns_window, setIgnoresMouseEvents: false];
+        }
+        println!("Window restored to normal mouse handling");
+    }
+}
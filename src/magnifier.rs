@@ -0,0 +1,150 @@
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use serde::Deserialize;
+
+#[cfg(target_os = "macos")]
+use core_graphics::access::ScreenCaptureAccess;
+#[cfg(target_os = "macos")]
+use core_graphics::base::{kCGBitmapByteOrder32Big, kCGImageAlphaPremultipliedLast};
+#[cfg(target_os = "macos")]
+use core_graphics::color_space::{kCGColorSpaceSRGB, CGColorSpace};
+#[cfg(target_os = "macos")]
+use core_graphics::context::CGContext;
+#[cfg(target_os = "macos")]
+use core_graphics::geometry::{CGPoint, CGRect, CGSize};
+#[cfg(target_os = "macos")]
+use core_graphics::window::{self, kCGWindowImageBestResolution, kCGWindowListOptionOnScreenBelowWindow};
+
+/// Gap between the picked cell and the lens beside it.
+const LENS_MARGIN: f32 = 12.0;
+/// Shown the first time the lens can't be captured in a run.
+pub const PERMISSION_NOTICE: &str =
+    "Magnifier needs Screen Recording: System Settings → Privacy & Security → Screen Recording, allow mouseless, then restart it";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MagnifierConfig {
+    /// Show a magnified copy of the picked cell next to it while typing the sub-grid label.
+    pub enabled: bool,
+    /// How many times larger the lens is than the cell, 1.0-4.0.
+    pub zoom: f32,
+    /// Minimum time between captures, so a lens that's left up doesn't keep a core busy.
+    pub refresh_ms: u64,
+}
+
+impl Default for MagnifierConfig {
+    fn default() -> Self {
+        Self { enabled: false, zoom: 2.5, refresh_ms: 250 }
+    }
+}
+
+/// Magnified capture of the cell being refined. The capture is taken from below the overlay's
+/// window, so the image shows the screen without our own cells and labels on it.
+#[derive(Default)]
+pub struct Magnifier {
+    texture: Option<egui::TextureHandle>,
+    /// Window-relative rect the texture shows, and when it was captured.
+    captured: Option<(egui::Rect, Instant)>,
+    /// Screen Recording isn't granted; the notice is shown once per run and then the lens is left off.
+    permission_missing: bool,
+    notice_shown: bool,
+    notice_pending: bool,
+}
+
+impl Magnifier {
+    /// Keeps the capture of `cell` (window-relative; `global_cell` is the same rect in global
+    /// points) fresh. `window_number` is the overlay window to capture below.
+    #[cfg(target_os = "macos")]
+    pub fn update(&mut self, ctx: &egui::Context, cell: egui::Rect, global_cell: egui::Rect, window_number: u32, config: &MagnifierConfig) {
+        if self.permission_missing {
+            return;
+        }
+        let refresh = Duration::from_millis(config.refresh_ms);
+        if let Some((captured_cell, captured_at)) = self.captured
+            && captured_cell == cell && captured_at.elapsed() < refresh
+        {
+            ctx.request_repaint_after(refresh - captured_at.elapsed());
+            return;
+        }
+        if !ScreenCaptureAccess.preflight() {
+            println!("Screen Recording isn't allowed, magnifier disabled");
+            self.permission_missing = true;
+            self.notice_pending = !self.notice_shown;
+            self.clear();
+            return;
+        }
+        match capture(global_cell, window_number) {
+            Some(image) => match self.texture.as_mut() {
+                Some(texture) => texture.set(image, egui::TextureOptions::LINEAR),
+                None => self.texture = Some(ctx.load_texture("magnifier", image, egui::TextureOptions::LINEAR)),
+            },
+            None => {
+                eprintln!("Failed to capture {:?} for the magnifier", global_cell);
+                self.texture = None;
+            }
+        }
+        self.captured = Some((cell, Instant::now()));
+        ctx.request_repaint_after(refresh);
+    }
+
+    /// Drops the capture, e.g. when leaving the sub-grid. The permission state is kept.
+    pub fn clear(&mut self) {
+        self.texture = None;
+        self.captured = None;
+    }
+
+    /// Texture of the capture of `cell`, if one is ready.
+    pub fn texture_for(&self, cell: egui::Rect) -> Option<&egui::TextureHandle> {
+        self.texture.as_ref().filter(|_| self.captured.is_some_and(|(captured_cell, _)| captured_cell == cell))
+    }
+
+    /// Whether the permission notice should be up; it stays until `notice_seen`.
+    pub fn notice_pending(&self) -> bool {
+        self.notice_pending
+    }
+
+    pub fn notice_seen(&mut self) {
+        if self.notice_pending {
+            self.notice_pending = false;
+            self.notice_shown = true;
+        }
+    }
+}
+
+/// Lens rect for `cell` at `zoom`: beside the cell on whichever side has room, kept inside `screen_rect`.
+pub fn lens_rect(cell: egui::Rect, zoom: f32, screen_rect: egui::Rect) -> egui::Rect {
+    let size = cell.size() * zoom.clamp(1.0, 4.0);
+    let x = if cell.max.x + LENS_MARGIN + size.x <= screen_rect.max.x {
+        cell.max.x + LENS_MARGIN
+    } else if cell.min.x - LENS_MARGIN - size.x >= screen_rect.min.x {
+        cell.min.x - LENS_MARGIN - size.x
+    } else {
+        // No room on either side: the widest margin wins, even if the lens covers part of the cell
+        if screen_rect.max.x - cell.max.x >= cell.min.x - screen_rect.min.x { screen_rect.max.x - size.x } else { screen_rect.min.x }
+    };
+    let y = (cell.center().y - size.y / 2.0).clamp(screen_rect.min.y, (screen_rect.max.y - size.y).max(screen_rect.min.y));
+    egui::Rect::from_min_size(egui::pos2(x, y), size)
+}
+
+/// Maps a rect inside `cell` to the same spot in `lens`.
+pub fn to_lens(rect: egui::Rect, cell: egui::Rect, lens: egui::Rect) -> egui::Rect {
+    let scale = lens.size() / cell.size();
+    egui::Rect::from_min_max(lens.min + (rect.min - cell.min) * scale, lens.min + (rect.max - cell.min) * scale)
+}
+
+/// Captures the global `rect` from everything below the window `window_number`, at the
+/// display's full resolution, converted to sRGB.
+#[cfg(target_os = "macos")]
+fn capture(rect: egui::Rect, window_number: u32) -> Option<egui::ColorImage> {
+    let bounds = CGRect::new(&CGPoint::new(rect.min.x as f64, rect.min.y as f64), &CGSize::new(rect.width() as f64, rect.height() as f64));
+    let image = window::create_image(bounds, kCGWindowListOptionOnScreenBelowWindow, window_number, kCGWindowImageBestResolution)?;
+    let (width, height) = (image.width(), image.height());
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let color_space = CGColorSpace::create_with_name(unsafe { kCGColorSpaceSRGB })?;
+    let mut context = CGContext::create_bitmap_context(None, width, height, 8, width * 4, &color_space, kCGImageAlphaPremultipliedLast | kCGBitmapByteOrder32Big);
+    context.draw_image(CGRect::new(&CGPoint::new(0.0, 0.0), &CGSize::new(width as f64, height as f64)), &image);
+    Some(egui::ColorImage::from_rgba_premultiplied([width, height], context.data()))
+}
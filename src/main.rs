@@ -1,9 +1,47 @@
 // Allow clippy warnings from the objc crate macros
 #![allow(unexpected_cfgs)]
 
+mod after_hide;
 mod app_ui;
+mod bisect;
+mod bookmarks;
+mod click;
+mod click_marker;
+mod clipboard;
+mod color_sample;
+mod config;
+mod diagnose;
+mod displays;
+mod error;
 mod event_handler;
-mod grid;
+mod feedback;
+mod hud;
+mod label_font;
+mod magnifier;
+mod overlay_opacity;
+mod pick_mode;
+mod pointer_motion;
+mod profiles;
+mod quick_jump;
+mod screenshot;
+mod scroll;
+mod selftest;
+mod single_instance;
+mod sub_cell_memory;
+mod tap_gesture;
+mod theme;
+mod tutorial;
+mod two_point;
+mod window_picker;
+mod window_snap;
+#[cfg(target_os = "macos")]
+mod ax;
+#[cfg(target_os = "macos")]
+mod dock;
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+mod menu_hints;
 
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
@@ -11,31 +49,77 @@ use std::thread;
 use std::sync::mpsc::{channel, Sender, Receiver}; 
 
 use eframe::NativeOptions;
+use mouseless::{grid, grid_controller, keybindings};
 use objc::{msg_send, sel, sel_impl, class}; 
 use objc::runtime::Object;
 #[cfg(target_os = "macos")]
 use objc2_app_kit::NSApplicationActivationPolicy;
 
 use app_ui::{MouselessApp, EframeControl};
-use event_handler::{global_event_listener_thread, EventTapSharedState, GlobalEvent};
+use config::Config;
+use error::{AppError, TapError};
+use event_handler::{global_event_listener_thread, EventSender, EventTapSharedState, GlobalEvent, ESCAPE_KEY_CODE};
+use keybindings::Action;
 
-fn main() -> Result<(), String> { 
+fn main() -> Result<(), AppError> { 
     println!("Starting mouseless");
 
+    let args: Vec<String> = std::env::args().collect();
+    if diagnose::requested(&args) {
+        return diagnose::run().map_err(AppError::Diagnose);
+    }
+    let config = Config::load().map_err(AppError::Config)?;
+    let self_test_runs = selftest::runs_from_args(&args);
+
     let (event_tx, event_rx): (Sender<GlobalEvent>, Receiver<GlobalEvent>) = channel();
+    let event_tx = EventSender::new(event_tx);
+    // Two instances would run two event taps fighting over the activation key
+    match single_instance::claim() {
+        Ok(single_instance::Claim::First(listener)) => single_instance::spawn_listener(listener, event_tx.clone()),
+        Ok(single_instance::Claim::AlreadyRunning) => {
+            println!("mouseless is already running; asked it to handle this launch");
+            return Ok(());
+        }
+        Err(e) => eprintln!("Skipping the single-instance check: {}", e),
+    }
     let eframe_control = EframeControl::default(); 
     let lshift_key_is_pressed_arc = Arc::new(AtomicBool::new(false));
 
+    let (tap_tx, tap_rx) = channel();
+    let tap_event_tx = event_tx.clone();
+    let tap_config = config.taps.clone();
+    thread::spawn(move || {
+        tap_gesture::tap_gesture_thread(tap_rx, tap_event_tx, tap_config, tap_gesture::SystemClock);
+    });
+
     let listener_shared_state = EventTapSharedState {
         event_tx: event_tx.clone(),
+        tap_tx,
         app_is_visible: eframe_control.is_visible.clone(),
-        eframe_hide_requested_by_listener: eframe_control.hide_requested.clone(),
+        overlay_rendered: eframe_control.overlay_rendered.clone(),
+        pointer_keys: eframe_control.pointer_keys.clone(),
+        cancel_key_code: config.key_bindings.mac_key_code(Action::Cancel).unwrap_or(ESCAPE_KEY_CODE),
         lshift_key_is_pressed: lshift_key_is_pressed_arc.clone(),
+        activation_key: config.taps.activation_key,
+        drop_grid: config.drop_grid.clone(),
     };
 
+    let (tap_ready_tx, tap_ready_rx) = channel();
     thread::spawn(move || {
-        global_event_listener_thread(listener_shared_state);
+        global_event_listener_thread(listener_shared_state, tap_ready_tx);
     });
+    let tap_ready = tap_ready_rx.recv()
+        .unwrap_or_else(|_| Err(TapError::Failed("the listener thread exited".to_string())))
+        .map_err(AppError::from);
+    if let Err(e) = tap_ready {
+        #[cfg(target_os = "macos")]
+        if matches!(e, AppError::AccessibilityNotGranted) {
+            // Opens the system prompt that links to the Accessibility list
+            ax::prompt_for_trust();
+        }
+        single_instance::release();
+        return Err(e);
+    }
     println!("Global event listener spawned");
 
     let placeholder_initial_rect = eframe::egui::Rect::from_min_size(eframe::egui::Pos2::ZERO, eframe::egui::vec2(100.0,100.0));
@@ -49,6 +133,8 @@ fn main() -> Result<(), String> {
             .with_visible(false)
             // .with_mouse_passthrough(true) // uncomment this if the overlay is bugging
             .with_title("Mouseless Overlay"),
+        // The overlay places and sizes itself on every show
+        persist_window: false,
         ..Default::default()
     };
     
@@ -67,14 +153,29 @@ fn main() -> Result<(), String> {
                 let _: () = msg_send![ns_app, setActivationPolicy: NSApplicationActivationPolicy::Accessory];
                 println!("Set app as accessory (won't appear in dock)");
             }
-            Ok(Box::new(MouselessApp::new(cc, eframe_control_clone_for_app, placeholder_initial_rect, event_rx, lshift_arc_clone_for_app)))
+            // From here on every event wakes the UI as it's sent
+            event_tx.attach(cc.egui_ctx.clone());
+            #[cfg(target_os = "macos")]
+            {
+                // Delivered on this thread's run loop
+                let appearance_tx = event_tx.clone();
+                macos::observe_interface_theme_changes(Box::new(move || {
+                    let _ = appearance_tx.send(GlobalEvent::AppearanceChanged {
+                        appearance: theme::system_appearance(),
+                        accessibility: theme::system_display_accessibility(),
+                    });
+                }));
+            }
+            let mut app = MouselessApp::new(&cc.egui_ctx, eframe_control_clone_for_app, placeholder_initial_rect, event_rx, lshift_arc_clone_for_app, config, self_test_runs);
+            if let Some(storage) = cc.storage {
+                app.restore(storage);
+            }
+            Ok(Box::new(app))
         }),
     );
 
-    if let Err(e) = run_result {
-        eprintln!("App error: {:?}", e);
-        return Err(format!("Eframe error: {:?}", e));
-    }
+    single_instance::release();
+    run_result?;
 
     println!("App exited successfully");
     Ok(())
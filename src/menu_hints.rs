@@ -0,0 +1,81 @@
+use core_graphics::geometry::CGRect;
+
+use crate::ax::{self, AXElement};
+
+/// What choosing a hinted menu entry did.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MenuStep {
+    /// A menu or submenu is opening; its entries can be read once it is shown.
+    Opened,
+    /// A menu item ran and the menus closed.
+    Activated,
+}
+
+/// Walks the frontmost app's menus through Accessibility: the menu bar titles first, then the
+/// entries of each menu pressed open, so a submenu is reached one pick at a time.
+pub struct MenuWalker {
+    menu_bar: AXElement,
+    /// Menu bar item and menu items pressed open, outermost first.
+    opened: Vec<AXElement>,
+    entries: Vec<(CGRect, AXElement)>,
+}
+
+impl MenuWalker {
+    pub fn start(pid: i32) -> Option<Self> {
+        let menu_bar = ax::menu_bar(pid)?;
+        let entries = ax::menu_entries(&menu_bar);
+        (!entries.is_empty()).then_some(Self { menu_bar, opened: Vec::new(), entries })
+    }
+
+    pub fn entries(&self) -> &[(CGRect, AXElement)] {
+        &self.entries
+    }
+
+    pub fn choose(&mut self, index: usize) -> Option<MenuStep> {
+        let entry = self.entries.get(index)?.1.clone();
+        let opens_menu = ax::submenu(&entry).is_some();
+        println!("Pressing menu entry {:?}", entry.title().unwrap_or_default());
+        if !entry.press() {
+            return None;
+        }
+        self.entries.clear();
+        if opens_menu {
+            self.opened.push(entry);
+            Some(MenuStep::Opened)
+        } else {
+            self.opened.clear();
+            Some(MenuStep::Activated)
+        }
+    }
+
+    /// Re-reads the entries of the innermost open menu. False while it is still opening.
+    pub fn refresh(&mut self) -> bool {
+        self.entries = match self.opened.last() {
+            Some(parent) => ax::submenu(parent).map(|menu| ax::menu_entries(&menu)).unwrap_or_default(),
+            None => ax::menu_entries(&self.menu_bar),
+        };
+        !self.entries.is_empty()
+    }
+
+    /// Closes the innermost open menu and shows the entries around it again. False when none is open.
+    pub fn back(&mut self) -> bool {
+        let Some(parent) = self.opened.pop() else {
+            return false;
+        };
+        if let Some(menu) = ax::submenu(&parent) {
+            menu.cancel();
+        }
+        self.refresh();
+        true
+    }
+
+    /// Closes every menu this walker opened, innermost first.
+    pub fn close_all(&mut self) {
+        while let Some(parent) = self.opened.pop() {
+            if let Some(menu) = ax::submenu(&parent) {
+                menu.cancel();
+            }
+        }
+        self.entries.clear();
+    }
+}
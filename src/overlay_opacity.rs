@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+/// Key the opacity is saved under in eframe's storage.
+pub const STORAGE_KEY: &str = "overlay_opacity";
+/// Faint enough to see through, not so faint the labels are lost.
+const MIN_OPACITY: f32 = 0.2;
+/// The painter can't go past the theme's own alphas; a bolder grid is a [theme] change.
+const MAX_OPACITY: f32 = 1.0;
+const OPACITY_STEP: f32 = 0.1;
+
+/// Multiplier on everything the overlay paints, on top of the theme's alphas. Changed with the
+/// opacity keys on the main grid and saved with the app's state so it survives restarts.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OverlayOpacity {
+    opacity: f32,
+}
+
+impl Default for OverlayOpacity {
+    fn default() -> Self {
+        Self { opacity: MAX_OPACITY }
+    }
+}
+
+impl OverlayOpacity {
+    /// A value read back from storage, brought within the limits.
+    pub fn restored(saved: Self) -> Self {
+        Self { opacity: clamped(saved.opacity) }
+    }
+
+    pub fn value(&self) -> f32 {
+        self.opacity
+    }
+
+    /// Percent, as shown to the user.
+    pub fn percent(&self) -> u32 {
+        (self.opacity * 100.0).round() as u32
+    }
+
+    /// Moves `steps` steps up (positive) or down; returns whether the value changed.
+    pub fn adjust(&mut self, steps: i32) -> bool {
+        let adjusted = clamped(self.opacity + steps as f32 * OPACITY_STEP);
+        if (adjusted - self.opacity).abs() < f32::EPSILON {
+            return false;
+        }
+        self.opacity = adjusted;
+        true
+    }
+}
+
+/// Rounded to the step so repeated presses land on whole percentages.
+fn clamped(opacity: f32) -> f32 {
+    let opacity = if opacity.is_finite() { opacity } else { MAX_OPACITY };
+    ((opacity / OPACITY_STEP).round() * OPACITY_STEP).clamp(MIN_OPACITY, MAX_OPACITY)
+}
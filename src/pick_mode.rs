@@ -0,0 +1,76 @@
+/// What the next pick does instead of the default click. One at a time: arming another replaces it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PickMode {
+    #[default]
+    Click,
+    Scroll,
+    FineAdjust,
+    /// Shown by holding the physical left button: the pick is where that drag is released.
+    Drop,
+    /// The pick double-clicks the word a text selection starts at.
+    SelectTextStart,
+    /// The start was double-clicked; the grid comes back for the shift-click at the end.
+    SelectTextEnd,
+}
+
+impl PickMode {
+    /// Arms `mode`, or goes back to clicking if it was already armed. Returns whether it is armed now.
+    pub fn toggle(&mut self, mode: PickMode) -> bool {
+        let arm = *self != mode;
+        *self = if arm { mode } else { PickMode::Click };
+        arm
+    }
+
+    pub fn is_selecting_text(&self) -> bool {
+        matches!(self, PickMode::SelectTextStart | PickMode::SelectTextEnd)
+    }
+}
+
+/// A prompt waiting for the next typed key. The pick it leads to still goes through the armed PickMode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyPrompt {
+    /// The next key picks a center, edge or corner target.
+    QuickJump,
+    /// Placing a window: the next key picks a snap preset.
+    WindowSnap,
+}
+
+impl KeyPrompt {
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeyPrompt::QuickJump => "Quick jump",
+            KeyPrompt::WindowSnap => "Window snap",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggling_arms_then_disarms() {
+        let mut mode = PickMode::default();
+        assert!(mode.toggle(PickMode::Scroll));
+        assert_eq!(mode, PickMode::Scroll);
+        assert!(!mode.toggle(PickMode::Scroll));
+        assert_eq!(mode, PickMode::Click);
+    }
+
+    #[test]
+    fn arming_another_mode_replaces_the_armed_one() {
+        let mut mode = PickMode::Scroll;
+        assert!(mode.toggle(PickMode::FineAdjust));
+        assert_eq!(mode, PickMode::FineAdjust);
+        assert!(mode.toggle(PickMode::SelectTextStart));
+        assert_eq!(mode, PickMode::SelectTextStart);
+    }
+
+    #[test]
+    fn select_text_rearms_from_its_end() {
+        let mut mode = PickMode::SelectTextEnd;
+        assert!(mode.is_selecting_text());
+        assert!(mode.toggle(PickMode::SelectTextStart));
+        assert_eq!(mode, PickMode::SelectTextStart);
+    }
+}
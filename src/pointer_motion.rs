@@ -0,0 +1,282 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use core_graphics::display::CGDisplay;
+use core_graphics::geometry::{CGPoint, CGRect, CGSize};
+use serde::Deserialize;
+
+use crate::click;
+
+pub const H_KEY_CODE: i64 = 4;
+pub const J_KEY_CODE: i64 = 38;
+pub const K_KEY_CODE: i64 = 40;
+pub const L_KEY_CODE: i64 = 37;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PointerConfig {
+    /// Speed as soon as a direction key goes down, in px/s.
+    pub base_speed: f64,
+    /// Speed reached after holding for `accel_time_ms`, in px/s.
+    pub max_speed: f64,
+    pub accel_time_ms: u64,
+    /// Speed cap while Shift is held, for precise positioning.
+    pub precision_speed: f64,
+    pub tick_ms: u64,
+}
+
+impl Default for PointerConfig {
+    fn default() -> Self {
+        Self {
+            base_speed: 250.0,
+            max_speed: 2500.0,
+            accel_time_ms: 700,
+            precision_speed: 80.0,
+            tick_ms: 8,
+        }
+    }
+}
+
+/// h/j/k/l held state, written by the event tap (egui's key repeat is too coarse for smooth motion).
+#[derive(Default)]
+pub struct HeldDirections {
+    left: AtomicBool,
+    down: AtomicBool,
+    up: AtomicBool,
+    right: AtomicBool,
+}
+
+impl HeldDirections {
+    /// Records a key down/up. Returns false if the key isn't one of h/j/k/l.
+    pub fn update_from_key(&self, key_code: i64, is_down: bool) -> bool {
+        let flag = match key_code {
+            H_KEY_CODE => &self.left,
+            J_KEY_CODE => &self.down,
+            K_KEY_CODE => &self.up,
+            L_KEY_CODE => &self.right,
+            _ => return false,
+        };
+        flag.store(is_down, AtomicOrdering::SeqCst);
+        true
+    }
+
+    pub fn release_all(&self) {
+        for flag in [&self.left, &self.down, &self.up, &self.right] {
+            flag.store(false, AtomicOrdering::SeqCst);
+        }
+    }
+
+    /// Unit-less direction, components in -1..=1 (x: right, y: down).
+    pub fn direction(&self) -> (f64, f64) {
+        let axis = |negative: &AtomicBool, positive: &AtomicBool| {
+            (positive.load(AtomicOrdering::SeqCst) as i8 - negative.load(AtomicOrdering::SeqCst) as i8) as f64
+        };
+        (axis(&self.left, &self.right), axis(&self.up, &self.down))
+    }
+}
+
+/// Speed after `held` of continuous holding. Eases in quadratically from base to max so short
+/// taps stay precise; `slow` caps it at the precision speed.
+pub fn speed_after(held: Duration, slow: bool, config: &PointerConfig) -> f64 {
+    let accel_time = config.accel_time_ms as f64 / 1000.0;
+    let progress = if accel_time > 0.0 { (held.as_secs_f64() / accel_time).min(1.0) } else { 1.0 };
+    let speed = config.base_speed + (config.max_speed - config.base_speed).max(0.0) * progress * progress;
+    if slow { speed.min(config.precision_speed) } else { speed }
+}
+
+/// Velocity in px/s for the held direction. Diagonals are normalized so they aren't faster.
+pub fn velocity(direction: (f64, f64), speed: f64) -> (f64, f64) {
+    let (dx, dy) = direction;
+    let length = (dx * dx + dy * dy).sqrt();
+    if length == 0.0 {
+        return (0.0, 0.0);
+    }
+    (dx / length * speed, dy / length * speed)
+}
+
+/// Tracks how long any direction has been held; releasing every key restarts the ramp.
+#[derive(Default)]
+pub struct MotionRamp {
+    hold_started: Option<Instant>,
+}
+
+impl MotionRamp {
+    /// Displacement in px for the `dt` ending at `now`.
+    pub fn tick(&mut self, direction: (f64, f64), slow: bool, now: Instant, dt: Duration, config: &PointerConfig) -> (f64, f64) {
+        if direction == (0.0, 0.0) {
+            self.hold_started = None;
+            return (0.0, 0.0);
+        }
+        let hold_started = *self.hold_started.get_or_insert(now - dt);
+        let speed = speed_after(now.duration_since(hold_started), slow, config);
+        let (vx, vy) = velocity(direction, speed);
+        (vx * dt.as_secs_f64(), vy * dt.as_secs_f64())
+    }
+}
+
+/// Background ticker that moves the real cursor while direction keys are held.
+pub struct PointerMotion {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PointerMotion {
+    /// `slow` is the Shift state shared with the event tap.
+    pub fn start(held: Arc<HeldDirections>, slow: Arc<AtomicBool>, config: PointerConfig) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let thread_running = running.clone();
+        let handle = thread::spawn(move || run_motion_loop(held, slow, config, thread_running));
+        println!("Pointer motion started");
+        Self { running, handle: Some(handle) }
+    }
+}
+
+impl Drop for PointerMotion {
+    fn drop(&mut self) {
+        self.running.store(false, AtomicOrdering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+        println!("Pointer motion stopped");
+    }
+}
+
+fn desktop_bounds() -> Option<CGRect> {
+    let display_ids = CGDisplay::active_displays().ok()?;
+    display_ids.into_iter()
+        .map(|id| CGDisplay::new(id).bounds())
+        .reduce(|a, b| {
+            let min_x = a.origin.x.min(b.origin.x);
+            let min_y = a.origin.y.min(b.origin.y);
+            let max_x = (a.origin.x + a.size.width).max(b.origin.x + b.size.width);
+            let max_y = (a.origin.y + a.size.height).max(b.origin.y + b.size.height);
+            CGRect::new(&CGPoint::new(min_x, min_y), &CGSize::new(max_x - min_x, max_y - min_y))
+        })
+}
+
+fn run_motion_loop(held: Arc<HeldDirections>, slow: Arc<AtomicBool>, config: PointerConfig, running: Arc<AtomicBool>) {
+    let tick = Duration::from_millis(config.tick_ms.max(1));
+    let bounds = desktop_bounds();
+    let mut position = click::current_cursor_location();
+    let mut ramp = MotionRamp::default();
+    let mut last_tick = Instant::now();
+
+    while running.load(AtomicOrdering::SeqCst) {
+        thread::sleep(tick);
+        let now = Instant::now();
+        // Sleep overshoot varies, so integrate over the measured interval
+        let dt = now.duration_since(last_tick);
+        last_tick = now;
+
+        let (move_x, move_y) = ramp.tick(held.direction(), slow.load(AtomicOrdering::SeqCst), now, dt, &config);
+        if move_x == 0.0 && move_y == 0.0 {
+            // Pick up any physical mouse movement while idle
+            position = click::current_cursor_location();
+            continue;
+        }
+        let Some(current) = position else {
+            position = click::current_cursor_location();
+            continue;
+        };
+        let mut next = CGPoint::new(current.x + move_x, current.y + move_y);
+        if let Some(bounds) = bounds {
+            next.x = next.x.clamp(bounds.origin.x, bounds.origin.x + bounds.size.width - 1.0);
+            next.y = next.y.clamp(bounds.origin.y, bounds.origin.y + bounds.size.height - 1.0);
+        }
+        click::post_mouse_move(next);
+        position = Some(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn speed_eases_in_from_base_to_max() {
+        let config = PointerConfig::default();
+        assert_close(speed_after(Duration::ZERO, false, &config), 250.0);
+        // Halfway through the ramp is a quarter of the way up, quadratically
+        assert_close(speed_after(350 * MS, false, &config), 250.0 + 2250.0 * 0.25);
+        assert_close(speed_after(700 * MS, false, &config), 2500.0);
+        assert_close(speed_after(5000 * MS, false, &config), 2500.0);
+    }
+
+    #[test]
+    fn speed_ramps_up_monotonically() {
+        let config = PointerConfig::default();
+        let speeds: Vec<f64> = (0..=80).map(|step| speed_after(step * 10 * MS, false, &config)).collect();
+        assert!(speeds.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", speeds);
+    }
+
+    #[test]
+    fn precision_caps_the_speed() {
+        let config = PointerConfig::default();
+        assert_close(speed_after(Duration::ZERO, true, &config), 80.0);
+        assert_close(speed_after(700 * MS, true, &config), 80.0);
+        // A base speed under the cap is left alone
+        let config = PointerConfig { precision_speed: 300.0, ..PointerConfig::default() };
+        assert_close(speed_after(Duration::ZERO, true, &config), 250.0);
+        assert_close(speed_after(700 * MS, true, &config), 300.0);
+    }
+
+    #[test]
+    fn degenerate_ramps_hold_a_steady_speed() {
+        let instant = PointerConfig { accel_time_ms: 0, ..PointerConfig::default() };
+        assert_close(speed_after(Duration::ZERO, false, &instant), 2500.0);
+        // A max under the base never slows the pointer down
+        let inverted = PointerConfig { max_speed: 100.0, ..PointerConfig::default() };
+        assert_close(speed_after(700 * MS, false, &inverted), 250.0);
+    }
+
+    #[test]
+    fn diagonals_are_no_faster_than_straight_lines() {
+        assert_eq!(velocity((1.0, 0.0), 100.0), (100.0, 0.0));
+        assert_eq!(velocity((0.0, -1.0), 100.0), (0.0, -100.0));
+        let (vx, vy) = velocity((1.0, 1.0), 100.0);
+        assert_close(vx, 100.0 / 2f64.sqrt());
+        assert_close(vy, 100.0 / 2f64.sqrt());
+        assert_close(vx.hypot(vy), 100.0);
+        assert_eq!(velocity((0.0, 0.0), 100.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn the_ramp_restarts_once_every_key_is_released() {
+        let config = PointerConfig::default();
+        let start = Instant::now();
+        let dt = 10 * MS;
+        let mut ramp = MotionRamp::default();
+        // The first tick counts as held for its own interval
+        let (dx, dy) = ramp.tick((1.0, 0.0), false, start, dt, &config);
+        assert_close(dx, speed_after(dt, false, &config) * 0.01);
+        assert_close(dy, 0.0);
+        let (dx, _) = ramp.tick((1.0, 0.0), false, start + 690 * MS, dt, &config);
+        assert_close(dx, 2500.0 * 0.01);
+
+        assert_eq!(ramp.tick((0.0, 0.0), false, start + 700 * MS, dt, &config), (0.0, 0.0));
+        let (_, dy) = ramp.tick((0.0, 1.0), false, start + 710 * MS, dt, &config);
+        assert_close(dy, speed_after(dt, false, &config) * 0.01);
+    }
+
+    #[test]
+    fn opposite_keys_cancel_out() {
+        let held = HeldDirections::default();
+        assert!(held.update_from_key(H_KEY_CODE, true));
+        assert!(held.update_from_key(L_KEY_CODE, true));
+        assert!(held.update_from_key(J_KEY_CODE, true));
+        assert_eq!(held.direction(), (0.0, 1.0));
+        assert!(held.update_from_key(L_KEY_CODE, false));
+        assert_eq!(held.direction(), (-1.0, 1.0));
+        assert!(!held.update_from_key(0, true));
+        held.release_all();
+        assert_eq!(held.direction(), (0.0, 0.0));
+    }
+}
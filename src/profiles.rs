@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use eframe::egui;
+use serde::Deserialize;
+
+use crate::click::ClickKind;
+use crate::config::GridConfig;
+
+/// Profile used when the frontmost app has none of its own.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Per-application overrides, keyed by bundle identifier in the `[profiles]` config table
+/// (e.g. `[profiles."com.apple.Safari"]`). Unset fields keep the global config.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ProfileConfig {
+    pub main_cols: Option<usize>,
+    pub main_rows: Option<usize>,
+    pub target_cells: Option<usize>,
+    pub sub_grid_cols: Option<usize>,
+    pub sub_grid_rows: Option<usize>,
+    /// Main cell background as [r, g, b, a].
+    pub cell_color: Option<[u8; 4]>,
+    /// Sub-grid cell background as [r, g, b, a].
+    pub sub_cell_color: Option<[u8; 4]>,
+    pub default_click: Option<ClickKind>,
+}
+
+impl ProfileConfig {
+    /// The global grid config with this profile's overrides applied.
+    pub fn apply_to(&self, grid: &GridConfig) -> GridConfig {
+        let mut grid = grid.clone();
+        grid.main_cols = self.main_cols.unwrap_or(grid.main_cols);
+        grid.main_rows = self.main_rows.unwrap_or(grid.main_rows);
+        grid.target_cells = self.target_cells.unwrap_or(grid.target_cells);
+        grid.sub_grid_cols = self.sub_grid_cols.unwrap_or(grid.sub_grid_cols);
+        grid.sub_grid_rows = self.sub_grid_rows.unwrap_or(grid.sub_grid_rows);
+        grid
+    }
+
+    /// Sub-grid size if the profile sets either side; the other side comes from `fallback`.
+    pub fn sub_grid_dims(&self, fallback: (usize, usize)) -> Option<(usize, usize)> {
+        if self.sub_grid_cols.is_none() && self.sub_grid_rows.is_none() {
+            return None;
+        }
+        Some((self.sub_grid_cols.unwrap_or(fallback.0), self.sub_grid_rows.unwrap_or(fallback.1)))
+    }
+
+    pub fn cell_color(&self, fallback: egui::Color32) -> egui::Color32 {
+        self.cell_color.map(|[r, g, b, a]| egui::Color32::from_rgba_unmultiplied(r, g, b, a)).unwrap_or(fallback)
+    }
+
+    pub fn sub_cell_color(&self, fallback: egui::Color32) -> egui::Color32 {
+        self.sub_cell_color.map(|[r, g, b, a]| egui::Color32::from_rgba_unmultiplied(r, g, b, a)).unwrap_or(fallback)
+    }
+}
+
+/// Profile for the app with `bundle_id`, falling back to the default profile, then to no overrides.
+pub fn profile_for(profiles: &HashMap<String, ProfileConfig>, bundle_id: Option<&str>) -> ProfileConfig {
+    bundle_id
+        .and_then(|id| profiles.get(id))
+        .or_else(|| profiles.get(DEFAULT_PROFILE))
+        .cloned()
+        .unwrap_or_default()
+}
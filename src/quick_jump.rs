@@ -0,0 +1,102 @@
+use eframe::egui;
+use serde::Deserialize;
+
+/// Fixed spots on a display reachable with the quick jump key and one more character.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum QuickJumpTarget {
+    Center,
+    Left,
+    Bottom,
+    Top,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl QuickJumpTarget {
+    pub const ALL: [QuickJumpTarget; 9] = [
+        QuickJumpTarget::Center,
+        QuickJumpTarget::Left,
+        QuickJumpTarget::Bottom,
+        QuickJumpTarget::Top,
+        QuickJumpTarget::Right,
+        QuickJumpTarget::TopLeft,
+        QuickJumpTarget::TopRight,
+        QuickJumpTarget::BottomLeft,
+        QuickJumpTarget::BottomRight,
+    ];
+
+    /// The spot in `display_rect`, kept `inset` in from its edges so a corner isn't the very
+    /// first pixel, where hot corners fire.
+    pub fn point_in(&self, display_rect: egui::Rect, inset: f32) -> egui::Pos2 {
+        let rect = display_rect.shrink(inset.min(display_rect.width() / 2.0).min(display_rect.height() / 2.0));
+        let center = rect.center();
+        match self {
+            QuickJumpTarget::Center => center,
+            QuickJumpTarget::Left => egui::pos2(rect.min.x, center.y),
+            QuickJumpTarget::Bottom => egui::pos2(center.x, rect.max.y),
+            QuickJumpTarget::Top => egui::pos2(center.x, rect.min.y),
+            QuickJumpTarget::Right => egui::pos2(rect.max.x, center.y),
+            QuickJumpTarget::TopLeft => rect.min,
+            QuickJumpTarget::TopRight => rect.right_top(),
+            QuickJumpTarget::BottomLeft => rect.left_bottom(),
+            QuickJumpTarget::BottomRight => rect.max,
+        }
+    }
+}
+
+/// Keys default to vim's direction letters: hjkl for the edges, yubn for the corners.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct QuickJumpConfig {
+    /// Distance kept from the display edges, in points.
+    pub inset_px: f32,
+    pub center_key: char,
+    pub left_key: char,
+    pub bottom_key: char,
+    pub top_key: char,
+    pub right_key: char,
+    pub top_left_key: char,
+    pub top_right_key: char,
+    pub bottom_left_key: char,
+    pub bottom_right_key: char,
+}
+
+impl Default for QuickJumpConfig {
+    fn default() -> Self {
+        Self {
+            inset_px: 16.0,
+            center_key: 'C',
+            left_key: 'H',
+            bottom_key: 'J',
+            top_key: 'K',
+            right_key: 'L',
+            top_left_key: 'Y',
+            top_right_key: 'U',
+            bottom_left_key: 'B',
+            bottom_right_key: 'N',
+        }
+    }
+}
+
+impl QuickJumpConfig {
+    pub fn target_for_char(&self, char_code: char) -> Option<QuickJumpTarget> {
+        QuickJumpTarget::ALL.into_iter().find(|target| self.key_for(*target) == char_code)
+    }
+
+    pub fn key_for(&self, target: QuickJumpTarget) -> char {
+        match target {
+            QuickJumpTarget::Center => self.center_key,
+            QuickJumpTarget::Left => self.left_key,
+            QuickJumpTarget::Bottom => self.bottom_key,
+            QuickJumpTarget::Top => self.top_key,
+            QuickJumpTarget::Right => self.right_key,
+            QuickJumpTarget::TopLeft => self.top_left_key,
+            QuickJumpTarget::TopRight => self.top_right_key,
+            QuickJumpTarget::BottomLeft => self.bottom_left_key,
+            QuickJumpTarget::BottomRight => self.bottom_right_key,
+        }
+    }
+}
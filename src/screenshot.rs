@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+use std::process::Command;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use eframe::egui;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScreenshotConfig {
+    /// Copy the region to the clipboard instead of writing a PNG.
+    pub to_clipboard: bool,
+    /// Where PNGs go when `to_clipboard` is off. A leading `~` is the home directory.
+    pub directory: String,
+}
+
+impl Default for ScreenshotConfig {
+    fn default() -> Self {
+        Self { to_clipboard: true, directory: "~/Desktop".to_string() }
+    }
+}
+
+/// `path` with a leading "~" replaced by the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Captures `region` (global points, top-left origin) with screencapture, off the UI thread.
+/// The overlay has to be hidden already or it ends up in the shot.
+pub fn capture(region: egui::Rect, config: &ScreenshotConfig) {
+    let region = egui::Rect::from_min_max(region.min.round(), region.max.round());
+    if region.width() < 1.0 || region.height() < 1.0 {
+        println!("Screenshot region {:?} is empty, nothing captured", region);
+        return;
+    }
+    let mut command = Command::new("screencapture");
+    // -x: no shutter sound
+    command.arg("-x").arg(format!("-R{},{},{},{}", region.min.x, region.min.y, region.width(), region.height()));
+    if config.to_clipboard {
+        command.arg("-c");
+    } else {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+        command.arg(expand_home(&config.directory).join(format!("mouseless-{}.png", seconds)));
+    }
+    println!("Capturing {:?}: {:?}", region, command);
+    thread::spawn(move || match command.status() {
+        Ok(status) if status.success() => println!("Screenshot captured"),
+        Ok(status) => eprintln!("screencapture failed: {}", status),
+        Err(e) => eprintln!("Failed to run screencapture: {}", e),
+    });
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use std::ffi::c_void;
+
+use core_foundation::base::CFRelease;
+use core_graphics::event::{CGScrollEventUnit, ScrollEventUnit};
+use eframe::egui;
+use serde::Deserialize;
+
+/// kCGEventSourceStatePrivate, so a held modifier doesn't turn the scroll into a zoom
+const EVENT_SOURCE_STATE_PRIVATE: i32 = -1;
+/// kCGHIDEventTap
+const HID_EVENT_TAP: u32 = 0;
+
+// core-graphics has no scroll-wheel constructor, so the non-variadic CGEventCreateScrollWheelEvent2 is used directly
+#[link(name = "ApplicationServices", kind = "framework")]
+unsafe extern "C" {
+    fn CGEventSourceCreate(state_id: i32) -> *mut c_void;
+    fn CGEventCreateScrollWheelEvent2(
+        source: *mut c_void,
+        units: CGScrollEventUnit,
+        wheel_count: u32,
+        wheel1: i32,
+        wheel2: i32,
+        wheel3: i32,
+    ) -> *mut c_void;
+    fn CGEventPost(tap: u32, event: *mut c_void);
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ScrollConfig {
+    /// Pixels scrolled by a single press of an arrow key.
+    pub step_px: f32,
+    /// Speed once an arrow key is held, in px/s.
+    pub speed: f32,
+    /// Speed gained per second of holding, in px/s².
+    pub acceleration: f32,
+    pub max_speed: f32,
+    /// How long a key must stay down before continuous scrolling starts.
+    pub repeat_delay_ms: u64,
+    /// Share of the display height scrolled by PageUp / PageDown; just under 1.0 keeps a few lines in view.
+    pub page_fraction: f32,
+}
+
+impl Default for ScrollConfig {
+    fn default() -> Self {
+        Self {
+            step_px: 40.0,
+            speed: 600.0,
+            acceleration: 1500.0,
+            max_speed: 4000.0,
+            repeat_delay_ms: 200,
+            page_fraction: 0.9,
+        }
+    }
+}
+
+/// Scroll speed after `held` of continuous holding (excluding the repeat delay).
+pub fn speed_after(held: Duration, config: &ScrollConfig) -> f32 {
+    (config.speed + config.acceleration * held.as_secs_f32()).min(config.max_speed)
+}
+
+/// Turns "arrow keys are down" into scroll deltas, frame-rate independently.
+#[derive(Default)]
+pub struct ScrollRepeater {
+    hold_started: Option<Instant>,
+    last_tick: Option<Instant>,
+    remainder: egui::Vec2,
+}
+
+impl ScrollRepeater {
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// `direction` has components in -1..=1 (x: right, y: down). Returns whole pixels to scroll now.
+    pub fn tick(&mut self, direction: egui::Vec2, now: Instant, config: &ScrollConfig) -> Option<egui::Vec2> {
+        if direction == egui::Vec2::ZERO {
+            self.reset();
+            return None;
+        }
+        let hold_started = *self.hold_started.get_or_insert(now);
+        let last_tick = self.last_tick.replace(now).unwrap_or(now);
+
+        let repeat_delay = Duration::from_millis(config.repeat_delay_ms);
+        let held = now.duration_since(hold_started);
+        if held < repeat_delay {
+            return None;
+        }
+        let speed = speed_after(held - repeat_delay, config);
+        // Only count the part of this frame that falls after the repeat delay
+        let dt = now.duration_since(last_tick.max(hold_started + repeat_delay)).as_secs_f32();
+        self.remainder += direction * speed * dt;
+
+        let whole = egui::vec2(self.remainder.x.trunc(), self.remainder.y.trunc());
+        if whole == egui::Vec2::ZERO {
+            return None;
+        }
+        self.remainder -= whole;
+        Some(whole)
+    }
+}
+
+/// Posts a pixel scroll at the current cursor position. Positive `delta.y` scrolls content down.
+pub fn post_scroll(delta: egui::Vec2) {
+    // Wheel values follow the physical wheel: positive wheel1 scrolls up, positive wheel2 scrolls left
+    let wheel_y = -delta.y.round() as i32;
+    let wheel_x = -delta.x.round() as i32;
+    unsafe {
+        let event_source = CGEventSourceCreate(EVENT_SOURCE_STATE_PRIVATE);
+        if event_source.is_null() {
+            eprintln!("Failed to create event source");
+            return;
+        }
+        let event = CGEventCreateScrollWheelEvent2(event_source, ScrollEventUnit::PIXEL, 2, wheel_y, wheel_x, 0);
+        if event.is_null() {
+            eprintln!("Failed to create scroll event");
+        } else {
+            CGEventPost(HID_EVENT_TAP, event);
+            CFRelease(event);
+        }
+        CFRelease(event_source);
+    }
+}
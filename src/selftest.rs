@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+
+pub const SELF_TEST_FLAG: &str = "--self-test";
+pub const DEFAULT_SELF_TEST_RUNS: usize = 20;
+const SHOW_TO_SELECT_DELAY_MS: u64 = 100;
+const BETWEEN_RUNS_DELAY_MS: u64 = 250;
+
+/// `--self-test [N]`: number of simulated selections to time, if the flag is present.
+pub fn runs_from_args(args: &[String]) -> Option<usize> {
+    let flag_index = args.iter().position(|arg| arg == SELF_TEST_FLAG)?;
+    let runs = args.get(flag_index + 1)
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(DEFAULT_SELF_TEST_RUNS);
+    Some(runs.max(1))
+}
+
+/// Drives repeated show -> select -> click cycles and records the time from the click being
+/// queued to the synthetic event being posted (hide wait and repaint scheduling included).
+pub struct SelfTest {
+    runs: usize,
+    samples: Vec<Duration>,
+    shown_at: Option<Instant>,
+    queued_at: Option<Instant>,
+    next_show_at: Instant,
+}
+
+impl SelfTest {
+    pub fn new(runs: usize) -> Self {
+        println!("Self-test: timing {} simulated selections (events are posted as mouse moves, not clicks)", runs);
+        Self {
+            runs,
+            samples: Vec::with_capacity(runs),
+            shown_at: None,
+            queued_at: None,
+            next_show_at: Instant::now() + Duration::from_millis(500),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.samples.len() >= self.runs
+    }
+
+    pub fn should_show(&self, now: Instant) -> bool {
+        !self.is_done() && self.shown_at.is_none() && self.queued_at.is_none() && now >= self.next_show_at
+    }
+
+    pub fn mark_shown(&mut self, now: Instant) {
+        self.shown_at = Some(now);
+    }
+
+    pub fn should_select(&self, now: Instant) -> bool {
+        self.queued_at.is_none()
+            && self.shown_at.is_some_and(|shown| now.duration_since(shown) >= Duration::from_millis(SHOW_TO_SELECT_DELAY_MS))
+    }
+
+    pub fn mark_queued(&mut self, now: Instant) {
+        self.shown_at = None;
+        self.queued_at = Some(now);
+    }
+
+    pub fn mark_posted(&mut self, now: Instant) {
+        if let Some(queued_at) = self.queued_at.take() {
+            let latency = now.duration_since(queued_at);
+            self.samples.push(latency);
+            println!("Self-test run {}/{}: {:.1} ms", self.samples.len(), self.runs, latency.as_secs_f64() * 1000.0);
+            self.next_show_at = now + Duration::from_millis(BETWEEN_RUNS_DELAY_MS);
+        }
+    }
+
+    pub fn report(&self) -> String {
+        if self.samples.is_empty() {
+            return "Self-test: no samples recorded".to_string();
+        }
+        let mut sorted_ms: Vec<f64> = self.samples.iter().map(|d| d.as_secs_f64() * 1000.0).collect();
+        sorted_ms.sort_by(|a, b| a.total_cmp(b));
+        let count = sorted_ms.len();
+        let mean = sorted_ms.iter().sum::<f64>() / count as f64;
+        let percentile = |p: f64| sorted_ms[(((count - 1) as f64) * p).round() as usize];
+        format!(
+            "Self-test: {} runs, queued->posted latency ms: min {:.1}, median {:.1}, mean {:.1}, p95 {:.1}, max {:.1}",
+            count, sorted_ms[0], percentile(0.5), mean, percentile(0.95), sorted_ms[count - 1]
+        )
+    }
+}
@@ -0,0 +1,75 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread;
+
+use crate::config;
+use crate::event_handler::{EventSender, GlobalEvent};
+
+const SOCKET_FILE_NAME: &str = "mouseless.sock";
+/// Sent by a second launch before it exits; the running instance answers with `taps.relaunch`.
+const RELAUNCH_MESSAGE: &str = "relaunch";
+
+pub enum Claim {
+    /// No other instance answered; keep the listener alive for the life of the app.
+    First(UnixListener),
+    /// Another instance is running and has been told about this launch.
+    AlreadyRunning,
+}
+
+fn socket_path() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join(SOCKET_FILE_NAME))
+}
+
+/// Binds the instance socket in the config dir. A socket nobody answers on is left over from a
+/// crash and is replaced. An error means the check couldn't run, not that another instance exists.
+/// Two launches racing through that window can both start; the event taps
+/// are what matter, and a stale socket is rare enough that this isn't worth a lock file on top.
+pub fn claim() -> Result<Claim, String> {
+    let path = socket_path().ok_or_else(|| "HOME not set".to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    }
+    match UnixListener::bind(&path) {
+        Ok(listener) => Ok(Claim::First(listener)),
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => match UnixStream::connect(&path) {
+            Ok(mut stream) => {
+                if let Err(e) = writeln!(stream, "{}", RELAUNCH_MESSAGE) {
+                    eprintln!("Couldn't signal the running instance: {}", e);
+                }
+                Ok(Claim::AlreadyRunning)
+            }
+            Err(_) => {
+                println!("Removing stale instance socket {}", path.display());
+                std::fs::remove_file(&path).map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+                UnixListener::bind(&path)
+                    .map(Claim::First)
+                    .map_err(|e| format!("Failed to bind {}: {}", path.display(), e))
+            }
+        },
+        Err(e) => Err(format!("Failed to bind {}: {}", path.display(), e)),
+    }
+}
+
+/// Forwards each relaunch message from a later launch to the app as `GlobalEvent::Relaunched`.
+pub fn spawn_listener(listener: UnixListener, event_tx: EventSender) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else { continue };
+            let mut line = String::new();
+            if BufReader::new(stream).read_line(&mut line).is_ok() && line.trim() == RELAUNCH_MESSAGE {
+                println!("Another launch found this instance running");
+                if event_tx.send(GlobalEvent::Relaunched).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+}
+
+/// Removes the socket so the next launch doesn't have to probe a dead one.
+pub fn release() {
+    if let Some(path) = socket_path() {
+        let _ = std::fs::remove_file(path);
+    }
+}
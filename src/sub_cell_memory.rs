@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const MEMORY_FILE_NAME: &str = "sub_cells.toml";
+
+/// Last sub-grid pick per main cell, kept next to the config so it survives restarts.
+/// Picks are stored as a fraction of the main cell, so a different sub-grid size still
+/// lands on the sub-cell covering the same spot.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SubCellMemory {
+    /// Main cell index (as a string, for TOML keys) -> x, y within the cell, 0.0-1.0.
+    cells: HashMap<String, [f32; 2]>,
+}
+
+fn memory_path() -> Option<PathBuf> {
+    config::config_dir().map(|dir| dir.join(MEMORY_FILE_NAME))
+}
+
+impl SubCellMemory {
+    pub fn load() -> Self {
+        let Some(path) = memory_path().filter(|path| path.exists()) else {
+            return Self::default();
+        };
+        let loaded = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|contents| toml::from_str(&contents).map_err(|e| e.to_string()));
+        loaded.unwrap_or_else(|e| {
+            eprintln!("Ignoring remembered sub-cells in {}: {}", path.display(), e);
+            Self::default()
+        })
+    }
+
+    pub fn remember(&mut self, main_index: usize, main_rect: egui::Rect, point: egui::Pos2) {
+        let fraction = (point - main_rect.min) / main_rect.size();
+        self.cells.insert(main_index.to_string(), [fraction.x, fraction.y]);
+        self.save();
+    }
+
+    /// Where the last pick inside this main cell was, for its current rect.
+    pub fn recall(&self, main_index: usize, main_rect: egui::Rect) -> Option<egui::Pos2> {
+        let [x, y] = *self.cells.get(&main_index.to_string())?;
+        Some(main_rect.min + egui::vec2(x, y) * main_rect.size())
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.save();
+    }
+
+    fn save(&self) {
+        let Some(path) = memory_path() else {
+            return;
+        };
+        let saved = toml::to_string(self).map_err(|e| e.to_string()).and_then(|contents| {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+            }
+            fs::write(&path, contents).map_err(|e| e.to_string())
+        });
+        if let Err(e) = saved {
+            eprintln!("Failed to save remembered sub-cells to {}: {}", path.display(), e);
+        }
+    }
+}
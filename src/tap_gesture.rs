@@ -0,0 +1,470 @@
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use eframe::egui;
+use serde::Deserialize;
+
+use crate::event_handler::{self, EventSender, GlobalEvent};
+
+/// Key whose taps are recognized as gestures.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivationKey {
+    RightCommand,
+    RightOption,
+    /// Fn / Globe; macOS reports it through the secondary-fn flag rather than a device bit.
+    Fn,
+    /// Each press toggles the lock and sends a single event, which counts as a whole tap.
+    CapsLock,
+}
+
+impl ActivationKey {
+    pub fn key_code(&self) -> i64 {
+        match self {
+            ActivationKey::RightCommand => event_handler::RIGHT_COMMAND_KEY_CODE,
+            ActivationKey::RightOption => event_handler::RIGHT_OPTION_KEY_CODE,
+            ActivationKey::Fn => event_handler::FN_KEY_CODE,
+            ActivationKey::CapsLock => event_handler::CAPS_LOCK_KEY_CODE,
+        }
+    }
+}
+
+/// What a recognized activation key tap gesture does while the overlay is hidden.
+/// Any gesture while the overlay is visible hides it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TapAction {
+    None,
+    ShowGrid,
+    ShowGridRightClick,
+    ShowScrollGrid,
+    RepeatLastClick,
+    /// Show labels on the frontmost app's clickable elements, or the grid if it has none.
+    ShowHints,
+    /// Show labels on the frontmost app's menu bar.
+    ShowMenuHints,
+    /// Show labels on the Dock's items.
+    ShowDockHints,
+    /// Show labels on every on-screen window.
+    ShowWindowHints,
+    /// Show the grid for rapid fire: once a main cell is picked, each sub-grid label clicks at once
+    /// and the grid stays up until Escape.
+    ShowRapidFireGrid,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TapConfig {
+    pub activation_key: ActivationKey,
+    /// A press held longer than this is not a tap.
+    pub max_tap_ms: u64,
+    /// Max time between two releases for them to count as one multi-tap.
+    pub multi_tap_window_ms: u64,
+    pub single: TapAction,
+    pub double: TapAction,
+    pub triple: TapAction,
+    /// What launching mouseless again while it's running does.
+    pub relaunch: TapAction,
+}
+
+impl Default for TapConfig {
+    fn default() -> Self {
+        Self {
+            activation_key: ActivationKey::RightCommand,
+            max_tap_ms: 100,
+            multi_tap_window_ms: 200,
+            single: TapAction::ShowGrid,
+            double: TapAction::None,
+            triple: TapAction::None,
+            relaunch: TapAction::ShowGrid,
+        }
+    }
+}
+
+impl TapConfig {
+    pub fn action_for(&self, taps: usize) -> TapAction {
+        match taps {
+            1 => self.single,
+            2 => self.double,
+            3 => self.triple,
+            _ => TapAction::None,
+        }
+    }
+
+    /// Highest tap count that does something; reaching it resolves without waiting for the window.
+    pub fn max_taps(&self) -> usize {
+        (1..=3).rev().find(|taps| self.action_for(*taps) != TapAction::None).unwrap_or(1)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TapStep {
+    /// The release didn't complete a tap (no press seen, or held too long).
+    Ignored,
+    /// A tap was counted; more may follow within the window.
+    Pending,
+    Recognized(usize),
+}
+
+/// Counts taps of a single key. All timestamps are passed in, so the timing rules don't depend on the clock.
+pub struct TapDetector {
+    max_tap: Duration,
+    window: Duration,
+    max_taps: usize,
+    press_started: Option<Instant>,
+    taps: usize,
+    last_release: Option<Instant>,
+}
+
+impl TapDetector {
+    pub fn new(max_tap: Duration, window: Duration, max_taps: usize) -> Self {
+        Self { max_tap, window, max_taps: max_taps.max(1), press_started: None, taps: 0, last_release: None }
+    }
+
+    pub fn from_config(config: &TapConfig) -> Self {
+        Self::new(
+            Duration::from_millis(config.max_tap_ms),
+            Duration::from_millis(config.multi_tap_window_ms),
+            config.max_taps(),
+        )
+    }
+
+    fn reset(&mut self) {
+        self.press_started = None;
+        self.taps = 0;
+        self.last_release = None;
+    }
+
+    pub fn press(&mut self, now: Instant) {
+        if self.press_started.is_none() {
+            self.press_started = Some(now);
+        }
+    }
+
+    pub fn release(&mut self, now: Instant) -> TapStep {
+        let Some(press_started) = self.press_started.take() else {
+            return TapStep::Ignored;
+        };
+        if now.duration_since(press_started) >= self.max_tap {
+            self.reset();
+            return TapStep::Ignored;
+        }
+        if self.last_release.is_some_and(|last| now.duration_since(last) >= self.window) {
+            // Too late to extend the previous sequence; this tap starts a new one
+            self.taps = 0;
+        }
+        self.taps += 1;
+        self.last_release = Some(now);
+        if self.taps >= self.max_taps {
+            let taps = self.taps;
+            self.reset();
+            TapStep::Recognized(taps)
+        } else {
+            TapStep::Pending
+        }
+    }
+
+    /// Another key interfered with the press in progress. Returns true if that dropped anything.
+    pub fn interrupt(&mut self) -> bool {
+        if self.press_started.is_none() {
+            return false;
+        }
+        self.reset();
+        true
+    }
+
+    /// When the pending sequence resolves if no further tap arrives.
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.press_started.is_some() {
+            return None;
+        }
+        self.last_release.filter(|_| self.taps > 0).map(|last| last + self.window)
+    }
+
+    /// Resolves the pending sequence once its window has passed.
+    pub fn expire(&mut self, now: Instant) -> Option<usize> {
+        let deadline = self.deadline()?;
+        if now < deadline {
+            return None;
+        }
+        let taps = self.taps;
+        self.reset();
+        Some(taps)
+    }
+}
+
+/// Source of timestamps for tap detection, so the timing rules can be driven without real time.
+pub trait Clock: Send + 'static {
+    fn now(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// Raw activation key input from the event tap; timestamps are taken from the gesture thread's clock.
+pub enum TapInput {
+    Press,
+    Release(Option<egui::Pos2>),
+    Interrupt,
+}
+
+/// Runs the detector off the event tap thread, which has no timer to resolve pending sequences with.
+pub fn tap_gesture_thread<C: Clock>(input_rx: Receiver<TapInput>, event_tx: EventSender, config: TapConfig, clock: C) {
+    let mut detector = TapDetector::from_config(&config);
+    let mut cursor_pos = None;
+    let emit = |taps: usize, cursor_pos: Option<egui::Pos2>| {
+        let action = config.action_for(taps);
+        println!("Recognized {}-tap gesture: {:?}", taps, action);
+        let _ = event_tx.send(GlobalEvent::TapGesture { taps, action, cursor_pos });
+    };
+
+    loop {
+        let input = match detector.deadline() {
+            Some(deadline) => match input_rx.recv_timeout(deadline.saturating_duration_since(clock.now())) {
+                Ok(input) => Some(input),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            },
+            None => match input_rx.recv() {
+                Ok(input) => Some(input),
+                Err(_) => return,
+            },
+        };
+        match input {
+            Some(TapInput::Press) => {
+                let now = clock.now();
+                // The press can come in past the deadline before the timeout has fired; the finished
+                // sequence goes out first rather than being folded into this one
+                if let Some(taps) = detector.expire(now) {
+                    emit(taps, cursor_pos);
+                }
+                detector.press(now);
+            }
+            Some(TapInput::Release(pos)) => {
+                cursor_pos = pos;
+                match detector.release(clock.now()) {
+                    TapStep::Recognized(taps) => emit(taps, cursor_pos),
+                    TapStep::Pending => {}
+                    TapStep::Ignored => println!("RCmd held too long, not a tap"),
+                }
+            }
+            Some(TapInput::Interrupt) => {
+                if detector.interrupt() {
+                    println!("Other key pressed while RCmd held, tap sequence cancelled");
+                }
+            }
+            None => {
+                if let Some(taps) = detector.expire(clock.now()) {
+                    emit(taps, cursor_pos);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::{channel, sync_channel, Receiver, SyncSender};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::*;
+
+    const MAX_TAP: Duration = Duration::from_millis(100);
+    const WINDOW: Duration = Duration::from_millis(200);
+    const MS: Duration = Duration::from_millis(1);
+
+    /// A tap pressed at `at` and held for `held`.
+    fn tap(detector: &mut TapDetector, at: Instant, held: Duration) -> TapStep {
+        detector.press(at);
+        detector.release(at + held)
+    }
+
+    #[test]
+    fn a_hold_just_under_the_limit_is_a_tap() {
+        let mut detector = TapDetector::new(MAX_TAP, WINDOW, 1);
+        assert_eq!(tap(&mut detector, Instant::now(), MAX_TAP - MS), TapStep::Recognized(1));
+    }
+
+    #[test]
+    fn a_hold_as_long_as_the_limit_is_not_a_tap() {
+        let start = Instant::now();
+        let mut detector = TapDetector::new(MAX_TAP, WINDOW, 2);
+        assert_eq!(tap(&mut detector, start, MS), TapStep::Pending);
+        // Holding too long also drops the tap counted before it
+        assert_eq!(tap(&mut detector, start + 10 * MS, MAX_TAP), TapStep::Ignored);
+        assert_eq!(detector.deadline(), None);
+        assert_eq!(tap(&mut detector, start + 200 * MS, MS), TapStep::Pending);
+    }
+
+    #[test]
+    fn a_second_tap_just_inside_the_window_completes_a_double() {
+        let start = Instant::now();
+        let mut detector = TapDetector::new(MAX_TAP, WINDOW, 2);
+        assert_eq!(tap(&mut detector, start, MS), TapStep::Pending);
+        let first_release = start + MS;
+        assert_eq!(tap(&mut detector, first_release + WINDOW - 2 * MS, MS), TapStep::Recognized(2));
+    }
+
+    #[test]
+    fn a_second_tap_too_late_starts_a_new_sequence() {
+        let start = Instant::now();
+        let mut detector = TapDetector::new(MAX_TAP, WINDOW, 2);
+        assert_eq!(tap(&mut detector, start, MS), TapStep::Pending);
+        let first_release = start + MS;
+        assert_eq!(tap(&mut detector, first_release + WINDOW - MS, MS), TapStep::Pending);
+        assert_eq!(detector.deadline(), Some(first_release + WINDOW + WINDOW));
+    }
+
+    #[test]
+    fn a_pending_tap_resolves_at_its_deadline() {
+        let start = Instant::now();
+        let mut detector = TapDetector::new(MAX_TAP, WINDOW, 3);
+        tap(&mut detector, start, MS);
+        tap(&mut detector, start + 50 * MS, MS);
+        let deadline = start + 51 * MS + WINDOW;
+        assert_eq!(detector.deadline(), Some(deadline));
+        assert_eq!(detector.expire(deadline - MS), None);
+        assert_eq!(detector.expire(deadline), Some(2));
+        assert_eq!(detector.deadline(), None);
+    }
+
+    #[test]
+    fn no_deadline_while_the_key_is_down() {
+        let start = Instant::now();
+        let mut detector = TapDetector::new(MAX_TAP, WINDOW, 2);
+        tap(&mut detector, start, MS);
+        detector.press(start + 50 * MS);
+        assert_eq!(detector.deadline(), None);
+        assert_eq!(detector.expire(start + 10 * WINDOW), None);
+    }
+
+    #[test]
+    fn another_key_drops_the_press_in_progress() {
+        let start = Instant::now();
+        let mut detector = TapDetector::new(MAX_TAP, WINDOW, 1);
+        assert!(!detector.interrupt());
+        detector.press(start);
+        assert!(detector.interrupt());
+        assert_eq!(detector.release(start + MS), TapStep::Ignored);
+    }
+
+    /// Time that only moves when the test moves it.
+    #[derive(Clone)]
+    struct ManualClock(Arc<Mutex<Instant>>);
+
+    impl ManualClock {
+        fn advance(&self, by: Duration) {
+            *self.0.lock().unwrap() += by;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    /// The gesture thread on a manual clock. Input goes over a rendezvous channel, so each send
+    /// returns only once the thread has taken it.
+    struct GestureThread {
+        clock: ManualClock,
+        input: SyncSender<TapInput>,
+        events: Receiver<GlobalEvent>,
+    }
+
+    impl GestureThread {
+        fn start(config: TapConfig) -> Self {
+            let clock = ManualClock(Arc::new(Mutex::new(Instant::now())));
+            let (input, input_rx) = sync_channel(0);
+            let (event_tx, events) = channel();
+            let thread_clock = clock.clone();
+            thread::spawn(move || tap_gesture_thread(input_rx, EventSender::new(event_tx), config, thread_clock));
+            Self { clock, input, events }
+        }
+
+        /// A press and release at the current time, handled before this returns.
+        fn tap(&self) {
+            self.input.send(TapInput::Press).unwrap();
+            self.input.send(TapInput::Release(None)).unwrap();
+            self.settle();
+        }
+
+        /// Waits until the thread is back waiting for input. An interrupt with no press in progress
+        /// changes nothing, so it serves as a marker.
+        fn settle(&self) {
+            self.input.send(TapInput::Interrupt).unwrap();
+        }
+
+        /// Moves the clock, then lets the thread resolve whatever that made due.
+        fn wait(&self, by: Duration) {
+            self.clock.advance(by);
+            self.settle();
+        }
+
+        fn next_gesture(&self) -> (usize, TapAction) {
+            match self.events.recv_timeout(Duration::from_secs(1)) {
+                Ok(GlobalEvent::TapGesture { taps, action, .. }) => (taps, action),
+                Ok(_) => panic!("expected a tap gesture"),
+                Err(error) => panic!("no tap gesture: {:?}", error),
+            }
+        }
+
+        fn assert_no_gesture(&self) {
+            assert!(self.events.try_recv().is_err(), "no gesture should have been recognized yet");
+        }
+    }
+
+    fn single_and_double() -> TapConfig {
+        TapConfig { double: TapAction::ShowGridRightClick, ..TapConfig::default() }
+    }
+
+    #[test]
+    fn a_single_tap_is_recognized_at_once_when_only_single_is_bound() {
+        let gesture = GestureThread::start(TapConfig::default());
+        gesture.tap();
+        assert_eq!(gesture.next_gesture(), (1, TapAction::ShowGrid));
+    }
+
+    #[test]
+    fn a_single_tap_waits_out_the_window_when_a_double_is_bound() {
+        let config = single_and_double();
+        let window = Duration::from_millis(config.multi_tap_window_ms);
+        let gesture = GestureThread::start(config);
+        gesture.tap();
+        gesture.wait(window - MS);
+        gesture.assert_no_gesture();
+        gesture.wait(MS);
+        assert_eq!(gesture.next_gesture(), (1, TapAction::ShowGrid));
+    }
+
+    #[test]
+    fn a_second_tap_just_inside_the_window_is_a_double_tap() {
+        let config = single_and_double();
+        let window = Duration::from_millis(config.multi_tap_window_ms);
+        let gesture = GestureThread::start(config);
+        gesture.tap();
+        gesture.wait(window - MS);
+        gesture.tap();
+        assert_eq!(gesture.next_gesture(), (2, TapAction::ShowGridRightClick));
+        gesture.assert_no_gesture();
+    }
+
+    #[test]
+    fn a_second_tap_just_outside_the_window_is_two_single_taps() {
+        let config = single_and_double();
+        let window = Duration::from_millis(config.multi_tap_window_ms);
+        let gesture = GestureThread::start(config);
+        gesture.tap();
+        gesture.clock.advance(window);
+        gesture.tap();
+        assert_eq!(gesture.next_gesture(), (1, TapAction::ShowGrid));
+        gesture.wait(window);
+        assert_eq!(gesture.next_gesture(), (1, TapAction::ShowGrid));
+    }
+}
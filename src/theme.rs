@@ -0,0 +1,489 @@
+use eframe::egui;
+use serde::Deserialize;
+
+use crate::grid::LabelPosition;
+use crate::hud::HudStyle;
+#[cfg(target_os = "macos")]
+use crate::macos;
+
+/// WCAG AA for body text; high contrast lifts labels to at least this against their cell.
+const MIN_LABEL_CONTRAST: f32 = 4.5;
+/// Fill alpha under "Reduce transparency": the screen behind barely shows through.
+const REDUCED_TRANSPARENCY_ALPHA: u8 = 235;
+
+/// Light or dark mode, which picks the built-in theme while `[theme] name = "auto"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemAppearance {
+    Light,
+    Dark,
+}
+
+/// The system's current appearance. Dark where there's none to follow.
+pub fn system_appearance() -> SystemAppearance {
+    #[cfg(target_os = "macos")]
+    if !macos::interface_style_is_dark() {
+        return SystemAppearance::Light;
+    }
+    SystemAppearance::Dark
+}
+
+/// The system's display accessibility settings that change how the overlay is painted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DisplayAccessibility {
+    /// "Increase contrast": the high contrast theme, whatever `[theme]` names.
+    pub increase_contrast: bool,
+    /// "Reduce transparency": fills nearly opaque, whatever alphas are configured.
+    pub reduce_transparency: bool,
+}
+
+/// The settings as they are now; all off where they can't be read.
+pub fn system_display_accessibility() -> DisplayAccessibility {
+    #[cfg(target_os = "macos")]
+    {
+        let (increase_contrast, reduce_transparency) = macos::display_accessibility_options();
+        DisplayAccessibility { increase_contrast, reduce_transparency }
+    }
+    #[cfg(not(target_os = "macos"))]
+    DisplayAccessibility::default()
+}
+
+/// Colors and strokes the overlay paints with, resolved from `[theme]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    pub main_cell_fill: egui::Color32,
+    /// Main cells other than the picked one while the sub-grid is up, and displays not picked.
+    pub dimmed_fill: egui::Color32,
+    /// Main cells the typed prefix can no longer reach, and cells under the notch.
+    pub filtered_fill: egui::Color32,
+    /// Main cells the typed prefix can still reach.
+    pub preview_fill: egui::Color32,
+    pub sub_cell_fill: egui::Color32,
+    pub grid_stroke: egui::Stroke,
+    /// Outline of the main cells the typed prefix can still reach.
+    pub match_stroke: egui::Stroke,
+    pub label_color: egui::Color32,
+    pub sub_label_color: egui::Color32,
+    /// The picked cell's outline and the keys left to type in a label.
+    pub accent: egui::Color32,
+    /// Laid over everything outside the cell being refined.
+    pub shade: egui::Color32,
+    pub hud: HudStyle,
+    /// Mark matching and picked cells with a second, inner border, so they don't differ by hue alone.
+    pub double_outline: bool,
+    /// Multiplier on grid label sizes, which otherwise follow the cell size.
+    pub label_scale: f32,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            main_cell_fill: egui::Color32::from_rgba_unmultiplied(50, 50, 50, 120),
+            dimmed_fill: egui::Color32::from_rgba_unmultiplied(30, 30, 30, 70),
+            filtered_fill: egui::Color32::from_rgba_unmultiplied(20, 20, 20, 40),
+            preview_fill: egui::Color32::from_rgba_unmultiplied(80, 120, 80, 150),
+            sub_cell_fill: egui::Color32::from_rgba_unmultiplied(70, 70, 20, 160),
+            grid_stroke: egui::Stroke::new(0.5, egui::Color32::from_rgba_unmultiplied(200, 200, 200, 100)),
+            match_stroke: egui::Stroke::new(1.5, egui::Color32::from_rgba_unmultiplied(140, 230, 140, 220)),
+            label_color: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 200),
+            sub_label_color: egui::Color32::WHITE,
+            accent: egui::Color32::YELLOW,
+            shade: egui::Color32::from_rgba_unmultiplied(20, 20, 20, 150),
+            hud: HudStyle::default(),
+            double_outline: false,
+            label_scale: 1.0,
+        }
+    }
+
+    /// Dark labels on pale cells, for light desktops where the dark theme reads as smudges.
+    pub fn light() -> Self {
+        Self {
+            main_cell_fill: egui::Color32::from_rgba_unmultiplied(245, 245, 245, 140),
+            dimmed_fill: egui::Color32::from_rgba_unmultiplied(230, 230, 230, 70),
+            filtered_fill: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 40),
+            preview_fill: egui::Color32::from_rgba_unmultiplied(170, 220, 170, 170),
+            sub_cell_fill: egui::Color32::from_rgba_unmultiplied(255, 240, 170, 180),
+            grid_stroke: egui::Stroke::new(0.5, egui::Color32::from_rgba_unmultiplied(60, 60, 60, 120)),
+            match_stroke: egui::Stroke::new(1.5, egui::Color32::from_rgba_unmultiplied(40, 140, 40, 230)),
+            label_color: egui::Color32::from_rgba_unmultiplied(20, 20, 20, 230),
+            sub_label_color: egui::Color32::from_rgb(20, 20, 20),
+            accent: egui::Color32::from_rgb(230, 120, 0),
+            shade: egui::Color32::from_rgba_unmultiplied(255, 255, 255, 140),
+            hud: HudStyle {
+                text_color: egui::Color32::from_rgb(20, 20, 20),
+                background: egui::Color32::from_rgba_unmultiplied(245, 245, 245, 220),
+                ..HudStyle::default()
+            },
+            double_outline: false,
+            label_scale: 1.0,
+        }
+    }
+
+    /// Near-opaque black cells with white labels, thick strokes, and blue and orange for matches
+    /// and the accent, which stay apart under the common kinds of color blindness.
+    pub fn high_contrast() -> Self {
+        Self {
+            main_cell_fill: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 200),
+            dimmed_fill: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 140),
+            filtered_fill: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 70),
+            preview_fill: egui::Color32::from_rgba_unmultiplied(0, 50, 130, 230),
+            sub_cell_fill: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 215),
+            grid_stroke: egui::Stroke::new(1.5, egui::Color32::from_rgba_unmultiplied(255, 255, 255, 220)),
+            match_stroke: egui::Stroke::new(3.0, egui::Color32::from_rgb(255, 170, 0)),
+            label_color: egui::Color32::WHITE,
+            sub_label_color: egui::Color32::WHITE,
+            accent: egui::Color32::from_rgb(255, 190, 40),
+            shade: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 190),
+            hud: HudStyle {
+                text_color: egui::Color32::WHITE,
+                background: egui::Color32::from_rgba_unmultiplied(0, 0, 0, 235),
+                ..HudStyle::default()
+            },
+            double_outline: true,
+            label_scale: 1.0,
+        }
+    }
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high_contrast" => Some(Self::high_contrast()),
+            _ => None,
+        }
+    }
+}
+
+impl Theme {
+    /// Raises every fill to near-opaque, keeping its color.
+    fn reduce_transparency(&mut self) {
+        for fill in [
+            &mut self.main_cell_fill,
+            &mut self.dimmed_fill,
+            &mut self.filtered_fill,
+            &mut self.preview_fill,
+            &mut self.sub_cell_fill,
+            &mut self.shade,
+            &mut self.hud.background,
+        ] {
+            let [r, g, b, a] = fill.to_srgba_unmultiplied();
+            *fill = egui::Color32::from_rgba_unmultiplied(r, g, b, a.max(REDUCED_TRANSPARENCY_ALPHA));
+        }
+    }
+
+    /// Swaps any label color that falls below `min_ratio` against the fill behind it for black or
+    /// white, whichever reads better.
+    fn enforce_label_contrast(&mut self, min_ratio: f32) {
+        for (text, fill) in [
+            (&mut self.label_color, self.main_cell_fill),
+            (&mut self.sub_label_color, self.sub_cell_fill),
+            (&mut self.accent, self.preview_fill),
+            (&mut self.hud.text_color, self.hud.background),
+        ] {
+            *text = readable_text_color(*text, fill, min_ratio);
+        }
+    }
+}
+
+/// WCAG relative luminance of the color's RGB, 0.0 (black) to 1.0 (white). Alpha is ignored.
+pub fn relative_luminance(color: egui::Color32) -> f32 {
+    let [r, g, b, _] = color.to_srgba_unmultiplied();
+    let linear = |channel: u8| {
+        let c = channel as f32 / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    };
+    0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+}
+
+/// WCAG contrast ratio between two opaque colors, from 1.0 (same) to 21.0 (black on white).
+pub fn contrast_ratio(a: egui::Color32, b: egui::Color32) -> f32 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// `fill` blended over an opaque `backdrop`.
+pub fn composite_over(fill: egui::Color32, backdrop: egui::Color32) -> egui::Color32 {
+    let [r, g, b, a] = fill.to_srgba_unmultiplied();
+    let alpha = a as f32 / 255.0;
+    let blend = |top: u8, bottom: u8| (top as f32 * alpha + bottom as f32 * (1.0 - alpha)).round() as u8;
+    egui::Color32::from_rgb(blend(r, backdrop.r()), blend(g, backdrop.g()), blend(b, backdrop.b()))
+}
+
+/// Contrast of `text` over a translucent `fill` in the worst case, whether the screen behind the
+/// fill is black or white.
+pub fn worst_case_contrast(text: egui::Color32, fill: egui::Color32) -> f32 {
+    [egui::Color32::BLACK, egui::Color32::WHITE]
+        .into_iter()
+        .map(|backdrop| contrast_ratio(composite_over(text, composite_over(fill, backdrop)), composite_over(fill, backdrop)))
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// `text` if it reaches `min_ratio` over `fill`, otherwise opaque black or white, whichever has the
+/// better worst case.
+pub fn readable_text_color(text: egui::Color32, fill: egui::Color32, min_ratio: f32) -> egui::Color32 {
+    if worst_case_contrast(text, fill) >= min_ratio {
+        return text;
+    }
+    [egui::Color32::WHITE, egui::Color32::BLACK]
+        .into_iter()
+        .max_by(|a, b| worst_case_contrast(*a, fill).total_cmp(&worst_case_contrast(*b, fill)))
+        .unwrap_or(text)
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+/// A built-in theme and any per-field overrides. Colors are "#RRGGBB" or "#RRGGBBAA"; a field left
+/// unset keeps the built-in theme's value.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// "auto" (follow the system's light or dark mode), "dark", "light" or "high_contrast".
+    pub name: String,
+    /// Use the high contrast theme whatever `name` says, and keep labels readable against their fills.
+    pub high_contrast: bool,
+    /// Draw an outline around grid labels so they stay readable over busy content.
+    pub label_outline: bool,
+    /// Where main grid labels sit in their cell.
+    pub label_position: LabelPosition,
+    /// Where sub-grid labels sit; cells too small for a corner label keep it centered.
+    pub sub_label_position: LabelPosition,
+    /// Show the status pill with the mode and the keys typed so far, e.g. for screencasts.
+    pub hud: bool,
+    pub hud_font_size: f32,
+    /// Grid labels bigger (> 1.0) or smaller than their cell size gives, e.g. on 4K and 5K displays.
+    pub label_scale: f32,
+    /// TTF/OTF file for grid labels, e.g. one that tells I from l and O from 0. Empty: egui's own.
+    pub font_path: String,
+    /// egui's bundled fonts to use for glyphs `font_path` lacks, or for all labels without it:
+    /// "proportional" or "monospace".
+    pub font_family: String,
+    pub main_cell_fill: Option<String>,
+    pub dimmed_fill: Option<String>,
+    pub filtered_fill: Option<String>,
+    pub preview_fill: Option<String>,
+    pub sub_cell_fill: Option<String>,
+    pub grid_stroke: Option<String>,
+    pub grid_stroke_width: Option<f32>,
+    pub match_stroke: Option<String>,
+    pub label_color: Option<String>,
+    pub sub_label_color: Option<String>,
+    pub accent: Option<String>,
+    pub shade: Option<String>,
+    pub hud_text_color: Option<String>,
+    pub hud_background: Option<String>,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            name: "auto".to_string(),
+            high_contrast: false,
+            label_outline: true,
+            label_position: LabelPosition::Center,
+            sub_label_position: LabelPosition::Center,
+            hud: true,
+            hud_font_size: 13.0,
+            label_scale: 1.0,
+            font_path: String::new(),
+            font_family: "proportional".to_string(),
+            main_cell_fill: None,
+            dimmed_fill: None,
+            filtered_fill: None,
+            preview_fill: None,
+            sub_cell_fill: None,
+            grid_stroke: None,
+            grid_stroke_width: None,
+            match_stroke: None,
+            label_color: None,
+            sub_label_color: None,
+            accent: None,
+            shade: None,
+            hud_text_color: None,
+            hud_background: None,
+        }
+    }
+}
+
+impl ThemeConfig {
+    /// Whether the theme changes with the system appearance, rather than being pinned by name.
+    pub fn follows_system(&self) -> bool {
+        self.name.eq_ignore_ascii_case("auto")
+    }
+
+    /// The named built-in theme, or the one for `appearance` under "auto", with the overrides
+    /// applied and adjusted for `accessibility`. Errors name the offending field.
+    pub fn resolve(&self, appearance: SystemAppearance, accessibility: DisplayAccessibility) -> Result<Theme, String> {
+        let high_contrast = self.high_contrast || accessibility.increase_contrast || self.name.eq_ignore_ascii_case("high_contrast");
+        let mut theme = if high_contrast {
+            Theme::high_contrast()
+        } else if self.follows_system() {
+            match appearance {
+                SystemAppearance::Light => Theme::light(),
+                SystemAppearance::Dark => Theme::dark(),
+            }
+        } else {
+            Theme::named(&self.name.to_ascii_lowercase())
+                .ok_or_else(|| format!("theme.name {:?} isn't a built-in theme (auto, dark, light, high_contrast)", self.name))?
+        };
+        for (field, value, target) in [
+            ("main_cell_fill", &self.main_cell_fill, &mut theme.main_cell_fill),
+            ("dimmed_fill", &self.dimmed_fill, &mut theme.dimmed_fill),
+            ("filtered_fill", &self.filtered_fill, &mut theme.filtered_fill),
+            ("preview_fill", &self.preview_fill, &mut theme.preview_fill),
+            ("sub_cell_fill", &self.sub_cell_fill, &mut theme.sub_cell_fill),
+            ("grid_stroke", &self.grid_stroke, &mut theme.grid_stroke.color),
+            ("match_stroke", &self.match_stroke, &mut theme.match_stroke.color),
+            ("label_color", &self.label_color, &mut theme.label_color),
+            ("sub_label_color", &self.sub_label_color, &mut theme.sub_label_color),
+            ("accent", &self.accent, &mut theme.accent),
+            ("shade", &self.shade, &mut theme.shade),
+            ("hud_text_color", &self.hud_text_color, &mut theme.hud.text_color),
+            ("hud_background", &self.hud_background, &mut theme.hud.background),
+        ] {
+            if let Some(value) = value {
+                *target = parse_hex_color(value).map_err(|e| format!("theme.{}: {}", field, e))?;
+            }
+        }
+        if let Some(width) = self.grid_stroke_width {
+            if !(0.0..=8.0).contains(&width) {
+                return Err(format!("theme.grid_stroke_width must be between 0 and 8, got {}", width));
+            }
+            theme.grid_stroke.width = width;
+        }
+        theme.hud.font_size = self.hud_font_size.clamp(8.0, 48.0);
+        theme.label_scale = self.label_scale.clamp(0.5, 3.0);
+        if accessibility.reduce_transparency {
+            theme.reduce_transparency();
+        }
+        // Last, so neither the overrides nor the opaque fills can leave a label unreadable
+        if high_contrast {
+            theme.enforce_label_contrast(MIN_LABEL_CONTRAST);
+        }
+        Ok(theme)
+    }
+}
+
+/// Parses "#RRGGBB" (opaque) or "#RRGGBBAA". Alpha is straight, not premultiplied.
+pub fn parse_hex_color(value: &str) -> Result<egui::Color32, String> {
+    let Some(hex) = value.trim().strip_prefix('#') else {
+        return Err(format!("{:?} should start with '#', e.g. \"#3C3C3C80\"", value));
+    };
+    if !matches!(hex.len(), 6 | 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("{:?} should be '#' and 6 or 8 hex digits (RRGGBB or RRGGBBAA)", value));
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index * 2..index * 2 + 2], 16).map_err(|e| format!("{:?}: {}", value, e));
+    let alpha = if hex.len() == 8 { channel(3)? } else { 255 };
+    Ok(egui::Color32::from_rgba_unmultiplied(channel(0)?, channel(1)?, channel(2)?, alpha))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-3, "{} != {}", actual, expected);
+    }
+
+    fn grey(level: u8) -> egui::Color32 {
+        egui::Color32::from_rgb(level, level, level)
+    }
+
+    #[test]
+    fn luminance_of_known_colors() {
+        assert_close(relative_luminance(egui::Color32::BLACK), 0.0);
+        assert_close(relative_luminance(egui::Color32::WHITE), 1.0);
+        assert_close(relative_luminance(egui::Color32::from_rgb(255, 0, 0)), 0.2126);
+        assert_close(relative_luminance(egui::Color32::from_rgb(0, 255, 0)), 0.7152);
+        assert_close(relative_luminance(egui::Color32::from_rgb(0, 0, 255)), 0.0722);
+        assert_close(relative_luminance(grey(128)), 0.2159);
+        // Alpha doesn't count
+        assert_close(relative_luminance(egui::Color32::from_rgba_unmultiplied(255, 255, 255, 10)), 1.0);
+    }
+
+    #[test]
+    fn contrast_of_known_pairs() {
+        assert_close(contrast_ratio(egui::Color32::BLACK, egui::Color32::WHITE), 21.0);
+        assert_close(contrast_ratio(egui::Color32::WHITE, egui::Color32::BLACK), 21.0);
+        assert_close(contrast_ratio(grey(128), grey(128)), 1.0);
+        // #767676 is the darkest grey that passes AA against white
+        assert_close(contrast_ratio(grey(0x76), egui::Color32::WHITE), 4.542);
+        assert!(contrast_ratio(grey(0x77), egui::Color32::WHITE) < MIN_LABEL_CONTRAST);
+    }
+
+    #[test]
+    fn compositing_blends_by_alpha() {
+        let half_white = egui::Color32::from_rgba_unmultiplied(255, 255, 255, 128);
+        assert_eq!(composite_over(half_white, egui::Color32::BLACK), grey(128));
+        assert_eq!(composite_over(egui::Color32::from_rgb(10, 20, 30), egui::Color32::WHITE), egui::Color32::from_rgb(10, 20, 30));
+        assert_eq!(composite_over(egui::Color32::TRANSPARENT, grey(77)), grey(77));
+    }
+
+    #[test]
+    fn worst_case_contrast_assumes_the_unkindest_backdrop() {
+        assert_close(worst_case_contrast(egui::Color32::WHITE, egui::Color32::BLACK), 21.0);
+        // A clear fill over a white screen leaves white text invisible
+        assert_close(worst_case_contrast(egui::Color32::WHITE, egui::Color32::TRANSPARENT), 1.0);
+        let half_black = egui::Color32::from_rgba_unmultiplied(0, 0, 0, 128);
+        let over_white = contrast_ratio(egui::Color32::WHITE, composite_over(half_black, egui::Color32::WHITE));
+        assert_close(worst_case_contrast(egui::Color32::WHITE, half_black), over_white);
+    }
+
+    #[test]
+    fn unreadable_labels_turn_black_or_white() {
+        let opaque_black = egui::Color32::BLACK;
+        assert_eq!(readable_text_color(grey(0x76), opaque_black, MIN_LABEL_CONTRAST), grey(0x76));
+        assert_eq!(readable_text_color(grey(40), opaque_black, MIN_LABEL_CONTRAST), egui::Color32::WHITE);
+        assert_eq!(readable_text_color(egui::Color32::YELLOW, grey(245), MIN_LABEL_CONTRAST), egui::Color32::BLACK);
+    }
+
+    #[test]
+    fn hex_colors_parse_with_and_without_alpha() {
+        assert_eq!(parse_hex_color("#FF8000"), Ok(egui::Color32::from_rgb(255, 128, 0)));
+        assert_eq!(parse_hex_color(" #3c3c3c80 "), Ok(egui::Color32::from_rgba_unmultiplied(60, 60, 60, 128)));
+        for bad in ["FF8000", "#FFF", "#FF80001", "#GG0000", ""] {
+            assert!(parse_hex_color(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn auto_follows_the_appearance_and_names_pin_a_theme() {
+        let auto = ThemeConfig::default();
+        let none = DisplayAccessibility::default();
+        assert_eq!(auto.resolve(SystemAppearance::Light, none).unwrap().main_cell_fill, Theme::light().main_cell_fill);
+        assert_eq!(auto.resolve(SystemAppearance::Dark, none).unwrap().main_cell_fill, Theme::dark().main_cell_fill);
+        let dark = ThemeConfig { name: "Dark".to_string(), ..ThemeConfig::default() };
+        assert_eq!(dark.resolve(SystemAppearance::Light, none).unwrap().main_cell_fill, Theme::dark().main_cell_fill);
+        let unknown = ThemeConfig { name: "solarized".to_string(), ..ThemeConfig::default() };
+        assert!(unknown.resolve(SystemAppearance::Dark, none).is_err());
+    }
+
+    #[test]
+    fn increase_contrast_keeps_overridden_labels_readable() {
+        let config = ThemeConfig { label_color: Some("#202020".to_string()), ..ThemeConfig::default() };
+        let accessibility = DisplayAccessibility { increase_contrast: true, reduce_transparency: false };
+        let theme = config.resolve(SystemAppearance::Light, accessibility).unwrap();
+        assert_eq!(theme.main_cell_fill, Theme::high_contrast().main_cell_fill);
+        assert_eq!(theme.label_color, egui::Color32::WHITE);
+        assert!(worst_case_contrast(theme.label_color, theme.main_cell_fill) >= MIN_LABEL_CONTRAST);
+    }
+
+    #[test]
+    fn reduce_transparency_makes_fills_nearly_opaque() {
+        let accessibility = DisplayAccessibility { increase_contrast: false, reduce_transparency: true };
+        let theme = ThemeConfig::default().resolve(SystemAppearance::Dark, accessibility).unwrap();
+        assert_eq!(theme.main_cell_fill, egui::Color32::from_rgba_unmultiplied(50, 50, 50, REDUCED_TRANSPARENCY_ALPHA));
+        assert_eq!(theme.filtered_fill.a(), REDUCED_TRANSPARENCY_ALPHA);
+    }
+
+    #[test]
+    fn bad_overrides_name_their_field() {
+        let none = DisplayAccessibility::default();
+        let config = ThemeConfig { accent: Some("orange".to_string()), ..ThemeConfig::default() };
+        assert!(config.resolve(SystemAppearance::Dark, none).unwrap_err().starts_with("theme.accent"));
+        let config = ThemeConfig { grid_stroke_width: Some(9.0), ..ThemeConfig::default() };
+        assert!(config.resolve(SystemAppearance::Dark, none).unwrap_err().contains("grid_stroke_width"));
+    }
+}
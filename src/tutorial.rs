@@ -0,0 +1,62 @@
+use eframe::egui;
+
+use crate::tap_gesture::{ActivationKey, TapAction, TapConfig};
+
+/// eframe storage key for whether the tutorial has been shown.
+pub const STORAGE_KEY: &str = "tutorial_seen";
+const CARD_PADDING: egui::Vec2 = egui::vec2(28.0, 22.0);
+const LINE_GAP: f32 = 10.0;
+
+fn activation_key_label(key: ActivationKey) -> &'static str {
+    match key {
+        ActivationKey::RightCommand => "right ⌘",
+        ActivationKey::RightOption => "right ⌥",
+        ActivationKey::Fn => "fn",
+        ActivationKey::CapsLock => "caps lock",
+    }
+}
+
+/// The tutorial's text, following the configured gesture and keys.
+pub fn lines(taps: &TapConfig, back_key: Option<egui::Key>, cancel_key: Option<egui::Key>) -> Vec<String> {
+    let key = activation_key_label(taps.activation_key);
+    let gesture = [("Tap", taps.single), ("Double-tap", taps.double), ("Triple-tap", taps.triple)]
+        .into_iter()
+        .find(|(_, action)| *action == TapAction::ShowGrid)
+        .map(|(tap, _)| format!("{} {} to show this grid, and again to hide it", tap, key))
+        .unwrap_or_else(|| "Set a [taps] gesture to show_grid in ~/.config/mouseless/config.toml to show this grid".to_string());
+    vec![
+        "Welcome to mouseless".to_string(),
+        gesture,
+        "Type a cell's two letters, then the letter of a spot inside it to click there".to_string(),
+        format!(
+            "{} goes back a step · {} hides the grid",
+            back_key.map_or("Backspace", |key| key.name()),
+            cancel_key.map_or("Escape", |key| key.name()),
+        ),
+        "Press any key to start".to_string(),
+    ]
+}
+
+/// Centered card over the grid; the first line is the title.
+pub fn paint(painter: &egui::Painter, screen_rect: egui::Rect, lines: &[String]) {
+    let galleys: Vec<_> = lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            let size = if index == 0 { 26.0 } else { 17.0 };
+            painter.layout_no_wrap(line.clone(), egui::FontId::proportional(size), egui::Color32::WHITE)
+        })
+        .collect();
+    let width = galleys.iter().map(|galley| galley.size().x).fold(0.0, f32::max);
+    let height = galleys.iter().map(|galley| galley.size().y).sum::<f32>() + LINE_GAP * galleys.len().saturating_sub(1) as f32;
+    let card = egui::Rect::from_center_size(screen_rect.center(), egui::vec2(width, height) + 2.0 * CARD_PADDING);
+    painter.rect_filled(card, 12.0, egui::Color32::from_rgba_unmultiplied(20, 20, 20, 235));
+    painter.rect_stroke(card, 12.0, egui::Stroke::new(1.0, egui::Color32::from_white_alpha(60)));
+    let mut y = card.min.y + CARD_PADDING.y;
+    for galley in galleys {
+        let x = card.center().x - galley.size().x / 2.0;
+        let line_height = galley.size().y;
+        painter.galley(egui::pos2(x, y), galley, egui::Color32::WHITE);
+        y += line_height + LINE_GAP;
+    }
+}
@@ -0,0 +1,89 @@
+use eframe::egui;
+
+/// What a pair of picks is for.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TwoPointPurpose {
+    /// Opposite corners of a region to capture.
+    Screenshot,
+    /// Ends of a distance to measure.
+    Measure,
+    /// Where the frontmost window's top-left goes, then optionally the opposite corner to resize it to.
+    PlaceWindow,
+}
+
+impl TwoPointPurpose {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TwoPointPurpose::Screenshot => "screenshot",
+            TwoPointPurpose::Measure => "measure",
+            TwoPointPurpose::PlaceWindow => "window",
+        }
+    }
+}
+
+/// Collects two successive picks, in global coordinates, for the modes that need a start and an end.
+#[derive(Debug, Default)]
+pub struct TwoPointPicker {
+    purpose: Option<TwoPointPurpose>,
+    first: Option<egui::Pos2>,
+}
+
+impl TwoPointPicker {
+    /// Arms for `purpose`, or disarms if it was already armed for it. Returns whether it is armed now.
+    pub fn toggle(&mut self, purpose: TwoPointPurpose) -> bool {
+        let arm = self.purpose != Some(purpose);
+        *self = Self { purpose: arm.then_some(purpose), first: None };
+        arm
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn purpose(&self) -> Option<TwoPointPurpose> {
+        self.purpose
+    }
+
+    pub fn first(&self) -> Option<egui::Pos2> {
+        self.first
+    }
+
+    /// Takes a pick. The first is kept and gives `None`; the second gives both and disarms.
+    pub fn pick(&mut self, point: egui::Pos2) -> Option<(egui::Pos2, egui::Pos2)> {
+        match self.first {
+            None => {
+                self.first = Some(point);
+                None
+            }
+            Some(first) => {
+                self.reset();
+                Some((first, point))
+            }
+        }
+    }
+}
+
+/// Distance between two global points, in points and in the display's physical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Measurement {
+    pub start: egui::Pos2,
+    pub end: egui::Pos2,
+    pub scale_factor: f32,
+}
+
+impl Measurement {
+    pub fn delta(&self) -> egui::Vec2 {
+        self.end - self.start
+    }
+
+    pub fn summary(&self) -> String {
+        let delta = self.delta();
+        let pixels = delta * self.scale_factor;
+        format!(
+            "Δx {:.0}  Δy {:.0}  distance {:.1} pt  ·  Δx {:.0}  Δy {:.0}  distance {:.1} px @{}x",
+            delta.x.abs(), delta.y.abs(), delta.length(),
+            pixels.x.abs(), pixels.y.abs(), pixels.length(),
+            self.scale_factor,
+        )
+    }
+}
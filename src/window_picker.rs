@@ -0,0 +1,59 @@
+use eframe::egui;
+use serde::Deserialize;
+
+/// How far inside the display edge hints for windows centered on another display are drawn.
+const OFF_DISPLAY_INSET: f32 = 48.0;
+/// Vertical step between off-display hints that would otherwise land on the same spot.
+const OFF_DISPLAY_STACK_GAP: f32 = 44.0;
+
+/// What choosing a window in the window picker does.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WindowPickAction {
+    /// Raise the window and activate its app.
+    Focus,
+    /// Move the cursor to the window's center.
+    MoveCursor,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WindowPickerConfig {
+    pub action: WindowPickAction,
+    /// Windows narrower or shorter than this (palettes, utility panels) get no hint.
+    pub min_side: f32,
+}
+
+impl Default for WindowPickerConfig {
+    fn default() -> Self {
+        Self { action: WindowPickAction::Focus, min_side: 100.0 }
+    }
+}
+
+/// Where to draw each window's hint: its center when that is on this display, otherwise the nearest
+/// spot just inside this display's edge, stacked so hints pointing at the same display don't overlap.
+pub fn hint_anchors(windows: &[egui::Rect], screen_rect: egui::Rect) -> Vec<egui::Pos2> {
+    let edge = screen_rect.shrink(OFF_DISPLAY_INSET);
+    let mut off_display: Vec<egui::Pos2> = Vec::new();
+    windows.iter()
+        .map(|window| {
+            let center = window.center();
+            if screen_rect.contains(center) {
+                return center;
+            }
+            let mut anchor = edge.clamp(center);
+            while off_display.iter().any(|taken| (taken.y - anchor.y).abs() < OFF_DISPLAY_STACK_GAP && (taken.x - anchor.x).abs() < 1.0) {
+                anchor.y += OFF_DISPLAY_STACK_GAP;
+            }
+            off_display.push(anchor);
+            anchor
+        })
+        .collect()
+}
+
+/// `window` moved (and shrunk if it has to be) so it lies entirely on `display`.
+pub fn fit_on_display(window: egui::Rect, display: egui::Rect) -> egui::Rect {
+    let size = window.size().min(display.size());
+    let min = window.min.clamp(display.min, display.max - size);
+    egui::Rect::from_min_size(min, size)
+}
@@ -0,0 +1,197 @@
+use eframe::egui;
+use serde::Deserialize;
+
+#[cfg(target_os = "macos")]
+use core_graphics::geometry::CGRect;
+
+#[cfg(target_os = "macos")]
+use crate::macos;
+
+/// A spot for the focused window, as fractions of the display's visible frame.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SnapPreset {
+    pub key: char,
+    pub name: String,
+    /// Left and top edge, 0.0-1.0 from the visible frame's top-left.
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl SnapPreset {
+    fn new(key: char, name: &str, x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self { key, name: name.to_string(), x, y, width, height }
+    }
+
+    /// Halves on hl, thirds on uio, quarters on 1-4 (reading order).
+    pub fn built_in() -> Vec<SnapPreset> {
+        vec![
+            SnapPreset::new('H', "left half", 0.0, 0.0, 0.5, 1.0),
+            SnapPreset::new('L', "right half", 0.5, 0.0, 0.5, 1.0),
+            SnapPreset::new('U', "left third", 0.0, 0.0, 1.0 / 3.0, 1.0),
+            SnapPreset::new('I', "center third", 1.0 / 3.0, 0.0, 1.0 / 3.0, 1.0),
+            SnapPreset::new('O', "right third", 2.0 / 3.0, 0.0, 1.0 / 3.0, 1.0),
+            SnapPreset::new('1', "top-left quarter", 0.0, 0.0, 0.5, 0.5),
+            SnapPreset::new('2', "top-right quarter", 0.5, 0.0, 0.5, 0.5),
+            SnapPreset::new('3', "bottom-left quarter", 0.0, 0.5, 0.5, 0.5),
+            SnapPreset::new('4', "bottom-right quarter", 0.5, 0.5, 0.5, 0.5),
+        ]
+    }
+
+    /// This preset's rect inside `visible_frame`, on whole points so neighbouring presets meet
+    /// without a gap or overlap.
+    pub fn rect_in(&self, visible_frame: egui::Rect) -> egui::Rect {
+        let at = |fraction_x: f32, fraction_y: f32| egui::pos2(
+            (visible_frame.min.x + visible_frame.width() * fraction_x).round(),
+            (visible_frame.min.y + visible_frame.height() * fraction_y).round(),
+        );
+        egui::Rect::from_min_max(at(self.x, self.y), at(self.x + self.width, self.y + self.height))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct WindowSnapConfig {
+    /// Added to the built-in presets; one that reuses a built-in's key replaces it.
+    pub presets: Vec<SnapPreset>,
+}
+
+impl WindowSnapConfig {
+    /// Built-in presets with the configured ones applied; presets on new keys come last.
+    pub fn presets(&self) -> Vec<SnapPreset> {
+        let mut presets = SnapPreset::built_in();
+        for preset in &self.presets {
+            match presets.iter_mut().find(|existing| existing.key == preset.key) {
+                Some(existing) => *existing = preset.clone(),
+                None => presets.push(preset.clone()),
+            }
+        }
+        presets
+    }
+
+    pub fn preset_for_char(&self, char_code: char) -> Option<SnapPreset> {
+        self.presets().into_iter().find(|preset| preset.key == char_code)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for preset in &self.presets {
+            let in_range = |value: f32| (0.0..=1.0).contains(&value);
+            if !(in_range(preset.x) && in_range(preset.y) && preset.width > 0.0 && preset.height > 0.0
+                && preset.x + preset.width <= 1.0 + f32::EPSILON && preset.y + preset.height <= 1.0 + f32::EPSILON)
+            {
+                return Err(format!("window_snap preset '{}' ({}) must lie within 0.0-1.0 and have a size", preset.key, preset.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An AppKit rect (bottom-left origin on the menu bar screen, y up) in global top-left points.
+/// `primary_height` is the height of the menu bar screen.
+pub fn top_left_rect(appkit: egui::Rect, primary_height: f32) -> egui::Rect {
+    egui::Rect::from_min_size(egui::pos2(appkit.min.x, primary_height - appkit.max.y), appkit.size())
+}
+
+/// Part of the display at `display_frame` (global, top-left) that the menu bar and Dock leave free,
+/// wherever the Dock is. The whole display when AppKit doesn't know it.
+#[cfg(target_os = "macos")]
+pub fn visible_frame(display_frame: egui::Rect) -> egui::Rect {
+    let screens = macos::screens();
+    let primary_height = screens.first().map(|screen| screen.frame.size.height as f32).unwrap_or_default();
+    let appkit_rect = |rect: &CGRect| egui::Rect::from_min_size(
+        egui::pos2(rect.origin.x as f32, rect.origin.y as f32),
+        egui::vec2(rect.size.width as f32, rect.size.height as f32),
+    );
+    screens.iter()
+        .find(|screen| {
+            let frame = top_left_rect(appkit_rect(&screen.frame), primary_height);
+            (frame.min - display_frame.min).length() < 1.0 && (frame.size() - display_frame.size()).length() < 1.0
+        })
+        .map(|screen| top_left_rect(appkit_rect(&screen.visible_frame), primary_height))
+        .unwrap_or(display_frame)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1440x900 display under a 25pt menu bar.
+    const VISIBLE: egui::Rect = egui::Rect { min: egui::pos2(0.0, 25.0), max: egui::pos2(1440.0, 900.0) };
+
+    fn rect(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> egui::Rect {
+        egui::Rect::from_min_max(egui::pos2(min_x, min_y), egui::pos2(max_x, max_y))
+    }
+
+    fn built_in(key: char) -> SnapPreset {
+        SnapPreset::built_in().into_iter().find(|preset| preset.key == key).unwrap()
+    }
+
+    #[test]
+    fn each_built_in_preset_covers_its_part_of_the_visible_frame() {
+        for (key, expected) in [
+            ('H', rect(0.0, 25.0, 720.0, 900.0)),
+            ('L', rect(720.0, 25.0, 1440.0, 900.0)),
+            ('U', rect(0.0, 25.0, 480.0, 900.0)),
+            ('I', rect(480.0, 25.0, 960.0, 900.0)),
+            ('O', rect(960.0, 25.0, 1440.0, 900.0)),
+            // 875 tall: the middle falls on 462.5 and rounds to whole points
+            ('1', rect(0.0, 25.0, 720.0, 463.0)),
+            ('2', rect(720.0, 25.0, 1440.0, 463.0)),
+            ('3', rect(0.0, 463.0, 720.0, 900.0)),
+            ('4', rect(720.0, 463.0, 1440.0, 900.0)),
+        ] {
+            assert_eq!(built_in(key).rect_in(VISIBLE), expected, "preset {}", key);
+        }
+    }
+
+    #[test]
+    fn neighbouring_presets_meet_without_a_gap_on_odd_sizes() {
+        let visible = rect(13.0, 30.0, 13.0 + 1001.0, 30.0 + 777.0);
+        let edge = |key: char| built_in(key).rect_in(visible);
+        assert_eq!(edge('H').max.x, edge('L').min.x);
+        assert_eq!(edge('U').max.x, edge('I').min.x);
+        assert_eq!(edge('I').max.x, edge('O').min.x);
+        assert_eq!(edge('1').max.y, edge('3').min.y);
+        assert_eq!(edge('1').max.x, edge('2').min.x);
+        assert_eq!(edge('L').max, visible.max);
+        assert_eq!(edge('4').max, visible.max);
+        assert_eq!(edge('U').min, visible.min);
+    }
+
+    #[test]
+    fn configured_presets_replace_built_ins_by_key_and_append_new_ones() {
+        let config = WindowSnapConfig {
+            presets: vec![
+                SnapPreset::new('H', "left two thirds", 0.0, 0.0, 2.0 / 3.0, 1.0),
+                SnapPreset::new('C', "center", 0.25, 0.25, 0.5, 0.5),
+            ],
+        };
+        let presets = config.presets();
+        assert_eq!(presets.len(), SnapPreset::built_in().len() + 1);
+        assert_eq!(presets[0].name, "left two thirds");
+        assert_eq!(presets.last().unwrap().key, 'C');
+        assert_eq!(config.preset_for_char('C').unwrap().rect_in(VISIBLE), rect(360.0, 244.0, 1080.0, 681.0));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn presets_off_the_frame_or_without_a_size_are_rejected() {
+        for preset in [
+            SnapPreset::new('X', "too wide", 0.5, 0.0, 0.6, 1.0),
+            SnapPreset::new('X', "no height", 0.0, 0.0, 1.0, 0.0),
+            SnapPreset::new('X', "negative", -0.1, 0.0, 0.5, 1.0),
+        ] {
+            assert!(WindowSnapConfig { presets: vec![preset] }.validate().is_err());
+        }
+    }
+
+    #[test]
+    fn appkit_rects_flip_to_top_left_points() {
+        // The upper 875pt of a 900pt screen in AppKit's bottom-left space
+        let appkit = rect(0.0, 0.0, 1440.0, 875.0);
+        assert_eq!(top_left_rect(appkit, 900.0), rect(0.0, 25.0, 1440.0, 900.0));
+        // A display above the primary one has negative top-left y
+        assert_eq!(top_left_rect(rect(0.0, 900.0, 1920.0, 1980.0), 900.0), rect(0.0, -1080.0, 1920.0, 0.0));
+    }
+}